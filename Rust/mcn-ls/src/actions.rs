@@ -0,0 +1,343 @@
+//! Code actions: quick fixes of diagnostics and refactorings of the selected code
+
+use std::collections::{HashMap, HashSet};
+
+use lsp_types::{
+    CodeAction, CodeActionKind, Diagnostic, Position, Range as LspRange, TextEdit, Url,
+    WorkspaceEdit,
+};
+use redstone_compiler::{
+    backend::eval_constant,
+    frontend::{tokenize, Ast, ExprId, Expression, ExpressionType, Location, Range, TokenType},
+    Fix,
+};
+use ropey::Rope;
+
+use crate::positions::{char_index, to_lsp_range, to_position};
+
+/// Name of the extracted constant, numbered if the program already uses it
+const CONSTANT_NAME: &str = "CONSTANT";
+
+/// `diagnostics` are the ones the client sent along, at the selection
+pub fn code_actions(
+    ast: &Ast,
+    text: &Rope,
+    uri: &Url,
+    range: LspRange,
+    diagnostics: &[Diagnostic],
+) -> Vec<CodeAction> {
+    diagnostics
+        .iter()
+        .filter_map(|diagnostic| quick_fix(ast, text, uri, diagnostic))
+        .chain(extract_constant(ast, text, uri, range))
+        .collect()
+}
+
+/// Applies the [`Fix`]es the compiler attached to a diagnostic
+fn quick_fix(ast: &Ast, text: &Rope, uri: &Url, diagnostic: &Diagnostic) -> Option<CodeAction> {
+    let fixes: Vec<Fix> = serde_json::from_value(diagnostic.data.clone()?).ok()?;
+    let mut edits = vec![];
+    let mut changes = vec![];
+    for fix in fixes {
+        match fix {
+            Fix::Rename { from, to } => {
+                let start = char_index(text, diagnostic.range.start);
+                let end = char_index(text, diagnostic.range.end);
+                let start = start + find_word(&text.slice(start..end).to_string(), &from)?;
+                let end = start + from.chars().count();
+                edits.push(TextEdit {
+                    range: LspRange::new(char_position(text, start), char_position(text, end)),
+                    new_text: to.clone(),
+                });
+                changes.push(format!("change `{from}` to `{to}`"));
+            }
+            Fix::AddUse(module) => {
+                let last_use = ast
+                    .statements()
+                    .take_while(|statement| matches!(statement.typ, ExpressionType::Use(_)))
+                    .last();
+                // at the end of the line, so it doesn't touch edits on the next one
+                let (position, new_text) = match last_use {
+                    Some(statement) => (
+                        to_position(text, statement.location.1 .0, usize::MAX),
+                        format!("\nuse {module}"),
+                    ),
+                    None => (Position::new(0, 0), format!("use {module}\n")),
+                };
+                edits.push(TextEdit {
+                    range: LspRange::new(position, position),
+                    new_text,
+                });
+                changes.push(format!("add `use {module}`"));
+            }
+        }
+    }
+    let title = changes.join(" and ");
+    let mut chars = title.chars();
+    let title = chars.next()?.to_uppercase().chain(chars).collect();
+    Some(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..WorkspaceEdit::default()
+        }),
+        is_preferred: Some(true),
+        ..CodeAction::default()
+    })
+}
+
+/// Char offset of `word` in `text` where it isn't part of a longer identifier
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    let is_identifier = |char: char| char.is_ascii_alphanumeric() || char == '_';
+    text.match_indices(word)
+        .find(|&(index, _)| {
+            let before = text[..index].chars().next_back();
+            let after = text[index + word.len()..].chars().next();
+            !before.is_some_and(is_identifier) && !after.is_some_and(is_identifier)
+        })
+        .map(|(index, _)| text[..index].chars().count())
+}
+
+fn char_position(text: &Rope, char: usize) -> Position {
+    let line = text.char_to_line(char);
+    to_position(text, line as u16, char - text.line_to_char(line))
+}
+
+/// The selected expression and the statement it's in
+struct Selection<'a> {
+    expression: &'a Expression,
+    /// The block of the statement the expression is in and its index in there
+    block: &'a [ExprId],
+    index: usize,
+}
+
+/// Moves the selected expression into an `inline` declaration above its statement and
+/// replaces every identical expression after it in the same block with the constant,
+/// if the compiler can fold it
+fn extract_constant(ast: &Ast, text: &Rope, uri: &Url, range: LspRange) -> Option<CodeAction> {
+    let selected = trim(
+        text,
+        char_index(text, range.start),
+        char_index(text, range.end),
+    )?;
+    let Selection {
+        expression,
+        block,
+        index,
+    } = select(ast, text, ast.body(), selected, &mut HashMap::new())?;
+    let statement = &ast[block[index]];
+    let source = text.slice(selected.0..selected.1).to_string();
+    // parentheses aren't part of the location of an expression
+    let inner = source_of(text, expression.location);
+
+    // the constant can't stand for different values, so its identifiers have to mean the
+    // same until the last replaced expression
+    let identifiers = identifiers(ast, expression);
+    let stop = block[index + 1..]
+        .iter()
+        .map(|&id| &ast[id])
+        .find(|statement| {
+            matches!(&statement.typ, ExpressionType::InlineDeclaration { ident, .. }
+                if identifiers.contains(&ident.symbol))
+        })
+        .map(|statement| statement.location.0);
+    let block_end = block.last().map(|&id| ast[id].location.1)?;
+    let occurrences = ast.expressions().filter(|candidate| {
+        Range(statement.location.0, block_end).contains_range(candidate.location)
+            && stop.is_none_or(|stop| candidate.location.1 < stop)
+            && source_of(text, candidate.location) == inner
+    });
+
+    let name = unused_name(text)?;
+    let line = usize::from(statement.location.0 .0);
+    let indent: String = text
+        .line(line)
+        .chars()
+        .take_while(|char| *char == ' ')
+        .collect();
+    let line_start = Position::new(line as u32, 0);
+    let mut edits = vec![TextEdit {
+        range: LspRange::new(line_start, line_start),
+        new_text: format!("{indent}inline {name} = {}\n", strip_parens(&source)),
+    }];
+    edits.extend(occurrences.map(|occurrence| TextEdit {
+        range: to_lsp_range(text, occurrence.location),
+        new_text: name.clone(),
+    }));
+    Some(CodeAction {
+        title: format!("Extract to inline constant {name}"),
+        kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            ..WorkspaceEdit::default()
+        }),
+        ..CodeAction::default()
+    })
+}
+
+/// The selected expression if it folds to a constant, `inline_variables` are the values
+/// of the inline variables declared before it
+fn select<'a>(
+    ast: &'a Ast,
+    text: &Rope,
+    block: &'a [ExprId],
+    selected: (usize, usize),
+    inline_variables: &mut HashMap<String, i16>,
+) -> Option<Selection<'a>> {
+    for (index, &id) in block.iter().enumerate() {
+        let statement = &ast[id];
+        let (start, end) = char_range(text, statement.location);
+        if start <= selected.0 && selected.1 <= end {
+            let select_in = |body: &'a [ExprId]| {
+                select(ast, text, body, selected, &mut inline_variables.clone())
+            };
+            let nested = match &statement.typ {
+                ExpressionType::Conditional {
+                    body,
+                    paths,
+                    alternate,
+                    ..
+                } => select_in(body).or_else(|| {
+                    paths
+                        .iter()
+                        .find_map(|(_, body)| select_in(body))
+                        .or_else(|| select_in(alternate.as_deref()?))
+                }),
+                ExpressionType::EndlessLoop { body } | ExpressionType::WhileLoop { body, .. } => {
+                    select_in(body)
+                }
+                _ => None,
+            };
+            if nested.is_some() {
+                return nested;
+            }
+            let expression = ast.expressions().find(|expression| {
+                !matches!(
+                    expression.typ,
+                    ExpressionType::Identifier(_) | ExpressionType::InlineDeclaration { .. }
+                ) && covers(text, selected, expression.location)
+                    && eval_constant(ast, expression, inline_variables).is_ok()
+            })?;
+            return Some(Selection {
+                expression,
+                block,
+                index,
+            });
+        }
+        if let ExpressionType::InlineDeclaration { ident, value } = &statement.typ {
+            if let Ok(value) = eval_constant(ast, &ast[*value], inline_variables) {
+                inline_variables.insert(ident.symbol.clone(), value);
+            }
+        }
+    }
+    None
+}
+
+/// Whether the selection is the expression at `location`, maybe with parentheses around it
+fn covers(text: &Rope, (start, end): (usize, usize), location: Range) -> bool {
+    let (location_start, location_end) = char_range(text, location);
+    start <= location_start
+        && location_end <= end
+        && text
+            .slice(start..location_start)
+            .chars()
+            .all(|char| char == '(' || char.is_whitespace())
+        && text
+            .slice(location_end..end)
+            .chars()
+            .all(|char| char == ')' || char.is_whitespace())
+}
+
+/// Names of the inline variables an expression uses
+fn identifiers(ast: &Ast, expression: &Expression) -> HashSet<String> {
+    match &expression.typ {
+        ExpressionType::Identifier(name) => HashSet::from([name.clone()]),
+        ExpressionType::BinaryExpr { left, right, .. }
+        | ExpressionType::EqExpr { left, right, .. } => {
+            let mut names = identifiers(ast, &ast[*left]);
+            names.extend(identifiers(ast, &ast[*right]));
+            names
+        }
+        ExpressionType::UnaryExpr { value, .. } => identifiers(ast, &ast[*value]),
+        _ => HashSet::new(),
+    }
+}
+
+/// Char range without the whitespace around the selection, if its parentheses match
+fn trim(text: &Rope, mut start: usize, mut end: usize) -> Option<(usize, usize)> {
+    while start < end && text.char(start).is_whitespace() {
+        start += 1;
+    }
+    while start < end && text.char(end - 1).is_whitespace() {
+        end -= 1;
+    }
+    let mut depth = 0_usize;
+    for char in text.slice(start..end).chars() {
+        match char {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1)?,
+            _ => {}
+        }
+    }
+    (start < end && depth == 0).then_some((start, end))
+}
+
+/// The value without the parentheses around all of it
+fn strip_parens(mut value: &str) -> &str {
+    while let Some(inner) = value
+        .strip_prefix('(')
+        .and_then(|value| value.strip_suffix(')'))
+    {
+        let mut depth = 0_usize;
+        let balanced = inner.chars().all(|char| {
+            match char {
+                '(' => depth += 1,
+                ')' if depth == 0 => return false,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            true
+        });
+        if !balanced {
+            break;
+        }
+        value = inner.trim();
+    }
+    value
+}
+
+/// Chars a compiler range covers, the end is exclusive
+fn char_range(text: &Rope, Range(start, end): Range) -> (usize, usize) {
+    let index = |Location(line, column): Location| {
+        let line = usize::from(line).min(text.len_lines() - 1);
+        (text.line_to_char(line) + usize::from(column.max(1)) - 1).min(text.len_chars())
+    };
+    (index(start), (index(end) + 1).min(text.len_chars()))
+}
+
+fn source_of(text: &Rope, location: Range) -> String {
+    let (start, end) = char_range(text, location);
+    text.slice(start..end.max(start)).to_string()
+}
+
+/// [`CONSTANT_NAME`], followed by a number if the program already has an identifier
+/// with that name
+fn unused_name(text: &Rope) -> Option<String> {
+    let code = text.to_string();
+    let tokens = tokenize(&code).ok()?;
+    let used: HashSet<_> = tokens
+        .iter()
+        .filter_map(|token| match token.typ {
+            TokenType::Identifier(name) => Some(name),
+            _ => None,
+        })
+        .collect();
+    (1..)
+        .map(|number| match number {
+            1 => CONSTANT_NAME.to_string(),
+            _ => format!("{CONSTANT_NAME}_{number}"),
+        })
+        .find(|name| !used.contains(name.as_str()))
+}
@@ -0,0 +1,101 @@
+use redstone_compiler::{
+    backend::{compile_program_with_debug_info, Cache, CompileOptions, DebugInfo, Instruction},
+    frontend::{tokenize, Ast, ExprId, ExpressionType, Parser, Range},
+    Error,
+};
+
+/// Everything the providers need from a successful compilation
+pub struct CompileOutput {
+    pub instructions: Vec<Instruction>,
+    /// Ranges of the top-level loops and conditionals
+    pub blocks: Vec<Range>,
+    /// Ranges of the statements that aren't blocks, the ones inside of blocks included
+    pub statements: Vec<Range>,
+    pub debug_info: DebugInfo,
+}
+
+impl CompileOutput {
+    /// One instruction per line
+    pub fn assembly(&self) -> String {
+        self.instructions
+            .iter()
+            .map(|instr| format!("{instr}\n"))
+            .collect()
+    }
+}
+
+/// Runs the whole pipeline on `code`
+pub fn analyze(code: &str, options: CompileOptions) -> Result<CompileOutput, Vec<Error>> {
+    let tokens = tokenize(code).map_err(|err| vec![err])?;
+    let ast = Parser::new().produce_ast(&tokens)?;
+    let (instructions, debug_info) = compile_program_with_debug_info(&ast, options)?;
+    Ok(CompileOutput {
+        instructions,
+        blocks: blocks(&ast),
+        statements: statements(&ast, ast.body()),
+        debug_info,
+    })
+}
+
+/// Like [`analyze`], only compiles the top-level statements that changed since the
+/// last time `cache` was used
+pub fn analyze_incremental(
+    code: &str,
+    options: CompileOptions,
+    cache: &mut Cache,
+) -> Result<CompileOutput, Vec<Error>> {
+    let tokens = tokenize(code).map_err(|err| vec![err])?;
+    let ast = Parser::new().produce_ast(&tokens)?;
+    let (instructions, debug_info) = cache.compile(&ast, &tokens, options)?;
+    Ok(CompileOutput {
+        instructions,
+        blocks: blocks(&ast),
+        statements: statements(&ast, ast.body()),
+        debug_info,
+    })
+}
+
+/// The syntax tree, if the code parses
+pub fn parse(code: &str) -> Option<Ast> {
+    let tokens = tokenize(code).ok()?;
+    Parser::new().produce_ast(&tokens).ok()
+}
+
+fn blocks(ast: &Ast) -> Vec<Range> {
+    ast.statements()
+        .filter(|statement| {
+            matches!(
+                statement.typ,
+                ExpressionType::Conditional { .. }
+                    | ExpressionType::EndlessLoop { .. }
+                    | ExpressionType::WhileLoop { .. }
+            )
+        })
+        .map(|statement| statement.location)
+        .collect()
+}
+
+fn statements(ast: &Ast, body: &[ExprId]) -> Vec<Range> {
+    body.iter()
+        .map(|&id| &ast[id])
+        .flat_map(|statement| match &statement.typ {
+            ExpressionType::Conditional {
+                body,
+                paths,
+                alternate,
+                ..
+            } => {
+                let mut inner = statements(ast, body);
+                for (_, body) in paths {
+                    inner.extend(statements(ast, body));
+                }
+                inner.extend(statements(ast, alternate.as_deref().unwrap_or_default()));
+                inner
+            }
+            ExpressionType::EndlessLoop { body } | ExpressionType::WhileLoop { body, .. } => {
+                statements(ast, body)
+            }
+            _ => vec![statement.location],
+        })
+        .collect()
+}
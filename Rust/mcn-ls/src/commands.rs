@@ -0,0 +1,55 @@
+use lsp_types::Range;
+use redstone_compiler::{backend::DebugInfo, emulator::StopReason};
+use ropey::Rope;
+use serde::Serialize;
+
+use crate::{analysis::CompileOutput, positions::to_lsp_range};
+
+pub const COMPILE: &str = "mcn.compile";
+/// Runs the program in the emulator, its output comes as [`Output`](crate::requests::Output)
+/// notifications
+pub const RUN: &str = "mcn.run";
+
+pub const ALL: [&str; 2] = [COMPILE, RUN];
+
+/// Result of [`COMPILE`]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompileArtifacts {
+    pub assembly: String,
+    pub binary: Vec<u16>,
+    /// Source range of every instruction, indexed by address
+    pub source_map: Vec<Range>,
+    /// To debug the binary later, its source map counts chars like the compiler
+    pub debug_info: DebugInfo,
+}
+
+impl CompileArtifacts {
+    pub fn new(output: &CompileOutput, text: &Rope) -> Self {
+        Self {
+            assembly: output.assembly(),
+            binary: output
+                .instructions
+                .iter()
+                .map(|instr| instr.to_bin())
+                .collect(),
+            source_map: output
+                .debug_info
+                .source_map
+                .locations()
+                .iter()
+                .map(|location| to_lsp_range(text, *location))
+                .collect(),
+            debug_info: output.debug_info.clone(),
+        }
+    }
+}
+
+/// Result of [`RUN`]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunResult {
+    pub stop_reason: StopReason,
+    pub cycles: u64,
+    pub ticks: u64,
+}
@@ -0,0 +1,113 @@
+//! Completions are kept small, the docs of a method are only attached once the client
+//! resolves its item
+
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Documentation, MarkupContent, MarkupKind, Position,
+};
+use redstone_compiler::{
+    backend::{module_doc, ModuleDoc, BUILTINS, MODULES},
+    frontend::KEYWORDS,
+};
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+
+use crate::{links::method_markdown, positions::char_index};
+
+/// What a method item remembers until it's resolved
+#[derive(Deserialize, Serialize)]
+struct MethodRef {
+    module: String,
+    method: String,
+}
+
+/// Members of a module after `module.`, modules after `use` and keywords and builtins
+/// everywhere else. Only looks at the line before the cursor, so it works while the
+/// program doesn't parse
+pub fn completions(text: &Rope, position: Position) -> Vec<CompletionItem> {
+    let end = char_index(text, position);
+    let start = text.line_to_char(text.char_to_line(end));
+    let before: String = text.slice(start..end).chars().collect();
+    let before = before.trim_start();
+    // the word that is being typed doesn't matter, the client filters by it
+    let typed = before
+        .rfind(|char: char| !(char.is_ascii_alphanumeric() || char == '_'))
+        .map_or("", |index| &before[..=index]);
+    if let Some(object) = typed.strip_suffix('.') {
+        let name_start = object
+            .rfind(|char: char| !(char.is_ascii_alphanumeric() || char == '_'))
+            .map_or(0, |index| index + 1);
+        return module_doc(&object[name_start..])
+            .map(members)
+            .unwrap_or_default();
+    }
+    if typed.trim_end() == "use" {
+        return MODULES
+            .iter()
+            .map(|module| CompletionItem {
+                label: module.name.to_string(),
+                kind: Some(CompletionItemKind::MODULE),
+                detail: Some(module.doc.to_string()),
+                ..CompletionItem::default()
+            })
+            .collect();
+    }
+    let keywords = KEYWORDS.iter().map(|keyword| CompletionItem {
+        label: (*keyword).to_string(),
+        kind: Some(CompletionItemKind::KEYWORD),
+        ..CompletionItem::default()
+    });
+    let builtins = BUILTINS.iter().map(|builtin| CompletionItem {
+        label: builtin.name.to_string(),
+        kind: Some(CompletionItemKind::FUNCTION),
+        detail: Some(format!("{}.{}", builtin.module, builtin.method)),
+        data: serde_json::to_value(MethodRef {
+            module: builtin.module.to_string(),
+            method: builtin.method.to_string(),
+        })
+        .ok(),
+        ..CompletionItem::default()
+    });
+    keywords.chain(builtins).collect()
+}
+
+fn members(module: &ModuleDoc) -> Vec<CompletionItem> {
+    let methods = module.methods.iter().map(|method| CompletionItem {
+        label: method.name.to_string(),
+        kind: Some(CompletionItemKind::METHOD),
+        detail: Some(method.signature()),
+        data: serde_json::to_value(MethodRef {
+            module: module.name.to_string(),
+            method: method.name.to_string(),
+        })
+        .ok(),
+        ..CompletionItem::default()
+    });
+    let constants = module.constants.iter().map(|constant| CompletionItem {
+        label: constant.name.to_string(),
+        kind: Some(CompletionItemKind::CONSTANT),
+        detail: Some(constant.value.to_string()),
+        ..CompletionItem::default()
+    });
+    methods.chain(constants).collect()
+}
+
+/// Attaches the docs and an example to the item of a method, other items stay as they are
+pub fn resolve(mut item: CompletionItem) -> CompletionItem {
+    let Some(method) = item
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<MethodRef>(data).ok())
+    else {
+        return item;
+    };
+    let Some(module) = module_doc(&method.module) else {
+        return item;
+    };
+    if let Some(method) = module.method(&method.method) {
+        item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: method_markdown(module, method),
+        }));
+    }
+    item
+}
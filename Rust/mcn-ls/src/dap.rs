@@ -0,0 +1,466 @@
+use std::collections::HashSet;
+
+use js_sys::Function;
+use redstone_compiler::{
+    backend::{CompileOptions, DebugInfo, Symbol, Target},
+    emulator::{Emulator, StopReason, PORT_COUNT},
+    frontend::{Location, Range},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    analysis::analyze,
+    js::{to_json_value, Callable},
+    settings::ServerSettings,
+};
+
+/// Cycles a `continue` or step may take before the program is paused,
+/// so a busy program can't block the worker forever
+const CYCLES_PER_REQUEST: u64 = 1_000_000;
+
+const THREAD_ID: u32 = 1;
+const VARIABLES_REFERENCE: u32 = 1;
+const REGISTERS_REFERENCE: u32 = 2;
+const PORTS_REFERENCE: u32 = 3;
+
+#[derive(Deserialize)]
+struct Request {
+    seq: i64,
+    command: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct InitializeArguments {
+    lines_start_at1: Option<bool>,
+    columns_start_at1: Option<bool>,
+}
+
+/// Arguments of `launch`, the host reads the program since the adapter has no file access.
+/// Either `source` is compiled or `binary` is loaded with its `debugInfo`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchArguments {
+    /// Path shown in stack frames
+    program: Option<String>,
+    source: Option<String>,
+    binary: Option<Vec<u16>>,
+    debug_info: Option<DebugInfo>,
+    #[serde(default)]
+    stop_on_entry: bool,
+    #[serde(default)]
+    settings: ServerSettings,
+}
+
+#[derive(Deserialize)]
+struct SourceBreakpoint {
+    line: u32,
+}
+
+#[derive(Deserialize)]
+struct SetBreakpointsArguments {
+    #[serde(default)]
+    breakpoints: Vec<SourceBreakpoint>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StepArguments {
+    granularity: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VariablesArguments {
+    variables_reference: u32,
+}
+
+/// A launched program
+struct Session {
+    emulator: Emulator,
+    program: Option<String>,
+    symbols: Vec<Symbol>,
+    stop_on_entry: bool,
+    /// Output writes already sent as output events
+    reported_outputs: usize,
+}
+
+/// Debug Adapter Protocol on top of the emulator. The host passes every message
+/// from the client to `handle_message` and forwards whatever `send_message` is called with
+#[wasm_bindgen]
+pub struct DebugAdapter {
+    send_message: Function,
+    seq: i64,
+    lines_start_at1: bool,
+    columns_start_at1: bool,
+    /// 0-based lines, kept for programs launched later
+    breakpoint_lines: Vec<u32>,
+    configured: bool,
+    session: Option<Session>,
+    /// Sent after the response to the current request
+    events: Vec<(&'static str, Value)>,
+}
+
+#[wasm_bindgen]
+impl DebugAdapter {
+    pub fn new(send_message: Function) -> Self {
+        Self {
+            send_message,
+            seq: 0,
+            lines_start_at1: true,
+            columns_start_at1: true,
+            breakpoint_lines: vec![],
+            configured: false,
+            session: None,
+            events: vec![],
+        }
+    }
+
+    pub fn handle_message(&mut self, message: JsValue) -> Result<(), JsValue> {
+        let request: Request = serde_wasm_bindgen::from_value(message)?;
+        let (success, message, body) = match self.dispatch(&request.command, request.arguments) {
+            Ok(body) => (true, None, body),
+            Err(message) => (false, Some(message), Value::Null),
+        };
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": success,
+            "command": request.command,
+            "message": message,
+            "body": body,
+        }))?;
+        for (event, body) in std::mem::take(&mut self.events) {
+            self.send(json!({ "type": "event", "event": event, "body": body }))?;
+        }
+        Ok(())
+    }
+}
+
+impl DebugAdapter {
+    fn dispatch(&mut self, command: &str, arguments: Value) -> Result<Value, String> {
+        match command {
+            "initialize" => self.initialize(parse(arguments)?),
+            "launch" => self.launch(parse(arguments)?),
+            "setBreakpoints" => self.set_breakpoints(parse(arguments)?),
+            "setExceptionBreakpoints" => Ok(json!({})),
+            "configurationDone" => {
+                self.configured = true;
+                self.start();
+                Ok(Value::Null)
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(json!({ "scopes": [
+                scope("Variables", VARIABLES_REFERENCE),
+                scope("Registers", REGISTERS_REFERENCE),
+                scope("Ports", PORTS_REFERENCE),
+            ]})),
+            "variables" => self.variables(&parse(arguments)?),
+            "continue" => {
+                let session = self.session()?;
+                let max_cycles = session.emulator.cycles() + CYCLES_PER_REQUEST;
+                let reason = session.emulator.run(max_cycles);
+                self.stopped(reason);
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            // there are no subroutines, so stepping in or out is the same as stepping over
+            "next" | "stepIn" | "stepOut" => {
+                let arguments: StepArguments = parse(arguments).unwrap_or_default();
+                let emulator = &mut self.session()?.emulator;
+                let reason = if arguments.granularity.as_deref() == Some("instruction") {
+                    emulator.step()
+                } else {
+                    emulator.step_statement(emulator.cycles() + CYCLES_PER_REQUEST)
+                };
+                match reason {
+                    Some(reason) => self.stopped(reason),
+                    None => self.stopped_event("step", None),
+                }
+                Ok(Value::Null)
+            }
+            // requests run to completion, so the program is always paused in between
+            "pause" => Ok(Value::Null),
+            "terminate" | "disconnect" => {
+                if self.session.take().is_some() {
+                    self.events.push(("terminated", json!({})));
+                }
+                Ok(Value::Null)
+            }
+            _ => Err(format!("Unsupported request {command}")),
+        }
+    }
+
+    fn initialize(&mut self, arguments: InitializeArguments) -> Result<Value, String> {
+        self.lines_start_at1 = arguments.lines_start_at1.unwrap_or(true);
+        self.columns_start_at1 = arguments.columns_start_at1.unwrap_or(true);
+        self.events.push(("initialized", json!({})));
+        Ok(json!({
+            "supportsConfigurationDoneRequest": true,
+            "supportsSteppingGranularity": true,
+            "supportsTerminateRequest": true,
+        }))
+    }
+
+    fn launch(&mut self, arguments: LaunchArguments) -> Result<Value, String> {
+        let (emulator, symbols) = match (arguments.source, arguments.binary) {
+            (Some(source), _) => {
+                // asserts are what a debugger is for
+                let options = CompileOptions {
+                    assertions: true,
+                    ..arguments.settings.compile_options()
+                };
+                let output = analyze(&source, options).map_err(|errors| {
+                    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                    messages.join("\n")
+                })?;
+                let emulator = Emulator::new(&output.instructions, options.target);
+                (emulator, output.debug_info.symbols)
+            }
+            (None, Some(binary)) => load_binary(&binary, arguments.debug_info)?,
+            (None, None) => return Err("Launch needs a source or a binary".to_string()),
+        };
+        self.session = Some(Session {
+            emulator,
+            program: arguments.program,
+            symbols,
+            stop_on_entry: arguments.stop_on_entry,
+            reported_outputs: 0,
+        });
+        self.apply_breakpoints();
+        self.start();
+        Ok(Value::Null)
+    }
+
+    /// Runs the program once it is launched and configured
+    fn start(&mut self) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        if !self.configured || session.emulator.cycles() > 0 {
+            return;
+        }
+        if session.stop_on_entry {
+            self.stopped_event("entry", None);
+        } else {
+            let reason = session.emulator.run(CYCLES_PER_REQUEST);
+            self.stopped(reason);
+        }
+    }
+
+    fn set_breakpoints(&mut self, arguments: SetBreakpointsArguments) -> Result<Value, String> {
+        let offset = u32::from(self.lines_start_at1);
+        self.breakpoint_lines = arguments
+            .breakpoints
+            .iter()
+            .map(|breakpoint| breakpoint.line.saturating_sub(offset))
+            .collect();
+        let verified = self.apply_breakpoints();
+        let breakpoints: Vec<_> = arguments
+            .breakpoints
+            .iter()
+            .zip(verified)
+            .map(|(breakpoint, verified)| json!({ "verified": verified, "line": breakpoint.line }))
+            .collect();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /// Sets the breakpoints of the current program, returns which lines have code to break on
+    fn apply_breakpoints(&mut self) -> Vec<bool> {
+        let Some(session) = &mut self.session else {
+            return vec![true; self.breakpoint_lines.len()];
+        };
+        session.emulator.clear_breakpoints();
+        self.breakpoint_lines
+            .iter()
+            .map(|&line| {
+                let line = line as u16;
+                let range = Range(Location(line, 1), Location(line, u16::MAX));
+                !session.emulator.add_breakpoint_in(range).is_empty()
+            })
+            .collect()
+    }
+
+    fn stack_trace(&mut self) -> Result<Value, String> {
+        let lines_start_at1 = self.lines_start_at1;
+        let columns_start_at1 = self.columns_start_at1;
+        let session = self.session()?;
+        let location = session.emulator.current_location();
+        let (line, column) = location.map_or((0, 0), |Range(start, _)| {
+            (
+                u32::from(start.0) + u32::from(lines_start_at1),
+                u32::from(start.1).saturating_sub(u32::from(!columns_start_at1)),
+            )
+        });
+        let name = session
+            .program
+            .as_deref()
+            .and_then(|path| path.rsplit(['/', '\\']).next())
+            .unwrap_or("program");
+        Ok(json!({
+            "stackFrames": [{
+                "id": 1,
+                "name": "main",
+                "line": line,
+                "column": column,
+                "source": { "name": name, "path": session.program },
+                "instructionPointerReference": session.emulator.state().pc.to_string(),
+            }],
+            "totalFrames": 1,
+        }))
+    }
+
+    fn variables(&mut self, arguments: &VariablesArguments) -> Result<Value, String> {
+        let session = self.session()?;
+        let state = session.emulator.state();
+        let variable = |name: String, value: String| json!({ "name": name, "value": value, "variablesReference": 0 });
+        let variables: Vec<_> =
+            match arguments.variables_reference {
+                VARIABLES_REFERENCE => {
+                    // a name declared in several blocks is shown with its first slot
+                    let mut seen = HashSet::new();
+                    session
+                        .symbols
+                        .iter()
+                        .filter(|symbol| seen.insert(&symbol.name))
+                        .map(|symbol| {
+                            variable(
+                                symbol.name.clone(),
+                                state.slots[symbol.slot as usize].to_string(),
+                            )
+                        })
+                        .collect()
+                }
+                REGISTERS_REFERENCE => vec![
+                    variable("A".to_string(), state.a.to_string()),
+                    variable("B".to_string(), state.b.to_string()),
+                    variable("C".to_string(), state.c.to_string()),
+                    variable("PC".to_string(), state.pc.to_string()),
+                    variable("RAM page".to_string(), state.ram_page.to_string()),
+                    variable("cycles".to_string(), state.cycles.to_string()),
+                ],
+                PORTS_REFERENCE => (0..PORT_COUNT)
+                    .map(|port| variable(format!("in {port}"), state.inputs[port].to_string()))
+                    .chain((0..PORT_COUNT).map(|port| {
+                        variable(format!("out {port}"), state.outputs[port].to_string())
+                    }))
+                    .collect(),
+                _ => vec![],
+            };
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// Queues the events for why the program stopped and what it wrote since the last time
+    fn stopped(&mut self, reason: StopReason) {
+        if let Some(session) = &mut self.session {
+            let writes = &session.emulator.output_log()[session.reported_outputs..];
+            session.reported_outputs += writes.len();
+            let output: String = writes
+                .iter()
+                .map(|write| format!("out {}: {}\n", write.port, write.value))
+                .collect();
+            if !output.is_empty() {
+                self.events
+                    .push(("output", json!({ "category": "stdout", "output": output })));
+            }
+        }
+        let halted_in_code = self
+            .session
+            .as_ref()
+            .is_some_and(|session| session.emulator.current_location().is_some());
+        match reason {
+            // the compiler only halts for asserts and todos
+            StopReason::Halted if halted_in_code => {
+                self.stopped_event(
+                    "exception",
+                    Some("Assertion failed or unfinished code reached".to_string()),
+                );
+            }
+            StopReason::Halted | StopReason::EndOfProgram => {
+                self.events.push(("exited", json!({ "exitCode": 0 })));
+                self.events.push(("terminated", json!({})));
+            }
+            StopReason::Breakpoint(_) => self.stopped_event("breakpoint", None),
+            StopReason::Watchpoint(hit) => self.stopped_event(
+                "data breakpoint",
+                Some(format!(
+                    "{:?} changed from {} to {}",
+                    hit.watch, hit.old, hit.new
+                )),
+            ),
+            StopReason::CycleLimit => self.stopped_event(
+                "pause",
+                Some(format!("Paused after {CYCLES_PER_REQUEST} cycles")),
+            ),
+            StopReason::PossibleInfiniteLoop(_) => {
+                self.stopped_event("pause", Some("Stuck in an infinite loop".to_string()));
+            }
+            StopReason::InvalidInstruction(address) => self.stopped_event(
+                "exception",
+                Some(format!("Invalid instruction at address {address}")),
+            ),
+        }
+    }
+
+    fn stopped_event(&mut self, reason: &str, text: Option<String>) {
+        self.events.push((
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": text,
+                "text": text,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        ));
+    }
+
+    fn session(&mut self) -> Result<&mut Session, String> {
+        self.session
+            .as_mut()
+            .ok_or_else(|| "No program is running".to_string())
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<(), JsValue> {
+        self.seq += 1;
+        message["seq"] = self.seq.into();
+        self.send_message.call_1(&to_json_value(&message)?)?;
+        Ok(())
+    }
+}
+
+/// Emulator for an encoded program and the variables from its debug info
+fn load_binary(
+    binary: &[u16],
+    debug_info: Option<DebugInfo>,
+) -> Result<(Emulator, Vec<Symbol>), String> {
+    let target = debug_info
+        .as_ref()
+        .and_then(DebugInfo::target)
+        .unwrap_or(&Target::MCN_16);
+    let mut emulator = Emulator::from_binary(binary, *target).map_err(|invalid| {
+        format!(
+            "Word {:#06x} at address {} isn't an instruction",
+            invalid.word, invalid.address
+        )
+    })?;
+    let Some(debug_info) = debug_info else {
+        return Ok((emulator, vec![]));
+    };
+    if !emulator.load_debug_info(&debug_info) {
+        return Err("The debug info belongs to another binary or compiler version".to_string());
+    }
+    Ok((emulator, debug_info.symbols))
+}
+
+fn scope(name: &str, variables_reference: u32) -> Value {
+    json!({ "name": name, "variablesReference": variables_reference, "expensive": false })
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(arguments: Value) -> Result<T, String> {
+    serde_json::from_value(arguments).map_err(|error| error.to_string())
+}
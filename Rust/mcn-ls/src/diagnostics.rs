@@ -0,0 +1,71 @@
+use lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag, Location,
+    NumberOrString, Url,
+};
+use redstone_compiler::{
+    backend::{Warning, WarningType},
+    Error, ErrorType, Related,
+};
+use ropey::Rope;
+
+use crate::{positions::to_lsp_range, settings::WarningLevel};
+
+/// The related locations of the error need the `uri` of the document it's in
+pub fn to_diagnostic(error: &Error, text: &Rope, uri: Option<&Url>) -> Diagnostic {
+    let related_information = related_information(&error.related, text, uri);
+    let fixes = error.typ.fixes();
+    Diagnostic {
+        range: to_lsp_range(text, error.location),
+        severity: Some(DiagnosticSeverity::ERROR),
+        code: Some(NumberOrString::String(error.typ.get_code().to_string())),
+        source: Some("mcn".to_string()),
+        message: error.typ.get_message(),
+        related_information,
+        // the quick fixes are built from them
+        data: (!fixes.is_empty())
+            .then(|| serde_json::to_value(fixes).ok())
+            .flatten(),
+        ..Diagnostic::default()
+    }
+}
+
+/// `None` if warnings are ignored. Unreachable code is marked, so editors can fade it
+pub fn warning_to_diagnostic(
+    warning: &Warning,
+    text: &Rope,
+    uri: Option<&Url>,
+    level: WarningLevel,
+) -> Option<Diagnostic> {
+    let severity = match level {
+        WarningLevel::Ignore => return None,
+        WarningLevel::Warn => DiagnosticSeverity::WARNING,
+        WarningLevel::Error => DiagnosticSeverity::ERROR,
+    };
+    Some(Diagnostic {
+        range: to_lsp_range(text, warning.location),
+        severity: Some(severity),
+        code: Some(NumberOrString::String(warning.typ.get_code().to_string())),
+        source: Some("mcn".to_string()),
+        message: warning.typ.get_message(),
+        related_information: related_information(&warning.related, text, uri),
+        tags: (warning.typ == WarningType::Unreachable).then(|| vec![DiagnosticTag::UNNECESSARY]),
+        ..Diagnostic::default()
+    })
+}
+
+fn related_information(
+    related: &[Related],
+    text: &Rope,
+    uri: Option<&Url>,
+) -> Option<Vec<DiagnosticRelatedInformation>> {
+    let uri = uri.filter(|_| !related.is_empty())?;
+    Some(
+        related
+            .iter()
+            .map(|related| DiagnosticRelatedInformation {
+                location: Location::new(uri.clone(), to_lsp_range(text, related.location)),
+                message: related.message.clone(),
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,224 @@
+use std::borrow::Cow;
+
+use lsp_types::{Diagnostic, TextDocumentContentChangeEvent, Url};
+use redstone_compiler::backend::{Cache, CompileOptions};
+use ropey::Rope;
+
+use crate::{
+    analysis::{analyze_incremental, CompileOutput},
+    diagnostics::{to_diagnostic, warning_to_diagnostic},
+    positions::char_index,
+    settings::WarningLevel,
+};
+
+pub struct DocumentState {
+    text: Rope,
+    version: i32,
+    /// Timestamp of the last change in milliseconds
+    changed_at: f64,
+    /// Version the diagnostics and output belong to
+    compiled_version: Option<i32>,
+    dirty: bool,
+    failed: bool,
+    diagnostics: Vec<Diagnostic>,
+    last_output: Option<CompileOutput>,
+    /// Code of the top-level statements, so a keystroke only compiles the one it's in
+    cache: Cache,
+}
+
+impl DocumentState {
+    pub fn new(text: &str, version: i32) -> Self {
+        Self {
+            text: Rope::from_str(text),
+            version,
+            changed_at: 0.0,
+            compiled_version: None,
+            dirty: true,
+            failed: false,
+            diagnostics: vec![],
+            last_output: None,
+            cache: Cache::new(),
+        }
+    }
+
+    /// Borrows the text if it's stored in one piece
+    pub fn text(&self) -> Cow<'_, str> {
+        (&self.text).into()
+    }
+
+    pub const fn rope(&self) -> &Rope {
+        &self.text
+    }
+
+    pub const fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Records the version and time after a batch of changes was applied
+    pub fn set_version(&mut self, version: i32, changed_at: f64) {
+        self.version = version;
+        self.changed_at = changed_at;
+    }
+
+    pub const fn changed_at(&self) -> f64 {
+        self.changed_at
+    }
+
+    /// Version of the last compilation, `None` if the document was never compiled
+    pub const fn compiled_version(&self) -> Option<i32> {
+        self.compiled_version
+    }
+
+    /// Top-level statements the last compilation could reuse
+    pub const fn reused(&self) -> usize {
+        self.cache.reused()
+    }
+
+    pub const fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Applies a single content change, either replacing a range or the whole text
+    pub fn apply_change(&mut self, change: TextDocumentContentChangeEvent) {
+        match change.range {
+            Some(range) => {
+                let start = char_index(&self.text, range.start);
+                let end = char_index(&self.text, range.end).max(start);
+                self.text.remove(start..end);
+                self.text.insert(start, &change.text);
+            }
+            None => self.text = Rope::from_str(&change.text),
+        }
+        self.dirty = true;
+    }
+
+    /// Forces the next compilation, e.g. after the settings changed
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Recompiles the document if it changed since the last compilation,
+    /// returns whether it did. `uri` is where the document is, for the related
+    /// locations of its errors
+    pub fn compile(&mut self, uri: &Url, options: CompileOptions) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        self.dirty = false;
+        self.compiled_version = Some(self.version);
+        let text: Cow<'_, str> = (&self.text).into();
+        match analyze_incremental(&text, options, &mut self.cache) {
+            Ok(output) => {
+                self.failed = false;
+                self.diagnostics = vec![];
+                self.last_output = Some(output);
+            }
+            Err(errors) => {
+                self.failed = true;
+                self.diagnostics = errors
+                    .iter()
+                    .map(|error| to_diagnostic(error, &self.text, Some(uri)))
+                    .collect();
+            }
+        }
+        true
+    }
+
+    /// The errors, or the warnings if there are none. `uri` is where the document is,
+    /// for the related locations of its warnings
+    pub fn diagnostics(&self, uri: &Url, warnings: WarningLevel) -> Vec<Diagnostic> {
+        let Some(output) = self.output() else {
+            return self.diagnostics.clone();
+        };
+        output
+            .debug_info
+            .warnings
+            .iter()
+            .filter_map(|warning| warning_to_diagnostic(warning, &self.text, Some(uri), warnings))
+            .collect()
+    }
+
+    /// Output of the current text, if it compiled
+    pub const fn output(&self) -> Option<&CompileOutput> {
+        if self.failed {
+            None
+        } else {
+            self.last_output.as_ref()
+        }
+    }
+
+    /// Output of the last compilation that succeeded, which may be older than the text
+    pub const fn last_output(&self) -> Option<&CompileOutput> {
+        self.last_output.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{Position, Range, TextDocumentContentChangeEvent};
+    use redstone_compiler::backend::CompileOptions;
+
+    use super::DocumentState;
+
+    fn change(
+        range: Option<((u32, u32), (u32, u32))>,
+        text: &str,
+    ) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: range.map(|(start, end)| {
+                Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+            }),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn ranged_edits() {
+        let mut document = DocumentState::new("a = 1\nb = 2\n", 1);
+        document.apply_change(change(Some(((0, 4), (0, 5))), "10"));
+        document.apply_change(change(Some(((1, 0), (1, 1))), "c"));
+        assert_eq!(document.text(), "a = 10\nc = 2\n");
+        // across lines
+        document.apply_change(change(Some(((0, 5), (1, 3))), "3"));
+        assert_eq!(document.text(), "a = 13 2\n");
+        // past the end of a line only goes up to its end
+        document.apply_change(change(Some(((0, 6), (0, 100))), ""));
+        assert_eq!(document.text(), "a = 13\n");
+    }
+
+    #[test]
+    fn full_replacement() {
+        let mut document = DocumentState::new("a = 1", 1);
+        let uri = "file:///a.mcn".parse().expect("Valid uri");
+        document.compile(&uri, CompileOptions::default());
+        assert!(!document.is_dirty());
+        document.apply_change(change(None, "b = 2\nc = 3"));
+        assert_eq!(document.text(), "b = 2\nc = 3");
+        assert!(document.is_dirty());
+    }
+
+    #[test]
+    fn edits_at_the_end() {
+        let mut document = DocumentState::new("a = 1\n", 1);
+        document.apply_change(change(Some(((1, 0), (1, 0))), "b = 2"));
+        assert_eq!(document.text(), "a = 1\nb = 2");
+        // lines after the last one are the end of the text
+        document.apply_change(change(Some(((5, 0), (5, 0))), "\n"));
+        assert_eq!(document.text(), "a = 1\nb = 2\n");
+        document.apply_change(change(Some(((1, 5), (2, 0))), ""));
+        assert_eq!(document.text(), "a = 1\nb = 2");
+    }
+
+    #[test]
+    fn multi_byte_text() {
+        // é is one UTF-16 code unit, 𝄞 is two
+        let mut document = DocumentState::new("# é𝄞x\na = 1", 1);
+        document.apply_change(change(Some(((0, 2), (0, 3))), "e"));
+        assert_eq!(document.text(), "# e𝄞x\na = 1");
+        document.apply_change(change(Some(((0, 3), (0, 5))), "ü"));
+        assert_eq!(document.text(), "# eüx\na = 1");
+        document.apply_change(change(Some(((0, 5), (1, 1))), "ñ"));
+        assert_eq!(document.text(), "# eüxñ = 1");
+    }
+}
@@ -0,0 +1,230 @@
+use js_sys::Function;
+use lsp_types::Range;
+use redstone_compiler::{
+    backend::{DebugInfo, Instruction, Target},
+    emulator::{Emulator, OutputWrite, State},
+    frontend::{self, Location},
+};
+use ropey::Rope;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    analysis::analyze, diagnostics::to_diagnostic, js::to_json_value, positions::to_lsp_range,
+    settings::ServerSettings,
+};
+
+/// Debugging session for the editor, holds the emulator of the loaded program
+#[wasm_bindgen(js_name = Emulator)]
+#[derive(Default)]
+pub struct EmulatorSession {
+    emulator: Option<Emulator>,
+    /// Source of the loaded program, empty for binaries
+    text: Rope,
+    on_input: Option<Function>,
+    on_output: Option<Function>,
+}
+
+/// Result of [`EmulatorSession::state`]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StateDump<'a> {
+    #[serde(flatten)]
+    state: &'a State,
+    ticks: u64,
+    /// Code of the next instruction
+    location: Option<Range>,
+    instruction: Option<String>,
+}
+
+#[wasm_bindgen(js_class = Emulator)]
+impl EmulatorSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compiles and loads `code`, `options` are shaped like the `mcn` settings.
+    /// Returns the diagnostics, the previous program stays loaded if there are any.
+    /// Breakpoints and watches don't carry over to the new program
+    pub fn load(&mut self, code: &str, options: JsValue) -> Result<JsValue, JsError> {
+        let settings: ServerSettings = if options.is_undefined() || options.is_null() {
+            ServerSettings::default()
+        } else {
+            serde_wasm_bindgen::from_value(options)?
+        };
+        let text = Rope::from_str(code);
+        let options = settings.compile_options();
+        let diagnostics: Vec<_> = match analyze(code, options) {
+            Ok(output) => {
+                self.emulator = Some(Emulator::new(&output.instructions, options.target));
+                self.text = text;
+                self.attach_handlers();
+                vec![]
+            }
+            Err(errors) => errors
+                .iter()
+                .map(|error| to_diagnostic(error, &text, None))
+                .collect(),
+        };
+        Ok(to_json_value(&diagnostics)?)
+    }
+
+    /// Loads an encoded program, one instruction per word. `debug_info` is the JSON
+    /// saved with the binary, without it there are no locations to break on
+    pub fn load_binary(
+        &mut self,
+        words: &[u16],
+        debug_info: Option<String>,
+    ) -> Result<(), JsError> {
+        let debug_info: Option<DebugInfo> = debug_info
+            .map(|json| serde_json::from_str(&json))
+            .transpose()?;
+        let target = debug_info
+            .as_ref()
+            .and_then(DebugInfo::target)
+            .unwrap_or(&Target::MCN_16);
+        let mut emulator = Emulator::from_binary(words, *target).map_err(|invalid| {
+            JsError::new(&format!(
+                "Word {:#06x} at address {} isn't an instruction",
+                invalid.word, invalid.address
+            ))
+        })?;
+        if let Some(debug_info) = &debug_info {
+            if !emulator.load_debug_info(debug_info) {
+                return Err(JsError::new(
+                    "The debug info belongs to another binary or compiler version",
+                ));
+            }
+        }
+        self.emulator = Some(emulator);
+        self.text = Rope::new();
+        self.attach_handlers();
+        Ok(())
+    }
+
+    /// Executes one instruction, returns why the program stopped or null
+    pub fn step(&mut self) -> Result<JsValue, JsError> {
+        let reason = self.emulator()?.step();
+        Ok(to_json_value(&reason)?)
+    }
+
+    /// Executes the rest of the line, returns why the program stopped early or null
+    pub fn step_statement(&mut self, max_cycles: u32) -> Result<JsValue, JsError> {
+        let emulator = self.emulator()?;
+        let reason = emulator.step_statement(emulator.cycles() + u64::from(max_cycles));
+        Ok(to_json_value(&reason)?)
+    }
+
+    /// Runs for at most `max_cycles` more cycles, returns why it stopped
+    pub fn run(&mut self, max_cycles: u32) -> Result<JsValue, JsError> {
+        let emulator = self.emulator()?;
+        let reason = emulator.run(emulator.cycles() + u64::from(max_cycles));
+        Ok(to_json_value(&reason)?)
+    }
+
+    /// Starts the program over, keeps inputs and breakpoints
+    pub fn reset(&mut self) -> Result<(), JsError> {
+        self.emulator()?.reset();
+        Ok(())
+    }
+
+    pub fn set_input(&mut self, port: u8, value: i16) -> Result<(), JsError> {
+        self.emulator()?.set_input(port, value);
+        Ok(())
+    }
+
+    /// Last value written to each output port
+    pub fn read_outputs(&self) -> Result<Vec<i16>, JsError> {
+        let emulator = self.emulator.as_ref().ok_or_else(not_loaded)?;
+        Ok(emulator.state().outputs.to_vec())
+    }
+
+    /// See [`set_js_input_handler`], applies to programs loaded later too
+    pub fn set_input_handler(&mut self, on_input: Function) {
+        self.on_input = Some(on_input);
+        self.attach_handlers();
+    }
+
+    /// See [`set_js_output_handler`], applies to programs loaded later too
+    pub fn set_output_handler(&mut self, on_output: Function) {
+        self.on_output = Some(on_output);
+        self.attach_handlers();
+    }
+
+    /// Breaks where execution enters the 0-based `line`, returns the addresses it breaks at
+    pub fn set_breakpoint(&mut self, line: u32) -> Result<Vec<usize>, JsError> {
+        let line = line as u16;
+        let range = frontend::Range(Location(line, 1), Location(line, u16::MAX));
+        Ok(self.emulator()?.add_breakpoint_in(range))
+    }
+
+    pub fn clear_breakpoints(&mut self) -> Result<(), JsError> {
+        self.emulator()?.clear_breakpoints();
+        Ok(())
+    }
+
+    /// Registers, slots, ports and the next instruction with its location
+    pub fn state(&self) -> Result<JsValue, JsError> {
+        let emulator = self.emulator.as_ref().ok_or_else(not_loaded)?;
+        let dump = StateDump {
+            state: emulator.state(),
+            ticks: emulator.ticks(),
+            location: emulator
+                .current_location()
+                .map(|location| to_lsp_range(&self.text, location)),
+            instruction: emulator.current_instruction().map(|(variant, arg)| {
+                let arg = variant.has_arg().then_some(arg);
+                format!(
+                    "{}",
+                    Instruction::new(variant, arg, frontend::Range::default())
+                )
+            }),
+        };
+        Ok(to_json_value(&dump)?)
+    }
+}
+
+impl EmulatorSession {
+    fn emulator(&mut self) -> Result<&mut Emulator, JsError> {
+        self.emulator.as_mut().ok_or_else(not_loaded)
+    }
+
+    fn attach_handlers(&mut self) {
+        let Some(emulator) = &mut self.emulator else {
+            return;
+        };
+        if let Some(on_input) = &self.on_input {
+            set_js_input_handler(emulator, on_input.clone());
+        }
+        if let Some(on_output) = &self.on_output {
+            set_js_output_handler(emulator, on_output.clone());
+        }
+    }
+}
+
+fn not_loaded() -> JsError {
+    JsError::new("No program is loaded")
+}
+
+/// Asks `on_input(port)` for input values, anything but a number keeps the current one
+pub fn set_js_input_handler(emulator: &mut Emulator, on_input: Function) {
+    emulator.on_input(move |port| {
+        on_input
+            .call1(&JsValue::NULL, &port.into())
+            .ok()
+            .and_then(|value| value.as_f64())
+            .map(|value| value as i16)
+    });
+}
+
+/// Calls `on_output(port, value, cycle)` for every output write, exceptions are ignored
+pub fn set_js_output_handler(emulator: &mut Emulator, on_output: Function) {
+    emulator.on_output(move |write: OutputWrite| {
+        let _ = on_output.call3(
+            &JsValue::NULL,
+            &write.port.into(),
+            &write.value.into(),
+            &(write.cycle as f64).into(),
+        );
+    });
+}
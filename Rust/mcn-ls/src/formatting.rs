@@ -0,0 +1,130 @@
+use lsp_types::{FormattingOptions, Position, Range, TextEdit};
+use redstone_compiler::frontend::{tokenize, TokenType};
+
+/// Used where the editor doesn't say how wide an indent is
+pub const DEFAULT_INDENT_WIDTH: u32 = 4;
+
+/// Indents the line after a block header and closes the block with `end` if it isn't already.
+/// Always indents with spaces, since the lexer rejects tabs
+pub fn on_type_formatting(
+    text: &str,
+    position: Position,
+    options: &FormattingOptions,
+) -> Vec<TextEdit> {
+    let lines: Vec<_> = text
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    let line_index = position.line as usize;
+    let (Some(header), Some(current)) = (
+        line_index.checked_sub(1).and_then(|i| lines.get(i)),
+        lines.get(line_index),
+    ) else {
+        return vec![];
+    };
+
+    let opens_block = match header_keyword(header) {
+        Some(TokenType::If | TokenType::While | TokenType::Forever) => true,
+        Some(TokenType::Elif | TokenType::Else) => false,
+        _ => return vec![],
+    };
+
+    let header_indent = indentation(header);
+    let current_indent = indentation(current);
+    let body_indent = " ".repeat(header_indent + options.tab_size as usize);
+    let mut edits = vec![TextEdit {
+        range: Range::new(
+            Position::new(position.line, 0),
+            Position::new(position.line, current_indent as u32),
+        ),
+        new_text: body_indent,
+    }];
+
+    if opens_block && is_missing_end(text) {
+        let end = format!("{}end", " ".repeat(header_indent));
+        edits.push(if line_index + 1 < lines.len() {
+            TextEdit {
+                range: Range::new(
+                    Position::new(position.line + 1, 0),
+                    Position::new(position.line + 1, 0),
+                ),
+                new_text: end + "\n",
+            }
+        } else {
+            let line_end = Position::new(position.line, current.encode_utf16().count() as u32);
+            TextEdit {
+                range: Range::new(line_end, line_end),
+                new_text: "\n".to_string() + &end,
+            }
+        })
+    }
+    edits
+}
+
+/// Reindents every line by its block depth and strips trailing whitespace,
+/// lines that don't tokenize on their own are left alone
+pub fn format_document(text: &str, indent_width: usize) -> String {
+    let mut depth: usize = 0;
+    let lines: Vec<_> = text
+        .split('\n')
+        .map(|line| {
+            let (content, line_ending) = match line.strip_suffix('\r') {
+                Some(content) => (content, "\r"),
+                None => (line, ""),
+            };
+            let trimmed = content.trim();
+            let Ok(tokens) = tokenize(trimmed) else {
+                return line.to_string();
+            };
+            let dedent = matches!(
+                tokens.first().map(|token| &token.typ),
+                Some(TokenType::End | TokenType::Elif | TokenType::Else)
+            );
+            let line_depth = if dedent {
+                depth.saturating_sub(1)
+            } else {
+                depth
+            };
+            for token in &tokens {
+                match token.typ {
+                    TokenType::If | TokenType::While | TokenType::Forever => depth += 1,
+                    TokenType::End => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            if trimmed.is_empty() {
+                return line_ending.to_string();
+            }
+            format!(
+                "{}{trimmed}{line_ending}",
+                " ".repeat(line_depth * indent_width)
+            )
+        })
+        .collect();
+    lines.join("\n")
+}
+
+fn header_keyword(line: &str) -> Option<TokenType<'_>> {
+    tokenize(line)
+        .ok()?
+        .into_iter()
+        .next()
+        .map(|token| token.typ)
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Whether the document opens more blocks than it closes
+fn is_missing_end(text: &str) -> bool {
+    let Ok(tokens) = tokenize(text) else {
+        return false;
+    };
+    let balance = tokens.iter().fold(0, |balance, token| match token.typ {
+        TokenType::If | TokenType::While | TokenType::Forever => balance + 1,
+        TokenType::End => balance - 1,
+        _ => balance,
+    });
+    balance > 0
+}
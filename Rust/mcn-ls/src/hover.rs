@@ -0,0 +1,73 @@
+use std::fmt::Write;
+
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind};
+use redstone_compiler::{
+    backend::Timing,
+    frontend::{Ast, ExprId, ExpressionType, Location, Range},
+};
+use ropey::Rope;
+
+use crate::{analysis::CompileOutput, positions::to_lsp_range};
+
+/// The instructions of the innermost statement at `location` and what they cost
+pub fn hover(
+    ast: &Ast,
+    output: &CompileOutput,
+    location: Location,
+    timing: Timing,
+    text: &Rope,
+) -> Option<Hover> {
+    let statement = statement_at(ast, ast.body(), location)?;
+    let addresses: Vec<_> = output
+        .debug_info
+        .source_map
+        .addresses_in(statement)
+        .collect();
+    let cycles: u32 = addresses
+        .iter()
+        .map(|&address| u32::from(timing.cycles(output.instructions[address].variant)))
+        .sum();
+    let plural = if addresses.len() == 1 { "" } else { "s" };
+    let mut value = format!("{} instruction{plural}, ~{cycles} cycles", addresses.len());
+    if !addresses.is_empty() {
+        value.push_str("\n```\n");
+        for address in addresses {
+            let _ = writeln!(value, "{address:>3}: {}", output.instructions[address]);
+        }
+        value.push_str("```");
+    }
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(to_lsp_range(text, statement)),
+    })
+}
+
+/// Blocks are searched for a statement inside of them before the whole block is taken
+fn statement_at(ast: &Ast, body: &[ExprId], location: Location) -> Option<Range> {
+    let statement = body
+        .iter()
+        .map(|&id| &ast[id])
+        .find(|statement| statement.location.contains(location))?;
+    let inner = match &statement.typ {
+        ExpressionType::Conditional {
+            body,
+            paths,
+            alternate,
+            ..
+        } => statement_at(ast, body, location)
+            .or_else(|| {
+                paths
+                    .iter()
+                    .find_map(|(_, body)| statement_at(ast, body, location))
+            })
+            .or_else(|| statement_at(ast, alternate.as_deref()?, location)),
+        ExpressionType::EndlessLoop { body } | ExpressionType::WhileLoop { body, .. } => {
+            statement_at(ast, body, location)
+        }
+        _ => None,
+    };
+    Some(inner.unwrap_or(statement.location))
+}
@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use lsp_types::{InlayHint, InlayHintLabel, Position, Range};
+use redstone_compiler::{
+    backend::{ComputerState, RegisterContents},
+    frontend::Location,
+};
+use ropey::Rope;
+
+use crate::{analysis::CompileOutput, positions::to_position, settings::InlayHintSettings};
+
+/// The hints `settings` turns on that are inside of `range`
+pub fn inlay_hints(
+    output: &CompileOutput,
+    settings: InlayHintSettings,
+    text: &Rope,
+    range: Range,
+) -> Vec<InlayHint> {
+    let mut hints = vec![];
+    if settings.variable_slots {
+        hints.extend(output.debug_info.symbols.iter().map(|symbol| {
+            let end = symbol.location.1;
+            hint(
+                to_position(text, end.0, end.1.into()),
+                format!("slot {}", symbol.slot),
+            )
+        }));
+    }
+    if settings.register_contents {
+        hints.extend(
+            a_after_statements(output)
+                .into_iter()
+                .filter_map(|(position, contents)| {
+                    let value = match contents? {
+                        RegisterContents::Number(number) => number.to_string(),
+                        RegisterContents::Variable(slot) => variable_name(output, slot)?,
+                        _ => return None,
+                    };
+                    Some(hint(
+                        to_position(text, position.0, position.1.into()),
+                        format!("A = {value}"),
+                    ))
+                }),
+        );
+    }
+    hints.retain(|hint| range.start <= hint.position && hint.position <= range.end);
+    hints
+}
+
+fn hint(position: Position, label: String) -> InlayHint {
+    InlayHint {
+        position,
+        label: InlayHintLabel::String(label),
+        kind: None,
+        text_edits: None,
+        tooltip: None,
+        padding_left: Some(true),
+        padding_right: None,
+        data: None,
+    }
+}
+
+/// Register A after the last instruction of every run of instructions that come from the
+/// end of a statement, by the end of the statement. Nothing is known where jumps land, and code that
+/// ends up with different contents in different places has `None`
+fn a_after_statements(output: &CompileOutput) -> BTreeMap<Location, Option<RegisterContents>> {
+    let targets: HashSet<_> = output.debug_info.jump_marks.iter().copied().collect();
+    let ends: BTreeSet<_> = output
+        .statements
+        .iter()
+        .map(|statement| statement.1)
+        .collect();
+    let locations = output.debug_info.source_map.locations();
+    let mut state = ComputerState::default();
+    let mut contents = BTreeMap::new();
+    for (address, instruction) in output.instructions.iter().enumerate() {
+        if targets.contains(&address) {
+            state = ComputerState::unknown();
+        }
+        instruction.execute(&mut state);
+        let Some(&location) = locations.get(address) else {
+            continue;
+        };
+        if locations.get(address + 1) == Some(&location) || !ends.contains(&location.1) {
+            continue;
+        }
+        contents
+            .entry(location.1)
+            .and_modify(|known: &mut Option<_>| {
+                if *known != Some(state.a) {
+                    *known = None;
+                }
+            })
+            .or_insert(Some(state.a));
+    }
+    contents
+}
+
+/// Slots are reused after a block, so only a slot that one name has is shown by that name
+fn variable_name(output: &CompileOutput, slot: u8) -> Option<String> {
+    let mut names = output
+        .debug_info
+        .symbols
+        .iter()
+        .filter(|symbol| symbol.slot == slot)
+        .map(|symbol| &symbol.name);
+    let name = names.next()?;
+    names.all(|other| other == name).then(|| name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{InlayHintLabel, Position, Range};
+    use redstone_compiler::backend::CompileOptions;
+    use ropey::Rope;
+
+    use super::inlay_hints;
+    use crate::{analysis::analyze, settings::InlayHintSettings};
+
+    fn labels(code: &str, settings: InlayHintSettings) -> Vec<(u32, u32, String)> {
+        let output = analyze(code, CompileOptions::default()).expect("code to compile");
+        let range = Range::new(Position::new(0, 0), Position::new(u32::MAX, 0));
+        inlay_hints(&output, settings, &Rope::from_str(code), range)
+            .into_iter()
+            .map(|hint| match hint.label {
+                InlayHintLabel::String(label) => {
+                    (hint.position.line, hint.position.character, label)
+                }
+                InlayHintLabel::LabelParts(_) => panic!("Expected a plain label"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn variable_slots() {
+        let settings = InlayHintSettings::default();
+        assert_eq!(
+            labels("a = 1\nb = a\n", settings),
+            [(0, 5, "slot 0".to_string()), (1, 5, "slot 1".to_string())]
+        );
+        let off = InlayHintSettings {
+            variable_slots: false,
+            ..settings
+        };
+        assert_eq!(labels("a = 1\n", off), []);
+    }
+
+    #[test]
+    fn register_contents() {
+        let settings = InlayHintSettings {
+            variable_slots: false,
+            register_contents: true,
+        };
+        // a stored value is still in A, so the next statement doesn't have to load it
+        assert_eq!(
+            labels("a = 5\nb = a\n", settings),
+            [(0, 5, "A = a".to_string()), (1, 5, "A = b".to_string())]
+        );
+        // nothing is known after the loop, the jump back lands in front of the condition
+        assert_eq!(
+            labels("a = 0\nwhile a < 3\n  a = a + 1\nend\nb = 2\n", settings),
+            [
+                (0, 5, "A = a".to_string()),
+                (2, 11, "A = a".to_string()),
+                (4, 5, "A = b".to_string())
+            ]
+        );
+    }
+}
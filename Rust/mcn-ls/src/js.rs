@@ -0,0 +1,128 @@
+use std::rc::Rc;
+
+use js_sys::Function;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::server::{Client, Server, ServerError, ServerResult};
+
+type JsResult<T = JsValue> = Result<T, JsError>;
+
+#[allow(dead_code)]
+pub trait Callable {
+    fn call_0(&self) -> Result<JsValue, JsValue>;
+    fn call_1(&self, arg1: &JsValue) -> Result<JsValue, JsValue>;
+    fn call_2(&self, arg1: &JsValue, arg2: &JsValue) -> Result<JsValue, JsValue>;
+    fn call_3(&self, arg1: &JsValue, arg2: &JsValue, arg3: &JsValue) -> Result<JsValue, JsValue>;
+}
+
+impl Callable for Function {
+    fn call_0(&self) -> Result<JsValue, JsValue> {
+        self.call0(&JsValue::UNDEFINED)
+    }
+
+    fn call_1(&self, arg1: &JsValue) -> Result<JsValue, JsValue> {
+        self.call1(&JsValue::UNDEFINED, arg1)
+    }
+
+    fn call_2(&self, arg1: &JsValue, arg2: &JsValue) -> Result<JsValue, JsValue> {
+        self.call2(&JsValue::UNDEFINED, arg1, arg2)
+    }
+
+    fn call_3(&self, arg1: &JsValue, arg2: &JsValue, arg3: &JsValue) -> Result<JsValue, JsValue> {
+        self.call3(&JsValue::UNDEFINED, arg1, arg2, arg3)
+    }
+}
+
+/// The host's callbacks, both are called with the method and the params
+struct JsClient {
+    send_notification: Function,
+    send_request: Function,
+}
+
+impl JsClient {
+    fn send(function: &Function, method: &str, params: &Value) -> ServerResult<()> {
+        let params = to_json_value(params)?;
+        function
+            .call_2(&method.into(), &params)
+            .map_err(|error| ServerError::new(format!("{error:?}")))?;
+        Ok(())
+    }
+}
+
+impl Client for JsClient {
+    fn send_notification(&self, method: &str, params: Value) -> ServerResult<()> {
+        Self::send(&self.send_notification, method, &params)
+    }
+
+    fn send_request(&self, method: &str, params: Value) -> ServerResult<()> {
+        Self::send(&self.send_request, method, &params)
+    }
+}
+
+impl From<ServerError> for JsError {
+    fn from(error: ServerError) -> Self {
+        Self::new(&error.message)
+    }
+}
+
+/// The language server for a host that passes the messages in and out, like a web worker
+#[wasm_bindgen]
+pub struct LspServer {
+    server: Server,
+}
+
+#[wasm_bindgen]
+impl LspServer {
+    pub fn new(send_notification: Function, send_request: Function) -> Self {
+        Self {
+            server: Server::new(Rc::new(JsClient {
+                send_notification,
+                send_request,
+            })),
+        }
+    }
+
+    pub fn initialize(&mut self, params: JsValue) -> JsResult<JsValue> {
+        let result = self
+            .server
+            .initialize(serde_wasm_bindgen::from_value(params)?)?;
+        Ok(to_json_value(&result)?)
+    }
+
+    /// Routes a request to its handler and serializes the response
+    pub fn handle_request(&mut self, method: &str, params: JsValue) -> JsResult<JsValue> {
+        let result = self
+            .server
+            .handle_request(method, serde_wasm_bindgen::from_value(params)?)?;
+        Ok(to_json_value(&result)?)
+    }
+
+    /// Routes a notification to its handler, unknown notifications are ignored
+    pub fn handle_notification(&mut self, method: &str, params: JsValue) -> JsResult<()> {
+        Ok(self
+            .server
+            .handle_notification(method, serde_wasm_bindgen::from_value(params)?)?)
+    }
+
+    /// Compiles with the options of an `mcn.toml`, or with the ones from the settings again
+    /// without one
+    pub fn set_manifest(&mut self, text: Option<String>) -> JsResult<()> {
+        Ok(self.server.set_manifest(text.as_deref())?)
+    }
+
+    /// Milliseconds until a document has been quiet long enough to compile it for diagnostics,
+    /// or until all documents have if no uri is given
+    pub fn diagnostic_delay(&self, uri: Option<String>) -> JsResult<f64> {
+        Ok(self.server.diagnostic_delay(uri.as_deref())?)
+    }
+}
+
+// Copied from: slint-ui/slint tools/lsp/wasm_main.rs
+// Credit: https://github.com/slint-ui/slint
+/// Use a JSON friendly representation to avoid using ES maps instead of JS objects.
+pub fn to_json_value<T: serde::Serialize + ?Sized>(
+    value: &T,
+) -> Result<JsValue, serde_wasm_bindgen::Error> {
+    value.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+}
@@ -2,14 +2,56 @@ extern crate cfg_if;
 extern crate redstone_compiler;
 extern crate wasm_bindgen;
 
+mod actions;
+mod analysis;
+mod commands;
+mod completion;
+mod dap;
+mod diagnostics;
+mod document;
+mod emulation;
+mod formatting;
+mod hover;
+mod inlay_hints;
+mod js;
 mod language;
+mod links;
+mod positions;
+mod progress;
+mod references;
+mod requests;
 mod server;
+mod settings;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stdio;
+mod tokens;
+mod trace;
 mod utils;
 
-use cfg_if::cfg_if;
+use analysis::analyze;
+use diagnostics::{to_diagnostic, warning_to_diagnostic};
+use emulation::{set_js_input_handler, set_js_output_handler};
+use formatting::{format_document, DEFAULT_INDENT_WIDTH};
+use js::to_json_value;
+use js_sys::Function;
+use lsp_types::Diagnostic;
+use redstone_compiler::{
+    backend::{self, CompileOptions, Target},
+    emulator::{Emulator, OutputWrite, StopReason},
+    export::{
+        json::program_json,
+        report::report,
+        schem::{self, Version},
+        Layout, Region,
+    },
+    frontend::{self, Ast, Parser, TokenType},
+};
+use ropey::Rope;
+use serde::Serialize;
+use settings::{FormatSettings, ServerSettings, WarningLevel};
+use tokens::{line_tokens, LineToken, TokenInfo};
 use wasm_bindgen::prelude::*;
 
-
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
@@ -26,20 +68,336 @@ pub fn greet(name: &str) {
     log(&format!("Hello, {}!", name));
 }
 
+/// Result of [`compile`], `assembly` is null if there are errors
+#[derive(Serialize)]
+struct CompileResult {
+    assembly: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[wasm_bindgen]
+pub fn compile(code: &str) -> Result<JsValue, JsError> {
+    compile_with(code, CompileOptions::default(), WarningLevel::default())
+}
+
+/// Like [`compile`], with options shaped like the `mcn` settings of the language server
+#[wasm_bindgen]
+pub fn compile_with_options(code: &str, options: JsValue) -> Result<JsValue, JsError> {
+    let settings: ServerSettings = serde_wasm_bindgen::from_value(options)?;
+    compile_with(code, settings.compile_options(), settings.warnings)
+}
+
+fn compile_with(
+    code: &str,
+    options: CompileOptions,
+    warnings: WarningLevel,
+) -> Result<JsValue, JsError> {
+    let result = match analyze(code, options) {
+        Ok(output) => {
+            let text = Rope::from_str(code);
+            CompileResult {
+                assembly: Some(output.assembly()),
+                diagnostics: output
+                    .debug_info
+                    .warnings
+                    .iter()
+                    .filter_map(|warning| warning_to_diagnostic(warning, &text, None, warnings))
+                    .collect(),
+            }
+        }
+        Err(errors) => {
+            let text = Rope::from_str(code);
+            CompileResult {
+                assembly: None,
+                diagnostics: errors
+                    .iter()
+                    .map(|error| to_diagnostic(error, &text, None))
+                    .collect(),
+            }
+        }
+    };
+    Ok(to_json_value(&result)?)
+}
+
+/// Token stream of `code` with the type, text and range of every token
+#[wasm_bindgen]
+pub fn tokenize(code: &str) -> Result<JsValue, JsError> {
+    let tokens = frontend::tokenize(code).map_err(|error| JsError::new(&error.to_string()))?;
+    let text = Rope::from_str(code);
+    let tokens: Vec<_> = tokens
+        .iter()
+        .filter(|token| token.typ != TokenType::Eof)
+        .map(|token| TokenInfo::new(token, &text))
+        .collect();
+    Ok(to_json_value(&tokens)?)
+}
+
+/// Result of [`parse`], `ast` is null if there are diagnostics
+#[derive(Serialize)]
+struct ParseResult {
+    ast: Option<Ast>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Syntax tree of `code`
+#[wasm_bindgen]
+pub fn parse(code: &str) -> Result<JsValue, JsError> {
+    let result = match frontend::tokenize(code)
+        .map_err(|error| vec![error])
+        .and_then(|tokens| Parser::new().produce_ast(&tokens))
+    {
+        Ok(ast) => ParseResult {
+            ast: Some(ast),
+            diagnostics: vec![],
+        },
+        Err(errors) => {
+            let text = Rope::from_str(code);
+            ParseResult {
+                ast: None,
+                diagnostics: errors
+                    .iter()
+                    .map(|error| to_diagnostic(error, &text, None))
+                    .collect(),
+            }
+        }
+    };
+    Ok(to_json_value(&result)?)
+}
+
+/// Encoded program, each instruction is a little-endian word
+#[wasm_bindgen(getter_with_clone)]
+pub struct Binary {
+    pub bytes: Vec<u8>,
+    pub instruction_count: usize,
+    pub page_count: usize,
+    /// CRC-32 of every page, to check a built ROM page by page
+    pub page_checksums: Vec<u32>,
+    /// CRC-32 of the whole program
+    pub checksum: u32,
+    /// JSON of the `DebugInfo`, to save next to the binary
+    pub debug_info: String,
+}
+
+#[wasm_bindgen]
+pub fn compile_to_binary(code: &str) -> Result<Binary, JsError> {
+    let options = CompileOptions::default();
+    let output = analyze(code, options).map_err(|errors| {
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        JsError::new(&messages.join("\n"))
+    })?;
+    let instructions = &output.instructions;
+    Ok(Binary {
+        bytes: instructions
+            .iter()
+            .flat_map(|instr| instr.to_bin().to_le_bytes())
+            .collect(),
+        instruction_count: instructions.len(),
+        page_count: instructions.len().div_ceil(options.target.page_size),
+        page_checksums: output.debug_info.checksums.pages.clone(),
+        checksum: output.debug_info.checksums.program,
+        debug_info: serde_json::to_string(&output.debug_info)?,
+    })
+}
+
+/// The [`report`] of `code`: pages, timing and jumps of every instruction, to draw its
+/// control flow
+#[wasm_bindgen]
+pub fn compile_report(code: &str) -> Result<JsValue, JsError> {
+    let options = CompileOptions::default();
+    let output = analyze(code, options).map_err(|errors| {
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        JsError::new(&messages.join("\n"))
+    })?;
+    Ok(to_json_value(&report(
+        &output.instructions,
+        &options.target,
+    ))?)
+}
+
+/// `code` compiled to the [`program_json`] format, the same as `mcnc --emit json`
+#[wasm_bindgen]
+pub fn compile_json(code: &str) -> Result<JsValue, JsError> {
+    let options = CompileOptions::default();
+    let output = analyze(code, options).map_err(|errors| {
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        JsError::new(&messages.join("\n"))
+    })?;
+    Ok(to_json_value(&program_json(
+        &output.instructions,
+        &output.debug_info,
+        &options.target,
+    ))?)
+}
+
+/// Gzipped Sponge schematic of the ROM of `code`, `version` is 2 or 3 and `layout` the
+/// JSON of a ROM layout, the standard one if it's missing
+#[wasm_bindgen]
+pub fn export_schematic(
+    code: &str,
+    version: u8,
+    layout: Option<String>,
+) -> Result<Vec<u8>, JsError> {
+    let version = match version {
+        2 => Version::V2,
+        3 => Version::V3,
+        _ => {
+            return Err(JsError::new(&format!(
+                "Unknown schematic version {version}"
+            )))
+        }
+    };
+    let layout: Layout = match layout {
+        Some(layout) => serde_json::from_str(&layout)?,
+        None => Layout::default(),
+    };
+    let options = CompileOptions::default();
+    let output = analyze(code, options).map_err(|errors| {
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        JsError::new(&messages.join("\n"))
+    })?;
+    let words: Vec<_> = output
+        .instructions
+        .iter()
+        .map(|instr| instr.to_bin())
+        .collect();
+    let region = Region::new(&words, options.target.page_size, &layout);
+    Ok(schem::write(&region, version))
+}
+
+/// Result of [`compile_and_run`], everything but `diagnostics` is null if there are diagnostics
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunResult {
+    outputs: Option<Vec<OutputWrite>>,
+    stop_reason: Option<StopReason>,
+    cycles: Option<u64>,
+    /// Estimated run time in a game running at full speed
+    seconds: Option<f64>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Compiles `code` and runs it for at most `max_cycles`, collecting every output write.
+/// Stops early when the program is stuck in a loop that can't change anything anymore.
+/// `on_input(port)` can supply input values and `on_output(port, value, cycle)` sees
+/// every write as it happens
+#[wasm_bindgen]
+pub fn compile_and_run(
+    code: &str,
+    max_cycles: u32,
+    on_input: Option<Function>,
+    on_output: Option<Function>,
+) -> Result<JsValue, JsError> {
+    let options = CompileOptions::default();
+    let result = match analyze(code, options) {
+        Ok(output) => {
+            let mut emulator = Emulator::new(&output.instructions, options.target);
+            emulator.detect_loops(true);
+            if let Some(on_input) = on_input {
+                set_js_input_handler(&mut emulator, on_input);
+            }
+            if let Some(on_output) = on_output {
+                set_js_output_handler(&mut emulator, on_output);
+            }
+            let stop_reason = emulator.run(max_cycles.into());
+            RunResult {
+                outputs: Some(emulator.output_log().to_vec()),
+                stop_reason: Some(stop_reason),
+                cycles: Some(emulator.cycles()),
+                seconds: Some(emulator.elapsed_seconds()),
+                diagnostics: vec![],
+            }
+        }
+        Err(errors) => {
+            let text = Rope::from_str(code);
+            RunResult {
+                outputs: None,
+                stop_reason: None,
+                cycles: None,
+                seconds: None,
+                diagnostics: errors
+                    .iter()
+                    .map(|error| to_diagnostic(error, &text, None))
+                    .collect(),
+            }
+        }
+    };
+    Ok(to_json_value(&result)?)
+}
+
+/// Formats a whole program, `options` are shaped like the `mcn.format` settings
 #[wasm_bindgen]
-pub fn receive_message(message: &str) {
-    log(&format!("Received message: {}", message));
+pub fn format_code(code: &str, options: JsValue) -> Result<String, JsValue> {
+    let settings: FormatSettings = if options.is_undefined() || options.is_null() {
+        FormatSettings::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)?
+    };
+    let indent_width = settings.indent_width.unwrap_or(DEFAULT_INDENT_WIDTH);
+    Ok(format_document(code, indent_width as usize))
+}
+
+/// Modules with their methods and constants
+#[wasm_bindgen]
+pub fn get_module_docs() -> Result<JsValue, JsError> {
+    Ok(to_json_value(&backend::MODULES)?)
+}
+
+/// Result of [`tokenize_line`]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LineTokens {
+    tokens: Vec<LineToken>,
+    end_state: u32,
+}
+
+/// Tokenizes a line for Monaco's `TokensProvider`,
+/// the state only passes through since no token spans multiple lines
+#[wasm_bindgen]
+pub fn tokenize_line(line: &str, prev_state: u32) -> Result<JsValue, JsError> {
+    Ok(to_json_value(&LineTokens {
+        tokens: line_tokens(line),
+        end_state: prev_state,
+    })?)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TargetInfo {
+    name: &'static str,
+    page_size: usize,
+}
+
+/// Result of [`info`]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Info {
+    version: &'static str,
+    targets: Vec<TargetInfo>,
+    keywords: &'static [&'static str],
+    modules: Vec<&'static str>,
+    /// Calls that work without `use`
+    builtins: Vec<&'static str>,
+    instruction_set_revision: u32,
 }
 
+/// What the compiler supports, so the editor can adapt to it
 #[wasm_bindgen]
-pub fn compile(code: &str) -> Result<String, String> {
-    let tokens = redstone_compiler::frontend::tokenize(code).map_err(|_| "Tokenization error")?;
-    let mut parser = redstone_compiler::frontend::Parser::new();
-    let ast = parser.produce_ast(tokens).map_err(|_| "Parsing error")?;
-    let code = redstone_compiler::backend::compile_program(ast).map_err(|_| "Compilation error")?;
-    let mut asm_string = String::new();
-    code.iter()
-        .map(|instr| format!("{instr}\n"))
-        .for_each(|line| asm_string.push_str(&line));
-    Ok(asm_string)
+pub fn info() -> Result<JsValue, JsError> {
+    Ok(to_json_value(&Info {
+        version: redstone_compiler::VERSION,
+        targets: Target::ALL
+            .iter()
+            .map(|target| TargetInfo {
+                name: target.name,
+                page_size: target.page_size,
+            })
+            .collect(),
+        keywords: &frontend::KEYWORDS,
+        modules: backend::MODULES.iter().map(|module| module.name).collect(),
+        builtins: backend::BUILTINS
+            .iter()
+            .map(|builtin| builtin.name)
+            .collect(),
+        instruction_set_revision: backend::INSTRUCTION_SET_REVISION,
+    })?)
 }
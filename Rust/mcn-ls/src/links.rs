@@ -0,0 +1,120 @@
+//! Links from `use` statements to the docs of their modules. The docs are virtual
+//! documents, the client asks for their text with [`ModuleDocs`](crate::requests::ModuleDocs)
+
+use std::{borrow::Cow, fmt::Write};
+
+use lsp_types::{DocumentLink, Url};
+use redstone_compiler::{
+    backend::{module_doc, MethodDoc, ModuleDoc},
+    frontend::{tokenize, TokenType},
+};
+use ropey::Rope;
+
+use crate::positions::to_lsp_range;
+
+pub const DOCS_SCHEME: &str = "mcn-docs";
+
+/// Works on the tokens, so the links stay while the rest of the program doesn't parse
+pub fn document_links(text: &Rope) -> Vec<DocumentLink> {
+    let code: Cow<'_, str> = text.into();
+    let Ok(tokens) = tokenize(&code) else {
+        return vec![];
+    };
+    let mut links = vec![];
+    // `use` is followed by names separated by dots
+    let mut expect_name = false;
+    let mut after_name = false;
+    for token in &tokens {
+        (expect_name, after_name) = match token.typ {
+            TokenType::Use => (true, false),
+            TokenType::Dot if after_name => (true, false),
+            TokenType::Identifier(name) if expect_name => {
+                if let Some(target) = module_doc(name).and_then(module_uri) {
+                    links.push(DocumentLink {
+                        range: to_lsp_range(text, token.location),
+                        target: Some(target),
+                        tooltip: Some(format!("Docs of {name}")),
+                        data: None,
+                    });
+                }
+                (false, true)
+            }
+            _ => (false, false),
+        };
+    }
+    links
+}
+
+fn module_uri(module: &ModuleDoc) -> Option<Url> {
+    Url::parse(&format!("{DOCS_SCHEME}:/{}.md", module.name)).ok()
+}
+
+/// Text of a document [`document_links`] links to
+pub fn module_docs(uri: &Url) -> Option<String> {
+    if uri.scheme() != DOCS_SCHEME {
+        return None;
+    }
+    let name = uri.path().strip_prefix('/')?.strip_suffix(".md")?;
+    module_doc(name).map(markdown)
+}
+
+fn markdown(module: &ModuleDoc) -> String {
+    let mut text = format!(
+        "# {}\n\n{}\n\n`use {}`\n",
+        module.name, module.doc, module.name
+    );
+    if !module.methods.is_empty() {
+        text.push_str("\n## Methods\n");
+    }
+    for method in module.methods {
+        let _ = write!(
+            text,
+            "\n### `{}.{}`\n\n{}",
+            module.name,
+            method.name,
+            method_markdown(module, method)
+        );
+    }
+    if !module.constants.is_empty() {
+        text.push_str("\n## Constants\n\n");
+    }
+    for constant in module.constants {
+        let _ = writeln!(
+            text,
+            "- `{}.{}` = {}",
+            module.name, constant.name, constant.value
+        );
+    }
+    text
+}
+
+/// What a method does and how it's called
+pub fn method_markdown(module: &ModuleDoc, method: &MethodDoc) -> String {
+    let mut text = format!("{}\n", method.doc);
+    let constants: Vec<_> = method
+        .params
+        .iter()
+        .filter(|param| param.constant)
+        .map(|param| format!("`{}`", param.name))
+        .collect();
+    if !constants.is_empty() {
+        let _ = writeln!(
+            text,
+            "\n{} {} known at compile-time",
+            constants.join(", "),
+            if constants.len() == 1 {
+                "has to be"
+            } else {
+                "have to be"
+            }
+        );
+    }
+    let call = format!("{}.{}", module.name, method.signature());
+    let example = if method.returns {
+        format!("value = {call}")
+    } else {
+        call
+    };
+    let _ = writeln!(text, "\n```mcn\nuse {}\n{example}\n```", module.name);
+    text
+}
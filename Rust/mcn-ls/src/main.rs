@@ -0,0 +1,8 @@
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> std::io::Result<std::process::ExitCode> {
+    mcn_ls::stdio::run()
+}
+
+// the browser uses the library, there is no process to run
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,142 @@
+//! The compiler counts chars in a line, LSP counts UTF-16 code units.
+//! Everything crossing that boundary goes through here
+
+use lsp_types::{Position, Range};
+use redstone_compiler::frontend::{self, Location};
+use ropey::Rope;
+
+/// Position after the first `chars` chars of a line, clamped to the end of the line
+pub fn to_position(text: &Rope, line: u16, chars: usize) -> Position {
+    let line_index = usize::from(line);
+    if line_index >= text.len_lines() {
+        return Position::new(line.into(), chars as u32);
+    }
+    let line_text = text.line(line_index);
+    let units = line_text.char_to_utf16_cu(chars.min(line_content_len(text, line_index)));
+    Position::new(line.into(), units as u32)
+}
+
+/// Compiler ranges are inclusive with 1-based columns, LSP ranges are exclusive and 0-based
+pub fn to_lsp_range(text: &Rope, range: frontend::Range) -> Range {
+    let frontend::Range(start, end) = range;
+    Range {
+        start: to_position(text, start.0, usize::from(start.1.saturating_sub(1))),
+        end: to_position(text, end.0, end.1.into()),
+    }
+}
+
+/// Compiler range of the chars inside an LSP range, an empty range covers its whole line
+pub fn to_compiler_range(text: &Rope, range: Range) -> frontend::Range {
+    if range.start == range.end {
+        let line = range.start.line as u16;
+        return frontend::Range(Location(line, 1), Location(line, u16::MAX));
+    }
+    let start = to_location(text, range.start);
+    let end = to_location(text, range.end);
+    frontend::Range(start, Location(end.0, end.1 - 1))
+}
+
+/// Location of the char after a position
+pub fn to_location(text: &Rope, position: Position) -> Location {
+    let line_index = (position.line as usize).min(text.len_lines() - 1);
+    let chars_before = char_index(text, position) - text.line_to_char(line_index);
+    Location(position.line as u16, chars_before as u16 + 1)
+}
+
+/// Char index of a position in the whole text, clamped to the end of its line
+pub fn char_index(text: &Rope, position: Position) -> usize {
+    let line_index = position.line as usize;
+    if line_index >= text.len_lines() {
+        return text.len_chars();
+    }
+    let line_start = text.line_to_char(line_index);
+    let line_len = line_content_len(text, line_index);
+    let line_text = text.line(line_index);
+    let units = position.character as usize;
+    if units >= line_text.char_to_utf16_cu(line_len) {
+        return line_start + line_len;
+    }
+    line_start + line_text.utf16_cu_to_char(units)
+}
+
+/// Length of a line in chars, without the line ending
+fn line_content_len(text: &Rope, line_index: usize) -> usize {
+    let line = text.line(line_index);
+    let mut len = line.len_chars();
+    for ending in ['\n', '\r'] {
+        if len > 0 && line.char(len - 1) == ending {
+            len -= 1;
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{Position, Range};
+    use redstone_compiler::frontend::{self, Location};
+    use ropey::Rope;
+
+    use super::{char_index, to_compiler_range, to_location, to_lsp_range, to_position};
+
+    #[test]
+    fn surrogate_pairs() {
+        // 𝄞 is two UTF-16 code units but one char
+        let text = Rope::from_str("a𝄞b = 1\n");
+        assert_eq!(to_position(&text, 0, 1), Position::new(0, 1));
+        assert_eq!(to_position(&text, 0, 2), Position::new(0, 3));
+        assert_eq!(to_position(&text, 0, 3), Position::new(0, 4));
+        assert_eq!(char_index(&text, Position::new(0, 3)), 2);
+        assert_eq!(to_location(&text, Position::new(0, 3)), Location(0, 3));
+        // inside of the pair is the char it belongs to
+        assert_eq!(char_index(&text, Position::new(0, 2)), 1);
+        for chars in 0..=7 {
+            let position = to_position(&text, 0, chars);
+            assert_eq!(char_index(&text, position), chars);
+        }
+    }
+
+    #[test]
+    fn combining_characters() {
+        // e and the accent on it are two chars and two code units
+        let text = Rope::from_str("x = 1 # e\u{301}!");
+        assert_eq!(to_position(&text, 0, 9), Position::new(0, 9));
+        assert_eq!(to_position(&text, 0, 10), Position::new(0, 10));
+        assert_eq!(char_index(&text, Position::new(0, 10)), 10);
+    }
+
+    #[test]
+    fn line_end_and_eof() {
+        let text = Rope::from_str("ab\r\nc𝄞\n");
+        // the line ending isn't part of the line
+        assert_eq!(to_position(&text, 0, 10), Position::new(0, 2));
+        assert_eq!(char_index(&text, Position::new(0, 10)), 2);
+        assert_eq!(to_position(&text, 1, 2), Position::new(1, 3));
+        assert_eq!(char_index(&text, Position::new(1, 3)), 6);
+        // the empty line after the last line break
+        assert_eq!(to_position(&text, 2, 0), Position::new(2, 0));
+        assert_eq!(char_index(&text, Position::new(2, 0)), text.len_chars());
+        // lines past the end are the end of the text
+        assert_eq!(char_index(&text, Position::new(7, 1)), text.len_chars());
+        assert_eq!(to_position(&text, 7, 1), Position::new(7, 1));
+    }
+
+    #[test]
+    fn range_round_trips() {
+        let text = Rope::from_str("𝄞 = 1\nif é𝄞 > 0\n  pass\nend");
+        let ranges = [
+            frontend::Range(Location(0, 1), Location(0, 1)),
+            frontend::Range(Location(0, 1), Location(0, 5)),
+            frontend::Range(Location(1, 4), Location(1, 5)),
+            frontend::Range(Location(1, 1), Location(3, 3)),
+        ];
+        for range in ranges {
+            let lsp = to_lsp_range(&text, range);
+            assert_eq!(to_compiler_range(&text, lsp), range);
+        }
+        assert_eq!(
+            to_lsp_range(&text, frontend::Range(Location(1, 4), Location(1, 5))),
+            Range::new(Position::new(1, 3), Position::new(1, 6))
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use lsp_types::{
+    notification::{Notification, Progress as ProgressNotification},
+    ProgressParams, ProgressParamsValue, ProgressToken, WorkDoneProgress, WorkDoneProgressBegin,
+    WorkDoneProgressEnd, WorkDoneProgressReport,
+};
+
+use crate::server::Client;
+
+/// Work-done progress reported to the client, ended when dropped
+pub struct Progress<'a> {
+    token: ProgressToken,
+    client: &'a dyn Client,
+}
+
+impl<'a> Progress<'a> {
+    pub fn begin(client: &'a dyn Client, token: ProgressToken, title: &str) -> Self {
+        let progress = Self { token, client };
+        progress.send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.to_string(),
+            cancellable: Some(false),
+            message: None,
+            percentage: Some(0),
+        }));
+        progress
+    }
+
+    pub fn report(&self, message: String, percentage: u32) {
+        self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: Some(false),
+            message: Some(message),
+            percentage: Some(percentage),
+        }));
+    }
+
+    fn send(&self, value: WorkDoneProgress) {
+        let params = ProgressParams {
+            token: self.token.clone(),
+            value: ProgressParamsValue::WorkDone(value),
+        };
+        // progress is best effort, the work goes on if the client can't be told about it
+        if let Ok(params) = serde_json::to_value(params) {
+            let _ = self
+                .client
+                .send_notification(ProgressNotification::METHOD, params);
+        }
+    }
+}
+
+impl Drop for Progress<'_> {
+    fn drop(&mut self) {
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd { message: None }));
+    }
+}
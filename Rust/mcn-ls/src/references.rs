@@ -0,0 +1,181 @@
+//! Which identifiers refer to the same variable, resolved the way the compiler does:
+//! inline variables before variables, and an assignment only declares a variable
+//! in its block if no enclosing block has one with that name
+
+use std::collections::HashMap;
+
+use redstone_compiler::frontend::{Ast, ExprId, Expression, ExpressionType, Location, Range};
+
+/// Every variable and inline variable of a program with all of its occurrences
+#[derive(Debug, Default)]
+pub struct References {
+    /// The occurrences of every variable in the order of the code, so its declaration
+    /// comes first
+    variables: Vec<Vec<Range>>,
+}
+
+impl References {
+    pub fn new(ast: &Ast) -> Self {
+        let mut indexer = Indexer {
+            ast,
+            scopes: vec![Scope::default()],
+            variables: vec![],
+        };
+        indexer.block(ast.body());
+        Self {
+            variables: indexer.variables,
+        }
+    }
+
+    /// The occurrences of the variable at `location`, which can also be right after
+    /// the name, where the cursor is while typing it
+    pub fn at(&self, location: Location) -> Option<&[Range]> {
+        self.variables
+            .iter()
+            .find(|occurrences| {
+                occurrences.iter().any(|&Range(start, end)| {
+                    Range(start, Location(end.0, end.1 + 1)).contains(location)
+                })
+            })
+            .map(Vec::as_slice)
+    }
+}
+
+#[derive(Default)]
+struct Scope {
+    /// Index of the variable in [`Indexer::variables`]
+    variables: HashMap<String, usize>,
+    inline_variables: HashMap<String, usize>,
+}
+
+struct Indexer<'a> {
+    ast: &'a Ast,
+    scopes: Vec<Scope>,
+    variables: Vec<Vec<Range>>,
+}
+
+impl Indexer<'_> {
+    fn block(&mut self, body: &[ExprId]) {
+        for &statement in body {
+            self.expression(&self.ast[statement]);
+        }
+    }
+
+    fn nested_block(&mut self, body: &[ExprId]) {
+        self.scopes.push(Scope::default());
+        self.block(body);
+        self.scopes.pop();
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        let ast = self.ast;
+        match &expression.typ {
+            ExpressionType::InlineDeclaration { ident, value } => {
+                self.expression(&ast[*value]);
+                let index = self.variables.len();
+                let scope = self
+                    .scopes
+                    .last_mut()
+                    .expect("the global scope is never popped");
+                let index = *scope
+                    .inline_variables
+                    .entry(ident.symbol.clone())
+                    .or_insert(index);
+                self.occurrence(index, ident.location);
+            }
+            ExpressionType::Assignment { ident, value } => {
+                self.expression(&ast[*value]);
+                self.declare(&ident.symbol, ident.location);
+            }
+            ExpressionType::VarDeclaration { ident } => {
+                self.declare(&ident.symbol, ident.location);
+            }
+            ExpressionType::Identifier(name) => {
+                let scopes = || self.scopes.iter().rev();
+                let found = scopes()
+                    .find_map(|scope| scope.inline_variables.get(name))
+                    .or_else(|| scopes().find_map(|scope| scope.variables.get(name)))
+                    .copied();
+                if let Some(index) = found {
+                    self.occurrence(index, expression.location);
+                }
+            }
+            ExpressionType::Conditional {
+                condition,
+                body,
+                paths,
+                alternate,
+            } => {
+                self.expression(&ast[*condition]);
+                self.nested_block(body);
+                for (condition, body) in paths {
+                    self.expression(&ast[*condition]);
+                    self.nested_block(body);
+                }
+                self.nested_block(alternate.as_deref().unwrap_or_default());
+            }
+            ExpressionType::EndlessLoop { body } => self.nested_block(body),
+            ExpressionType::WhileLoop { condition, body } => {
+                self.expression(&ast[*condition]);
+                self.nested_block(body);
+            }
+            ExpressionType::BinaryExpr { left, right, .. }
+            | ExpressionType::EqExpr { left, right, .. }
+            | ExpressionType::Swap { left, right } => {
+                self.expression(&ast[*left]);
+                self.expression(&ast[*right]);
+            }
+            ExpressionType::IAssignment {
+                variable, value, ..
+            } => {
+                self.expression(&ast[*value]);
+                self.expression(&ast[*variable]);
+            }
+            ExpressionType::UnaryExpr { value, .. } => self.expression(&ast[*value]),
+            ExpressionType::Assert { condition }
+            | ExpressionType::StaticAssert { condition, .. } => {
+                self.expression(&ast[*condition]);
+            }
+            ExpressionType::Wait { ticks } => self.expression(&ast[*ticks]),
+            ExpressionType::Call { args, function } => {
+                self.expression(&ast[*function]);
+                self.block(args);
+            }
+            // the object is a module
+            ExpressionType::Member { .. }
+            | ExpressionType::Use(_)
+            | ExpressionType::Pass
+            | ExpressionType::Todo
+            | ExpressionType::NumericLiteral(_)
+            | ExpressionType::Debug => {}
+        }
+    }
+
+    fn declare(&mut self, name: &str, location: Range) {
+        let existing = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(name));
+        let index = match existing {
+            Some(&index) => index,
+            None => {
+                let index = self.variables.len();
+                self.scopes
+                    .last_mut()
+                    .expect("the global scope is never popped")
+                    .variables
+                    .insert(name.to_string(), index);
+                index
+            }
+        };
+        self.occurrence(index, location);
+    }
+
+    fn occurrence(&mut self, index: usize, location: Range) {
+        if index == self.variables.len() {
+            self.variables.push(vec![]);
+        }
+        self.variables[index].push(location);
+    }
+}
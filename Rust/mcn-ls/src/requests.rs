@@ -0,0 +1,71 @@
+//! Requests and notifications that aren't part of the protocol
+
+use lsp_types::{notification::Notification, request::Request, Range, TextDocumentIdentifier, Url};
+use serde::{Deserialize, Serialize};
+
+/// Addresses of the instructions generated from a range,
+/// an empty range stands for the whole line it's on
+pub enum InstructionsForRange {}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionsForRangeParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+}
+
+impl Request for InstructionsForRange {
+    type Params = InstructionsForRangeParams;
+    type Result = Vec<usize>;
+    const METHOD: &'static str = "mcn/instructionsForRange";
+}
+
+/// Source range an instruction was generated from
+pub enum RangeForInstruction {}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeForInstructionParams {
+    pub text_document: TextDocumentIdentifier,
+    pub address: usize,
+}
+
+impl Request for RangeForInstruction {
+    type Params = RangeForInstructionParams;
+    type Result = Option<Range>;
+    const METHOD: &'static str = "mcn/rangeForInstruction";
+}
+
+/// Markdown of a module, for the links of `use` statements
+pub enum ModuleDocs {}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleDocsParams {
+    pub uri: Url,
+}
+
+impl Request for ModuleDocs {
+    type Params = ModuleDocsParams;
+    type Result = Option<String>;
+    const METHOD: &'static str = "mcn/moduleDocs";
+}
+
+/// A value the program of [`RUN`](crate::commands::RUN) wrote to an output port, sent
+/// while it runs
+pub enum Output {}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputParams {
+    pub uri: Url,
+    pub port: u8,
+    pub value: i16,
+    /// Cycles spent up to and including the write
+    pub cycle: u64,
+}
+
+impl Notification for Output {
+    type Params = OutputParams;
+    const METHOD: &'static str = "mcn/output";
+}
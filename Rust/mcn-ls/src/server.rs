@@ -1,74 +1,815 @@
-use js_sys::Function;
-use wasm_bindgen::prelude::*;
+use std::{collections::HashMap, rc::Rc};
 
-use crate::language::initialize_result;
+use lsp_types::{
+    notification::{
+        DidChangeConfiguration, DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification, PublishDiagnostics, SetTrace, ShowMessage,
+    },
+    request::{
+        CodeActionRequest, CodeLensRequest, Completion, DocumentDiagnosticRequest,
+        DocumentLinkRequest, ExecuteCommand, HoverRequest, InlayHintRefreshRequest,
+        InlayHintRequest, LinkedEditingRange, OnTypeFormatting, Request, ResolveCompletionItem,
+        WorkDoneProgressCreate, WorkspaceDiagnosticRequest,
+    },
+    CodeActionOrCommand, CodeActionParams, CodeActionResponse, CodeLens, CodeLensParams, Command,
+    CompletionItem, CompletionParams, CompletionResponse, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentDiagnosticParams,
+    DocumentDiagnosticReport, DocumentDiagnosticReportResult, DocumentLink, DocumentLinkParams,
+    DocumentOnTypeFormattingParams, ExecuteCommandParams, FullDocumentDiagnosticReport, Hover,
+    HoverParams, InitializeParams, InlayHint, InlayHintParams, LinkedEditingRangeParams,
+    LinkedEditingRanges, MessageType, ProgressToken, PublishDiagnosticsParams,
+    RelatedFullDocumentDiagnosticReport, RelatedUnchangedDocumentDiagnosticReport, SetTraceParams,
+    ShowMessageParams, TextEdit, TraceValue, UnchangedDocumentDiagnosticReport, Url,
+    WorkDoneProgressCreateParams, WorkspaceDiagnosticParams, WorkspaceDiagnosticReport,
+    WorkspaceDiagnosticReportResult, WorkspaceDocumentDiagnosticReport,
+    WorkspaceFullDocumentDiagnosticReport,
+};
+use redstone_compiler::{
+    backend::CompileOptions,
+    emulator::Emulator,
+    frontend::ExpressionType,
+    project::{Manifest, MANIFEST_FILE},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 
-#[wasm_bindgen]
-pub struct LspServer {
-    document: String,
-    last_document_version: i32,
-    send_notification: Function,
-    send_request: Function,
+use crate::{
+    actions::code_actions,
+    analysis::parse,
+    commands::{self, CompileArtifacts, RunResult},
+    completion::{completions, resolve},
+    document::DocumentState,
+    formatting::on_type_formatting,
+    hover::hover,
+    inlay_hints::inlay_hints,
+    language::initialize_result,
+    links::{document_links, module_docs},
+    positions::{to_compiler_range, to_location, to_lsp_range},
+    progress::Progress,
+    references::References,
+    requests::{
+        InstructionsForRange, InstructionsForRangeParams, ModuleDocs, ModuleDocsParams, Output,
+        OutputParams, RangeForInstruction, RangeForInstructionParams,
+    },
+    settings::{ConfigurationParams, ServerSettings},
+    trace::Tracer,
+    utils::now,
+};
+
+/// Sends messages to the client: JS callbacks in the browser, stdout in the native binary
+pub trait Client {
+    fn send_notification(&self, method: &str, params: Value) -> ServerResult<()>;
+    /// Fire and forget, responses to requests of the server are ignored
+    fn send_request(&self, method: &str, params: Value) -> ServerResult<()>;
 }
 
-type JsResult<T = JsValue> = Result<T, JsError>;
+/// JSON-RPC error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
 
-pub trait Callable {
-    fn call_0(&self) -> Result<JsValue, JsValue>;
-    fn call_1(&self, arg1: &JsValue) -> Result<JsValue, JsValue>;
-    fn call_2(&self, arg1: &JsValue, arg2: &JsValue) -> Result<JsValue, JsValue>;
-    fn call_3(&self, arg1: &JsValue, arg2: &JsValue, arg3: &JsValue) -> Result<JsValue, JsValue>;
+/// Error response to a request
+#[derive(Debug)]
+pub struct ServerError {
+    pub code: i64,
+    pub message: String,
 }
 
-impl Callable for Function {
-    fn call_0(&self) -> Result<JsValue, JsValue> {
-        self.call0(&JsValue::UNDEFINED)
+impl ServerError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            code: INTERNAL_ERROR,
+            message: message.into(),
+        }
     }
 
-    fn call_1(&self, arg1: &JsValue) -> Result<JsValue, JsValue> {
-        self.call1(&JsValue::UNDEFINED, arg1)
+    pub fn with_code(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
     }
+}
 
-    fn call_2(&self, arg1: &JsValue, arg2: &JsValue) -> Result<JsValue, JsValue> {
-        self.call2(&JsValue::UNDEFINED, arg1, arg2)
+impl<E: std::error::Error> From<E> for ServerError {
+    fn from(error: E) -> Self {
+        Self::new(error.to_string())
     }
+}
 
-    fn call_3(&self, arg1: &JsValue, arg2: &JsValue, arg3: &JsValue) -> Result<JsValue, JsValue> {
-        self.call3(&JsValue::UNDEFINED, arg1, arg2, arg3)
-    }
+pub type ServerResult<T = Value> = Result<T, ServerError>;
+
+/// Handles the language server protocol independent of the transport
+pub struct Server {
+    documents: HashMap<Url, DocumentState>,
+    settings: ServerSettings,
+    /// The options of the project replace the ones from the settings
+    manifest: Option<Manifest>,
+    /// Whether the client accepts progress tokens created by the server
+    work_done_progress: bool,
+    /// Whether the client pulls diagnostics, otherwise they are published
+    pull_diagnostics: bool,
+    /// Whether the client asks for inlay hints again when the server tells it to
+    inlay_hint_refresh: bool,
+    next_progress_token: i32,
+    tracer: Tracer,
+    client: Rc<dyn Client>,
 }
 
-#[wasm_bindgen]
-impl LspServer {
-    pub fn new(send_notification: Function, send_request: Function) -> Self {
+impl Server {
+    pub fn new(client: Rc<dyn Client>) -> Self {
         Self {
-            document: String::new(),
-            last_document_version: -1,
-            send_notification,
-            send_request,
+            documents: HashMap::new(),
+            settings: ServerSettings::default(),
+            manifest: None,
+            work_done_progress: false,
+            pull_diagnostics: true,
+            inlay_hint_refresh: false,
+            next_progress_token: 0,
+            tracer: Tracer::new(Rc::clone(&client)),
+            client,
         }
     }
 
-    pub fn initialize(&self, params: JsValue) -> JsResult<JsValue> {
-        Ok(to_json_value(&initialize_result(
-            &serde_wasm_bindgen::from_value(params)?,
-        ))?)
+    pub fn initialize(&mut self, params: Value) -> ServerResult {
+        let params: InitializeParams = parse_params(params)?;
+        self.work_done_progress = params
+            .capabilities
+            .window
+            .as_ref()
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or(false);
+        self.pull_diagnostics = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .is_some_and(|text_document| text_document.diagnostic.is_some());
+        self.inlay_hint_refresh = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.inlay_hint.as_ref())
+            .and_then(|inlay_hint| inlay_hint.refresh_support)
+            .unwrap_or(false);
+        if let Some(level) = params.trace {
+            self.tracer.level = level;
+        }
+        if let Some(text) = read_manifest(&params) {
+            if let Err(error) = self.set_manifest(Some(&text)) {
+                self.show_message(MessageType::WARNING, error.message);
+            }
+        }
+        Ok(serde_json::to_value(initialize_result(&params))?)
     }
 
-    pub fn reload_document(&mut self, text: String, version: i32) {
-        if version <= self.last_document_version {
+    /// Compiles with the options of the project from now on, or with the ones from the
+    /// settings again without a manifest
+    pub fn set_manifest(&mut self, text: Option<&str>) -> ServerResult<()> {
+        let manifest = text
+            .map(Manifest::parse)
+            .transpose()
+            .map_err(|error| ServerError::new(format!("Invalid {MANIFEST_FILE}: {error}")))?;
+        if manifest == self.manifest {
+            return Ok(());
+        }
+        self.manifest = manifest;
+        for document in self.documents.values_mut() {
+            document.invalidate();
+        }
+        Ok(())
+    }
+
+    /// Routes a request to its handler and serializes the response
+    pub fn handle_request(&mut self, method: &str, params: Value) -> ServerResult {
+        let start = now();
+        let verbose = (self.tracer.level == TraceValue::Verbose)
+            .then(|| serde_json::to_string(&params))
+            .and_then(Result::ok);
+        let result = self.dispatch_request(method, params);
+        let duration = now() - start;
+        self.tracer.log(
+            || format!("Handled {method} in {duration}ms"),
+            || verbose.unwrap_or_default(),
+        );
+        result
+    }
+
+    /// Routes a notification to its handler, unknown notifications are ignored
+    pub fn handle_notification(&mut self, method: &str, params: Value) -> ServerResult<()> {
+        match method {
+            DidOpenTextDocument::METHOD => {
+                self.notification::<DidOpenTextDocument>(params, Self::did_open)
+            }
+            DidChangeTextDocument::METHOD => {
+                self.notification::<DidChangeTextDocument>(params, Self::did_change)
+            }
+            DidChangeConfiguration::METHOD => {
+                let params: ConfigurationParams = parse_params(params)?;
+                self.did_change_configuration(params);
+                Ok(())
+            }
+            DidCloseTextDocument::METHOD => {
+                self.notification::<DidCloseTextDocument>(params, Self::did_close)
+            }
+            SetTrace::METHOD => self.notification::<SetTrace>(params, Self::set_trace),
+            _ => Ok(()),
+        }
+    }
+
+    /// Milliseconds until a document has been quiet long enough to compile it for diagnostics,
+    /// or until all documents have if no uri is given
+    pub fn diagnostic_delay(&self, uri: Option<&str>) -> ServerResult<f64> {
+        let now = now();
+        let delay = f64::from(self.settings.diagnostic_delay);
+        let remaining = |doc: &DocumentState| {
+            if doc.is_dirty() {
+                (doc.changed_at() + delay - now).max(0.0)
+            } else {
+                0.0
+            }
+        };
+        Ok(match uri {
+            Some(uri) => self.documents.get(&Url::parse(uri)?).map_or(0.0, remaining),
+            None => self.documents.values().map(remaining).fold(0.0, f64::max),
+        })
+    }
+
+    /// Compiles the changed documents and publishes their diagnostics,
+    /// unless the client pulls them itself
+    pub fn publish_diagnostics(&mut self) {
+        if self.pull_diagnostics {
             return;
         }
-        self.last_document_version = version;
-        self.document = text;
+        let options = self.compile_options();
+        for (uri, document) in &mut self.documents {
+            if !document.is_dirty() {
+                continue;
+            }
+            self.tracer.compile(uri, document, options);
+            let params = PublishDiagnosticsParams {
+                uri: uri.clone(),
+                diagnostics: document.diagnostics(uri, self.settings.warnings),
+                version: document.compiled_version(),
+            };
+            if let Ok(params) = serde_json::to_value(params) {
+                let _ = self
+                    .client
+                    .send_notification(PublishDiagnostics::METHOD, params);
+            }
+        }
+    }
+}
+
+impl Server {
+    fn compile_options(&self) -> CompileOptions {
+        self.manifest.as_ref().map_or_else(
+            || self.settings.compile_options(),
+            Manifest::compile_options,
+        )
+    }
+
+    fn show_message(&self, typ: MessageType, message: String) {
+        if let Ok(params) = serde_json::to_value(ShowMessageParams { typ, message }) {
+            let _ = self.client.send_notification(ShowMessage::METHOD, params);
+        }
+    }
+
+    fn dispatch_request(&mut self, method: &str, params: Value) -> ServerResult {
+        match method {
+            DocumentDiagnosticRequest::METHOD => {
+                self.request::<DocumentDiagnosticRequest>(params, Self::document_diagnostic)
+            }
+            WorkspaceDiagnosticRequest::METHOD => {
+                self.request::<WorkspaceDiagnosticRequest>(params, Self::workspace_diagnostic)
+            }
+            Completion::METHOD => self.request::<Completion>(params, Self::completion),
+            ResolveCompletionItem::METHOD => {
+                self.request::<ResolveCompletionItem>(params, Self::resolve_completion_item)
+            }
+            HoverRequest::METHOD => self.request::<HoverRequest>(params, Self::hover),
+            InlayHintRequest::METHOD => self.request::<InlayHintRequest>(params, Self::inlay_hint),
+            CodeActionRequest::METHOD => {
+                self.request::<CodeActionRequest>(params, Self::code_action)
+            }
+            CodeLensRequest::METHOD => self.request::<CodeLensRequest>(params, Self::code_lens),
+            DocumentLinkRequest::METHOD => {
+                self.request::<DocumentLinkRequest>(params, Self::document_link)
+            }
+            ModuleDocs::METHOD => self.request::<ModuleDocs>(params, Self::module_docs),
+            LinkedEditingRange::METHOD => {
+                self.request::<LinkedEditingRange>(params, Self::linked_editing_range)
+            }
+            ExecuteCommand::METHOD => self.request::<ExecuteCommand>(params, Self::execute_command),
+            InstructionsForRange::METHOD => {
+                self.request::<InstructionsForRange>(params, Self::instructions_for_range)
+            }
+            RangeForInstruction::METHOD => {
+                self.request::<RangeForInstruction>(params, Self::range_for_instruction)
+            }
+            OnTypeFormatting::METHOD => {
+                self.request::<OnTypeFormatting>(params, Self::on_type_formatting)
+            }
+            _ => Err(ServerError::with_code(
+                METHOD_NOT_FOUND,
+                format!("Unhandled request {method}"),
+            )),
+        }
+    }
+
+    fn request<R: Request>(
+        &mut self,
+        params: Value,
+        handler: fn(&mut Self, R::Params) -> ServerResult<R::Result>,
+    ) -> ServerResult
+    where
+        R::Params: DeserializeOwned,
+        R::Result: Serialize,
+    {
+        let result = handler(self, parse_params(params)?)?;
+        Ok(serde_json::to_value(result)?)
+    }
+
+    fn notification<N: Notification>(
+        &mut self,
+        params: Value,
+        handler: fn(&mut Self, N::Params),
+    ) -> ServerResult<()>
+    where
+        N::Params: DeserializeOwned,
+    {
+        handler(self, parse_params(params)?);
+        Ok(())
+    }
+
+    /// Uses the token the client sent along, or creates one if the client supports that
+    fn progress_token(&mut self, token: Option<ProgressToken>) -> Option<ProgressToken> {
+        if token.is_some() || !self.work_done_progress {
+            return token;
+        }
+        self.next_progress_token += 1;
+        let token = ProgressToken::String(format!("mcn-{}", self.next_progress_token));
+        let params = serde_json::to_value(WorkDoneProgressCreateParams {
+            token: token.clone(),
+        })
+        .ok()?;
+        self.client
+            .send_request(WorkDoneProgressCreate::METHOD, params)
+            .ok()?;
+        Some(token)
+    }
+
+    fn did_open(&mut self, params: DidOpenTextDocumentParams) {
+        let document = params.text_document;
+        if matches!(self.documents.get(&document.uri), Some(doc) if document.version <= doc.version())
+        {
+            return;
+        }
+        self.documents.insert(
+            document.uri,
+            DocumentState::new(&document.text, document.version),
+        );
+    }
+
+    fn did_change(&mut self, params: DidChangeTextDocumentParams) {
+        let version = params.text_document.version;
+        let Some(document) = self.documents.get_mut(&params.text_document.uri) else {
+            return;
+        };
+        if version <= document.version() {
+            return;
+        }
+        for change in params.content_changes {
+            document.apply_change(change);
+        }
+        document.set_version(version, now());
     }
+
+    fn did_change_configuration(&mut self, params: ConfigurationParams) {
+        if params.settings.mcn == self.settings {
+            return;
+        }
+        let hints_changed = params.settings.mcn.inlay_hints != self.settings.inlay_hints;
+        self.settings = params.settings.mcn;
+        for document in self.documents.values_mut() {
+            document.invalidate();
+        }
+        if hints_changed && self.inlay_hint_refresh {
+            let _ = self
+                .client
+                .send_request(InlayHintRefreshRequest::METHOD, Value::Null);
+        }
+    }
+
+    fn set_trace(&mut self, params: SetTraceParams) {
+        self.tracer.level = params.value;
+    }
+
+    fn did_close(&mut self, params: DidCloseTextDocumentParams) {
+        self.documents.remove(&params.text_document.uri);
+    }
+
+    /// Reports the diagnostics of the current version, with the version as result id
+    /// so that the client can tell when nothing changed
+    fn document_diagnostic(
+        &mut self,
+        params: DocumentDiagnosticParams,
+    ) -> ServerResult<DocumentDiagnosticReportResult> {
+        let options = self.compile_options();
+        let Some(document) = self.documents.get_mut(&params.text_document.uri) else {
+            return Ok(DocumentDiagnosticReport::Full(
+                RelatedFullDocumentDiagnosticReport::default(),
+            )
+            .into());
+        };
+        let previous = params.previous_result_id;
+        let unchanged = !document.is_dirty()
+            && previous.is_some()
+            && previous == document.compiled_version().map(|v| v.to_string());
+        self.tracer
+            .compile(&params.text_document.uri, document, options);
+        let result_id = document.compiled_version().map(|v| v.to_string());
+        let report = if unchanged {
+            DocumentDiagnosticReport::Unchanged(RelatedUnchangedDocumentDiagnosticReport {
+                related_documents: None,
+                unchanged_document_diagnostic_report: UnchangedDocumentDiagnosticReport {
+                    result_id: result_id.unwrap_or_default(),
+                },
+            })
+        } else {
+            DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+                related_documents: None,
+                full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                    result_id,
+                    items: document.diagnostics(&params.text_document.uri, self.settings.warnings),
+                },
+            })
+        };
+        Ok(report.into())
+    }
+
+    /// Reports every known document under its own uri
+    fn workspace_diagnostic(
+        &mut self,
+        params: WorkspaceDiagnosticParams,
+    ) -> ServerResult<WorkspaceDiagnosticReportResult> {
+        let options = self.compile_options();
+        let token = self.progress_token(params.work_done_progress_params.work_done_token);
+        let progress =
+            token.map(|token| Progress::begin(self.client.as_ref(), token, "Compiling documents"));
+        let total = self.documents.len();
+        let items = self
+            .documents
+            .iter_mut()
+            .enumerate()
+            .map(|(index, (uri, doc))| {
+                if let Some(progress) = &progress {
+                    progress.report(uri.to_string(), (index * 100 / total) as u32);
+                }
+                self.tracer.compile(uri, doc, options);
+                WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+                    uri: uri.clone(),
+                    version: doc.compiled_version().map(Into::into),
+                    full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                        result_id: doc.compiled_version().map(|v| v.to_string()),
+                        items: doc.diagnostics(uri, self.settings.warnings),
+                    },
+                })
+            })
+            .collect();
+        Ok(WorkspaceDiagnosticReport { items }.into())
+    }
+
+    /// Keywords, modules after `use` and the methods of a module after a `.`, their docs are
+    /// filled in lazily by `resolve_completion_item`
+    fn completion(&mut self, params: CompletionParams) -> ServerResult<Option<CompletionResponse>> {
+        let position = params.text_document_position;
+        Ok(self
+            .documents
+            .get(&position.text_document.uri)
+            .map(|document| {
+                CompletionResponse::Array(completions(document.rope(), position.position))
+            }))
+    }
+
+    fn resolve_completion_item(&mut self, item: CompletionItem) -> ServerResult<CompletionItem> {
+        Ok(resolve(item))
+    }
+
+    fn hover(&mut self, params: HoverParams) -> ServerResult<Option<Hover>> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        let options = self.compile_options();
+        let Some(document) = self.documents.get_mut(&uri) else {
+            return Ok(None);
+        };
+        self.tracer.compile(&uri, document, options);
+        let (Some(output), Some(ast)) = (document.output(), parse(&document.text())) else {
+            return Ok(None);
+        };
+        let location = to_location(document.rope(), position.position);
+        Ok(hover(
+            &ast,
+            output,
+            location,
+            options.target.timing,
+            document.rope(),
+        ))
+    }
+
+    /// Hints from the last compilation that succeeded, so they don't flicker while typing
+    fn inlay_hint(&mut self, params: InlayHintParams) -> ServerResult<Option<Vec<InlayHint>>> {
+        let options = self.compile_options();
+        let Some(document) = self.documents.get_mut(&params.text_document.uri) else {
+            return Ok(None);
+        };
+        self.tracer
+            .compile(&params.text_document.uri, document, options);
+        Ok(document.last_output().map(|output| {
+            inlay_hints(
+                output,
+                self.settings.inlay_hints,
+                document.rope(),
+                params.range,
+            )
+        }))
+    }
+
+    fn code_action(
+        &mut self,
+        params: CodeActionParams,
+    ) -> ServerResult<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let Some(document) = self.documents.get(&uri) else {
+            return Ok(None);
+        };
+        let Some(ast) = parse(&document.text()) else {
+            return Ok(None);
+        };
+        Ok(Some(
+            code_actions(
+                &ast,
+                document.rope(),
+                &uri,
+                params.range,
+                &params.context.diagnostics,
+            )
+            .into_iter()
+            .map(CodeActionOrCommand::CodeAction)
+            .collect(),
+        ))
+    }
+
+    /// Annotates every top-level block with the size and cost of its code, the start
+    /// of the program and its top-level `forever` loops can be run from here
+    fn code_lens(&mut self, params: CodeLensParams) -> ServerResult<Option<Vec<CodeLens>>> {
+        let options = self.compile_options();
+        let Some(document) = self.documents.get_mut(&params.text_document.uri) else {
+            return Ok(None);
+        };
+        self.tracer
+            .compile(&params.text_document.uri, document, options);
+        let Some(output) = document.last_output() else {
+            return Ok(None);
+        };
+        let timing = options.target.timing;
+        let lenses: Vec<_> = output
+            .blocks
+            .iter()
+            .map(|block| {
+                let (count, cycles) = output.debug_info.source_map.addresses_in(*block).fold(
+                    (0, 0),
+                    |(count, cycles), address| {
+                        let variant = output.instructions[address].variant;
+                        (count + 1, cycles + u32::from(timing.cycles(variant)))
+                    },
+                );
+                CodeLens {
+                    range: to_lsp_range(document.rope(), *block),
+                    command: Some(Command {
+                        title: format!("{count} instructions, ~{cycles} cycles"),
+                        command: String::new(),
+                        arguments: None,
+                    }),
+                    data: None,
+                }
+            })
+            .collect();
+        let lenses = run_lenses(&params.text_document.uri, document)
+            .into_iter()
+            .chain(lenses)
+            .collect();
+        Ok(Some(lenses))
+    }
+
+    /// Links the modules of `use` statements to their docs
+    fn document_link(
+        &mut self,
+        params: DocumentLinkParams,
+    ) -> ServerResult<Option<Vec<DocumentLink>>> {
+        Ok(self
+            .documents
+            .get(&params.text_document.uri)
+            .map(|document| document_links(document.rope())))
+    }
+
+    fn module_docs(&mut self, params: ModuleDocsParams) -> ServerResult<Option<String>> {
+        Ok(module_docs(&params.uri))
+    }
+
+    /// Every occurrence of the variable at the cursor, so renaming one renames all of them
+    fn linked_editing_range(
+        &mut self,
+        params: LinkedEditingRangeParams,
+    ) -> ServerResult<Option<LinkedEditingRanges>> {
+        let position = params.text_document_position_params;
+        let Some(document) = self.documents.get(&position.text_document.uri) else {
+            return Ok(None);
+        };
+        let Some(ast) = parse(&document.text()) else {
+            return Ok(None);
+        };
+        let references = References::new(&ast);
+        let location = to_location(document.rope(), position.position);
+        Ok(references
+            .at(location)
+            .map(|occurrences| LinkedEditingRanges {
+                ranges: occurrences
+                    .iter()
+                    .map(|&range| to_lsp_range(document.rope(), range))
+                    .collect(),
+                word_pattern: Some("[A-Za-z_][A-Za-z0-9_]*".to_string()),
+            }))
+    }
+
+    fn execute_command(
+        &mut self,
+        params: ExecuteCommandParams,
+    ) -> ServerResult<Option<serde_json::Value>> {
+        let token = self.progress_token(params.work_done_progress_params.work_done_token);
+        match params.command.as_str() {
+            commands::COMPILE => {
+                let uri: Url = serde_json::from_value(
+                    params.arguments.into_iter().next().unwrap_or_default(),
+                )?;
+                let _progress =
+                    token.map(|token| Progress::begin(self.client.as_ref(), token, "Compiling"));
+                let options = self.compile_options();
+                let document = self
+                    .documents
+                    .get_mut(&uri)
+                    .ok_or_else(|| ServerError::new(format!("Unknown document {uri}")))?;
+                self.tracer.compile(&uri, document, options);
+                match document.output() {
+                    Some(output) => Ok(Some(serde_json::to_value(CompileArtifacts::new(
+                        output,
+                        document.rope(),
+                    ))?)),
+                    None => Err(ServerError::new(format!("{uri} has errors"))),
+                }
+            }
+            commands::RUN => {
+                let uri: Url = serde_json::from_value(
+                    params.arguments.into_iter().next().unwrap_or_default(),
+                )?;
+                let _progress =
+                    token.map(|token| Progress::begin(self.client.as_ref(), token, "Running"));
+                let options = self.compile_options();
+                let document = self
+                    .documents
+                    .get_mut(&uri)
+                    .ok_or_else(|| ServerError::new(format!("Unknown document {uri}")))?;
+                self.tracer.compile(&uri, document, options);
+                let output = document
+                    .output()
+                    .ok_or_else(|| ServerError::new(format!("{uri} has errors")))?;
+                let mut emulator = Emulator::new(&output.instructions, options.target);
+                let client = Rc::clone(&self.client);
+                emulator.on_output(move |write| {
+                    let params = OutputParams {
+                        uri: uri.clone(),
+                        port: write.port,
+                        value: write.value,
+                        cycle: write.cycle,
+                    };
+                    if let Ok(params) = serde_json::to_value(params) {
+                        let _ = client.send_notification(Output::METHOD, params);
+                    }
+                });
+                let stop_reason = emulator.run(self.settings.run_cycles);
+                Ok(Some(serde_json::to_value(RunResult {
+                    stop_reason,
+                    cycles: emulator.cycles(),
+                    ticks: emulator.ticks(),
+                })?))
+            }
+            command => Err(ServerError::new(format!("Unknown command {command}"))),
+        }
+    }
+
+    fn instructions_for_range(
+        &mut self,
+        params: InstructionsForRangeParams,
+    ) -> ServerResult<Vec<usize>> {
+        let uri = params.text_document.uri;
+        let options = self.compile_options();
+        let Some(document) = self.documents.get_mut(&uri) else {
+            return Ok(vec![]);
+        };
+        self.tracer.compile(&uri, document, options);
+        let range = to_compiler_range(document.rope(), params.range);
+        Ok(document
+            .output()
+            .map(|output| output.debug_info.source_map.addresses_in(range).collect())
+            .unwrap_or_default())
+    }
+
+    fn range_for_instruction(
+        &mut self,
+        params: RangeForInstructionParams,
+    ) -> ServerResult<Option<lsp_types::Range>> {
+        let uri = params.text_document.uri;
+        let options = self.compile_options();
+        let Some(document) = self.documents.get_mut(&uri) else {
+            return Ok(None);
+        };
+        self.tracer.compile(&uri, document, options);
+        Ok(document
+            .output()
+            .and_then(|output| output.debug_info.source_map.location_of(params.address))
+            .map(|location| to_lsp_range(document.rope(), location)))
+    }
+
+    fn on_type_formatting(
+        &mut self,
+        mut params: DocumentOnTypeFormattingParams,
+    ) -> ServerResult<Option<Vec<TextEdit>>> {
+        if let Some(width) = self.settings.format.indent_width {
+            params.options.tab_size = width;
+        }
+        let position = params.text_document_position;
+        let Some(document) = self.documents.get(&position.text_document.uri) else {
+            return Ok(None);
+        };
+        Ok(Some(on_type_formatting(
+            &document.text(),
+            position.position,
+            &params.options,
+        )))
+    }
+}
+
+/// On the first statement and every top-level `forever` loop, they all run the whole
+/// program
+fn run_lenses(uri: &Url, document: &DocumentState) -> Vec<CodeLens> {
+    let Some(ast) = parse(&document.text()) else {
+        return vec![];
+    };
+    let loops = ast
+        .statements()
+        .filter(|statement| matches!(statement.typ, ExpressionType::EndlessLoop { .. }));
+    let lenses = ast
+        .statements()
+        .next()
+        .into_iter()
+        .chain(loops)
+        .map(|statement| {
+            let start = to_lsp_range(document.rope(), statement.location).start;
+            CodeLens {
+                range: lsp_types::Range::new(start, start),
+                command: Some(Command {
+                    title: "Run in emulator".to_string(),
+                    command: commands::RUN.to_string(),
+                    arguments: Some(vec![Value::String(uri.to_string())]),
+                }),
+                data: None,
+            }
+        })
+        .collect();
+    lenses
+}
+
+/// The manifest in the root of the workspace, if there is one
+#[cfg(not(target_arch = "wasm32"))]
+fn read_manifest(params: &InitializeParams) -> Option<String> {
+    #[allow(deprecated)]
+    let root = params
+        .workspace_folders
+        .as_ref()
+        .and_then(|folders| folders.first())
+        .map(|folder| &folder.uri)
+        .or(params.root_uri.as_ref())?;
+    std::fs::read_to_string(root.to_file_path().ok()?.join(MANIFEST_FILE)).ok()
+}
+
+/// The browser has no files, the editor passes the manifest to `setManifest` instead
+#[cfg(target_arch = "wasm32")]
+const fn read_manifest(_: &InitializeParams) -> Option<String> {
+    None
 }
 
-// Copied from: slint-ui/slint tools/lsp/wasm_main.rs
-// Credit: https://github.com/slint-ui/slint
-/// Use a JSON friendly representation to avoid using ES maps instead of JS objects.
-fn to_json_value<T: serde::Serialize + ?Sized>(
-    value: &T,
-) -> Result<JsValue, serde_wasm_bindgen::Error> {
-    value.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+fn parse_params<T: DeserializeOwned>(params: Value) -> ServerResult<T> {
+    serde_json::from_value(params)
+        .map_err(|error| ServerError::with_code(INVALID_PARAMS, error.to_string()))
 }
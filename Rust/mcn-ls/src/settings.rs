@@ -0,0 +1,128 @@
+use redstone_compiler::backend::{CompileOptions, OptLevel, Target};
+use serde::Deserialize;
+
+/// Client configuration under the `mcn` section
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ServerSettings {
+    pub opt_level: u8,
+    pub target: String,
+    /// Overrides the page size of the target, ignored outside of 1 to 256
+    pub page_size: Option<usize>,
+    pub warnings: WarningLevel,
+    pub inlay_hints: InlayHintSettings,
+    pub format: FormatSettings,
+    /// Milliseconds without changes before a document is compiled for diagnostics
+    pub diagnostic_delay: u32,
+    /// Cycles a program runs for at most when it's run from a code lens
+    pub run_cycles: u64,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            opt_level: 1,
+            target: Target::MCN_16.name.to_string(),
+            page_size: None,
+            warnings: WarningLevel::default(),
+            inlay_hints: InlayHintSettings::default(),
+            format: FormatSettings::default(),
+            diagnostic_delay: 250,
+            run_cycles: 10_000,
+        }
+    }
+}
+
+impl ServerSettings {
+    /// Unknown targets fall back to the default one
+    pub fn compile_options(&self) -> CompileOptions {
+        let target = *Target::by_name(&self.target).unwrap_or(&Target::MCN_16);
+        CompileOptions {
+            opt_level: if self.opt_level == 0 {
+                OptLevel::O0
+            } else {
+                OptLevel::O1
+            },
+            target: Target {
+                page_size: self
+                    .page_size
+                    .filter(|size| (1..=256).contains(size))
+                    .unwrap_or(target.page_size),
+                ..target
+            },
+            ..CompileOptions::default()
+        }
+    }
+}
+
+/// How warning diagnostics are reported
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum WarningLevel {
+    Ignore,
+    #[default]
+    Warn,
+    Error,
+}
+
+/// Which inlay hints are shown
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InlayHintSettings {
+    /// The RAM slot of a variable where it's introduced
+    pub variable_slots: bool,
+    /// What the compiler knows to be in register A after a statement
+    pub register_contents: bool,
+}
+
+impl Default for InlayHintSettings {
+    fn default() -> Self {
+        Self {
+            variable_slots: true,
+            register_contents: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FormatSettings {
+    /// Overrides the tab size the editor sends along with formatting requests
+    pub indent_width: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct SettingsSection {
+    pub mcn: ServerSettings,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigurationParams {
+    pub settings: SettingsSection,
+}
+
+#[cfg(test)]
+mod tests {
+    use redstone_compiler::backend::Target;
+
+    use super::ServerSettings;
+
+    #[test]
+    fn page_size() {
+        let page_size = |page_size| {
+            ServerSettings {
+                page_size,
+                ..ServerSettings::default()
+            }
+            .compile_options()
+            .target
+            .page_size
+        };
+        assert_eq!(page_size(None), Target::MCN_16.page_size);
+        assert_eq!(page_size(Some(4)), 4);
+        assert_eq!(page_size(Some(256)), 256);
+        assert_eq!(page_size(Some(0)), Target::MCN_16.page_size);
+        assert_eq!(page_size(Some(257)), Target::MCN_16.page_size);
+    }
+}
@@ -0,0 +1,145 @@
+//! The language server as a process talking over stdin and stdout, for editors like
+//! Neovim or Helix. Messages are JSON-RPC with `Content-Length` headers
+
+use std::{
+    cell::Cell,
+    io::{self, BufRead, Write},
+    process::ExitCode,
+    rc::Rc,
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::server::{Client, Server, ServerError, ServerResult, INVALID_REQUEST, PARSE_ERROR};
+
+/// Any message from the client, requests have an id and a method
+#[derive(Deserialize)]
+struct Message {
+    id: Option<Value>,
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Default)]
+struct StdoutClient {
+    next_request_id: Cell<i32>,
+}
+
+impl Client for StdoutClient {
+    fn send_notification(&self, method: &str, params: Value) -> ServerResult<()> {
+        write_message(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))?;
+        Ok(())
+    }
+
+    fn send_request(&self, method: &str, params: Value) -> ServerResult<()> {
+        let id = self.next_request_id.get();
+        self.next_request_id.set(id + 1);
+        write_message(&json!({
+            "jsonrpc": "2.0",
+            "id": format!("mcn-{id}"),
+            "method": method,
+            "params": params,
+        }))?;
+        Ok(())
+    }
+}
+
+/// Serves until the client sends `exit`, which succeeds if it was shut down first
+///
+/// # Errors
+///
+/// If stdin or stdout fail
+pub fn run() -> io::Result<ExitCode> {
+    let mut server = Server::new(Rc::new(StdoutClient::default()));
+    let mut input = io::stdin().lock();
+    let mut shut_down = false;
+    while let Some(body) = read_message(&mut input)? {
+        let message: Message = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(error) => {
+                let error = ServerError::with_code(PARSE_ERROR, error.to_string());
+                respond(Value::Null, Err(error))?;
+                continue;
+            }
+        };
+        match (message.id, message.method) {
+            (Some(id), Some(method)) => {
+                let result = match method.as_str() {
+                    "initialize" => server.initialize(message.params),
+                    "shutdown" => {
+                        shut_down = true;
+                        Ok(Value::Null)
+                    }
+                    _ if shut_down => Err(ServerError::with_code(
+                        INVALID_REQUEST,
+                        "The server is shut down",
+                    )),
+                    method => server.handle_request(method, message.params),
+                };
+                respond(id, result)?;
+            }
+            (None, Some(method)) => {
+                if method == "exit" {
+                    return Ok(if shut_down {
+                        ExitCode::SUCCESS
+                    } else {
+                        ExitCode::FAILURE
+                    });
+                }
+                // notifications have no response to report errors in
+                let _ = server.handle_notification(&method, message.params);
+                server.publish_diagnostics();
+            }
+            // responses to the server's own requests, nothing waits for them
+            _ => {}
+        }
+    }
+    Ok(ExitCode::FAILURE)
+}
+
+/// Body of the next message, `None` once stdin is closed
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Message without Content-Length")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn respond(id: Value, result: ServerResult) -> io::Result<()> {
+    let message = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": error.code, "message": error.message },
+        }),
+    };
+    write_message(&message)
+}
+
+fn write_message(message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    let mut output = io::stdout().lock();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
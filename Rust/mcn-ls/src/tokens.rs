@@ -0,0 +1,254 @@
+use lsp_types::Range;
+use redstone_compiler::frontend::{tokenize, Token, TokenType};
+use ropey::Rope;
+use serde::Serialize;
+
+use crate::positions::{char_index, to_lsp_range};
+
+/// A token as seen from JavaScript
+#[derive(Serialize)]
+pub struct TokenInfo {
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub text: String,
+    pub range: Range,
+}
+
+impl TokenInfo {
+    pub fn new(token: &Token, text: &Rope) -> Self {
+        let range = to_lsp_range(text, token.location);
+        let start = char_index(text, range.start);
+        let end = char_index(text, range.end).max(start);
+        Self {
+            typ: kind(&token.typ),
+            text: text.slice(start..end).to_string(),
+            range,
+        }
+    }
+}
+
+/// Name of the token type without its contents
+pub const fn kind(typ: &TokenType) -> &'static str {
+    match typ {
+        TokenType::Number(_) => "number",
+        TokenType::Identifier(_) => "identifier",
+        TokenType::Text(_) => "text",
+        TokenType::Equals => "equals",
+        TokenType::OpenParen => "openParen",
+        TokenType::OpenFuncParen => "openFuncParen",
+        TokenType::CloseParen => "closeParen",
+        TokenType::Comma => "comma",
+        TokenType::Dot => "dot",
+        TokenType::BinaryOperator(_) => "binaryOperator",
+        TokenType::IOperator(_) => "assignmentOperator",
+        TokenType::Step(_) => "stepOperator",
+        TokenType::EqOperator(_) => "comparisonOperator",
+        TokenType::Not => "not",
+        TokenType::Inline => "inline",
+        TokenType::If => "if",
+        TokenType::Elif => "elif",
+        TokenType::Else => "else",
+        TokenType::End => "end",
+        TokenType::Forever => "forever",
+        TokenType::While => "while",
+        TokenType::Pass => "pass",
+        TokenType::Debug => "debug",
+        TokenType::Use => "use",
+        TokenType::Var => "var",
+        TokenType::Swap => "swap",
+        TokenType::Assert => "assert",
+        TokenType::StaticAssert => "staticAssert",
+        TokenType::Todo => "todo",
+        TokenType::Wait => "wait",
+        TokenType::Eof => "eof",
+    }
+}
+
+/// A token in Monaco's format, it lasts until the next one starts
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineToken {
+    /// Offset in UTF-16 code units
+    pub start_index: usize,
+    pub scopes: &'static str,
+}
+
+/// Highlighting of a single line with the scopes the editor theme knows.
+/// Everything after something the lexer rejects is marked invalid
+pub fn line_tokens(line: &str) -> Vec<LineToken> {
+    let (code, comment) = match comment_start(line) {
+        Some(hash) => line.split_at(hash),
+        None => (line, ""),
+    };
+    // utf-16 offset of every char, the lexer counts chars
+    let offsets: Vec<_> = code
+        .chars()
+        .scan(0, |offset, char| {
+            let start = *offset;
+            *offset += char.len_utf16();
+            Some(start)
+        })
+        .collect();
+    let code_len = code.encode_utf16().count();
+    let offset_of = |column: u16| {
+        offsets
+            .get(usize::from(column.saturating_sub(1)))
+            .copied()
+            .unwrap_or(code_len)
+    };
+
+    // the tokens borrow from it
+    let valid: String;
+    let (tokens, invalid_from) = match tokenize(code) {
+        Ok(tokens) => (tokens, None),
+        Err(error) => {
+            let start = offset_of(error.location.0 .1);
+            valid = code
+                .chars()
+                .take(error.location.0 .1.saturating_sub(1).into())
+                .collect();
+            (tokenize(&valid).unwrap_or_default(), Some(start))
+        }
+    };
+
+    let mut result = vec![];
+    let mut end = 0;
+    for token in tokens.iter().filter(|token| token.typ != TokenType::Eof) {
+        let start = offset_of(token.location.0 .1);
+        if start > end {
+            result.push(LineToken {
+                start_index: end,
+                scopes: "",
+            });
+        }
+        let text = &code[code
+            .char_indices()
+            .nth(usize::from(token.location.0 .1.saturating_sub(1)))
+            .map_or(code.len(), |(index, _)| index)..];
+        result.push(LineToken {
+            start_index: start,
+            scopes: scope(&token.typ, text),
+        });
+        end = offset_of(token.location.1 .1 + 1);
+    }
+    let invalid_start = invalid_from.unwrap_or(code_len);
+    if end < invalid_start {
+        result.push(LineToken {
+            start_index: end,
+            scopes: "",
+        });
+    }
+    if invalid_from.is_some() {
+        result.push(LineToken {
+            start_index: invalid_start,
+            scopes: "invalid",
+        });
+    }
+    if !comment.is_empty() {
+        result.push(LineToken {
+            start_index: code_len,
+            scopes: "comment",
+        });
+    }
+    result
+}
+
+/// Byte offset of the `#` that starts the comment. Like in the lexer, text ends at the
+/// next `"` and a `#` inside of it is part of it
+fn comment_start(line: &str) -> Option<usize> {
+    let mut in_text = false;
+    line.char_indices().find_map(|(index, char)| match char {
+        '"' => {
+            in_text = !in_text;
+            None
+        }
+        '#' if !in_text => Some(index),
+        _ => None,
+    })
+}
+
+/// `text` starts with the token
+fn scope(typ: &TokenType, text: &str) -> &'static str {
+    match typ {
+        TokenType::Number(_) if text.starts_with("0x") => "number.hex",
+        TokenType::Number(_) if text.starts_with("0b") => "number.binary",
+        TokenType::Number(_) => "number",
+        TokenType::Identifier(_) => "identifier",
+        TokenType::Text(_) => "string",
+        TokenType::OpenParen | TokenType::OpenFuncParen | TokenType::CloseParen => {
+            "delimiter.parenthesis"
+        }
+        TokenType::Comma | TokenType::Dot => "punctuation.separator",
+        TokenType::Equals
+        | TokenType::BinaryOperator(_)
+        | TokenType::IOperator(_)
+        | TokenType::Step(_)
+        | TokenType::EqOperator(_)
+        | TokenType::Not => "operator",
+        TokenType::Inline
+        | TokenType::If
+        | TokenType::Elif
+        | TokenType::Else
+        | TokenType::End
+        | TokenType::Forever
+        | TokenType::While
+        | TokenType::Pass
+        | TokenType::Debug
+        | TokenType::Use
+        | TokenType::Var
+        | TokenType::Swap
+        | TokenType::Assert
+        | TokenType::StaticAssert
+        | TokenType::Todo
+        | TokenType::Wait => "keyword",
+        TokenType::Eof => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{line_tokens, LineToken};
+
+    fn scopes(tokens: &[LineToken]) -> Vec<(usize, &'static str)> {
+        tokens
+            .iter()
+            .map(|token| (token.start_index, token.scopes))
+            .collect()
+    }
+
+    #[test]
+    fn hash_in_text() {
+        let tokens = line_tokens("static_assert(X, \"a # b\") # done");
+        assert_eq!(
+            scopes(&tokens),
+            [
+                (0, "keyword"),
+                (13, "delimiter.parenthesis"),
+                (14, "identifier"),
+                (15, "punctuation.separator"),
+                (16, ""),
+                (17, "string"),
+                (24, "delimiter.parenthesis"),
+                (25, ""),
+                (26, "comment"),
+            ]
+        );
+    }
+
+    #[test]
+    fn comment() {
+        let tokens = line_tokens("a = 1 # \"not text");
+        assert_eq!(
+            scopes(&tokens),
+            [
+                (0, "identifier"),
+                (1, ""),
+                (2, "operator"),
+                (3, ""),
+                (4, "number"),
+                (5, ""),
+                (6, "comment"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    fmt::{self, Write},
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use lsp_types::{
+    notification::{LogTrace, Notification},
+    LogTraceParams, TraceValue, Url,
+};
+use redstone_compiler::backend::CompileOptions;
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    Layer, Registry,
+};
+
+use crate::{document::DocumentState, server::Client, utils::now};
+
+/// Sends `$/logTrace` notifications at the level the client asked for with `$/setTrace`
+pub struct Tracer {
+    pub level: TraceValue,
+    client: Rc<dyn Client>,
+}
+
+impl Tracer {
+    pub fn new(client: Rc<dyn Client>) -> Self {
+        Self {
+            level: TraceValue::Off,
+            client,
+        }
+    }
+
+    /// `verbose` is only built and sent at the verbose level
+    pub fn log(&self, message: impl FnOnce() -> String, verbose: impl FnOnce() -> String) {
+        let verbose = match self.level {
+            TraceValue::Off => return,
+            TraceValue::Messages => None,
+            TraceValue::Verbose => Some(verbose()),
+        };
+        let params = LogTraceParams {
+            message: message(),
+            verbose,
+        };
+        if let Ok(params) = serde_json::to_value(params) {
+            let _ = self.client.send_notification(LogTrace::METHOD, params);
+        }
+    }
+
+    /// Compiles the document if needed and traces how long it took,
+    /// at the verbose level also how long each phase of the compiler took
+    pub fn compile(&self, uri: &Url, document: &mut DocumentState, options: CompileOptions) {
+        let start = now();
+        let timings = Timings::default();
+        let compiled = if self.level == TraceValue::Verbose {
+            let subscriber = Registry::default().with(timings.clone());
+            tracing::subscriber::with_default(subscriber, || document.compile(uri, options))
+        } else {
+            document.compile(uri, options)
+        };
+        if compiled {
+            let duration = now() - start;
+            self.log(
+                || format!("Compiled {uri} in {duration}ms"),
+                || {
+                    let phases = timings.lines.lock().map(|lines| lines.join("\n"));
+                    format!(
+                        "version {}, {} statements reused, {options:?}\n{}",
+                        document.version(),
+                        document.reused(),
+                        phases.unwrap_or_default()
+                    )
+                },
+            );
+        }
+    }
+}
+
+/// One line per span of the compiler with its fields and duration, indented by nesting.
+/// Timed with [`now`] because there is no `Instant` in wasm
+#[derive(Clone, Default)]
+struct Timings {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+/// Line of the span and when it was created
+struct Started {
+    line: usize,
+    start: f64,
+}
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let _ = write!(self.0, " {}={value:?}", field.name());
+    }
+}
+
+impl<S> Layer<S> for Timings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields(String::new());
+        attrs.record(&mut fields);
+        let depth = span.scope().skip(1).count();
+        let Ok(mut lines) = self.lines.lock() else {
+            return;
+        };
+        lines.push(format!("{}{}{}", "  ".repeat(depth), span.name(), fields.0));
+        span.extensions_mut().insert(Started {
+            line: lines.len() - 1,
+            start: now(),
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let Some(started) = extensions.get::<Started>() else {
+            return;
+        };
+        if let Ok(mut lines) = self.lines.lock() {
+            let duration = now() - started.start;
+            let _ = write!(lines[started.line], ": {duration:.3}ms");
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Compiles without writing anything, for editors and CI jobs
+
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, ValueEnum};
+use redstone_compiler::{
+    backend::Warning,
+    frontend::{Location, Range},
+    project::MANIFEST_FILE,
+    Error, ErrorType, Fix, Related,
+};
+use serde::Serialize;
+
+use crate::{
+    compile_code, parallel, project::Project, read_error, report, report_warnings, watch::programs,
+    Failure, OptArgs, COMPILE_ERROR, IO_ERROR, USAGE_ERROR,
+};
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Programs or directories that are searched for programs recursively [default: the
+    /// entry and includes of the project]
+    paths: Vec<PathBuf>,
+    /// How errors are printed, json prints one object per line to stdout
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+    #[command(flatten)]
+    opt: OptArgs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
+/// Lines and columns start at 1 and `end` is inclusive, like in the human format
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    code: &'static str,
+    severity: &'static str,
+    file: &'a str,
+    range: JsonRange,
+    message: String,
+    related: Vec<JsonRelated>,
+    fixes: Vec<Fix>,
+}
+
+/// Another location that explains the diagnostic
+#[derive(Serialize)]
+struct JsonRelated {
+    range: JsonRange,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct JsonRange {
+    start: JsonLocation,
+    end: JsonLocation,
+}
+
+#[derive(Serialize)]
+struct JsonLocation {
+    line: u32,
+    column: u16,
+}
+
+impl From<Location> for JsonLocation {
+    fn from(Location(line, column): Location) -> Self {
+        Self {
+            line: u32::from(line) + 1,
+            column,
+        }
+    }
+}
+
+impl From<Range> for JsonRange {
+    fn from(Range(start, end): Range) -> Self {
+        Self {
+            start: start.into(),
+            end: end.into(),
+        }
+    }
+}
+
+/// Checks every program, even after one has errors
+pub fn check(args: &CheckArgs, project: Option<&Project>) -> Result<(), Failure> {
+    let options = args.opt.options(project);
+    let paths = match (&args.paths[..], project) {
+        ([], Some(project)) => project.paths(),
+        ([], None) => {
+            eprintln!("No programs given and no {MANIFEST_FILE} found");
+            return Err(Failure(USAGE_ERROR));
+        }
+        (paths, _) => paths.to_vec(),
+    };
+    let mut result = Ok(());
+    // paths can overlap, like the entry of a project and its includes
+    let mut all = BTreeSet::new();
+    for path in &paths {
+        match programs(path) {
+            Ok(programs) => all.extend(programs.into_keys()),
+            Err(error) => {
+                eprintln!("Couldn't read {}: {error}", path.display());
+                result = Err(Failure(IO_ERROR));
+            }
+        }
+    }
+    let programs: Vec<_> = all.into_iter().collect();
+    // only compiling is spread over the cores, the errors are printed in order
+    let compiled = parallel::map(&programs, |program| {
+        fs::read_to_string(program).map(|code| {
+            let compiled = compile_code(&code, options).map(|(_, debug_info)| debug_info.warnings);
+            (code, compiled)
+        })
+    });
+    for (program, compiled) in programs.iter().zip(compiled) {
+        let Err(failure) = check_program(program, compiled, args.message_format) else {
+            continue;
+        };
+        // an unreadable file is worse than errors in a program
+        if !matches!(result, Err(Failure(IO_ERROR))) {
+            result = Err(failure);
+        }
+    }
+    result
+}
+
+/// The warnings of a program, or its errors
+type Compiled = Result<Vec<Warning>, Vec<Error>>;
+
+fn check_program(
+    file: &Path,
+    compiled: io::Result<(String, Compiled)>,
+    format: MessageFormat,
+) -> Result<(), Failure> {
+    let (code, compiled) = compiled.map_err(|error| read_error(file, &error))?;
+    let errors = match compiled {
+        Ok(warnings) => {
+            match format {
+                MessageFormat::Human => report_warnings(&warnings, &code, file),
+                MessageFormat::Json => {
+                    let file = file.display().to_string();
+                    for warning in &warnings {
+                        println!(
+                            "{}",
+                            json(&warning.typ, "warning", warning.location, &[], &file)
+                        );
+                    }
+                }
+            }
+            return Ok(());
+        }
+        Err(errors) => errors,
+    };
+    match format {
+        MessageFormat::Human => report(&errors, &code, file),
+        MessageFormat::Json => {
+            let file = file.display().to_string();
+            for error in &errors {
+                println!(
+                    "{}",
+                    json(&*error.typ, "error", error.location, &error.related, &file)
+                );
+            }
+        }
+    }
+    Err(Failure(COMPILE_ERROR))
+}
+
+fn json(
+    typ: &dyn ErrorType,
+    severity: &'static str,
+    location: Range,
+    related: &[Related],
+    file: &str,
+) -> String {
+    let diagnostic = JsonDiagnostic {
+        code: typ.get_code(),
+        severity,
+        file,
+        range: location.into(),
+        message: typ.get_message(),
+        related: related
+            .iter()
+            .map(|related| JsonRelated {
+                range: related.location.into(),
+                message: related.message.clone(),
+            })
+            .collect(),
+        fixes: typ.fixes(),
+    };
+    serde_json::to_string(&diagnostic).unwrap_or_default()
+}
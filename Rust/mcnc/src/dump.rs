@@ -0,0 +1,72 @@
+//! Prints what the compiler makes of a program on the way, for debugging the compiler and
+//! reporting its bugs
+
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use clap::{Args, ValueEnum};
+use redstone_compiler::{
+    backend::compile_program_ir,
+    frontend::{tokenize, Parser},
+};
+use serde::Serialize;
+
+use crate::{
+    project::{program, Project},
+    read, report, Failure, OptArgs, COMPILE_ERROR, IO_ERROR,
+};
+
+#[derive(Args)]
+pub struct DumpArgs {
+    /// What to print
+    stage: Stage,
+    /// The program [default: the entry of the project]
+    file: Option<PathBuf>,
+    // only changes the IR
+    #[command(flatten)]
+    opt: OptArgs,
+}
+
+/// The representations the program goes through, as JSON
+#[derive(Clone, Copy, ValueEnum)]
+enum Stage {
+    /// What the lexer produces
+    Tokens,
+    /// The syntax tree from the parser
+    Ast,
+    /// The instructions in their scopes before linking, jumps still point to jump marks
+    Ir,
+}
+
+pub fn dump(args: &DumpArgs, project: Option<&Project>) -> Result<(), Failure> {
+    let file = program(args.file.as_deref(), project)?;
+    let code = read(&file)?;
+    let fail = |errors: Vec<_>| {
+        report(&errors, &code, &file);
+        Failure(COMPILE_ERROR)
+    };
+    let tokens = tokenize(&code).map_err(|error| fail(vec![error]))?;
+    if let Stage::Tokens = args.stage {
+        return print(&tokens);
+    }
+    let ast = Parser::new().produce_ast(&tokens).map_err(fail)?;
+    if let Stage::Ast = args.stage {
+        return print(&ast);
+    }
+    let options = args.opt.options(project);
+    print(&compile_program_ir(&ast, options).map_err(fail)?)
+}
+
+fn print(value: &impl Serialize) -> Result<(), Failure> {
+    let mut json = serde_json::to_string_pretty(value).map_err(|error| {
+        eprintln!("Couldn't serialize the output: {error}");
+        Failure(IO_ERROR)
+    })?;
+    json.push('\n');
+    io::stdout().write_all(json.as_bytes()).map_err(|error| {
+        eprintln!("Couldn't write to stdout: {error}");
+        Failure(IO_ERROR)
+    })
+}
@@ -0,0 +1,137 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use redstone_compiler::{
+    backend::{DebugInfo, Instruction, Target},
+    export::{json::program_json, report::report},
+    frontend::Range,
+};
+
+/// Intel HEX data bytes per record
+const HEX_RECORD_LEN: usize = 16;
+
+/// A file the compiler can write, next to each other they share the name of the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Emit {
+    /// Assembly text, one instruction per line
+    Asm,
+    /// The encoded ROM, each instruction is a little-endian word
+    Bin,
+    /// The encoded ROM as Intel HEX
+    Hex,
+    /// Assembly with addresses, encodings and the code every block comes from
+    Listing,
+    /// Source map and symbols for debugging the binary later
+    Debuginfo,
+    /// The instructions with their encodings, pages and locations and the debug info,
+    /// in the versioned format of `redstone_compiler::export::json`
+    Json,
+    /// Pages, timing and jumps of every instruction as JSON, what the editor draws
+    Report,
+}
+
+/// What the formats are made of
+pub struct Program<'a> {
+    pub code: &'a str,
+    pub instructions: &'a [Instruction],
+    pub debug_info: &'a DebugInfo,
+    pub target: &'a Target,
+}
+
+impl Emit {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Asm => "asm",
+            Self::Bin => "bin",
+            Self::Hex => "hex",
+            Self::Listing => "lst",
+            Self::Debuginfo => "debug.json",
+            Self::Json => "json",
+            Self::Report => "report.json",
+        }
+    }
+
+    pub fn render(self, program: &Program) -> Result<Vec<u8>, serde_json::Error> {
+        Ok(match self {
+            Self::Asm => assembly(program.instructions).into_bytes(),
+            Self::Bin => words(program.instructions)
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+            Self::Hex => intel_hex(&Self::Bin.render(program)?).into_bytes(),
+            Self::Listing => listing(program).into_bytes(),
+            Self::Debuginfo => serde_json::to_vec_pretty(program.debug_info)?,
+            Self::Json => serde_json::to_vec_pretty(&program_json(
+                program.instructions,
+                program.debug_info,
+                program.target,
+            ))?,
+            Self::Report => {
+                serde_json::to_vec_pretty(&report(program.instructions, program.target))?
+            }
+        })
+    }
+}
+
+fn words(instructions: &[Instruction]) -> impl Iterator<Item = u16> + '_ {
+    instructions.iter().map(Instruction::to_bin)
+}
+
+/// One instruction per line, in the format the assembler reads
+fn assembly(instructions: &[Instruction]) -> String {
+    instructions
+        .iter()
+        .map(|instruction| format!("{instruction}\n"))
+        .collect()
+}
+
+/// Data records of [`HEX_RECORD_LEN`] bytes, with an extended linear address record
+/// whenever the data crosses 64 KiB
+fn intel_hex(bytes: &[u8]) -> String {
+    let mut hex = String::new();
+    for (index, chunk) in bytes.chunks(HEX_RECORD_LEN).enumerate() {
+        let address = index * HEX_RECORD_LEN;
+        if address > 0 && address.is_multiple_of(0x1_0000) {
+            hex_record(&mut hex, 0, 4, &((address >> 16) as u16).to_be_bytes());
+        }
+        hex_record(&mut hex, address as u16, 0, chunk);
+    }
+    hex_record(&mut hex, 0, 1, &[]);
+    hex
+}
+
+fn hex_record(hex: &mut String, address: u16, record_type: u8, data: &[u8]) {
+    let [high, low] = address.to_be_bytes();
+    let header = [data.len() as u8, high, low, record_type];
+    let sum = header
+        .iter()
+        .chain(data)
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    hex.push(':');
+    for byte in header.iter().chain(data) {
+        let _ = write!(hex, "{byte:02X}");
+    }
+    let _ = writeln!(hex, "{:02X}", sum.wrapping_neg());
+}
+
+/// The code of every block as a comment, then its instructions with address and encoding
+fn listing(program: &Program) -> String {
+    let lines: Vec<_> = program.code.split('\n').collect();
+    let mut listing = String::new();
+    let mut last = None;
+    for (address, instruction) in program.instructions.iter().enumerate() {
+        let Range(start, end) = instruction.orig_location;
+        if last != Some((start.0, end.0)) {
+            last = Some((start.0, end.0));
+            for line in start.0..=end.0 {
+                let text = lines.get(usize::from(line)).unwrap_or(&"").trim_end();
+                let _ = writeln!(listing, "; {:>4} | {text}", line + 1);
+            }
+        }
+        let _ = writeln!(
+            listing,
+            "{address:>6}  {:04X}  {instruction}",
+            instruction.to_bin()
+        );
+    }
+    listing
+}
@@ -0,0 +1,230 @@
+//! Builds the ROM of a program out of blocks, to paste it into a world
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::{Args, ValueEnum};
+use redstone_compiler::export::{
+    barrels, datapack, litematic,
+    schem::{self, Version},
+    structure,
+    units::{self, Manifest},
+    Layout, Region,
+};
+
+use crate::{
+    compile_file,
+    project::{program, Project},
+    read, write, Failure, OptArgs, IO_ERROR, USAGE_ERROR,
+};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// What to write
+    format: Format,
+    /// The program [default: the entry of the project]
+    file: Option<PathBuf>,
+    /// Where to write it [default: FILE with the extension of the format]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// JSON or TOML file with the layout of the ROM [default: the standard layout, bits
+    /// going up, words along x and pages along z]. With pages-per-unit, bigger programs
+    /// are written to a file per unit, like OUTPUT.0.schem, and OUTPUT.index.json lists
+    /// them
+    #[arg(long)]
+    layout: Option<PathBuf>,
+    /// Version of the schematic format, 3 needs WorldEdit 7.3 or newer
+    #[arg(long, value_enum, default_value = "2")]
+    schem_version: SchemVersion,
+    #[command(flatten)]
+    opt: OptArgs,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Sponge schematic, for WorldEdit
+    Schem,
+    /// Litematica schematic
+    Litematic,
+    /// Structure file for structure blocks and `/place template`, without mods
+    Structure,
+    /// Function that fills the barrels of a ROM that's already built, run from the first
+    /// one
+    Barrels,
+    /// Zipped datapack with commands that build the ROM, `/function <name>:place` places
+    /// it where it's run
+    Datapack,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SchemVersion {
+    #[value(name = "2")]
+    Two,
+    #[value(name = "3")]
+    Three,
+}
+
+impl From<SchemVersion> for Version {
+    fn from(version: SchemVersion) -> Self {
+        match version {
+            SchemVersion::Two => Self::V2,
+            SchemVersion::Three => Self::V3,
+        }
+    }
+}
+
+impl Format {
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Schem => "schem",
+            Self::Litematic => "litematic",
+            Self::Structure => "nbt",
+            Self::Barrels => "mcfunction",
+            Self::Datapack => "zip",
+        }
+    }
+}
+
+pub fn export(args: &ExportArgs, project: Option<&Project>) -> Result<(), Failure> {
+    let file = program(args.file.as_deref(), project)?;
+    let layout = read_layout(args.layout.as_deref())?;
+    let options = args.opt.options(project);
+    let code = read(&file)?;
+    let (instructions, _) = compile_file(&code, &file, options)?;
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| file.with_extension(args.format.extension()));
+    let units = units::split(&instructions, &options.target, layout.pages_per_unit);
+
+    if let [unit] = units.as_slice() {
+        let region = Region::new(&unit.words, options.target.page_size, &layout);
+        let name = file.file_stem().unwrap_or_default().to_string_lossy();
+        write(
+            &output,
+            args.contents(
+                &region,
+                &unit.words,
+                options.target.page_size,
+                &layout,
+                &name,
+            ),
+        )?;
+        let [width, height, length] = region.size;
+        eprintln!(
+            "Exported {} to {} ({} instructions, {width}x{height}x{length} blocks)",
+            file.display(),
+            output.display(),
+            instructions.len(),
+        );
+        return Ok(());
+    }
+
+    let extension = output.extension().map_or_else(
+        || args.format.extension().into(),
+        |ext| ext.to_string_lossy(),
+    );
+    let mut files = vec![];
+    for (index, unit) in units.iter().enumerate() {
+        let path = output.with_extension(format!("{index}.{extension}"));
+        let region = Region::new(&unit.words, options.target.page_size, &layout);
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        write(
+            &path,
+            args.contents(
+                &region,
+                &unit.words,
+                options.target.page_size,
+                &layout,
+                &name,
+            ),
+        )?;
+        files.push(
+            path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into(),
+        );
+    }
+    let manifest = Manifest::new(&units, files, &options.target, layout.pages_per_unit);
+    let index = output.with_extension("index.json");
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|error| {
+        eprintln!("Couldn't write {}: {error}", index.display());
+        Failure(IO_ERROR)
+    })?;
+    write(&index, json)?;
+    eprintln!(
+        "Exported {} to {} units of {} pages ({} instructions), listed in {}",
+        file.display(),
+        units.len(),
+        layout.pages_per_unit.unwrap_or_default(),
+        instructions.len(),
+        index.display(),
+    );
+    Ok(())
+}
+
+impl ExportArgs {
+    /// The file of one ROM, `name` is the one of the program or unit
+    fn contents(
+        &self,
+        region: &Region,
+        words: &[u16],
+        page_size: usize,
+        layout: &Layout,
+        name: &str,
+    ) -> Vec<u8> {
+        match self.format {
+            Format::Schem => schem::write(region, self.schem_version.into()),
+            Format::Litematic => {
+                let time = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_millis() as i64);
+                litematic::write(region, name, time)
+            }
+            Format::Structure => structure::write(region),
+            Format::Barrels => barrels::write(words, page_size, layout),
+            Format::Datapack => datapack::write(region, &namespace(name)),
+        }
+    }
+}
+
+/// TOML if it has the extension, JSON otherwise, the standard layout without a file
+pub fn read_layout(path: Option<&Path>) -> Result<Layout, Failure> {
+    let Some(path) = path else {
+        return Ok(Layout::default());
+    };
+    let text = read(path)?;
+    if path.extension().is_some_and(|ext| ext == "toml") {
+        return Layout::parse_toml(&text).map_err(|error| {
+            eprintln!("{}", error.render(&text, &path.display().to_string()));
+            Failure(USAGE_ERROR)
+        });
+    }
+    serde_json::from_str(&text).map_err(|error| {
+        eprintln!("Invalid layout {}: {error}", path.display());
+        Failure(USAGE_ERROR)
+    })
+}
+
+/// `name` with only the characters namespaces allow
+fn namespace(name: &str) -> String {
+    let namespace: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_-.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if namespace.is_empty() {
+        "rom".to_string()
+    } else {
+        namespace
+    }
+}
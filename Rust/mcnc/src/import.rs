@@ -0,0 +1,69 @@
+//! Recovers the program of a ROM that was built in a world, from a schematic of it
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use clap::Args;
+use redstone_compiler::{
+    backend::{disassemble, Target},
+    export::{schem, RomLayout},
+};
+
+use crate::{export::read_layout, project::Project, write, Failure, COMPILE_ERROR, IO_ERROR};
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// Sponge schematic of the ROM, like one saved with WorldEdit
+    file: PathBuf,
+    /// Where to write the assembly [default: stdout]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// JSON or TOML file with the layout of the ROM [default: the standard layout]
+    #[arg(long)]
+    layout: Option<PathBuf>,
+}
+
+pub fn import(args: &ImportArgs, project: Option<&Project>) -> Result<(), Failure> {
+    let layout = read_layout(args.layout.as_deref())?;
+    let page_size = project.map_or(Target::MCN_16.page_size, |project| {
+        project.manifest.compile_options().target.page_size
+    });
+    let bytes = fs::read(&args.file).map_err(|error| {
+        eprintln!("Couldn't read {}: {error}", args.file.display());
+        Failure(IO_ERROR)
+    })?;
+    let region = schem::read(&bytes).map_err(|error| {
+        eprintln!("Couldn't read {}: {error}", args.file.display());
+        Failure(COMPILE_ERROR)
+    })?;
+    let words = layout.read(&region, page_size);
+    let instructions = disassemble(&words).map_err(|invalid| {
+        eprintln!(
+            "The word at address {} is {:#06X}, which isn't an instruction",
+            invalid.address, invalid.word
+        );
+        Failure(COMPILE_ERROR)
+    })?;
+    let assembly: String = instructions
+        .iter()
+        .map(|instruction| format!("{instruction}\n"))
+        .collect();
+    match &args.output {
+        Some(output) => write(output, assembly.into_bytes())?,
+        None => io::stdout()
+            .write_all(assembly.as_bytes())
+            .map_err(|error| {
+                eprintln!("Couldn't write to stdout: {error}");
+                Failure(IO_ERROR)
+            })?,
+    }
+    eprintln!(
+        "Imported {} instructions from {}",
+        instructions.len(),
+        args.file.display()
+    );
+    Ok(())
+}
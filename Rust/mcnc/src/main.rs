@@ -0,0 +1,318 @@
+//! Compiler for build scripts: errors go to stderr and the exit code tells whether it
+//! worked, 1 for errors in the program, 2 for wrong usage and 3 if a file couldn't be
+//! read or written
+
+mod check;
+mod dump;
+mod emit;
+mod export;
+mod import;
+mod parallel;
+mod project;
+mod stats;
+mod verify;
+mod watch;
+
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use check::CheckArgs;
+use clap::{Args, Parser as _, Subcommand, ValueEnum};
+use dump::DumpArgs;
+use emit::{Emit, Program};
+use export::ExportArgs;
+use import::ImportArgs;
+use project::{program, NewArgs, Project};
+use redstone_compiler::{
+    backend::{
+        compile_program_with_debug_info, CompileOptions, DebugInfo, Instruction, OptLevel, Warning,
+    },
+    frontend::{tokenize, Parser},
+    Error,
+};
+use stats::StatsArgs;
+use verify::VerifyArgs;
+use watch::WatchArgs;
+
+/// The program has errors
+const COMPILE_ERROR: u8 = 1;
+/// The arguments don't make sense together, like clap's own usage errors
+const USAGE_ERROR: u8 = 2;
+/// A file couldn't be read or written
+const IO_ERROR: u8 = 3;
+
+#[derive(clap::Parser)]
+#[command(name = "mcnc", version, about, after_help = EXIT_CODES)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+const EXIT_CODES: &str = "Exits with 1 if the program has errors, 2 on wrong usage \
+                          and 3 if a file couldn't be read or written";
+
+#[derive(Subcommand)]
+enum Command {
+    /// Creates a project with an mcn.toml, commands run inside of it use its programs and
+    /// options
+    New(NewArgs),
+    /// Compiles a program to assembly
+    Compile(CompileArgs),
+    /// Compiles programs again whenever they change
+    Watch(WatchArgs),
+    /// Reports the errors in programs without writing any output
+    Check(CheckArgs),
+    /// Prints the tokens, the syntax tree or the instructions before linking as JSON
+    Dump(DumpArgs),
+    /// Shows how full the ROM pages and variable slots are and how long loops take
+    Stats(StatsArgs),
+    /// Writes the ROM of a program as blocks to paste into a world
+    Export(ExportArgs),
+    /// Reads the ROM in a schematic back and prints it as assembly
+    Import(ImportArgs),
+    /// Compares the ROM in a schematic with a program, page by page
+    Verify(VerifyArgs),
+}
+
+#[derive(Args)]
+struct CompileArgs {
+    /// The program to compile [default: the entry of the project]
+    file: Option<PathBuf>,
+    /// Where to write the output, each format replaces the extension with its own.
+    /// `-` writes a single format to stdout [default: FILE]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    #[command(flatten)]
+    build: BuildArgs,
+}
+
+/// What to compile programs to
+#[derive(Args)]
+struct BuildArgs {
+    /// Formats to write, separated by commas
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "asm")]
+    emit: Vec<Emit>,
+    #[command(flatten)]
+    opt: OptArgs,
+}
+
+#[derive(Args)]
+struct OptArgs {
+    /// Optimization level [default: the one of the project, otherwise 1]
+    #[arg(short = 'O', long, value_enum)]
+    opt_level: Option<Level>,
+    /// Make failing asserts halt the program instead of leaving them out
+    #[arg(long)]
+    assertions: bool,
+}
+
+/// How hard the compiler tries to avoid redundant instructions
+#[derive(Clone, Copy, ValueEnum)]
+enum Level {
+    /// Loads every value again, even if it is already in the register
+    #[value(name = "0")]
+    Zero,
+    /// Tracks register contents to skip redundant loads
+    #[value(name = "1")]
+    One,
+}
+
+impl OptArgs {
+    /// The options of the project, if there is one, with the level from the arguments
+    fn options(&self, project: Option<&Project>) -> CompileOptions {
+        let options = project.map_or_else(CompileOptions::default, |project| {
+            project.manifest.compile_options()
+        });
+        CompileOptions {
+            opt_level: self.opt_level.map_or(options.opt_level, Into::into),
+            assertions: self.assertions,
+            ..options
+        }
+    }
+}
+
+impl From<Level> for OptLevel {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Zero => Self::O0,
+            Level::One => Self::O1,
+        }
+    }
+}
+
+/// A reason to stop, already printed
+struct Failure(u8);
+
+fn main() -> ExitCode {
+    if !io::stderr().is_terminal() {
+        colored::control::set_override(false);
+    }
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::New(args) => project::new(&args),
+        command => Project::find().and_then(|project| {
+            let project = project.as_ref();
+            match command {
+                Command::New(_) => unreachable!("new doesn't need a project"),
+                Command::Compile(args) => compile(&args, project),
+                Command::Watch(args) => watch::watch(&args, project),
+                Command::Check(args) => check::check(&args, project),
+                Command::Dump(args) => dump::dump(&args, project),
+                Command::Stats(args) => stats::stats(&args, project),
+                Command::Export(args) => export::export(&args, project),
+                Command::Import(args) => import::import(&args, project),
+                Command::Verify(args) => verify::verify(&args, project),
+            }
+        }),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure(code)) => ExitCode::from(code),
+    }
+}
+
+fn compile(args: &CompileArgs, project: Option<&Project>) -> Result<(), Failure> {
+    let file = program(args.file.as_deref(), project)?;
+    let output = args.output.as_deref().unwrap_or(&file);
+    let options = args.build.opt.options(project);
+    if output.as_os_str() != "-" {
+        return build(&file, output, &args.build.emit, options);
+    }
+    let [emit] = args.build.emit[..] else {
+        eprintln!("Only one format can be written to stdout");
+        return Err(Failure(USAGE_ERROR));
+    };
+    let code = read(&file)?;
+    let (instructions, debug_info) = compile_file(&code, &file, options)?;
+    let program = Program {
+        code: &code,
+        instructions: &instructions,
+        debug_info: &debug_info,
+        target: &options.target,
+    };
+    let contents = render(emit, &program)?;
+    io::stdout().write_all(&contents).map_err(|error| {
+        eprintln!("Couldn't write to stdout: {error}");
+        Failure(IO_ERROR)
+    })
+}
+
+/// Compiles `file` and writes every format next to `output`, replacing its extension
+fn build(
+    file: &Path,
+    output: &Path,
+    emit: &[Emit],
+    options: CompileOptions,
+) -> Result<(), Failure> {
+    let code = read(file)?;
+    let compiled = compile_code(&code, options);
+    write_build(file, output, emit, options, &code, compiled)
+}
+
+/// What [`build`] does once `code` is compiled
+fn write_build(
+    file: &Path,
+    output: &Path,
+    emit: &[Emit],
+    options: CompileOptions,
+    code: &str,
+    compiled: Result<(Vec<Instruction>, DebugInfo), Vec<Error>>,
+) -> Result<(), Failure> {
+    let (instructions, debug_info) = compiled.map_err(|errors| {
+        report(&errors, code, file);
+        Failure(COMPILE_ERROR)
+    })?;
+    report_warnings(&debug_info.warnings, code, file);
+    let program = Program {
+        code,
+        instructions: &instructions,
+        debug_info: &debug_info,
+        target: &options.target,
+    };
+    let mut written = vec![];
+    for &emit in emit {
+        let path = output.with_extension(emit.extension());
+        write(&path, render(emit, &program)?)?;
+        written.push(path.display().to_string());
+    }
+    eprintln!(
+        "Compiled {} to {} ({} instructions)",
+        file.display(),
+        written.join(", "),
+        instructions.len()
+    );
+    Ok(())
+}
+
+/// Reports the errors or warnings if there are any
+fn compile_file(
+    code: &str,
+    file: &Path,
+    options: CompileOptions,
+) -> Result<(Vec<Instruction>, DebugInfo), Failure> {
+    let (instructions, debug_info) = compile_code(code, options).map_err(|errors| {
+        report(&errors, code, file);
+        Failure(COMPILE_ERROR)
+    })?;
+    report_warnings(&debug_info.warnings, code, file);
+    Ok((instructions, debug_info))
+}
+
+fn compile_code(
+    code: &str,
+    options: CompileOptions,
+) -> Result<(Vec<Instruction>, DebugInfo), Vec<Error>> {
+    let tokens = tokenize(code).map_err(|error| vec![error])?;
+    let ast = Parser::new().produce_ast(&tokens)?;
+    compile_program_with_debug_info(&ast, options)
+}
+
+fn render(emit: Emit, program: &Program) -> Result<Vec<u8>, Failure> {
+    emit.render(program).map_err(|error| {
+        eprintln!("Couldn't write the {emit:?} output: {error}");
+        Failure(IO_ERROR)
+    })
+}
+
+fn report(errors: &[Error], code: &str, file: &Path) {
+    let file = file.display().to_string();
+    for error in errors {
+        eprintln!("{}", error.render(code, &file));
+    }
+    let plural = if errors.len() == 1 { "" } else { "s" };
+    eprintln!("{} error{plural} in {file}", errors.len());
+}
+
+fn report_warnings(warnings: &[Warning], code: &str, file: &Path) {
+    let file = file.display().to_string();
+    for warning in warnings {
+        eprintln!("{}", warning.render(code, &file));
+    }
+}
+
+fn read(path: &Path) -> Result<String, Failure> {
+    fs::read_to_string(path).map_err(|error| read_error(path, &error))
+}
+
+fn read_error(path: &Path, error: &io::Error) -> Failure {
+    eprintln!("Couldn't read {}: {error}", path.display());
+    Failure(IO_ERROR)
+}
+
+/// Also creates the directories it's in
+fn write(path: &Path, contents: Vec<u8>) -> Result<(), Failure> {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty());
+    parent
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(path, contents))
+        .map_err(|error| {
+            eprintln!("Couldn't write {}: {error}", path.display());
+            Failure(IO_ERROR)
+        })
+}
@@ -0,0 +1,91 @@
+//! Projects are directories with an `mcn.toml`, commands run inside of them take their
+//! programs and options from it
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use redstone_compiler::project::{Manifest, MANIFEST_FILE};
+
+use crate::{read, report, write, Failure, COMPILE_ERROR, IO_ERROR, USAGE_ERROR};
+
+/// Program of new projects, it counts on the first output
+const MAIN_PROGRAM: &str = "use io\n\na = 0\nforever\n  a += 1\n  io.write(a, 0)\nend\n";
+
+#[derive(Args)]
+pub struct NewArgs {
+    /// Name of the project and the directory it is created in
+    name: String,
+}
+
+pub struct Project {
+    /// Where the manifest is, its paths are relative to it
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Project {
+    /// The manifest in the current directory or the closest one above it
+    pub fn find() -> Result<Option<Self>, Failure> {
+        let Ok(current) = env::current_dir() else {
+            return Ok(None);
+        };
+        let Some(dir) = current
+            .ancestors()
+            .find(|dir| dir.join(MANIFEST_FILE).is_file())
+        else {
+            return Ok(None);
+        };
+        let path = dir.join(MANIFEST_FILE);
+        let text = read(&path)?;
+        let manifest = Manifest::parse(&text).map_err(|error| {
+            report(&[error], &text, &path);
+            Failure(COMPILE_ERROR)
+        })?;
+        Ok(Some(Self {
+            dir: dir.to_path_buf(),
+            manifest,
+        }))
+    }
+
+    pub fn entry(&self) -> PathBuf {
+        self.dir.join(&self.manifest.entry)
+    }
+
+    /// The entry and the includes
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let includes = self.manifest.includes.iter();
+        let mut paths = vec![self.entry()];
+        paths.extend(includes.map(|include| self.dir.join(include)));
+        paths
+    }
+}
+
+/// `file`, or the entry of the project if there is no file
+pub fn program(file: Option<&Path>, project: Option<&Project>) -> Result<PathBuf, Failure> {
+    file.map(Path::to_path_buf)
+        .or_else(|| project.map(Project::entry))
+        .ok_or_else(|| {
+            eprintln!("No program given and no {MANIFEST_FILE} found");
+            Failure(USAGE_ERROR)
+        })
+}
+
+/// Creates the manifest and an entry that counts
+pub fn new(args: &NewArgs) -> Result<(), Failure> {
+    let dir = Path::new(&args.name);
+    if fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        eprintln!("{} already exists and isn't empty", dir.display());
+        return Err(Failure(IO_ERROR));
+    }
+    let name = dir.file_name().map_or(args.name.clone(), |name| {
+        name.to_string_lossy().into_owned()
+    });
+    let manifest = Manifest::new(name);
+    write(&dir.join(&manifest.entry), MAIN_PROGRAM.into())?;
+    write(&dir.join(MANIFEST_FILE), manifest.to_string().into_bytes())?;
+    eprintln!("Created the project {} in {}", manifest.name, dir.display());
+    Ok(())
+}
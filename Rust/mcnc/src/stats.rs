@@ -0,0 +1,210 @@
+//! Where the space and the time of a program go
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use clap::Args;
+use redstone_compiler::{
+    backend::{compile_program_with_debug_info, Target, VAR_SLOTS},
+    frontend::{tokenize, Ast, ExprId, Expression, ExpressionType, Parser, Range},
+};
+
+use crate::{
+    project::{program, Project},
+    read, report, Failure, OptArgs, COMPILE_ERROR,
+};
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// The program [default: the entry of the project]
+    file: Option<PathBuf>,
+    #[command(flatten)]
+    opt: OptArgs,
+}
+
+/// A loop and how deep it is nested in other loops
+struct Loop {
+    kind: &'static str,
+    location: Range,
+    depth: usize,
+}
+
+/// What the statistics need from the syntax tree
+#[derive(Default)]
+struct Survey {
+    loops: Vec<Loop>,
+    /// Call sites of every module method by module
+    calls: BTreeMap<String, usize>,
+}
+
+pub fn stats(args: &StatsArgs, project: Option<&Project>) -> Result<(), Failure> {
+    let file = program(args.file.as_deref(), project)?;
+    let code = read(&file)?;
+    let options = args.opt.options(project);
+    let mut survey = Survey::default();
+    let compiled = tokenize(&code)
+        .map_err(|error| vec![error])
+        .and_then(|tokens| Parser::new().produce_ast(&tokens))
+        .and_then(|ast| {
+            survey.block(&ast, ast.body(), 0);
+            compile_program_with_debug_info(&ast, options)
+        });
+    let (instructions, debug_info) = compiled.map_err(|errors| {
+        report(&errors, &code, &file);
+        Failure(COMPILE_ERROR)
+    })?;
+    let target = debug_info.target().unwrap_or(&Target::MCN_16);
+    let page_size = target.page_size;
+
+    let pages = instructions.len().div_ceil(page_size);
+    println!(
+        "{}: {} instructions in {pages} page{} of {page_size} ({:.0}% used)",
+        file.display(),
+        instructions.len(),
+        plural(pages),
+        percent(instructions.len(), pages * page_size),
+    );
+    for (page, chunk) in instructions.chunks(page_size).enumerate() {
+        println!("  page {page:>3}  {:>3} instructions", chunk.len());
+    }
+
+    let mut slots: BTreeMap<u8, Vec<String>> = BTreeMap::new();
+    for symbol in &debug_info.symbols {
+        let name = if symbol.depth == 0 {
+            symbol.name.clone()
+        } else {
+            format!("{} (depth {})", symbol.name, symbol.depth)
+        };
+        slots.entry(symbol.slot).or_default().push(name);
+    }
+    println!(
+        "Variables: {} of {VAR_SLOTS} slots ({:.0}%)",
+        slots.len(),
+        percent(slots.len(), VAR_SLOTS)
+    );
+    for (slot, names) in &slots {
+        println!("  slot {slot:>3}  {}", names.join(", "));
+    }
+
+    println!("Modules: {}", debug_info.modules.len());
+    for module in &debug_info.modules {
+        let calls = survey.calls.get(module).copied().unwrap_or_default();
+        println!("  {module:<8}  {calls} call{}", plural(calls));
+    }
+
+    println!(
+        "Loops: {}, cycles of one pass with nested loops passed once",
+        survey.loops.len()
+    );
+    for Loop {
+        kind,
+        location,
+        depth,
+    } in &survey.loops
+    {
+        let cycles: u64 = debug_info
+            .source_map
+            .addresses_in(*location)
+            .map(|address| u64::from(target.timing.cycles(instructions[address].variant)))
+            .sum();
+        println!(
+            "  {:indent$}line {:<4} {kind:<7}  {cycles:>4} cycles ({:.1}s)",
+            "",
+            location.0 .0 + 1,
+            target.timing.seconds(cycles),
+            indent = depth * 2,
+        );
+    }
+    Ok(())
+}
+
+impl Survey {
+    fn block(&mut self, ast: &Ast, body: &[ExprId], depth: usize) {
+        for &expression in body {
+            self.expression(ast, &ast[expression], depth);
+        }
+    }
+
+    fn expression(&mut self, ast: &Ast, expression: &Expression, depth: usize) {
+        match &expression.typ {
+            ExpressionType::EndlessLoop { body } => {
+                self.push_loop("forever", expression.location, depth);
+                self.block(ast, body, depth + 1);
+            }
+            ExpressionType::WhileLoop { condition, body } => {
+                self.push_loop("while", expression.location, depth);
+                self.expression(ast, &ast[*condition], depth + 1);
+                self.block(ast, body, depth + 1);
+            }
+            ExpressionType::Conditional {
+                condition,
+                body,
+                paths,
+                alternate,
+            } => {
+                self.expression(ast, &ast[*condition], depth);
+                self.block(ast, body, depth);
+                for (condition, body) in paths {
+                    self.expression(ast, &ast[*condition], depth);
+                    self.block(ast, body, depth);
+                }
+                self.block(ast, alternate.as_deref().unwrap_or_default(), depth);
+            }
+            ExpressionType::Call { args, function } => {
+                if let ExpressionType::Member { object, .. } = &ast[*function].typ {
+                    if let ExpressionType::Identifier(module) = &ast[*object].typ {
+                        *self.calls.entry(module.clone()).or_default() += 1;
+                    }
+                }
+                self.block(ast, args, depth);
+            }
+            ExpressionType::InlineDeclaration { value, .. }
+            | ExpressionType::Assignment { value, .. }
+            | ExpressionType::IAssignment { value, .. }
+            | ExpressionType::UnaryExpr { value, .. } => self.expression(ast, &ast[*value], depth),
+            ExpressionType::BinaryExpr { left, right, .. }
+            | ExpressionType::EqExpr { left, right, .. } => {
+                self.expression(ast, &ast[*left], depth);
+                self.expression(ast, &ast[*right], depth);
+            }
+            ExpressionType::Member { object, .. } => self.expression(ast, &ast[*object], depth),
+            ExpressionType::Assert { condition } => {
+                self.expression(ast, &ast[*condition], depth);
+            }
+            ExpressionType::Use(_)
+            | ExpressionType::Pass
+            | ExpressionType::Todo
+            | ExpressionType::Identifier(_)
+            | ExpressionType::NumericLiteral(_)
+            | ExpressionType::VarDeclaration { .. }
+            | ExpressionType::Swap { .. }
+            | ExpressionType::StaticAssert { .. }
+            | ExpressionType::Wait { .. }
+            | ExpressionType::Debug => {}
+        }
+    }
+
+    fn push_loop(&mut self, kind: &'static str, location: Range, depth: usize) {
+        self.loops.push(Loop {
+            kind,
+            location,
+            depth,
+        });
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+const fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
@@ -0,0 +1,119 @@
+//! Checks a ROM that was built in a world against the program it should have, to catch
+//! blocks that were pasted wrong before debugging the program in the game
+
+use std::{fs, path::PathBuf};
+
+use clap::Args;
+use redstone_compiler::{
+    backend::{
+        checksum::{differences, Difference},
+        Checksums, Instruction,
+    },
+    export::{schem, RomLayout},
+};
+
+use crate::{
+    compile_file,
+    export::read_layout,
+    project::{program, Project},
+    read, Failure, OptArgs, COMPILE_ERROR, IO_ERROR,
+};
+
+/// Differences that are listed, the rest are only counted
+const LISTED: usize = 20;
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Sponge schematic of the ROM
+    schematic: PathBuf,
+    /// The program it should have [default: the entry of the project]
+    file: Option<PathBuf>,
+    /// JSON or TOML file with the layout of the ROM [default: the standard layout]
+    #[arg(long)]
+    layout: Option<PathBuf>,
+    #[command(flatten)]
+    opt: OptArgs,
+}
+
+/// Exits with 1 if the ROM differs from the program
+pub fn verify(args: &VerifyArgs, project: Option<&Project>) -> Result<(), Failure> {
+    let file = program(args.file.as_deref(), project)?;
+    let layout = read_layout(args.layout.as_deref())?;
+    let options = args.opt.options(project);
+    let page_size = options.target.page_size;
+    let code = read(&file)?;
+    let (instructions, debug_info) = compile_file(&code, &file, options)?;
+    let expected: Vec<_> = instructions.iter().map(Instruction::to_bin).collect();
+
+    let bytes = fs::read(&args.schematic).map_err(|error| {
+        eprintln!("Couldn't read {}: {error}", args.schematic.display());
+        Failure(IO_ERROR)
+    })?;
+    let region = schem::read(&bytes).map_err(|error| {
+        eprintln!("Couldn't read {}: {error}", args.schematic.display());
+        Failure(COMPILE_ERROR)
+    })?;
+    let actual = layout.read(&region, page_size);
+    let found = Checksums::new(&actual, page_size);
+
+    let pages = debug_info.checksums.pages.len().max(found.pages.len());
+    for page in 0..pages {
+        let checksum = |pages: &[u32]| {
+            pages
+                .get(page)
+                .map_or_else(|| "missing ".to_string(), |sum| format!("{sum:08X}"))
+        };
+        let expected_sum = checksum(&debug_info.checksums.pages);
+        let found_sum = checksum(&found.pages);
+        let status = if expected_sum == found_sum {
+            "ok"
+        } else {
+            "differs"
+        };
+        println!("Page {page:>3}  expected {expected_sum}  found {found_sum}  {status}");
+    }
+
+    let differences = differences(&expected, &actual);
+    for difference in differences.iter().take(LISTED) {
+        println!("{}", describe(difference));
+    }
+    if differences.len() > LISTED {
+        println!("... and {} more", differences.len() - LISTED);
+    }
+    if differences.is_empty() {
+        eprintln!(
+            "{} matches {} (checksum {:08X})",
+            args.schematic.display(),
+            file.display(),
+            found.program
+        );
+        Ok(())
+    } else {
+        let plural = if differences.len() == 1 { "" } else { "s" };
+        eprintln!(
+            "{} word{plural} of {} differ from {}",
+            differences.len(),
+            args.schematic.display(),
+            file.display()
+        );
+        Err(Failure(COMPILE_ERROR))
+    }
+}
+
+fn describe(difference: &Difference) -> String {
+    let word = |word: Option<u16>| {
+        word.map_or_else(
+            || "nothing".to_string(),
+            |word| match Instruction::from_bin(word) {
+                Some(instruction) => format!("{word:04X} ({instruction})"),
+                None => format!("{word:04X}"),
+            },
+        )
+    };
+    format!(
+        "Address {:>5}: expected {}, found {}",
+        difference.address,
+        word(difference.expected),
+        word(difference.actual)
+    )
+}
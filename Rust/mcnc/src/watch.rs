@@ -0,0 +1,117 @@
+//! Compiles programs again when they change, checking their modification times so it
+//! works the same everywhere
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use clap::Args;
+
+use crate::{
+    compile_code, parallel, project::Project, read_error, write_build, BuildArgs, Failure, IO_ERROR,
+};
+
+/// Extension of the programs in watched directories
+const EXTENSION: &str = "mcn";
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// A program or a directory that is searched for programs recursively
+    path: PathBuf,
+    /// Directory for the output, with the same structure as PATH [default: next to each
+    /// program]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Milliseconds between checks for changes
+    #[arg(long, default_value_t = 200)]
+    interval: u64,
+    #[command(flatten)]
+    build: BuildArgs,
+}
+
+/// Runs until it's interrupted, only stops early if PATH can't be read
+pub fn watch(args: &WatchArgs, project: Option<&Project>) -> Result<(), Failure> {
+    let options = args.build.opt.options(project);
+    let mut modified = BTreeMap::new();
+    loop {
+        let programs = programs(&args.path).map_err(|error| {
+            eprintln!("Couldn't read {}: {error}", args.path.display());
+            Failure(IO_ERROR)
+        })?;
+        modified.retain(|path, _| programs.contains_key(path));
+
+        let changed: Vec<_> = programs
+            .into_iter()
+            .filter(|(program, time)| modified.insert(program.clone(), *time) != Some(*time))
+            .map(|(program, _)| program)
+            .collect();
+        // compiled on all cores, written and reported in order
+        let compiled = parallel::map(&changed, |program| {
+            fs::read_to_string(program).map(|code| {
+                let compiled = compile_code(&code, options);
+                (code, compiled)
+            })
+        });
+        for (program, compiled) in changed.iter().zip(compiled) {
+            let Ok((code, compiled)) = compiled.map_err(|error| read_error(program, &error)) else {
+                continue;
+            };
+            // errors are reported while writing, they only matter until the next change
+            let _ = write_build(
+                program,
+                &output(args, program),
+                &args.build.emit,
+                options,
+                &code,
+                compiled,
+            );
+        }
+        if !changed.is_empty() {
+            eprintln!("Watching {} for changes", args.path.display());
+        }
+        thread::sleep(Duration::from_millis(args.interval));
+    }
+}
+
+/// Where the output of `program` goes, without an extension
+fn output(args: &WatchArgs, program: &Path) -> PathBuf {
+    let Some(dir) = &args.output else {
+        return program.to_path_buf();
+    };
+    let relative = program
+        .strip_prefix(&args.path)
+        .ok()
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .or_else(|| program.file_name().map(Path::new))
+        .unwrap_or(program);
+    dir.join(relative)
+}
+
+/// The program at `path` or every program in it, with the time it was last modified
+pub fn programs(path: &Path) -> io::Result<BTreeMap<PathBuf, SystemTime>> {
+    let mut programs = BTreeMap::new();
+    if fs::metadata(path)?.is_dir() {
+        collect(path, &mut programs)?;
+    } else {
+        programs.insert(path.to_path_buf(), fs::metadata(path)?.modified()?);
+    }
+    Ok(programs)
+}
+
+fn collect(dir: &Path, programs: &mut BTreeMap<PathBuf, SystemTime>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            collect(&path, programs)?;
+        } else if path.extension().is_some_and(|ext| ext == EXTENSION) {
+            programs.insert(path, metadata.modified()?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Time spent in each stage of the compiler on generated programs of a few sizes,
+//! run with `cargo bench -p redstone_compiler`
+
+use std::{fmt::Write, hint::black_box};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use redstone_compiler::{
+    backend::compile_program,
+    frontend::{tokenize, Parser},
+};
+
+const SIZES: [(&str, usize); 3] = [("small", 10), ("medium", 100), ("large", 1000)];
+const VARIABLES: [&str; 16] = [
+    "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p",
+];
+
+/// A program with `statements` top-level statements that cycles through assignments,
+/// arithmetic, conditionals, loops and module calls
+fn program(statements: usize) -> String {
+    let mut code = String::from("use io\n");
+    for variable in VARIABLES {
+        writeln!(code, "{variable} = 0").unwrap();
+    }
+    for i in 0..statements {
+        let (a, b) = (VARIABLES[i % 16], VARIABLES[(i * 7 + 3) % 16]);
+        let n = i % 100;
+        match i % 5 {
+            0 => writeln!(code, "{a} = {n}"),
+            1 => writeln!(code, "{a} = ({b} + {n}) * 3 - {a}"),
+            2 => writeln!(
+                code,
+                "if {a} > {n}\n  {b} += 1\nelif {a} == {b}\n  {b} &= 7\nelse\n  {b} -= {n}\nend"
+            ),
+            3 => writeln!(code, "while {a} < {n}\n  {a} += {b} + 1\nend"),
+            _ => writeln!(code, "io.write(io.read({}) - {a}, {})", i % 4, i % 8),
+        }
+        .unwrap();
+    }
+    code
+}
+
+fn tokenizer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize");
+    for (name, size) in SIZES {
+        let code = program(size);
+        group.throughput(Throughput::Bytes(code.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &code, |b, code| {
+            b.iter(|| tokenize(black_box(code)));
+        });
+    }
+    group.finish();
+}
+
+fn parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, size) in SIZES {
+        let program = program(size);
+        let tokens = tokenize(&program).expect("Program to tokenize");
+        group.throughput(Throughput::Elements(tokens.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &tokens, |b, tokens| {
+            b.iter(|| Parser::new().produce_ast(black_box(tokens)));
+        });
+    }
+    group.finish();
+}
+
+fn compiler(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compile");
+    for (name, size) in SIZES {
+        let program = program(size);
+        let tokens = tokenize(&program).expect("Program to tokenize");
+        let ast = Parser::new()
+            .produce_ast(&tokens)
+            .expect("Program to parse");
+        group.bench_with_input(BenchmarkId::from_parameter(name), &ast, |b, ast| {
+            b.iter(|| compile_program(black_box(ast)));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tokenizer, parser, compiler);
+criterion_main!(benches);
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redstone_compiler_fuzz::Program;
+
+fuzz_target!(|program: Program| {
+    redstone_compiler_fuzz::compile(&program.to_string());
+});
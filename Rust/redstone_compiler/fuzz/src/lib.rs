@@ -0,0 +1,262 @@
+//! Fuzz targets for the whole pipeline, run them with `cargo fuzz run compile` or
+//! `cargo fuzz run structured` from `redstone_compiler`.
+//!
+//! `compile` mutates raw source code, starting from the programs in `corpus/compile`.
+//! `structured` builds programs out of [`Program`] so most inputs get past the parser
+//! and reach the compiler. Neither checks the output, only that nothing panics
+
+use std::fmt::{self, Display, Formatter};
+
+use arbitrary::Arbitrary;
+use redstone_compiler::{
+    backend::{compile_program_with, CompileOptions, OptLevel, MODULES},
+    frontend::{tokenize, Parser},
+};
+
+/// Runs the code through the lexer, the parser and the compiler at every [`OptLevel`]
+pub fn compile(code: &str) {
+    let Ok(tokens) = tokenize(code) else {
+        return;
+    };
+    for opt_level in OptLevel::ALL {
+        let Ok(ast) = Parser::new().produce_ast(&tokens) else {
+            return;
+        };
+        let options = CompileOptions {
+            opt_level,
+            ..CompileOptions::default()
+        };
+        let _ = compile_program_with(&ast, options);
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct Program {
+    uses: Vec<Module>,
+    body: Vec<Statement>,
+}
+
+/// Index into [`MODULES`]
+#[derive(Arbitrary, Debug)]
+struct Module(u8);
+
+/// One of a few names so statements use each other's variables
+#[derive(Arbitrary, Debug)]
+struct Variable(u8);
+
+#[derive(Arbitrary, Debug)]
+enum Statement {
+    Assign(Variable, Expression),
+    Inline(Variable, Expression),
+    IAssign(Variable, Operator, Expression),
+    If {
+        condition: Expression,
+        body: Vec<Statement>,
+        elifs: Vec<(Expression, Vec<Statement>)>,
+        orelse: Option<Vec<Statement>>,
+    },
+    While(Expression, Vec<Statement>),
+    Forever(Vec<Statement>),
+    Call(Call),
+    Pass,
+    /// Arbitrary text so the parser also sees almost valid programs
+    Raw(String),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Expression {
+    Number(i16),
+    Variable(Variable),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+    Compare(Box<Expression>, Comparison, Box<Expression>),
+    Negate(Box<Expression>),
+    Not(Box<Expression>),
+    Assign(Variable, Box<Expression>),
+    Call(Call),
+    /// Constant of a module, or one of its methods if it has none
+    Member(Module, u8),
+    Paren(Box<Expression>),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Call {
+    module: Module,
+    /// Index into the module's methods
+    method: u8,
+    args: Vec<Expression>,
+}
+
+#[derive(Arbitrary, Debug, Clone, Copy)]
+enum Operator {
+    Plus,
+    Minus,
+    Mult,
+    And,
+    Or,
+    Xor,
+    Div,
+    Mod,
+    ShiftLeft,
+    ShiftRight,
+}
+
+#[derive(Arbitrary, Debug, Clone, Copy)]
+enum Comparison {
+    EqualTo,
+    NotEqual,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+}
+
+impl Module {
+    fn name(&self) -> &'static str {
+        MODULES[usize::from(self.0) % MODULES.len()].name
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Display for Variable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        const NAMES: [&str; 6] = ["a", "b", "c", "x", "i", "total"];
+        f.write_str(NAMES[usize::from(self.0) % NAMES.len()])
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Plus => "+",
+            Self::Minus => "-",
+            Self::Mult => "*",
+            Self::And => "&",
+            Self::Or => "|",
+            Self::Xor => "^",
+            Self::Div => "/",
+            Self::Mod => "%",
+            Self::ShiftLeft => "<<",
+            Self::ShiftRight => ">>",
+        })
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::EqualTo => "==",
+            Self::NotEqual => "!=",
+            Self::Greater => ">",
+            Self::GreaterEq => ">=",
+            Self::Less => "<",
+            Self::LessEq => "<=",
+        })
+    }
+}
+
+impl Display for Call {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let module = &MODULES[usize::from(self.module.0) % MODULES.len()];
+        let method = &module.methods[usize::from(self.method) % module.methods.len()];
+        write!(f, "{}.{}(", module.name, method.name)?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{arg}")?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{value}"),
+            Self::Variable(variable) => write!(f, "{variable}"),
+            Self::Binary(left, operator, right) => write!(f, "{left} {operator} {right}"),
+            Self::Compare(left, comparison, right) => write!(f, "{left} {comparison} {right}"),
+            // spaced so a negative number doesn't turn it into `--`
+            Self::Negate(value) => write!(f, "- {value}"),
+            Self::Not(value) => write!(f, "~{value}"),
+            Self::Assign(variable, value) => write!(f, "({variable} = {value})"),
+            Self::Call(call) => write!(f, "{call}"),
+            Self::Member(module, index) => {
+                let doc = &MODULES[usize::from(module.0) % MODULES.len()];
+                let index = usize::from(*index);
+                let name = if doc.constants.is_empty() {
+                    doc.methods[index % doc.methods.len()].name
+                } else {
+                    doc.constants[index % doc.constants.len()].name
+                };
+                write!(f, "{}.{name}", module.name())
+            }
+            Self::Paren(inner) => write!(f, "({inner})"),
+        }
+    }
+}
+
+fn write_block(f: &mut Formatter<'_>, body: &[Statement], indent: usize) -> fmt::Result {
+    for statement in body {
+        statement.write(f, indent)?;
+    }
+    Ok(())
+}
+
+impl Statement {
+    fn write(&self, f: &mut Formatter<'_>, indent: usize) -> fmt::Result {
+        let pad = "  ".repeat(indent);
+        match self {
+            Self::Assign(variable, value) => writeln!(f, "{pad}{variable} = {value}"),
+            Self::Inline(variable, value) => writeln!(f, "{pad}inline {variable} = {value}"),
+            Self::IAssign(variable, operator, value) => {
+                writeln!(f, "{pad}{variable} {operator}= {value}")
+            }
+            Self::If {
+                condition,
+                body,
+                elifs,
+                orelse,
+            } => {
+                writeln!(f, "{pad}if {condition}")?;
+                write_block(f, body, indent + 1)?;
+                for (condition, body) in elifs {
+                    writeln!(f, "{pad}elif {condition}")?;
+                    write_block(f, body, indent + 1)?;
+                }
+                if let Some(body) = orelse {
+                    writeln!(f, "{pad}else")?;
+                    write_block(f, body, indent + 1)?;
+                }
+                writeln!(f, "{pad}end")
+            }
+            Self::While(condition, body) => {
+                writeln!(f, "{pad}while {condition}")?;
+                write_block(f, body, indent + 1)?;
+                writeln!(f, "{pad}end")
+            }
+            Self::Forever(body) => {
+                writeln!(f, "{pad}forever")?;
+                write_block(f, body, indent + 1)?;
+                writeln!(f, "{pad}end")
+            }
+            Self::Call(call) => writeln!(f, "{pad}{call}"),
+            Self::Pass => writeln!(f, "{pad}pass"),
+            Self::Raw(text) => writeln!(f, "{pad}{text}"),
+        }
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for module in &self.uses {
+            writeln!(f, "use {module}")?;
+        }
+        write_block(f, &self.body, 0)
+    }
+}
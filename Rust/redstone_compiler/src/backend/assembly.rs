@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::Error,
+    frontend::{Location, Range},
+};
+
+use super::{Compiler, ErrorType, Instruction, InstructionVariant, Target};
+
+/// A word that doesn't encode any instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidWord {
+    pub address: usize,
+    pub word: u16,
+}
+
+/// Decodes a ROM dump, see [`InstructionVariant::from_byte`] for encodings that
+/// several instructions share
+///
+/// # Errors
+///
+/// The first word that isn't an instruction
+pub fn disassemble(words: &[u16]) -> Result<Vec<Instruction>, InvalidWord> {
+    words
+        .iter()
+        .enumerate()
+        .map(|(address, &word)| Instruction::from_bin(word).ok_or(InvalidWord { address, word }))
+        .collect()
+}
+
+/// Parses assembly in the format of [`Instruction`]'s `Display`, one instruction per line
+///
+/// Mnemonics are case-insensitive, arguments can be decimal, `0x` hex or `0b` binary,
+/// everything after a `;` is a comment and labels are skipped. Instructions are located
+/// in `text`
+///
+/// # Errors
+///
+/// Every line that isn't an instruction
+pub fn parse_assembly(text: &str) -> Result<Vec<Instruction>, Vec<Error>> {
+    let mut instructions = vec![];
+    let mut errors = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let code = line.split(';').next().unwrap_or_default();
+        let mut words = words(code, index as u16);
+        words.retain(|(word, _)| !word.ends_with(':'));
+        if words.is_empty() {
+            continue;
+        }
+        match parse_instruction(&words, false) {
+            Ok((instruction, _)) => instructions.push(instruction),
+            Err(error) => errors.push(error),
+        }
+    }
+    if errors.is_empty() {
+        Ok(instructions)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Assembles hand-written code for [`Target::MCN_16`], see [`assemble_with`]
+///
+/// # Errors
+///
+/// Every line that isn't an instruction and every label that can't be resolved
+pub fn assemble(text: &str) -> Result<Vec<Instruction>, Vec<Error>> {
+    assemble_with(text, Target::MCN_16)
+}
+
+/// Like [`parse_assembly`], but jumps can go to labels
+///
+/// A line can start with `name:` to label the instruction after it. Jumps take a label
+/// or the index of an instruction, and just like in compiled code, jumps that leave
+/// their page of `target` become disc jumps, so the result can be encoded and run as is
+///
+/// # Errors
+///
+/// Every line that isn't an instruction and every label that can't be resolved
+pub fn assemble_with(text: &str, target: Target) -> Result<Vec<Instruction>, Vec<Error>> {
+    let mut instructions = vec![];
+    let mut errors = vec![];
+    let mut labels = HashMap::new();
+    let mut references = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let code = line.split(';').next().unwrap_or_default();
+        let mut words = words(code, index as u16);
+        while let Some(&(word, location)) = words.first() {
+            let Some(name) = word.strip_suffix(':') else {
+                break;
+            };
+            words.remove(0);
+            if !is_label(name) {
+                errors.push(Error {
+                    typ: Box::new(ErrorType::InvalidLabel(name.to_string())),
+                    location,
+                    related: vec![],
+                });
+            } else if let Some((_, first)) = labels.insert(name, (instructions.len(), location)) {
+                errors.push(
+                    Error {
+                        typ: Box::new(ErrorType::DuplicateLabel(name.to_string())),
+                        location,
+                        related: vec![],
+                    }
+                    .with_related(first, format!("{name} is first defined here")),
+                );
+            }
+        }
+        if words.is_empty() {
+            continue;
+        }
+        match parse_instruction(&words, true) {
+            Ok((instruction, label)) => {
+                if let Some(label) = label {
+                    references.push((instructions.len(), label));
+                }
+                instructions.push(instruction);
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+
+    let mut jump_marks = vec![];
+    let mut labelled = references.into_iter().peekable();
+    for (address, instruction) in instructions.iter_mut().enumerate() {
+        if !instruction.variant.is_jump() {
+            continue;
+        }
+        let target_address = match labelled.next_if(|&(at, _)| at == address) {
+            Some((_, (name, location))) => {
+                let Some(&(target_address, _)) = labels.get(name) else {
+                    errors.push(Error {
+                        typ: Box::new(ErrorType::UnknownLabel(name.to_string())),
+                        location,
+                        related: vec![],
+                    });
+                    continue;
+                };
+                target_address
+            }
+            None => usize::from(instruction.arg.unwrap_or_default()),
+        };
+        instruction.jump_mark = Some(jump_marks.len());
+        instruction.arg = None;
+        jump_marks.push(target_address);
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    // the first one if there are several, linking names the other jump targets
+    let mut names: Vec<_> = labels.into_iter().collect();
+    names.sort_unstable_by_key(|&(_, (_, location))| location.0);
+    for (name, (address, _)) in names {
+        if let Some(instruction) = instructions.get_mut(address) {
+            instruction.label.get_or_insert_with(|| name.to_string());
+        }
+    }
+    Compiler::link(&mut instructions, &mut jump_marks, &target).map_err(|error| vec![error])?;
+    Ok(instructions)
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+/// A whitespace separated word of a line with its location
+type Word<'a> = (&'a str, Range);
+
+fn words(line: &str, line_index: u16) -> Vec<Word<'_>> {
+    let mut words = vec![];
+    let mut start = None;
+    for (column, (byte, char)) in line.char_indices().enumerate() {
+        let column = column as u16 + 1;
+        match (char.is_whitespace(), start) {
+            (false, None) => start = Some((byte, column)),
+            (true, Some((start_byte, start_column))) => {
+                words.push((
+                    &line[start_byte..byte],
+                    Range(
+                        Location(line_index, start_column),
+                        Location(line_index, column - 1),
+                    ),
+                ));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((start_byte, start_column)) = start {
+        let end_column = line.chars().count() as u16;
+        words.push((
+            &line[start_byte..],
+            Range(
+                Location(line_index, start_column),
+                Location(line_index, end_column),
+            ),
+        ));
+    }
+    words
+}
+
+/// Also returns the label a jump goes to if `labels` are allowed
+fn parse_instruction<'a>(
+    words: &[Word<'a>],
+    labels: bool,
+) -> Result<(Instruction, Option<Word<'a>>), Error> {
+    let (mnemonic, mnemonic_location) = words[0];
+    let location = words[0].1 + words[words.len() - 1].1;
+    let variant = InstructionVariant::ALL
+        .into_iter()
+        .find(|variant| variant.name().eq_ignore_ascii_case(mnemonic))
+        .ok_or_else(|| Error {
+            typ: Box::new(ErrorType::UnknownMnemonic(mnemonic.to_string())),
+            location: mnemonic_location,
+            related: vec![],
+        })?;
+    let mut label = None;
+    let arg = match (variant.has_arg(), words.get(1)) {
+        (true, None) => {
+            return Err(Error {
+                typ: Box::new(ErrorType::MissingArg(variant.name().to_string())),
+                location,
+                related: vec![],
+            })
+        }
+        (false, Some(&(_, arg_location))) => {
+            return Err(Error {
+                typ: Box::new(ErrorType::UnexpectedArg(variant.name().to_string())),
+                location: arg_location,
+                related: vec![],
+            })
+        }
+        (true, Some(&(arg, arg_location))) => match parse_arg(arg) {
+            Some(arg) => Some(arg),
+            None if labels && variant.is_jump() && is_label(arg) => {
+                label = Some((arg, arg_location));
+                Some(0)
+            }
+            None => {
+                return Err(Error {
+                    typ: Box::new(ErrorType::InvalidArg(arg.to_string())),
+                    location: arg_location,
+                    related: vec![],
+                })
+            }
+        },
+        (false, None) => None,
+    };
+    if let Some(&(_, extra_location)) = words.get(2) {
+        return Err(Error {
+            typ: Box::new(ErrorType::UnexpectedArg(variant.name().to_string())),
+            location: extra_location,
+            related: vec![],
+        });
+    }
+    Ok((Instruction::new(variant, arg, location), label))
+}
+
+fn parse_arg(arg: &str) -> Option<u8> {
+    let lower = arg.to_ascii_lowercase();
+    let (digits, radix) = match (lower.strip_prefix("0x"), lower.strip_prefix("0b")) {
+        (Some(hex), _) => (hex, 16),
+        (_, Some(binary)) => (binary, 2),
+        _ => (lower.as_str(), 10),
+    };
+    u8::from_str_radix(digits, radix).ok()
+}
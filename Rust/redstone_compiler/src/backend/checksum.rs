@@ -0,0 +1,78 @@
+//! Checksums of the ROM, to find out whether one that was built in a world has the
+//! program it should have, and where it doesn't
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// CRC-32 of every page and of the whole program, over the words in little-endian
+/// like the binary has them
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checksums {
+    pub pages: Vec<u32>,
+    pub program: u32,
+}
+
+impl Checksums {
+    #[must_use]
+    pub fn new(words: &[u16], page_size: usize) -> Self {
+        let bytes = |words: &[u16]| -> Vec<u8> {
+            words.iter().flat_map(|word| word.to_le_bytes()).collect()
+        };
+        Self {
+            pages: words
+                .chunks(page_size.max(1))
+                .map(|page| crc32(&bytes(page)))
+                .collect(),
+            program: crc32(&bytes(words)),
+        }
+    }
+}
+
+/// A word that isn't what it should be, `None` if the ROM is too short or too long
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Difference {
+    pub address: usize,
+    pub expected: Option<u16>,
+    pub actual: Option<u16>,
+}
+
+/// Every address where `actual` differs from `expected`
+#[must_use]
+pub fn differences(expected: &[u16], actual: &[u16]) -> Vec<Difference> {
+    (0..expected.len().max(actual.len()))
+        .map(|address| Difference {
+            address,
+            expected: expected.get(address).copied(),
+            actual: actual.get(address).copied(),
+        })
+        .filter(|difference| difference.expected != difference.actual)
+        .collect()
+}
+
+/// The checksum of gzip and zip, with the reversed polynomial `0xEDB88320`
+#[must_use]
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[usize::from(crc as u8 ^ byte)] ^ (crc >> 8)
+    })
+}
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+}
@@ -1,24 +1,33 @@
 use std::{
-    any::Any,
     collections::{HashMap, HashSet},
     fmt::Debug,
+    hash::BuildHasher,
 };
 
 use vec1::{vec1, Vec1};
 
 use crate::{
-    backend::{module::Call, ComputerState, Instr, RegisterContents, Scope},
+    backend::{
+        module::{Call, ModuleState},
+        CompileOptions, ComputerState, Instr, OptLevel, RegisterContents, Scope,
+    },
     err,
-    error::Error,
-    frontend::{EqualityOperator, Expression, ExpressionType, Ident, Operator, Range},
+    error::{Error, Related},
+    frontend::{
+        unparse_expression, Ast, EqualityOperator, ExprId, Expression, ExpressionType, Ident,
+        Operator, Range, UnaryOperator, MAX_NESTING,
+    },
 };
 
 use super::{
-    module::{call, exist, init},
-    ErrorType, Instruction, InstructionVariant,
+    incremental::{relocate, shift, Code, Mark, Snapshot},
+    module::{call, docs::builtin_doc, exist, init},
+    CompilationResult, DebugInfo, ErrorType, Instruction, InstructionVariant, Symbol, Target,
+    Warning, WarningType,
 };
 
-const VAR_SLOTS: usize = 32;
+/// RAM slots for variables, shared by named and temporary ones
+pub const VAR_SLOTS: usize = 32;
 
 type Res<T = (), E = Error> = Result<T, E>;
 
@@ -53,10 +62,11 @@ macro_rules! instr {
 /// # Examples
 ///
 /// ```
-/// use redstone_compiler::{frontend::{Expression, ExpressionType, Range, Location}, backend::{compile_program, Instruction, InstructionVariant}};
-/// let ast = vec![Expression { typ: ExpressionType::NumericLiteral(5), location: Range(Location(0, 0), Location(0, 0)) }];
+/// use redstone_compiler::{frontend::{Ast, Expression, ExpressionType, Range, Location}, backend::{compile_program, Instruction, InstructionVariant}};
+/// let mut ast = Ast::new();
+/// ast.push_statement(Expression { typ: ExpressionType::NumericLiteral(5), location: Range(Location(0, 0), Location(0, 0)) });
 ///
-/// let compiled = compile_program(ast).unwrap();
+/// let compiled = compile_program(&ast).unwrap();
 ///
 /// assert_eq!(
 ///     compiled,
@@ -64,48 +74,166 @@ macro_rules! instr {
 ///     Location(0, 0)))]
 /// );
 /// ```
-pub fn compile_program(ast: Vec<Expression>) -> Res<Vec<Instruction>, Vec<Error>> {
-    let compiler = Compiler::new();
-    compiler.generate_assembly(ast)
+pub fn compile_program(ast: &Ast) -> Res<Vec<Instruction>, Vec<Error>> {
+    compile_program_with(ast, CompileOptions::default())
+}
+
+/// compile with a specific optimization level and target
+///
+/// # Errors
+///
+/// on any compiler error
+pub fn compile_program_with(
+    ast: &Ast,
+    options: CompileOptions,
+) -> Res<Vec<Instruction>, Vec<Error>> {
+    phase!("compile", opt_level = ?options.opt_level, target = options.target.name);
+    let compiler = Compiler::new(ast, options);
+    compiler.generate_assembly()
+}
+
+/// Like [`compile_program_with`], also returns every variable with its slot
+///
+/// # Errors
+///
+/// on any compiler error
+pub fn compile_program_with_symbols(
+    ast: &Ast,
+    options: CompileOptions,
+) -> Res<(Vec<Instruction>, Vec<Symbol>), Vec<Error>> {
+    compile_program_with_debug_info(ast, options)
+        .map(|(instructions, debug_info)| (instructions, debug_info.symbols))
+}
+
+/// Like [`compile_program_with`], also returns what a debugger needs to load the binary
+///
+/// # Errors
+///
+/// on any compiler error
+pub fn compile_program_with_debug_info(
+    ast: &Ast,
+    options: CompileOptions,
+) -> Res<(Vec<Instruction>, DebugInfo), Vec<Error>> {
+    phase!("compile", opt_level = ?options.opt_level, target = options.target.name);
+    let mut compiler = Compiler::new(ast, options);
+    compiler.eval_program()?;
+    compiler.link_with_debug_info().map_err(|error| vec![error])
+}
+
+/// Like [`compile_program_with`], with what the compiler found out about the program
+///
+/// # Errors
+///
+/// on any compiler error
+pub fn compile(ast: &Ast, options: CompileOptions) -> Res<CompilationResult, Vec<Error>> {
+    compile_program_with_debug_info(ast, options)
+        .map(|(instructions, debug_info)| CompilationResult::new(instructions, debug_info))
+}
+
+/// Like [`compile_program_with`], but stops before linking
+///
+/// # Errors
+///
+/// on any compiler error
+pub fn compile_program_ir(ast: &Ast, options: CompileOptions) -> Res<Vec<Instr>, Vec<Error>> {
+    phase!("compile", opt_level = ?options.opt_level, target = options.target.name);
+    let mut compiler = Compiler::new(ast, options);
+    compiler.eval_program()?;
+    Ok(compiler.into_ir())
+}
+
+/// Value of an expression folded like the compiler folds it, `inline_variables` are
+/// the inline variables it can use
+///
+/// # Errors
+///
+/// if the value isn't known at compile-time or doesn't fit
+pub fn eval_constant<S: BuildHasher>(
+    ast: &Ast,
+    expression: &Expression,
+    inline_variables: &HashMap<String, i16, S>,
+) -> Res<i16> {
+    let mut compiler = Compiler::new(ast, CompileOptions::default());
+    for (symbol, &value) in inline_variables {
+        compiler.insert_inline_var(symbol.clone(), value);
+    }
+    compiler.try_eval_const(expression)
 }
 
 #[derive(Debug)]
-pub struct Compiler {
+pub struct Compiler<'a> {
+    /// The program being compiled, expressions are borrowed from it
+    ast: &'a Ast,
     scopes: Vec1<Scope>,
     main_scope: Vec<Instr>,
     modules: HashSet<String>,
-    jump_marks: HashMap<u8, u8>,
+    /// Where each module was used first. Not part of a [`Snapshot`], modules used by
+    /// earlier pieces of a [`Session`](super::Session) aren't in here
+    module_uses: HashMap<String, Range>,
+    /// Address every jump mark points to, by its id
+    jump_marks: Vec<usize>,
+    options: CompileOptions,
+    symbols: Vec<Symbol>,
+    warnings: Vec<Warning>,
+    /// Statements and expressions that are being evaluated
+    depth: usize,
     pub variables: [bool; VAR_SLOTS],
-    pub module_state: HashMap<&'static str, Box<dyn Any>>,
+    module_state: ModuleState,
 }
 
-impl Compiler {
-    fn new() -> Self {
+impl<'a> Compiler<'a> {
+    pub(super) fn new(ast: &'a Ast, options: CompileOptions) -> Self {
+        let state = if options.opt_level == OptLevel::O0 {
+            ComputerState::unknown()
+        } else {
+            ComputerState::default()
+        };
         Self {
-            scopes: vec1!(Scope::default()),
+            ast,
+            scopes: vec1!(Scope::with_state(state)),
             modules: HashSet::new(),
+            module_uses: HashMap::new(),
             main_scope: vec![],
-            jump_marks: HashMap::new(),
+            jump_marks: vec![],
+            options,
+            symbols: vec![],
+            warnings: vec![],
+            depth: 0,
             variables: [false; VAR_SLOTS],
-            module_state: HashMap::new(),
+            module_state: ModuleState::default(),
         }
     }
 
-    fn scope_len(scope: &Vec<Instr>) -> u8 {
-        let mut sum = 0;
-        for i in scope {
-            sum += match i {
-                Instr::Code(_) => 1,
-                Instr::Scope(s) => Self::scope_len(s),
-            }
-        }
-        sum
+    /// The expression `id` refers to
+    #[must_use]
+    pub fn expr(&self, id: ExprId) -> &'a Expression {
+        &self.ast[id]
     }
 
-    pub fn get_module_state<'a, V: 'static>(&'a mut self, key: &'static str) -> Option<&'a mut V> {
-        let value = self.module_state.get_mut(key)?;
+    /// Address of the next instruction, the open scopes are only added to their
+    /// parent when they are closed
+    fn current_address(&self) -> usize {
+        self.scopes.iter().map(|scope| scope.len).sum()
+    }
 
-        value.downcast_mut::<V>()
+    /// The state of type `T` a module stored with [`Compiler::set_state`]
+    #[must_use]
+    pub fn state<T: 'static>(&self) -> Option<&T> {
+        self.module_state.get()
+    }
+
+    pub fn set_state<T: 'static>(&mut self, value: T) {
+        self.module_state.insert(value);
+    }
+
+    /// Everything modules stored, so a [`Session`](super::Session) can hand it to the
+    /// compiler of the next statements
+    pub(super) fn take_module_state(&mut self) -> ModuleState {
+        std::mem::take(&mut self.module_state)
+    }
+
+    pub(super) fn set_module_state(&mut self, module_state: ModuleState) {
+        self.module_state = module_state;
     }
 
     fn insert_inline_var(&mut self, symbol: String, value: i16) {
@@ -113,7 +241,7 @@ impl Compiler {
         last_scope.inline_variables.insert(symbol, value);
     }
 
-    fn get_inline_var(&self, symbol: &String, location: Range) -> Res<i16> {
+    fn get_inline_var(&self, symbol: &str, location: Range) -> Res<i16> {
         for scope in self.scopes.iter().rev() {
             let entry = scope.inline_variables.get(symbol);
             if let Some(v) = entry {
@@ -121,8 +249,9 @@ impl Compiler {
             }
         }
         Err(Error {
-            typ: Box::new(ErrorType::NonexistentInlineVar(symbol.clone())),
+            typ: Box::new(ErrorType::NonexistentInlineVar(symbol.to_string())),
             location,
+            related: vec![],
         })
     }
 
@@ -142,10 +271,17 @@ impl Compiler {
         let slot = self.get_next_available_slot().ok_or(Error {
             typ: Box::new(ErrorType::TooManyVars),
             location,
+            related: vec![],
         })?;
         self.last_scope_mut()
             .variables
             .insert(symbol.to_owned(), slot);
+        self.symbols.push(Symbol {
+            name: symbol.to_owned(),
+            slot,
+            depth: self.scopes.len() - 1,
+            location,
+        });
         Ok(slot)
     }
 
@@ -154,12 +290,13 @@ impl Compiler {
     /// # Errors
     ///
     /// on any compiler error
-    pub fn get_var(&self, symbol: &String, location: Range) -> Res<u8> {
+    pub fn get_var(&self, symbol: &str, location: Range) -> Res<u8> {
         self.get_var_noerror(symbol).map_or_else(
             || {
                 Err(Error {
-                    typ: Box::new(ErrorType::NonexistentVar(symbol.clone())),
+                    typ: Box::new(ErrorType::NonexistentVar(symbol.to_string())),
                     location,
+                    related: vec![],
                 })
             },
             Ok,
@@ -167,7 +304,7 @@ impl Compiler {
     }
 
     #[must_use]
-    pub fn get_var_noerror(&self, symbol: &String) -> Option<u8> {
+    pub fn get_var_noerror(&self, symbol: &str) -> Option<u8> {
         for scope in self.scopes.iter().rev() {
             let entry = scope.variables.get(symbol);
             if let Some(v) = entry {
@@ -177,6 +314,17 @@ impl Compiler {
         None
     }
 
+    /// Name of the variable in `slot`, temporary variables don't have one
+    fn var_name(&self, slot: u8) -> Option<String> {
+        self.scopes.iter().rev().find_map(|scope| {
+            scope
+                .variables
+                .iter()
+                .find(|(_, &variable)| variable == slot)
+                .map(|(name, _)| name.clone())
+        })
+    }
+
     /// Inserts a temporary variable
     ///
     /// # Errors
@@ -186,35 +334,123 @@ impl Compiler {
         self.get_next_available_slot().ok_or(Error {
             typ: Box::new(ErrorType::TooManyVars),
             location,
+            related: vec![],
         })
     }
 
-    pub fn cleanup_temp_var(&mut self, index: u8) {
+    pub const fn cleanup_temp_var(&mut self, index: u8) {
         self.variables[index as usize] = false;
     }
 
     /// use the "instr" macro
-    pub fn push_instr(&mut self, instr: Instruction) {
+    pub fn push_instr(&mut self, mut instr: Instruction) {
+        if instr.uses_slot() && instr.comment.is_none() {
+            instr.comment = instr.arg.and_then(|slot| self.var_name(slot));
+        }
+        let forget_state = self.options.opt_level == OptLevel::O0;
         let last_scope = self.last_scope_mut();
-        instr.execute(&mut last_scope.state);
+        if forget_state {
+            last_scope.state = ComputerState::unknown();
+        } else {
+            instr.execute(&mut last_scope.state);
+        }
         last_scope.instructions.push(Instr::Code(instr));
+        last_scope.len += 1;
     }
 
-    fn get_instructions(mut self) -> Vec<Instruction> {
+    pub(super) fn link_with_debug_info(mut self) -> Res<(Vec<Instruction>, DebugInfo)> {
+        let symbols = std::mem::take(&mut self.symbols);
+        let warnings = std::mem::take(&mut self.warnings);
+        let mut modules: Vec<_> = std::mem::take(&mut self.modules).into_iter().collect();
+        modules.sort();
+        let target = self.options.target;
+        let (instructions, jump_marks) = self.link_program()?;
+        let debug_info = DebugInfo::new(
+            &instructions,
+            &target,
+            symbols,
+            jump_marks,
+            modules,
+            warnings,
+        );
+        Ok((instructions, debug_info))
+    }
+
+    fn get_instructions(self) -> Res<Vec<Instruction>> {
+        Ok(self.link_program()?.0)
+    }
+
+    /// Also returns the address of every jump mark
+    fn link_program(mut self) -> Res<(Vec<Instruction>, Vec<usize>)> {
+        phase!("link");
+        let mut jump_marks = std::mem::take(&mut self.jump_marks);
+        let target = self.options.target;
+        let mut instructions = Vec::with_capacity(self.scopes.iter().map(|scope| scope.len).sum());
+        Self::flatten_scope(self.into_ir(), &mut instructions);
+        Self::link(&mut instructions, &mut jump_marks, &target)?;
+        Ok((instructions, jump_marks))
+    }
+
+    /// The code of the program, with the main scope last
+    fn into_ir(mut self) -> Vec<Instr> {
         self.main_scope
             .push(Instr::Scope(self.scopes.split_off_first().0.instructions));
-        let mut instructions = vec![];
-        Self::flatten_scope(self.main_scope, &mut instructions);
-        Self::insert_disc_jumps(&mut instructions, &mut self.jump_marks);
-        Self::replace_jump_marks(&mut instructions, &self.jump_marks);
-        instructions
+        self.main_scope
     }
 
+    /// Turns the jump marks in the args of jumps into addresses, inserting disc jumps
+    /// where they leave their page
+    ///
+    /// # Errors
+    ///
+    /// If the program has more pages than the target
+    pub(super) fn link(
+        instructions: &mut Vec<Instruction>,
+        jump_marks: &mut [usize],
+        target: &Target,
+    ) -> Res {
+        Self::insert_disc_jumps(instructions, jump_marks, target)?;
+        Self::label_jump_targets(instructions, jump_marks);
+        Self::replace_jump_marks(instructions, jump_marks, target.page_size);
+        Ok(())
+    }
+
+    /// Names the instructions jumps go to, if they don't have a label yet, and
+    /// comments the jumps with the name, or `end` if they leave the program
+    fn label_jump_targets(instructions: &mut [Instruction], jump_marks: &[usize]) {
+        let target = |instr: &Instruction| jump_marks.get(instr.jump_mark?).copied();
+        let targets: Vec<_> = instructions.iter().filter_map(target).collect();
+        for target in targets {
+            if let Some(instr) = instructions.get_mut(target) {
+                instr.label.get_or_insert_with(|| format!("L{target}"));
+            }
+        }
+        for index in 0..instructions.len() {
+            if !instructions[index].variant.is_jump() {
+                continue;
+            }
+            let label = match target(&instructions[index]).map(|target| instructions.get(target)) {
+                Some(Some(target)) => target.label.clone(),
+                // the program halts after its last instruction
+                Some(None) => Some("end".to_string()),
+                None => None,
+            };
+            instructions[index].comment = label;
+        }
+    }
+
+    /// Without recursion, so deeply nested code can't overflow the stack
     fn flatten_scope(scope: Vec<Instr>, into: &mut Vec<Instruction>) {
-        scope.into_iter().for_each(|i| match i {
-            Instr::Code(instr) => into.push(instr),
-            Instr::Scope(s) => Self::flatten_scope(s, into),
-        });
+        let mut stack = vec![scope.into_iter()];
+        while let Some(scope) = stack.last_mut() {
+            match scope.next() {
+                Some(Instr::Code(instr)) => into.push(instr),
+                Some(Instr::Scope(inner)) => stack.push(inner.into_iter()),
+                None => {
+                    stack.pop();
+                }
+            }
+        }
     }
 
     #[must_use]
@@ -230,90 +466,247 @@ impl Compiler {
         self.scopes.len() == 1
     }
 
-    fn generate_assembly(mut self, body: Vec<Expression>) -> Res<Vec<Instruction>, Vec<Error>> {
-        let errors = body
-            .into_iter()
+    fn generate_assembly(mut self) -> Res<Vec<Instruction>, Vec<Error>> {
+        self.eval_program()?;
+        self.get_instructions().map_err(|error| vec![error])
+    }
+
+    /// Register contents are tracked while generating, so this is also where the
+    /// optimizations of [`OptLevel::O1`] happen
+    pub(super) fn eval_program(&mut self) -> Res<(), Vec<Error>> {
+        let ast = self.ast;
+        phase!("generate", statements = ast.body().len());
+        let errors = ast
+            .statements()
             .filter_map(|line| self.eval_statement(line).err())
             .collect::<Vec<_>>();
-        if !errors.is_empty() {
-            return Err(errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
+    }
 
-        Ok(self.get_instructions())
+    fn insert_jump_mark(&mut self) -> usize {
+        self.jump_marks.push(0);
+        self.jump_marks.len() - 1
     }
 
-    fn insert_jump_mark(&mut self) -> u8 {
-        let id = self.jump_marks.len() as u8;
-        self.jump_marks.insert(id, 0);
-        id
+    fn push_jump(&mut self, variant: InstructionVariant, jump_mark: usize, location: Range) {
+        self.push_instr(Instruction::jump(variant, jump_mark, location));
     }
 
-    fn eval_statement(&mut self, line: Expression) -> Res {
-        match line.typ {
-            ExpressionType::InlineDeclaration { ident, value } => {
-                let value = self.try_eval_const(&value).map_err(|loc| Error {
-                    typ: Box::new(ErrorType::ForbiddenInline),
-                    location: loc,
-                })?;
-                self.insert_inline_var(ident.symbol, value);
-                Ok(())
+    /// Runs `eval` one level deeper, errors instead if that's too deep. Parsed code
+    /// can't get there, but trees can also be built by hand
+    fn nested(&mut self, location: Range, eval: impl FnOnce(&mut Self) -> Res) -> Res {
+        if self.depth >= MAX_NESTING {
+            return err!(NestingTooDeep, location);
+        }
+        self.depth += 1;
+        let result = eval(self);
+        self.depth -= 1;
+        result
+    }
+
+    pub(super) fn eval_statement(&mut self, line: &'a Expression) -> Res {
+        if self.last_scope().stopped {
+            self.warn_unreachable(line.location);
+            return Ok(());
+        }
+        let start = self.last_scope().instructions.len();
+        self.nested(line.location, |compiler| {
+            compiler.eval_unnested_statement(line)
+        })?;
+        self.comment_statement(line, start);
+        if self.never_finishes(line) {
+            self.last_scope_mut().stopped = true;
+        }
+        Ok(())
+    }
+
+    /// Whether the code after a statement can't be reached from it. Jumps only go to
+    /// the start and the end of a block, so nothing can jump there either
+    fn never_finishes(&self, line: &Expression) -> bool {
+        let block_stops = |block: &[ExprId]| {
+            block
+                .iter()
+                .any(|&statement| self.never_finishes(self.expr(statement)))
+        };
+        match &line.typ {
+            ExpressionType::EndlessLoop { .. } | ExpressionType::Todo => true,
+            ExpressionType::Conditional {
+                body,
+                paths,
+                alternate: Some(alternate),
+                ..
+            } => {
+                block_stops(body)
+                    && paths.iter().all(|(_, body)| block_stops(body))
+                    && block_stops(alternate)
             }
-            ExpressionType::Use(modules) => {
-                for module in modules {
-                    if !self.is_root_scope() {
-                        return Err(Error {
-                            typ: Box::new(ErrorType::UseOutsideGlobalScope),
-                            location: line.location,
-                        });
-                    }
-                    if !exist(&module.symbol) {
-                        return Err(Error {
-                            typ: Box::new(ErrorType::NonexistentModule(module.symbol)),
-                            location: line.location,
-                        });
-                    }
-                    init(&module.symbol, self, line.location)?;
-                    self.modules.insert(module.symbol);
-                }
+            _ => false,
+        }
+    }
+
+    /// Warns about an unreachable statement, together with the ones right before it
+    fn warn_unreachable(&mut self, location: Range) {
+        if let Some(index) = self.last_scope().unreachable {
+            let warning = &mut self.warnings[index];
+            warning.location = warning.location + location;
+            return;
+        }
+        self.last_scope_mut().unreachable = Some(self.warnings.len());
+        self.warnings.push(Warning {
+            typ: WarningType::Unreachable,
+            location,
+            related: vec![],
+        });
+    }
+
+    /// Warns about a module that is used again, initializing it twice would repeat its
+    /// setup
+    fn warn_duplicate_use(&mut self, module: &Ident) {
+        let related = self
+            .module_uses
+            .get(&module.symbol)
+            .map(|&location| Related {
+                location,
+                message: format!("{} is first used here", module.symbol),
+            });
+        self.warnings.push(Warning {
+            typ: WarningType::DuplicateUse(module.symbol.clone()),
+            location: module.location,
+            related: related.into_iter().collect(),
+        });
+    }
+
+    /// Comments the first instruction of a statement with its code, the first line of
+    /// it for blocks
+    fn comment_statement(&mut self, line: &Expression, start: usize) {
+        let ast = self.ast;
+        let mut instructions = self.last_scope_mut().instructions.get_mut(start..);
+        let first = loop {
+            match instructions.and_then(|instructions| instructions.first_mut()) {
+                Some(Instr::Code(instr)) => break instr,
+                Some(Instr::Scope(inner)) => instructions = Some(inner),
+                None => return,
+            }
+        };
+        let code = unparse_expression(ast, line);
+        first.comment = code.lines().next().map(str::to_string);
+    }
+
+    fn eval_use(&mut self, modules: &[Ident], location: Range) -> Res {
+        for module in modules {
+            if !self.is_root_scope() {
+                return Err(Error {
+                    typ: Box::new(ErrorType::UseOutsideGlobalScope),
+                    location,
+                    related: vec![],
+                });
+            }
+            if !exist(&module.symbol) {
+                return Err(Error {
+                    typ: Box::new(ErrorType::NonexistentModule(module.symbol.clone())),
+                    location,
+                    related: vec![],
+                });
+            }
+            if self.modules.contains(&module.symbol) {
+                self.warn_duplicate_use(module);
+                continue;
+            }
+            init(&module.symbol, self, location)?;
+            self.modules.insert(module.symbol.clone());
+            self.module_uses
+                .insert(module.symbol.clone(), module.location);
+        }
+        Ok(())
+    }
+
+    fn eval_unnested_statement(&mut self, line: &'a Expression) -> Res {
+        match &line.typ {
+            ExpressionType::InlineDeclaration { ident, value } => {
+                let value = self.try_eval_const(self.expr(*value))?;
+                self.insert_inline_var(ident.symbol.clone(), value);
                 Ok(())
             }
+            ExpressionType::Use(modules) => self.eval_use(modules, line.location),
             ExpressionType::VarDeclaration { ident } => {
                 self.insert_var(&ident.symbol, line.location)?;
                 Ok(())
             }
             ExpressionType::Pass => Ok(()),
+            ExpressionType::Todo => {
+                self.warnings.push(Warning {
+                    typ: WarningType::Todo,
+                    location: line.location,
+                    related: vec![],
+                });
+                instr!(self, STOP, line.location);
+                Ok(())
+            }
+            ExpressionType::StaticAssert { condition, message } => {
+                if self.try_eval_const(self.expr(*condition))? == 0 {
+                    return err!(
+                        ErrorType::StaticAssertFailed(message.clone()),
+                        line.location
+                    );
+                }
+                Ok(())
+            }
+            ExpressionType::Assert { condition } => {
+                let condition = self.eval_condition(self.expr(*condition))?;
+                if !self.options.assertions {
+                    return Ok(());
+                }
+                let holds = self.insert_jump_mark();
+                self.put_comparison(condition, line.location, holds)?;
+                instr!(self, STOP, line.location);
+                self.jump_marks[holds] = self.current_address();
+                Ok(())
+            }
+            ExpressionType::Wait { ticks } => {
+                let ticks = self.try_eval_const(self.expr(*ticks))?;
+                self.eval_wait(ticks, line.location)
+            }
+            ExpressionType::Swap { left, right } => {
+                self.eval_swap(self.expr(*left), self.expr(*right), line.location)
+            }
             ExpressionType::EndlessLoop { body } => {
-                let mark = Self::scope_len(&self.scopes.first().instructions);
+                let mark = self.current_address();
                 let id = self.insert_jump_mark();
-                self.jump_marks.insert(id, mark);
+                self.jump_marks[id] = mark;
 
-                self.push_scope(body, ComputerState::default())?;
+                // the body is also entered from its end
+                self.push_scope(body, ComputerState::unknown())?;
                 self.pop_scope();
 
-                instr!(self, JMP, id, line.location);
+                self.push_jump(InstructionVariant::JMP, id, line.location);
 
                 Ok(())
             }
             ExpressionType::WhileLoop { condition, body } => {
-                let (left, right, operator) = eval_condition(*condition)?;
+                let (left, right, operator) = self.eval_condition(self.expr(*condition))?;
 
                 let start_id = self.insert_jump_mark();
                 let end_id = self.insert_jump_mark();
 
-                self.put_comparison((&left, &right, operator.opposite()), line.location, end_id)?;
+                self.put_comparison((left, right, operator.opposite()), line.location, end_id)?;
 
-                let start = Self::scope_len(&self.scopes.first().instructions);
+                let start = self.current_address();
 
-                self.jump_marks.insert(start_id, start);
+                self.jump_marks[start_id] = start;
 
-                self.push_scope(body, self.last_scope().state)?;
+                // the body is also entered from the comparison at its end
+                self.push_scope(body, ComputerState::unknown())?;
 
-                self.put_comparison((&left, &right, operator), line.location, start_id)?;
+                self.put_comparison((left, right, operator), line.location, start_id)?;
 
                 self.pop_scope();
-                let end = Self::scope_len(&self.scopes.first().instructions);
+                let end = self.current_address();
 
-                self.jump_marks.insert(end_id, end);
+                self.jump_marks[end_id] = end;
 
                 Ok(())
             }
@@ -322,60 +715,55 @@ impl Compiler {
                 body,
                 paths,
                 alternate,
-            } => self.eval_conditional(*condition, body, paths, alternate)?,
-            _ => self.eval_expr(&line),
+            } => self.eval_conditional(self.expr(*condition), body, paths, alternate.as_deref())?,
+            _ => self.eval_expr(line),
         }?;
         Ok(())
     }
 
     fn eval_conditional(
         &mut self,
-        condition: Expression,
-        body: Vec<Expression>,
-        paths: Vec<(Expression, Vec<Expression>)>,
-        alternate: Option<Vec<Expression>>,
+        condition: &'a Expression,
+        body: &'a [ExprId],
+        paths: &'a [(ExprId, Vec<ExprId>)],
+        alternate: Option<&'a [ExprId]>,
     ) -> Result<Result<(), Error>, Error> {
         let location = condition.location;
-        let (left, right, operator) = eval_condition(condition)?;
+        let (left, right, operator) = self.eval_condition(condition)?;
         let end_id = self.insert_jump_mark();
         let mut next_mark_id = self.insert_jump_mark();
 
-        self.put_comparison((&left, &right, operator.opposite()), location, next_mark_id)?;
+        self.put_comparison((left, right, operator.opposite()), location, next_mark_id)?;
 
         let mut last_state = self.last_scope().state;
 
         self.push_scope(body, last_state)?;
         if !paths.is_empty() || alternate.is_some() {
-            instr!(self, JMP, end_id, location);
+            self.push_jump(InstructionVariant::JMP, end_id, location);
         }
         self.pop_scope();
-        self.jump_marks.insert(
-            next_mark_id,
-            Self::scope_len(&self.scopes.first().instructions),
-        );
+        self.jump_marks[next_mark_id] = self.current_address();
         let path_len = paths.len();
-        paths.into_iter().enumerate().try_for_each(|path| {
+        paths.iter().enumerate().try_for_each(|path| {
             let (index, (condition, body)) = path;
+            let condition = self.expr(*condition);
             let location = condition.location;
-            let (left, right, operator) = eval_condition(condition)?;
+            let (left, right, operator) = self.eval_condition(condition)?;
 
             next_mark_id = self.insert_jump_mark();
 
-            self.put_comparison((&left, &right, operator.opposite()), location, next_mark_id)?;
+            self.put_comparison((left, right, operator.opposite()), location, next_mark_id)?;
 
             last_state = self.last_scope().state;
 
             self.push_scope(body, last_state)?;
 
             if index != path_len - 1 || alternate.is_some() {
-                instr!(self, JMP, end_id, location);
+                self.push_jump(InstructionVariant::JMP, end_id, location);
             }
 
             self.pop_scope();
-            self.jump_marks.insert(
-                next_mark_id,
-                Self::scope_len(&self.scopes.first().instructions),
-            );
+            self.jump_marks[next_mark_id] = self.current_address();
 
             Ok(())
         })?;
@@ -383,14 +771,18 @@ impl Compiler {
             self.push_scope(body, last_state)?;
             self.pop_scope();
         }
-        self.jump_marks
-            .insert(end_id, Self::scope_len(&self.scopes.first().instructions));
+        self.jump_marks[end_id] = self.current_address();
         Ok(Ok(()))
     }
 
+    /// Code after the scope can be reached from its end or by jumping over it,
+    /// so only what is known on both ways is kept
     fn pop_scope(&mut self) {
         let scope = self.scopes.pop().unwrap();
-        self.last_scope_mut()
+        let last_scope = self.last_scope_mut();
+        last_scope.state = last_scope.state.merge(scope.state);
+        last_scope.len += scope.len;
+        last_scope
             .instructions
             .push(Instr::Scope(scope.instructions));
         for i in scope.variables {
@@ -399,10 +791,10 @@ impl Compiler {
         }
     }
 
-    fn push_scope(&mut self, body: Vec<Expression>, state: ComputerState) -> Res {
+    fn push_scope(&mut self, body: &[ExprId], state: ComputerState) -> Res {
         self.scopes.push(Scope::with_state(state));
-        body.into_iter()
-            .try_for_each(|line| self.eval_statement(line))?;
+        body.iter()
+            .try_for_each(|&line| self.eval_statement(self.expr(line)))?;
         Ok(())
     }
 
@@ -410,7 +802,7 @@ impl Compiler {
         &mut self,
         condition: (&Expression, &Expression, EqualityOperator),
         location: Range,
-        jump_to: u8,
+        jump_to: usize,
     ) -> Res {
         let (left, right, operator) = condition;
         let op = if self.put_ab(left, right, true)? {
@@ -418,37 +810,52 @@ impl Compiler {
         } else {
             operator
         };
-        self.push_instr(Instruction::new(
-            InstructionVariant::from_op(op),
-            Some(jump_to),
-            location,
-        ));
+        self.push_jump(InstructionVariant::from_op(op), jump_to, location);
         Ok(())
     }
 
-    fn try_eval_const(&mut self, expr: &Expression) -> Result<i16, Range> {
+    fn try_eval_const(&mut self, expr: &Expression) -> Res<i16> {
         match &expr.typ {
-            ExpressionType::Identifier(name) => self
-                .get_inline_var(name, expr.location)
-                .map_err(|e| e.location),
+            ExpressionType::Identifier(name) => {
+                self.get_inline_var(name, expr.location)
+                    .map_err(|error| Error {
+                        typ: Box::new(ErrorType::ForbiddenInline),
+                        location: error.location,
+                        related: vec![],
+                    })
+            }
             ExpressionType::BinaryExpr {
                 left,
                 right,
                 operator,
             } => {
-                let left = self.try_eval_const(left)?;
-                let right = self.try_eval_const(right)?;
-                Ok(match operator {
-                    Operator::Plus => left + right,
-                    Operator::Minus => left - right,
-                    Operator::Mult => left * right,
-                    Operator::And => left & right,
-                    Operator::Or => left | right,
-                    Operator::Xor => left ^ right,
-                })
+                let left = self.try_eval_const(self.expr(*left))?;
+                let right = self.try_eval_const(self.expr(*right))?;
+                fold(left, right, *operator, expr.location)
+            }
+            ExpressionType::UnaryExpr { value, operator } => {
+                let value = self.try_eval_const(self.expr(*value))?;
+                match operator {
+                    UnaryOperator::Negate => value.checked_neg().ok_or(Error {
+                        typ: Box::new(ErrorType::ConstantOverflow),
+                        location: expr.location,
+                        related: vec![],
+                    }),
+                    UnaryOperator::Not => Ok(!value),
+                }
+            }
+            // comparisons are 1 if they hold and 0 otherwise
+            ExpressionType::EqExpr {
+                left,
+                right,
+                operator,
+            } => {
+                let left = self.try_eval_const(self.expr(*left))?;
+                let right = self.try_eval_const(self.expr(*right))?;
+                Ok(i16::from(operator.holds(left, right)))
             }
             ExpressionType::NumericLiteral(value) => Ok(*value),
-            _ => Err(expr.location),
+            _ => err!(ForbiddenInline, expr.location),
         }
     }
 
@@ -457,25 +864,40 @@ impl Compiler {
     ///
     /// on any compiler error
     pub fn eval_expr(&mut self, expr: &Expression) -> Res {
+        self.nested(expr.location, |compiler| compiler.eval_unnested_expr(expr))
+    }
+
+    fn eval_unnested_expr(&mut self, expr: &Expression) -> Res {
         match &expr.typ {
-            ExpressionType::NumericLiteral(..) => self.put_into_a(expr)?,
-            ExpressionType::Identifier(..) => self.put_into_a(expr)?,
+            ExpressionType::NumericLiteral(..) | ExpressionType::Identifier(..) => {
+                self.put_into_a(expr)?;
+            }
             ExpressionType::BinaryExpr {
                 left,
                 right,
                 operator,
-            } => self.eval_binary_expr(left, right, *operator, expr.location)?,
+            } => self.eval_binary_expr(
+                self.expr(*left),
+                self.expr(*right),
+                *operator,
+                expr.location,
+            )?,
             ExpressionType::Assignment { ident, value } => {
-                self.eval_assignment(&ident.symbol, value)?;
+                self.eval_assignment(&ident.symbol, self.expr(*value))?;
             }
             ExpressionType::IAssignment {
-                ident,
+                variable,
                 value,
                 operator,
             } => {
-                self.eval_iassignment(ident, value, *operator)?;
+                self.eval_iassignment(self.expr(*variable), self.expr(*value), *operator)?;
+            }
+            ExpressionType::Call { args, function } => {
+                self.eval_call(self.expr(*function), args)?;
+            }
+            ExpressionType::UnaryExpr { value, operator } => {
+                self.eval_unary(expr, self.expr(*value), *operator)?;
             }
-            ExpressionType::Call { args, function } => self.eval_call(function, args)?,
             ExpressionType::EqExpr { .. } => {
                 return err!(EqInNormalExpr, expr.location);
             }
@@ -487,11 +909,11 @@ impl Compiler {
     }
 
     #[must_use]
-    pub const fn can_put_into_a(expr: &Expression) -> bool {
+    pub fn can_put_into_a(&self, expr: &Expression) -> bool {
         use ExpressionType as E;
         match &expr.typ {
             E::NumericLiteral(..) | E::Identifier(..) => true,
-            E::Assignment { ident: _, value } => Self::can_put_into_a(value),
+            E::Assignment { ident: _, value } => self.can_put_into_a(self.expr(*value)),
             _ => false,
         }
     }
@@ -509,17 +931,86 @@ impl Compiler {
         operator: Operator,
         location: Range,
     ) -> Res {
-        self.put_ab(left, right, operator.is_commutative())?;
+        match operator {
+            Operator::Div | Operator::Mod => {
+                let (Some(left), Some(right)) =
+                    (self.try_get_constant(left), self.try_get_constant(right))
+                else {
+                    return err!(RuntimeDivision, location);
+                };
+                let value = fold(left, right, operator, location)?;
+                self.put_a_number(value, location);
+            }
+            Operator::ShiftLeft | Operator::ShiftRight => {
+                let Some(distance) = self.try_get_constant(right) else {
+                    return err!(
+                        ErrorType::CompileTimeArg("The distance of a shift".to_string()),
+                        right.location
+                    );
+                };
+                let Ok(distance @ 0..=15) = u8::try_from(distance) else {
+                    return err!(ShiftTooFar, right.location);
+                };
+                self.eval_expr(left)?;
+                if operator == Operator::ShiftLeft {
+                    instr!(self, SUP, distance, location);
+                } else {
+                    instr!(self, SDN, distance, location);
+                }
+            }
+            _ => {
+                self.put_ab(left, right, operator.is_commutative())?;
+                self.put_op(operator, location);
+            }
+        }
+        Ok(())
+    }
 
-        self.put_op(operator, location);
+    fn eval_unary(
+        &mut self,
+        expr: &Expression,
+        value: &Expression,
+        operator: UnaryOperator,
+    ) -> Res {
+        if let Some(constant) = self.try_get_constant(expr) {
+            self.put_a_number(constant, expr.location);
+            return Ok(());
+        }
+        match operator {
+            // 0 - value
+            UnaryOperator::Negate => {
+                if Self::can_put_into_b(value) {
+                    self.put_into_b(value)?;
+                } else {
+                    self.eval_expr(value)?;
+                    self.switch(expr.location)?;
+                }
+                self.put_a_number(0, expr.location);
+                instr!(self, SUB, expr.location);
+            }
+            UnaryOperator::Not => {
+                self.eval_expr(value)?;
+                self.put_b_number(-1, expr.location);
+                instr!(self, XOR, expr.location);
+            }
+        }
         Ok(())
     }
 
     /// # Returns
     /// if the arguments were swapped
-    fn put_ab(&mut self, left: &Expression, right: &Expression, is_commutative: bool) -> Res<bool> {
+    ///
+    /// # Errors
+    ///
+    /// on any compiler error
+    pub fn put_ab(
+        &mut self,
+        left: &Expression,
+        right: &Expression,
+        is_commutative: bool,
+    ) -> Res<bool> {
         let mut swapped = false;
-        match (Self::can_put_into_a(left), Self::can_put_into_b(right)) {
+        match (self.can_put_into_a(left), Self::can_put_into_b(right)) {
             (true, true) => {
                 if is_commutative
                     && ((self.is_in_a(right) || self.is_in_b(left))
@@ -589,21 +1080,90 @@ impl Compiler {
         Ok(())
     }
 
-    fn eval_iassignment(&mut self, ident: &Ident, value: &Expression, operator: Operator) -> Res {
-        self.eval_expr(value)?;
-        self.put_into_b(&Expression {
-            typ: ExpressionType::Identifier(ident.symbol.clone()),
-            location: value.location,
-        })?;
-
-        self.put_op(operator, value.location);
+    fn eval_iassignment(
+        &mut self,
+        variable: &Expression,
+        value: &Expression,
+        operator: Operator,
+    ) -> Res {
+        let ExpressionType::Identifier(symbol) = &variable.typ else {
+            return Err(Error {
+                typ: Box::new(ErrorType::SomethingElseWentWrong(
+                    "assignment to something else than a variable".to_string(),
+                )),
+                location: variable.location,
+                related: vec![],
+            });
+        };
+        self.eval_binary_expr(variable, value, operator, value.location)?;
 
-        let slot = self.get_var(&ident.symbol, value.location)?;
+        let slot = self.get_var(symbol, variable.location)?;
 
         self.save_to(slot, value.location);
         Ok(())
     }
 
+    /// None of the targets have a timer, so this waits with `NON`s. They are limited to a
+    /// page, a disc jump in between would add to the delay
+    fn eval_wait(&mut self, ticks: i16, location: Range) -> Res {
+        let target = self.options.target;
+        let per_cycle = target.timing.ticks(target.timing.instant.into());
+        let cycles = u64::try_from(ticks)
+            .ok()
+            .filter(|ticks| ticks % per_cycle == 0)
+            .map(|ticks| ticks / per_cycle)
+            .filter(|&cycles| cycles <= target.page_size as u64);
+        let Some(cycles) = cycles else {
+            return err!(
+                ErrorType::UnrepresentableWait(per_cycle as u32, target.page_size, target.name),
+                location
+            );
+        };
+        for _ in 0..cycles {
+            instr!(self, NON, location);
+        }
+        Ok(())
+    }
+
+    /// Stores A before B is copied over, so no temp variable is needed
+    fn eval_swap(&mut self, left: &Expression, right: &Expression, location: Range) -> Res {
+        let (first, second) = if self.is_in_a(right) || self.is_in_b(left) {
+            (right, left)
+        } else {
+            (left, right)
+        };
+        let slot = |variable: &Expression| match &variable.typ {
+            ExpressionType::Identifier(symbol) => self.get_var(symbol, variable.location),
+            _ => Err(Error {
+                typ: Box::new(ErrorType::SomethingElseWentWrong(
+                    "swap of something else than a variable".to_string(),
+                )),
+                location: variable.location,
+                related: vec![],
+            }),
+        };
+        let (first_slot, second_slot) = (slot(first)?, slot(second)?);
+
+        self.put_into_a(first)?;
+        self.put_into_b(second)?;
+        self.save_to(second_slot, location);
+        self.put_a_number(0, location);
+        instr!(self, ADD, location);
+        self.save_to(first_slot, location);
+        Ok(())
+    }
+
+    /// Copies B into A, unless `A operator B` holds
+    pub fn keep_a_if(&mut self, operator: EqualityOperator, location: Range) {
+        let skip = self.insert_jump_mark();
+        self.push_jump(InstructionVariant::from_op(operator), skip, location);
+        self.put_a_number(0, location);
+        instr!(self, ADD, location);
+        self.jump_marks[skip] = self.current_address();
+        // A is only known on one of the paths
+        self.last_scope_mut().state.a = RegisterContents::Unknown;
+    }
+
     fn put_op(&mut self, operator: Operator, location: Range) {
         use Operator as O;
         match operator {
@@ -613,6 +1173,8 @@ impl Compiler {
             O::And => instr!(self, AND, location),
             O::Or => instr!(self, OR, location),
             O::Xor => instr!(self, XOR, location),
+            // handled by `eval_binary_expr`
+            O::Div | O::Mod | O::ShiftLeft | O::ShiftRight => {}
         }
     }
 
@@ -622,12 +1184,7 @@ impl Compiler {
     ///
     /// on any compiler error
     pub fn try_get_constant(&mut self, value: &Expression) -> Option<i16> {
-        match &value.typ {
-            ExpressionType::NumericLiteral(value) => Some(*value),
-            ExpressionType::Identifier(symbol) => self.get_inline_var(symbol, value.location).ok(),
-            ExpressionType::BinaryExpr { .. } => self.try_eval_const(value).ok(),
-            _ => None,
-        }
+        self.try_eval_const(value).ok()
     }
 
     /// puts a into b
@@ -668,12 +1225,13 @@ impl Compiler {
                 }
             }
             E::Assignment { .. } => {
-                if Self::can_put_into_a(expr) {
+                if self.can_put_into_a(expr) {
                     self.eval_expr(expr)?;
                 } else {
                     return Err(Error {
                         typ: Box::new(ErrorType::SomethingElseWentWrong("put_a".to_string())),
                         location: expr.location,
+                        related: vec![],
                     });
                 }
             }
@@ -683,6 +1241,7 @@ impl Compiler {
                         "put_a called on wrong expression".to_string(),
                     )),
                     location: expr.location,
+                    related: vec![],
                 })
             }
         }
@@ -719,6 +1278,7 @@ impl Compiler {
                         "put_b called on wrong expression".to_string(),
                     )),
                     location: expr.location,
+                    related: vec![],
                 })
             }
         }
@@ -789,71 +1349,109 @@ impl Compiler {
         }
     }
 
-    fn eval_call(&mut self, function: &Expression, args: &Vec<Expression>) -> Res {
+    fn eval_call(&mut self, function: &Expression, args: &[ExprId]) -> Res {
         use ExpressionType as E;
-        let module;
-        let method;
+        let module: &str;
+        let method: &str;
+        let mut args: Vec<_> = args.iter().map(|&arg| self.expr(arg)).collect();
         match &function.typ {
-            E::Member { object, property } => match &object.typ {
+            E::Member { object, property } => match &self.expr(*object).typ {
                 E::Identifier(symbol) => {
                     module = symbol;
-                    method = property;
+                    method = &property.symbol;
+                    if !self.modules.contains(module) {
+                        return Err(Error {
+                            typ: Box::new(ErrorType::UnlodadedModule(module.to_string())),
+                            location: function.location,
+                            related: vec![],
+                        });
+                    }
                 }
                 _ => {
                     return Err(Error {
-                        typ: Box::new(ErrorType::NonexistentModule(format!("{object:?}"))),
+                        typ: Box::new(ErrorType::NonexistentModule(format!(
+                            "{:?}",
+                            self.expr(*object)
+                        ))),
                         location: function.location,
+                        related: vec![],
                     })
                 }
             },
+            // builtins don't need their module to be loaded
+            E::Identifier(symbol) => {
+                let Some(builtin) = builtin_doc(symbol) else {
+                    return err!(ErrorType::UnknownMethod(symbol.clone()), function.location);
+                };
+                module = builtin.module;
+                method = builtin.method;
+                if args.len() == builtin.order.len() {
+                    args = builtin.order.iter().map(|&index| args[index]).collect();
+                }
+            }
             _ => {
                 return Err(Error {
                     typ: Box::new(ErrorType::UnknownMethod(format!("{function:?}"))),
                     location: function.location,
+                    related: vec![],
                 })
             }
         }
-        if !self.modules.contains(module) {
-            return Err(Error {
-                typ: Box::new(ErrorType::UnlodadedModule(module.clone())),
-                location: function.location,
-            });
-        }
 
         call(
             module,
             self,
             &Call {
-                method_name: &method.symbol,
-                args,
+                method_name: method,
+                args: &args,
                 location: function.location,
             },
         )
     }
 
-    fn replace_jump_marks(instructions: &mut [Instruction], jump_marks: &HashMap<u8, u8>) {
+    /// The arg of a jump is the address it goes to inside of its page, the page comes
+    /// from the `LCL` in front of a disc jump
+    fn replace_jump_marks(
+        instructions: &mut [Instruction],
+        jump_marks: &[usize],
+        page_size: usize,
+    ) {
+        phase!("jump_marks", marks = jump_marks.len());
         for i in instructions.iter_mut() {
-            if i.variant.is_jump() {
-                i.arg = Some(
-                    *jump_marks
-                        .get(&i.arg.expect("jump does not have arg"))
-                        .expect("Invalid jump mark"),
-                );
+            if let Some(mark) = i.jump_mark.take() {
+                i.arg = Some((jump_marks[mark] % page_size) as u8);
             }
         }
     }
 
-    fn move_jump_marks(jump_marks: &mut HashMap<u8, u8>, from: u8, by: u8) {
-        for (_, value) in jump_marks.iter_mut() {
-            if *value >= from {
+    /// Moves the marks after `from` by `by`. Marks at `from` stay, so jumps to an
+    /// instruction also run what was inserted in front of it
+    fn move_jump_marks(jump_marks: &mut [usize], from: usize, by: usize) {
+        for value in jump_marks.iter_mut() {
+            if *value > from {
                 *value += by;
             }
         }
     }
 
-    fn insert_disc_jumps(instructions: &mut Vec<Instruction>, jump_marks: &mut HashMap<u8, u8>) {
+    /// Puts an `LCL` with the page in front of every jump to another page. Errors at the
+    /// jump to the farthest page if one doesn't exist, or at the first instruction past
+    /// the last page
+    fn insert_disc_jumps(
+        instructions: &mut Vec<Instruction>,
+        jump_marks: &mut [usize],
+        target: &Target,
+    ) -> Res {
+        phase!("disc_jumps", instructions = instructions.len());
+        let page_size = target.page_size;
+        let too_large = |location| Error {
+            typ: Box::new(ErrorType::ProgramTooLarge(target.max_pages, target.name)),
+            location,
+            related: vec![],
+        };
         loop {
             let mut changes = false;
+            let mut farthest: Option<(usize, Range)> = None;
 
             let mut i = 0;
             while i < instructions.len() {
@@ -861,44 +1459,204 @@ impl Compiler {
                     .get_mut(i)
                     .expect("Tried getting invalid instruction in insert_disc_jumps loop");
                 let location = instr.orig_location;
-                if instr.variant.is_jump() && !instr.variant.disc_jump() {
-                    let mark = instr.arg.expect("Jump instruction doesn't have arg");
-                    let current_page = i / 64;
-                    let jump_page = jump_marks.get(&mark).expect("Invalid jump mark") / 64;
-                    if current_page != jump_page as usize {
-                        instr.variant = instr.variant.to_disc_jump();
-                        instructions.insert(
-                            i,
-                            Instruction::new(InstructionVariant::LCL, Some(jump_page), location),
-                        );
-                        Self::move_jump_marks(jump_marks, i as u8, 1);
-                        i += 1;
-                        changes = true;
+                if let Some(mark) = instr.jump_mark.filter(|_| !instr.variant.disc_jump()) {
+                    let current_page = i / page_size;
+                    let jump_page = jump_marks[mark] / page_size;
+                    let page = u8::try_from(jump_page)
+                        .ok()
+                        .filter(|_| jump_page < target.max_pages);
+                    match page {
+                        _ if current_page == jump_page => {}
+                        Some(page) => {
+                            instr.variant = instr.variant.to_disc_jump();
+                            // the page is set again once nothing moves anymore
+                            instructions.insert(
+                                i,
+                                Instruction::new(InstructionVariant::LCL, Some(page), location),
+                            );
+                            Self::move_jump_marks(jump_marks, i, 1);
+                            i += 1;
+                            changes = true;
+                        }
+                        None if farthest.is_none_or(|(page, _)| page < jump_page) => {
+                            farthest = Some((jump_page, location));
+                        }
+                        None => {}
                     }
                 }
                 i += 1;
             }
 
+            if let Some((_, location)) = farthest {
+                return Err(too_large(location));
+            }
             if !changes {
                 break;
             }
         }
+        if let Some(instruction) = instructions.get(target.max_pages.saturating_mul(page_size)) {
+            return Err(too_large(instruction.orig_location));
+        }
+
+        // later insertions can move the target of a disc jump to the next page
+        for i in 1..instructions.len() {
+            let instr = &instructions[i];
+            let Some(mark) = instr.jump_mark.filter(|_| {
+                instr.variant.disc_jump() && instructions[i - 1].variant == InstructionVariant::LCL
+            }) else {
+                continue;
+            };
+            let location = instr.orig_location;
+            let jump_page = jump_marks[mark] / page_size;
+            let page = u8::try_from(jump_page)
+                .ok()
+                .filter(|_| jump_page < target.max_pages)
+                .ok_or_else(|| too_large(location))?;
+            instructions[i - 1].arg = Some(page);
+        }
+        Ok(())
     }
 }
 
-fn eval_condition(
-    condition: Expression,
-) -> Res<(Box<Expression>, Box<Expression>, EqualityOperator)> {
-    let ExpressionType::EqExpr {
-        left,
-        right,
-        operator,
-    } = condition.typ
-    else {
-        return Err(Error {
-            typ: Box::new(ErrorType::NormalInEqExpr),
-            location: condition.location,
-        });
+/// Used by [`Cache`](super::Cache) to reuse the code of top-level statements
+impl Compiler<'_> {
+    pub(super) fn snapshot(&self) -> Snapshot {
+        let root = self.scopes.first();
+        Snapshot {
+            state: root.state,
+            variables: root.variables.clone(),
+            inline_variables: root.inline_variables.clone(),
+            slots: self.variables,
+            modules: self.modules.clone(),
+            stopped: root.stopped,
+        }
+    }
+
+    pub(super) fn restore(&mut self, snapshot: &Snapshot) {
+        let root = self.scopes.first_mut();
+        root.state = snapshot.state;
+        root.variables.clone_from(&snapshot.variables);
+        root.inline_variables.clone_from(&snapshot.inline_variables);
+        self.variables = snapshot.slots;
+        self.modules.clone_from(&snapshot.modules);
+        root.stopped = snapshot.stopped;
+    }
+
+    pub(super) fn mark(&self) -> Mark {
+        Mark {
+            instructions: self.scopes.first().instructions.len(),
+            jump_marks: self.jump_marks.len(),
+            symbols: self.symbols.len(),
+            warnings: self.warnings.len(),
+            address: self.current_address(),
+        }
+    }
+
+    /// Everything generated since `mark`, with jump marks counted from there
+    pub(super) fn code_since(&self, mark: &Mark) -> Code {
+        let mut instructions = self.scopes.first().instructions[mark.instructions..].to_vec();
+        relocate(&mut instructions, mark.jump_marks, 0, 0);
+        let jump_marks = self.jump_marks[mark.jump_marks..]
+            .iter()
+            .map(|address| address - mark.address)
+            .collect();
+        Code {
+            len: Instr::count(&instructions),
+            instructions,
+            jump_marks,
+            symbols: self.symbols[mark.symbols..].to_vec(),
+            warnings: self.warnings[mark.warnings..].to_vec(),
+            after: self.snapshot(),
+        }
+    }
+
+    /// Appends code from [`Compiler::code_since`] as if its statement was evaluated
+    /// here, `lines` after where it was
+    pub(super) fn replay(&mut self, code: &Code, lines: i32) {
+        let mark = self.mark();
+        let mut instructions = code.instructions.clone();
+        relocate(&mut instructions, 0, mark.jump_marks, lines);
+        self.jump_marks
+            .extend(code.jump_marks.iter().map(|address| mark.address + address));
+        self.symbols
+            .extend(code.symbols.iter().map(|symbol| Symbol {
+                location: shift(symbol.location, lines),
+                ..symbol.clone()
+            }));
+        self.warnings.extend(code.warnings.iter().map(|warning| {
+            Warning {
+                location: shift(warning.location, lines),
+                related: warning
+                    .related
+                    .iter()
+                    .map(|related| Related {
+                        location: shift(related.location, lines),
+                        ..related.clone()
+                    })
+                    .collect(),
+                ..warning.clone()
+            }
+        }));
+        let root = self.scopes.first_mut();
+        root.instructions.extend(instructions);
+        root.len += code.len;
+        self.restore(&code.after);
+    }
+}
+
+impl<'a> Compiler<'a> {
+    fn eval_condition(
+        &self,
+        condition: &Expression,
+    ) -> Res<(&'a Expression, &'a Expression, EqualityOperator)> {
+        let ExpressionType::EqExpr {
+            left,
+            right,
+            operator,
+        } = condition.typ
+        else {
+            return Err(Error {
+                typ: Box::new(ErrorType::NormalInEqExpr),
+                location: condition.location,
+                related: vec![],
+            });
+        };
+        Ok((self.expr(left), self.expr(right), operator))
+    }
+}
+
+/// `left operator right` at compile-time, results that don't fit are an error instead
+/// of wrapping around like they would on the computer
+fn fold(left: i16, right: i16, operator: Operator, location: Range) -> Res<i16> {
+    use Operator as O;
+    let error = |typ| Error {
+        typ: Box::new(typ),
+        location,
+        related: vec![],
     };
-    Ok((left, right, operator))
+    match operator {
+        O::Plus => left
+            .checked_add(right)
+            .ok_or_else(|| error(ErrorType::ConstantOverflow)),
+        O::Minus => left
+            .checked_sub(right)
+            .ok_or_else(|| error(ErrorType::ConstantOverflow)),
+        O::Mult => left
+            .checked_mul(right)
+            .ok_or_else(|| error(ErrorType::ConstantOverflow)),
+        O::And => Ok(left & right),
+        O::Or => Ok(left | right),
+        O::Xor => Ok(left ^ right),
+        O::Div | O::Mod if right == 0 => Err(error(ErrorType::DivisionByZero)),
+        O::Div => left
+            .checked_div(right)
+            .ok_or_else(|| error(ErrorType::ConstantOverflow)),
+        // only the quotient of `i16::MIN / -1` doesn't fit, the remainder is 0
+        O::Mod => Ok(left.wrapping_rem(right)),
+        O::ShiftLeft | O::ShiftRight if !(0..16).contains(&right) => {
+            Err(error(ErrorType::ShiftTooFar))
+        }
+        O::ShiftLeft => Ok(left << right),
+        O::ShiftRight => Ok(left >> right),
+    }
 }
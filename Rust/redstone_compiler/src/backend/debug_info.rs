@@ -0,0 +1,70 @@
+use super::{Checksums, Instruction, SourceMap, Symbol, Target, Warning, INSTRUCTION_SET_REVISION};
+
+/// Changes whenever [`DebugInfo`] gets new fields or is serialized differently
+pub const DEBUG_INFO_VERSION: u32 = 3;
+
+/// Everything a debugger needs to map a binary back to its code, written next to the
+/// binary so a program can be compiled and debugged in separate sessions
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct DebugInfo {
+    /// [`DEBUG_INFO_VERSION`] of the compiler that wrote it
+    pub version: u32,
+    /// [`INSTRUCTION_SET_REVISION`] the binary is encoded with
+    pub instruction_set: u32,
+    /// Name of the [`Target`] the program was compiled for
+    pub target: String,
+    pub source_map: SourceMap,
+    pub symbols: Vec<Symbol>,
+    /// Address every jump mark of the compiler ended up at, indexed by mark
+    pub jump_marks: Vec<usize>,
+    /// Modules the program uses, sorted by name
+    pub modules: Vec<String>,
+    /// Of the binary, to check a ROM against
+    pub checksums: Checksums,
+    /// In the order the code was compiled in
+    pub warnings: Vec<Warning>,
+}
+
+impl DebugInfo {
+    #[must_use]
+    pub fn new(
+        instructions: &[Instruction],
+        target: &Target,
+        symbols: Vec<Symbol>,
+        jump_marks: Vec<usize>,
+        modules: Vec<String>,
+        warnings: Vec<Warning>,
+    ) -> Self {
+        Self {
+            version: DEBUG_INFO_VERSION,
+            instruction_set: INSTRUCTION_SET_REVISION,
+            target: target.name.to_string(),
+            source_map: SourceMap::new(instructions),
+            symbols,
+            jump_marks,
+            modules,
+            checksums: Checksums::new(
+                &instructions
+                    .iter()
+                    .map(Instruction::to_bin)
+                    .collect::<Vec<_>>(),
+                target.page_size,
+            ),
+            warnings,
+        }
+    }
+
+    /// Whether it was written in the current format for binaries in the current encoding
+    #[must_use]
+    pub const fn is_compatible(&self) -> bool {
+        self.version == DEBUG_INFO_VERSION && self.instruction_set == INSTRUCTION_SET_REVISION
+    }
+
+    /// The target, if this compiler knows it
+    #[must_use]
+    pub fn target(&self) -> Option<&'static Target> {
+        Target::by_name(&self.target)
+    }
+}
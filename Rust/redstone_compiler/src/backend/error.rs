@@ -1,4 +1,6 @@
-use crate::error::ErrorType;
+use crate::error::{ErrorType, Fix};
+
+use super::similar_module;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Type {
@@ -17,6 +19,23 @@ pub enum Type {
     NormalInEqExpr,
     UseOutsideGlobalScope,
     NoConstants,
+    UnknownMnemonic(String),
+    MissingArg(String),
+    UnexpectedArg(String),
+    InvalidArg(String),
+    InvalidLabel(String),
+    UnknownLabel(String),
+    DuplicateLabel(String),
+    NestingTooDeep,
+    ConstantOverflow,
+    DivisionByZero,
+    ShiftTooFar,
+    RuntimeDivision,
+    StaticAssertFailed(Option<String>),
+    /// Ticks of a cycle, most cycles that can be waited and the target
+    UnrepresentableWait(u32, usize, &'static str),
+    /// Pages of the target and its name
+    ProgramTooLarge(usize, &'static str),
 }
 
 impl ErrorType for Type {
@@ -59,6 +78,83 @@ impl ErrorType for Type {
                 format!("{name} has to be known at compile-time")
             }
             Self::NoConstants => "Constants are only supported inside module calls".to_string(),
+            Self::UnknownMnemonic(name) => format!("There is no instruction called {name}"),
+            Self::MissingArg(name) => format!("{name} needs an argument"),
+            Self::UnexpectedArg(name) => format!("{name} doesn't take this argument"),
+            Self::InvalidArg(arg) => format!("{arg} is not a number from 0 to 255"),
+            Self::InvalidLabel(name) => format!("{name} is not a valid label name"),
+            Self::UnknownLabel(name) => format!("The label {name} is not defined"),
+            Self::DuplicateLabel(name) => format!("The label {name} is already defined"),
+            Self::NestingTooDeep => "This is nested too deep, split it up".to_string(),
+            Self::ConstantOverflow => "This doesn't fit into 16 bits".to_string(),
+            Self::DivisionByZero => "Division by zero".to_string(),
+            Self::ShiftTooFar => "Can only shift by 0 to 15".to_string(),
+            Self::RuntimeDivision => "Can only divide values known at compile-time".to_string(),
+            Self::StaticAssertFailed(None) => "Static assert failed".to_string(),
+            Self::StaticAssertFailed(Some(message)) => format!("Static assert failed: {message}"),
+            Self::UnrepresentableWait(ticks, cycles, target) => format!(
+                "{target} can only wait multiples of {ticks} ticks, up to {} ticks",
+                *ticks as usize * cycles
+            ),
+            Self::ProgramTooLarge(pages, target) => {
+                format!("The program doesn't fit into the {pages} pages of {target}")
+            }
+        }
+    }
+
+    fn get_code(&self) -> &'static str {
+        match self {
+            Self::NonexistentVar(_) => "nonexistent-var",
+            Self::NonexistentInlineVar(_) => "nonexistent-inline-var",
+            Self::TooManyVars => "too-many-vars",
+            Self::ForbiddenInline => "forbidden-inline",
+            Self::NonexistentModule(_) => "nonexistent-module",
+            Self::UnlodadedModule(_) => "unloaded-module",
+            Self::UnknownMethod(_) => "unknown-method",
+            Self::InvalidArgs(_) => "invalid-args",
+            Self::CompileTimeArg(_) => "compile-time-arg",
+            Self::SomethingElseWentWrong(_) => "internal",
+            Self::ModuleInitTwice(_) => "module-init-twice",
+            Self::EqInNormalExpr => "eq-in-normal-expr",
+            Self::NormalInEqExpr => "normal-in-eq-expr",
+            Self::UseOutsideGlobalScope => "use-outside-global-scope",
+            Self::NoConstants => "no-constants",
+            Self::UnknownMnemonic(_) => "unknown-mnemonic",
+            Self::MissingArg(_) => "missing-arg",
+            Self::UnexpectedArg(_) => "unexpected-arg",
+            Self::InvalidArg(_) => "invalid-arg",
+            Self::InvalidLabel(_) => "invalid-label",
+            Self::UnknownLabel(_) => "unknown-label",
+            Self::DuplicateLabel(_) => "duplicate-label",
+            Self::NestingTooDeep => "nesting-too-deep",
+            Self::ConstantOverflow => "constant-overflow",
+            Self::DivisionByZero => "division-by-zero",
+            Self::ShiftTooFar => "shift-too-far",
+            Self::RuntimeDivision => "runtime-division",
+            Self::StaticAssertFailed(_) => "static-assert-failed",
+            Self::UnrepresentableWait(..) => "unrepresentable-wait",
+            Self::ProgramTooLarge(..) => "program-too-large",
+        }
+    }
+
+    fn fixes(&self) -> Vec<Fix> {
+        let (Self::UnlodadedModule(name) | Self::NonexistentModule(name)) = self else {
+            return vec![];
+        };
+        let Some(module) = similar_module(name) else {
+            return vec![];
+        };
+        let mut fixes = vec![];
+        if module.name != name {
+            fixes.push(Fix::Rename {
+                from: name.clone(),
+                to: module.name.to_string(),
+            });
+        }
+        // a nonexistent module comes from a `use` statement, renaming is enough
+        if matches!(self, Self::UnlodadedModule(_)) {
+            fixes.push(Fix::AddUse(module.name.to_string()));
         }
+        fixes
     }
 }
@@ -0,0 +1,211 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    iter::once,
+};
+
+use crate::{
+    error::Error,
+    frontend::{Ast, ExpressionType, Location, Range, Token},
+};
+
+use super::{
+    compiler::Compiler, CompileOptions, ComputerState, DebugInfo, Instr, Instruction, Symbol,
+    Warning, VAR_SLOTS,
+};
+
+/// Compiles a program again after it was edited, reusing the code of the top-level
+/// statements that didn't change
+///
+/// Statements are keyed by a hash of their tokens, so an edit inside a loop only
+/// compiles that loop again. A statement is only reused if the compiler is in the
+/// same state before it as when it was compiled, its code is then moved to its new
+/// address and line. Linking is always done for the whole program
+#[derive(Debug, Default)]
+pub struct Cache {
+    options: CompileOptions,
+    statements: HashMap<u64, Vec<Entry>>,
+    reused: usize,
+}
+
+impl Cache {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Like [`compile_program_with_debug_info`](super::compile_program_with_debug_info),
+    /// `tokens` are the ones `ast` was parsed from
+    ///
+    /// # Errors
+    ///
+    /// on any compiler error
+    pub fn compile(
+        &mut self,
+        ast: &Ast,
+        tokens: &[Token],
+        options: CompileOptions,
+    ) -> Result<(Vec<Instruction>, DebugInfo), Vec<Error>> {
+        phase!("compile", opt_level = ?options.opt_level, target = options.target.name);
+        if options != self.options {
+            self.options = options;
+            self.statements.clear();
+        }
+        self.reused = 0;
+
+        let mut compiler = Compiler::new(ast, options);
+        let mut statements: HashMap<u64, Vec<Entry>> = HashMap::new();
+        let mut errors = vec![];
+        // module state only changes in `use` statements, so it's the same if everything
+        // up to the last one is
+        let mut prefix = DefaultHasher::new();
+        let mut uses = 0;
+        for (statement, tokens) in ast.statements().zip(spans(ast, tokens)) {
+            let line = statement.location.0 .0;
+            let key = key(tokens, line);
+            key.hash(&mut prefix);
+            if matches!(statement.typ, ExpressionType::Use(_)) {
+                errors.extend(compiler.eval_statement(statement).err());
+                uses = prefix.finish();
+                continue;
+            }
+
+            let before = compiler.snapshot();
+            // unreachable statements only extend the warning of the ones before them
+            if before.stopped {
+                errors.extend(compiler.eval_statement(statement).err());
+                continue;
+            }
+            let cached = self.statements.get_mut(&key).and_then(|entries| {
+                let index = entries
+                    .iter()
+                    .position(|entry| entry.uses == uses && entry.before == before)?;
+                Some(entries.swap_remove(index))
+            });
+            let entry = if let Some(entry) = cached {
+                compiler.replay(&entry.code, i32::from(line) - i32::from(entry.line));
+                self.reused += 1;
+                entry
+            } else {
+                let mark = compiler.mark();
+                if let Err(error) = compiler.eval_statement(statement) {
+                    errors.push(error);
+                    continue;
+                }
+                Entry {
+                    line,
+                    uses,
+                    before,
+                    code: compiler.code_since(&mark),
+                }
+            };
+            statements.entry(key).or_default().push(entry);
+        }
+        self.statements = statements;
+
+        if errors.is_empty() {
+            compiler.link_with_debug_info().map_err(|error| vec![error])
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// How many statements the last compilation didn't have to compile again
+    #[must_use]
+    pub const fn reused(&self) -> usize {
+        self.reused
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// Line the statement started at when it was compiled
+    line: u16,
+    uses: u64,
+    before: Snapshot,
+    code: Code,
+}
+
+/// The state of the compiler a top-level statement depends on, besides modules
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Snapshot {
+    pub state: ComputerState,
+    pub variables: HashMap<String, u8>,
+    pub inline_variables: HashMap<String, i16>,
+    pub slots: [bool; VAR_SLOTS],
+    pub modules: HashSet<String>,
+    pub stopped: bool,
+}
+
+/// Where the compiler was before a top-level statement
+pub(super) struct Mark {
+    pub instructions: usize,
+    pub jump_marks: usize,
+    pub symbols: usize,
+    pub warnings: usize,
+    pub address: usize,
+}
+
+/// What a top-level statement generated. Its jump marks are counted from its first
+/// one and their addresses from its first instruction
+#[derive(Debug)]
+pub(super) struct Code {
+    pub instructions: Vec<Instr>,
+    pub len: usize,
+    pub jump_marks: Vec<usize>,
+    pub symbols: Vec<Symbol>,
+    pub warnings: Vec<Warning>,
+    pub after: Snapshot,
+}
+
+/// The tokens of every statement, up to where the next one starts so closing
+/// keywords are included
+fn spans<'t>(ast: &Ast, tokens: &'t [Token<'t>]) -> impl Iterator<Item = &'t [Token<'t>]> {
+    let starts: Vec<_> = ast
+        .statements()
+        .map(|statement| tokens.partition_point(|token| token.location.0 < statement.location.0))
+        .chain(once(tokens.len()))
+        .collect();
+    (0..starts.len() - 1).map(move |index| &tokens[starts[index]..starts[index + 1]])
+}
+
+/// Lines are counted from `line`, so moving a statement doesn't change it
+fn key(tokens: &[Token], line: u16) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for token in tokens {
+        token.typ.hash(&mut hasher);
+        let Range(Location(start_line, start), Location(end_line, end)) = token.location;
+        (start_line - line, start, end_line - line, end).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Counts the marks of the jumps from `to` instead of `from` and moves everything by
+/// `lines`
+pub(super) fn relocate(instructions: &mut [Instr], from: usize, to: usize, lines: i32) {
+    let mut stack = vec![instructions.iter_mut()];
+    while let Some(scope) = stack.last_mut() {
+        match scope.next() {
+            Some(Instr::Code(instruction)) => {
+                if let Some(mark) = &mut instruction.jump_mark {
+                    *mark = *mark - from + to;
+                }
+                instruction.orig_location = shift(instruction.orig_location, lines);
+            }
+            Some(Instr::Scope(scope)) => stack.push(scope.iter_mut()),
+            None => {
+                stack.pop();
+            }
+        }
+    }
+}
+
+pub(super) fn shift(range: Range, lines: i32) -> Range {
+    let shift = |location: Location| {
+        Location(
+            (i32::from(location.0) + lines).try_into().unwrap_or(0),
+            location.1,
+        )
+    };
+    Range(shift(range.0), shift(range.1))
+}
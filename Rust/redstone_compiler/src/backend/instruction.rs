@@ -3,6 +3,9 @@ use std::fmt::{self, Debug};
 
 use table_enum::table_enum;
 
+/// Changes whenever instructions are added or encoded differently
+pub const INSTRUCTION_SET_REVISION: u32 = 1;
+
 table_enum! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     #[allow(unused)]
@@ -81,6 +84,70 @@ table_enum! {
 }}
 
 impl InstructionVariant {
+    pub const ALL: [Self; 51] = [
+        Self::STOP,
+        Self::NON,
+        Self::LA,
+        Self::LB,
+        Self::LC,
+        Self::SVA,
+        Self::LAL,
+        Self::LAH,
+        Self::LBL,
+        Self::LBH,
+        Self::LCL,
+        Self::ADD,
+        Self::SUB,
+        Self::AND,
+        Self::OR,
+        Self::XOR,
+        Self::SUP,
+        Self::SDN,
+        Self::MUL,
+        Self::RW,
+        Self::RR,
+        Self::RC,
+        Self::INB,
+        Self::JMP,
+        Self::JE,
+        Self::JNE,
+        Self::JG,
+        Self::JGE,
+        Self::JL,
+        Self::JLE,
+        Self::JMD,
+        Self::JDE,
+        Self::JDN,
+        Self::JDG,
+        Self::JDGE,
+        Self::JDL,
+        Self::JDLE,
+        Self::SMP,
+        Self::SE,
+        Self::SNE,
+        Self::SG,
+        Self::SGE,
+        Self::SL,
+        Self::SLE,
+        Self::SMD,
+        Self::SDE,
+        Self::SDNE,
+        Self::SDG,
+        Self::SDGE,
+        Self::SDL,
+        Self::SDLE,
+    ];
+
+    /// Inverse of [`Self::to_byte`]. Where several variants share an encoding
+    /// the first one in [`Self::ALL`] wins, so `RR` decodes as `RW`, `INB` as `RC`
+    /// and skips as jumps
+    #[must_use]
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|variant| variant.to_byte() == byte)
+    }
+
     /// Converts a normal jump into a disc jump
     ///
     /// # Panics
@@ -120,6 +187,17 @@ impl InstructionVariant {
         }
     }
 
+    /// Estimated execution time, instructions that aren't instant take an extra cycle.
+    /// See [`super::Timing`] for the time on a specific target
+    #[must_use]
+    pub const fn cycles(self) -> u8 {
+        if self.instant() {
+            1
+        } else {
+            2
+        }
+    }
+
     #[must_use]
     pub const fn to_byte(&self) -> u8 {
         (self.jump() as u8) << 7
@@ -130,11 +208,64 @@ impl InstructionVariant {
     }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Instruction {
     pub variant: InstructionVariant,
     pub arg: Option<u8>,
     pub orig_location: Range,
+    /// Name of the instruction if jumps go to it, written before it like in
+    /// [`assemble`](super::assemble)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub label: Option<String>,
+    /// What the instruction is for: the variable it uses, the label it jumps to or the
+    /// code of the statement it starts
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub comment: Option<String>,
+    /// The jump mark a jump goes to before linking, which replaces it with the address
+    /// in `arg`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub jump_mark: Option<usize>,
+}
+
+/// Labels and comments are only for reading the code, they aren't compared
+impl PartialEq for Instruction {
+    fn eq(&self, other: &Self) -> bool {
+        self.variant == other.variant
+            && self.arg == other.arg
+            && self.orig_location == other.orig_location
+            && self.jump_mark == other.jump_mark
+    }
+}
+
+impl Eq for Instruction {}
+
+/// Serialized as its mnemonic
+#[cfg(feature = "serde")]
+impl serde::Serialize for InstructionVariant {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for InstructionVariant {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::ALL
+            .into_iter()
+            .find(|variant| variant.name() == name)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown instruction {name}")))
+    }
 }
 
 impl fmt::Display for Instruction {
@@ -149,7 +280,10 @@ impl Debug for Instruction {
     }
 }
 
-use crate::frontend::{EqualityOperator, Range};
+use crate::{
+    emulator::set_high_byte,
+    frontend::{EqualityOperator, Range},
+};
 
 use super::{ComputerState, RamPage, RegisterContents};
 
@@ -166,6 +300,27 @@ impl Instruction {
             variant,
             arg,
             orig_location,
+            label: None,
+            comment: None,
+            jump_mark: None,
+        }
+    }
+
+    /// A jump to a jump mark, it gets its arg when it's linked
+    ///
+    /// # Panics
+    ///
+    /// Panics if `variant` isn't a jump
+    #[must_use]
+    pub const fn jump(variant: InstructionVariant, jump_mark: usize, orig_location: Range) -> Self {
+        assert!(variant.is_jump());
+        Self {
+            variant,
+            arg: None,
+            orig_location,
+            label: None,
+            comment: None,
+            jump_mark: Some(jump_mark),
         }
     }
 
@@ -174,64 +329,115 @@ impl Instruction {
         (u16::from(self.arg.unwrap_or(0)) << 8) | u16::from(self.variant.to_byte())
     }
 
-    /// Used by Debug and Display
+    /// Decodes a word produced by [`Self::to_bin`], the location is lost
+    #[must_use]
+    pub fn from_bin(word: u16) -> Option<Self> {
+        let variant = InstructionVariant::from_byte(word as u8)?;
+        let arg = variant.has_arg().then_some((word >> 8) as u8);
+        Some(Self::new(variant, arg, Range::default()))
+    }
+
+    /// Used by Debug and Display, the label comes first and the comment after a `;`
     ///
     /// # Errors
     ///
     /// This function will return an error if something goes wrong, apparently
     pub fn to_string(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(label) = &self.label {
+            write!(f, "{label}: ")?;
+        }
         match self.arg {
-            None => write!(f, "{}", self.variant.name()),
-            Some(arg) => write!(f, "{} {}", self.variant.name(), arg),
+            None => write!(f, "{}", self.variant.name())?,
+            Some(arg) => write!(f, "{} {}", self.variant.name(), arg)?,
         }
+        if let Some(comment) = &self.comment {
+            write!(f, " ; {comment}")?;
+        }
+        Ok(())
+    }
+
+    /// Whether its arg is a RAM slot it reads or writes
+    #[must_use]
+    pub const fn uses_slot(&self) -> bool {
+        matches!(
+            self.variant,
+            InstructionVariant::LA
+                | InstructionVariant::LB
+                | InstructionVariant::LC
+                | InstructionVariant::SVA
+        )
     }
 
+    /// Updates what is known about the registers after this instruction runs
+    ///
+    /// Jumps, IO and everything else that doesn't write a register leave `on` as it is.
+    /// Values read from RAM are only known as the [`RegisterContents::Variable`] of their
+    /// slot, so a store to a slot forgets B and C if they hold it
     pub fn execute(&self, on: &mut ComputerState) {
         use InstructionVariant as IV;
         use RegisterContents as RC;
         match self.variant {
-            IV::LA | IV::SVA => on.a = RC::Variable(self.arg.unwrap_or(0)),
+            IV::LA => on.a = RC::Variable(self.arg.unwrap_or(0)),
+            IV::SVA => {
+                let slot = RC::Variable(self.arg.unwrap_or(0));
+                // B and C still hold the old value
+                if on.b == slot {
+                    on.b = RC::Unknown;
+                }
+                if on.c == slot {
+                    on.c = RC::Unknown;
+                }
+                on.a = slot;
+            }
             IV::LB => on.b = RC::Variable(self.arg.unwrap_or(0)),
             IV::LAL => on.a = RC::Number(self.arg.unwrap_or(0).into()),
             IV::LAH => {
                 on.a = match on.a {
-                    RC::Number(value) => {
-                        RC::Number(value + (i16::from(self.arg.unwrap_or(0)) << 8))
-                    }
+                    RC::Number(value) => RC::Number(set_high_byte(value, self.arg.unwrap_or(0))),
                     _ => RC::Unknown,
                 }
             }
             IV::LBL => on.b = RC::Number(self.arg.unwrap_or(0).into()),
             IV::LBH => {
                 on.b = match on.b {
-                    RC::Number(value) => {
-                        RC::Number(value + (i16::from(self.arg.unwrap_or(0)) << 8))
-                    }
+                    RC::Number(value) => RC::Number(set_high_byte(value, self.arg.unwrap_or(0))),
                     _ => RC::Unknown,
                 }
             }
-            IV::ADD | IV::SUB | IV::MUL | IV::AND | IV::OR | IV::XOR | IV::SUP | IV::SDN => {
+            IV::ADD | IV::SUB | IV::MUL | IV::AND | IV::OR | IV::XOR => {
                 on.a = match (on.a, on.b) {
                     (RC::Number(a), RC::Number(b)) => RC::Number(match self.variant {
-                        IV::ADD => a + b,
-                        IV::SUB => a - b,
+                        IV::ADD => a.wrapping_add(b),
+                        IV::SUB => a.wrapping_sub(b),
                         IV::AND => a & b,
                         IV::OR => a | b,
                         IV::XOR => a ^ b,
-                        IV::SUP => a << b,
-                        IV::SDN => a >> b,
-                        IV::MUL => a * b,
+                        IV::MUL => a.wrapping_mul(b),
                         _ => unreachable!(),
                     }),
                     _ => RC::Unknown,
                 }
             }
+            // shifts take the distance from their arg, not from B
+            IV::SUP | IV::SDN => {
+                on.a = match on.a {
+                    RC::Number(a) => {
+                        let by = self.arg.unwrap_or(0).into();
+                        RC::Number(if self.variant == IV::SUP {
+                            a.wrapping_shl(by)
+                        } else {
+                            a.wrapping_shr(by)
+                        })
+                    }
+                    _ => RC::Unknown,
+                }
+            }
             IV::LCL => on.c = RC::Number(self.arg.unwrap_or(0).into()),
             IV::LC => on.c = RC::Variable(self.arg.unwrap_or(0)),
             IV::RR => on.a = RC::Unknown,
             IV::INB => {
                 on.b = match on.b {
-                    RC::Number(value) => RC::Number(value + 1),
+                    RC::Number(value) => RC::Number(value.wrapping_add(1)),
                     _ => RC::Unknown,
                 }
             }
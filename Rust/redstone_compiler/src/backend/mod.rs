@@ -1,13 +1,41 @@
+mod assembly;
+pub mod checksum;
 mod compiler;
+mod debug_info;
 mod error;
+mod incremental;
 pub mod instruction;
 #[macro_use]
 mod module;
+mod options;
+mod result;
+mod session;
+mod source_map;
+mod symbols;
 mod types;
+mod warning;
 
-pub use compiler::compile_program;
-pub use instruction::{Instruction, InstructionVariant};
+pub use assembly::{assemble, assemble_with, disassemble, parse_assembly, InvalidWord};
+pub use checksum::Checksums;
+pub use compiler::{
+    compile, compile_program, compile_program_ir, compile_program_with,
+    compile_program_with_debug_info, compile_program_with_symbols, eval_constant, VAR_SLOTS,
+};
+pub use debug_info::{DebugInfo, DEBUG_INFO_VERSION};
+pub use incremental::Cache;
+pub use instruction::{Instruction, InstructionVariant, INSTRUCTION_SET_REVISION};
+pub use module::docs::{
+    builtin_doc, module_doc, similar_module, BuiltinDoc, ConstantDoc, MethodDoc, ModuleDoc,
+    ParamDoc, BUILTINS, MODULES,
+};
+pub use options::{CompileOptions, OptLevel, Target, Timing};
+pub use result::CompilationResult;
+pub use session::Session;
+pub use source_map::SourceMap;
+pub use symbols::Symbol;
+pub use types::{ComputerState, Instr, RamPage, RegisterContents};
+pub use warning::{Warning, WarningType};
 
 use compiler::Compiler;
 use error::Type as ErrorType;
-use types::{ComputerState, Instr, RamPage, RegisterContents, Scope};
+use types::Scope;
@@ -51,13 +51,13 @@ use crate::{
     instr, modul,
 };
 
-use super::{arg_parse, screen::put_xy, Arg, Call, ErrorType, Res};
+use super::{arg_parse, docs::module_doc, screen::put_xy, Arg, Call, ErrorType, Res};
 
 modul!(set set_at fill fill_xy fill_screen flip color_of);
 
 fn fill_screen(compiler: &mut Compiler, call: &Call) -> Res {
     let [color] = arg_parse(compiler, [Arg::Number("color")], call)?;
-    match is_const_color(color) {
+    match is_const_color(compiler, color) {
         Some(color) => compiler.put_a_number(color.into(), call.location),
         None => compiler.eval_expr(color)?,
     }
@@ -92,7 +92,7 @@ fn put_xy_color(
     y: &Expression,
     call: &Call<'_>,
 ) -> Res {
-    match is_const_color(color) {
+    match is_const_color(compiler, color) {
         Some(color) => {
             put_xy(compiler, x, y, call.location, 6)?;
             compiler.put_b_number(color.into(), call.location);
@@ -103,7 +103,7 @@ fn put_xy_color(
                 put_xy(compiler, x, y, call.location, 6)?;
                 compiler.put_into_b(color)?;
                 instr!(compiler, OR, call.location);
-            } else if is_color_of_call(&color.typ) {
+            } else if is_color_of_call(compiler, &color.typ) {
                 put_xy(compiler, x, y, call.location, 6)?;
                 compiler.eval_expr(color)?;
                 instr!(compiler, OR, call.location);
@@ -191,7 +191,10 @@ fn load_position_color(
     color: &Expression,
     call: &Call,
 ) -> Res {
-    match (compiler.try_get_constant(position), is_const_color(color)) {
+    match (
+        compiler.try_get_constant(position),
+        is_const_color(compiler, color),
+    ) {
         (None, None) => {
             let temp = compiler.insert_temp_var(call.location)?;
             compiler.eval_expr(color)?;
@@ -227,35 +230,17 @@ fn color_of(compiler: &mut Compiler, call: &Call) -> Res {
 }
 
 fn get_color(color: &str) -> Option<NonZeroI16> {
-    NonZeroI16::new(
-        match color {
-            "white" => 0,
-            "orange" => 1,
-            "magenta" => 2,
-            "light_blue" => 3,
-            "yellow" => 4,
-            "lime" => 5,
-            "pink" => 6,
-            "gray" => 7,
-            "light_gray" => 8,
-            "cyan" => 9,
-            "purple" => 10,
-            "blue" => 11,
-            "brown" => 12,
-            "green" => 13,
-            "red" => 14,
-            "black" => 15,
-            _ => return None,
-        } << 12,
-    )
+    let constants = module_doc("colorscreen")?.constants;
+    let constant = constants.iter().find(|constant| constant.name == color)?;
+    NonZeroI16::new(constant.value)
 }
 
-fn is_const_color(expr: &Expression) -> Option<NonZeroI16> {
+fn is_const_color(compiler: &Compiler, expr: &Expression) -> Option<NonZeroI16> {
     match &expr.typ {
         ExpressionType::Member { object, property } => {
             let color = get_color(&property.symbol);
             match color {
-                Some(_) if matches!(&object.typ, ExpressionType::Identifier(name) if name == "colorscreen") => {
+                Some(_) if matches!(&compiler.expr(*object).typ, ExpressionType::Identifier(name) if name == "colorscreen") => {
                     color
                 }
                 _ => None,
@@ -265,13 +250,13 @@ fn is_const_color(expr: &Expression) -> Option<NonZeroI16> {
     }
 }
 
-fn is_color_of_call(expr: &ExpressionType) -> bool {
+fn is_color_of_call(compiler: &Compiler, expr: &ExpressionType) -> bool {
     match expr {
-        ExpressionType::Call { args, function } => match &function.typ {
+        ExpressionType::Call { args, function } => match &compiler.expr(*function).typ {
             ExpressionType::Member { object, property }
-                if args.len() == 1 && Compiler::can_put_into_a(&args[0]) =>
+                if args.len() == 1 && compiler.can_put_into_a(compiler.expr(args[0])) =>
             {
-                matches!(&object.typ, ExpressionType::Identifier(name) if name == "colorscreen")
+                matches!(&compiler.expr(*object).typ, ExpressionType::Identifier(name) if name == "colorscreen")
                     && &property.symbol == "color_of"
             }
             _ => false,
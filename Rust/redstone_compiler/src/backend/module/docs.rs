@@ -0,0 +1,339 @@
+//! Descriptions of the modules for editors and other tooling
+
+/// A module that can be loaded with `use`
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ModuleDoc {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub methods: &'static [MethodDoc],
+    pub constants: &'static [ConstantDoc],
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MethodDoc {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub params: &'static [ParamDoc],
+    /// Whether the call can be used as a value
+    pub returns: bool,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ParamDoc {
+    pub name: &'static str,
+    /// Has to be known at compile-time
+    pub constant: bool,
+}
+
+/// A member like `colorscreen.red`, replaced by its value at compile-time
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ConstantDoc {
+    pub name: &'static str,
+    pub value: i16,
+}
+
+/// A call that works without `use`, like `in(2)` for `io.read(2)`
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BuiltinDoc {
+    pub name: &'static str,
+    pub module: &'static str,
+    pub method: &'static str,
+    /// Which argument of the builtin each parameter of the method gets
+    pub order: &'static [usize],
+}
+
+impl ModuleDoc {
+    #[must_use]
+    pub fn method(&self, name: &str) -> Option<&'static MethodDoc> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+}
+
+impl MethodDoc {
+    /// Like `write(value, Outslot)`
+    #[must_use]
+    pub fn signature(&self) -> String {
+        let params: Vec<_> = self.params.iter().map(|param| param.name).collect();
+        format!("{}({})", self.name, params.join(", "))
+    }
+}
+
+impl BuiltinDoc {
+    /// The method it stands for
+    #[must_use]
+    pub fn method(&self) -> Option<&'static MethodDoc> {
+        module_doc(self.module)?.method(self.method)
+    }
+}
+
+#[must_use]
+pub fn module_doc(name: &str) -> Option<&'static ModuleDoc> {
+    MODULES.iter().find(|module| module.name == name)
+}
+
+/// The module a misspelled name most likely means, the module itself if the name is right
+#[must_use]
+pub fn similar_module(name: &str) -> Option<&'static ModuleDoc> {
+    MODULES
+        .iter()
+        .map(|module| (edit_distance(name, module.name), module))
+        .filter(|&(distance, _)| distance <= 2 && distance < name.len())
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, module)| module)
+}
+
+/// Chars that have to be inserted, removed or replaced to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<_> = b.chars().collect();
+    let mut previous: Vec<_> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &b) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a != b);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[must_use]
+pub fn builtin_doc(name: &str) -> Option<&'static BuiltinDoc> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+pub const BUILTINS: [BuiltinDoc; 2] = [
+    BuiltinDoc {
+        name: "in",
+        module: "io",
+        method: "read",
+        order: &[0],
+    },
+    BuiltinDoc {
+        name: "out",
+        module: "io",
+        method: "write",
+        order: &[1, 0],
+    },
+];
+
+const fn number(name: &'static str) -> ParamDoc {
+    ParamDoc {
+        name,
+        constant: false,
+    }
+}
+
+const fn constant(name: &'static str) -> ParamDoc {
+    ParamDoc {
+        name,
+        constant: true,
+    }
+}
+
+const fn method(
+    name: &'static str,
+    params: &'static [ParamDoc],
+    returns: bool,
+    doc: &'static str,
+) -> MethodDoc {
+    MethodDoc {
+        name,
+        doc,
+        params,
+        returns,
+    }
+}
+
+const fn color(name: &'static str, index: i16) -> ConstantDoc {
+    ConstantDoc {
+        name,
+        value: index << 12,
+    }
+}
+
+pub const MODULES: [ModuleDoc; 6] = [
+    ModuleDoc {
+        name: "io",
+        doc: "Reads and writes the 8 I/O ports",
+        methods: &[
+            method(
+                "read",
+                &[constant("Inslot")],
+                true,
+                "Value of an input port",
+            ),
+            method(
+                "write",
+                &[number("value"), constant("Outslot")],
+                false,
+                "Writes a value to an output port",
+            ),
+        ],
+        constants: &[],
+    },
+    ModuleDoc {
+        name: "screen",
+        doc: "Black and white screen, positions are 0bXXXXXX_YYYYYY",
+        methods: &[
+            method("flip", &[], false, "Shows the buffer on the screen"),
+            method("clear", &[], false, "Clears the buffer"),
+            method(
+                "set_at",
+                &[number("x"), number("y")],
+                false,
+                "Turns a pixel on",
+            ),
+            method(
+                "invert_at",
+                &[number("x"), number("y")],
+                false,
+                "Inverts a pixel",
+            ),
+            method(
+                "off_at",
+                &[number("x"), number("y")],
+                false,
+                "Turns a pixel off",
+            ),
+            method("set", &[number("pos")], false, "Turns a pixel on"),
+            method("invert", &[number("pos")], false, "Inverts a pixel"),
+            method("off", &[number("pos")], false, "Turns a pixel off"),
+        ],
+        constants: &[],
+    },
+    ModuleDoc {
+        name: "ram",
+        doc: "Random access memory, in pages of 16 words",
+        methods: &[
+            method("read", &[number("address")], true, "Reads a word"),
+            method(
+                "write",
+                &[number("value"), number("address")],
+                true,
+                "Writes a word",
+            ),
+            method(
+                "copy",
+                &[number("from"), number("to")],
+                true,
+                "Copies a word to another address",
+            ),
+        ],
+        constants: &[],
+    },
+    ModuleDoc {
+        name: "list",
+        doc: "Stack in ram, takes up a variable slot for its pointer",
+        methods: &[
+            method(
+                "add",
+                &[number("value")],
+                true,
+                "Pushes a value, returns the new length",
+            ),
+            method("pop", &[], true, "Removes and returns the last value"),
+            method("get_pointer", &[], true, "Address after the last element"),
+            method(
+                "set_pointer",
+                &[number("value")],
+                false,
+                "Moves the end of the list",
+            ),
+            method("last", &[], true, "Last value"),
+            method("at", &[number("address")], true, "Value at an address"),
+        ],
+        constants: &[],
+    },
+    ModuleDoc {
+        name: "colorscreen",
+        doc: "16 color screen, positions are 0bXXXXXX_YYYYYY",
+        methods: &[
+            method(
+                "set",
+                &[number("position"), number("color")],
+                false,
+                "Paints a pixel",
+            ),
+            method(
+                "set_at",
+                &[number("x"), number("y"), number("color")],
+                false,
+                "Paints a pixel",
+            ),
+            method(
+                "fill",
+                &[number("from"), number("to"), number("color")],
+                false,
+                "Paints the rectangle between two positions",
+            ),
+            method(
+                "fill_xy",
+                &[
+                    number("x1"),
+                    number("y1"),
+                    number("x2"),
+                    number("y2"),
+                    number("color"),
+                ],
+                false,
+                "Paints the rectangle between two points",
+            ),
+            method(
+                "fill_screen",
+                &[number("color")],
+                false,
+                "Paints the whole screen",
+            ),
+            method("flip", &[], false, "Shows the buffer on the screen"),
+            method(
+                "color_of",
+                &[number("color")],
+                true,
+                "Color from its index, like the color constants",
+            ),
+        ],
+        constants: &[
+            color("white", 0),
+            color("orange", 1),
+            color("magenta", 2),
+            color("light_blue", 3),
+            color("yellow", 4),
+            color("lime", 5),
+            color("pink", 6),
+            color("gray", 7),
+            color("light_gray", 8),
+            color("cyan", 9),
+            color("purple", 10),
+            color("blue", 11),
+            color("brown", 12),
+            color("green", 13),
+            color("red", 14),
+            color("black", 15),
+        ],
+    },
+    ModuleDoc {
+        name: "math",
+        doc: "Calculations that need more than one instruction",
+        methods: &[
+            method(
+                "min",
+                &[number("a"), number("b")],
+                true,
+                "The smaller of two values",
+            ),
+            method(
+                "max",
+                &[number("a"), number("b")],
+                true,
+                "The bigger of two values",
+            ),
+        ],
+        constants: &[],
+    },
+];
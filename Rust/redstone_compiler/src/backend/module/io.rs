@@ -22,6 +22,7 @@ fn read(compiler: &mut Compiler, call: &Call) -> Res {
                 "Input slot has to be from 0 to 7".to_string(),
             )),
             location: call.args.first().unwrap().location,
+            related: vec![],
         });
     }
 
@@ -46,14 +47,15 @@ fn write(compiler: &mut Compiler, call: &Call) -> Res {
                 "Output slot has to be from 0 to 7".to_string(),
             )),
             location: call.args.get(1).unwrap().location,
+            related: vec![],
         });
     }
 
     let slot: u8 = slot.try_into().unwrap_or(0);
 
-    compiler.eval_expr(&call.args[0])?;
+    compiler.eval_expr(call.args[0])?;
 
-    instr!(compiler, SVA, slot + 32, call.location);
+    compiler.save_to_out(slot, call.location);
 
     Ok(())
 }
@@ -7,9 +7,6 @@ list.last() # return
 list.at(where) # return
 */
 
-const INIT: &str = "list_init";
-const POINTER: &str = "list_ptr";
-
 use crate::{
     backend::{compiler::Compiler, RamPage, RegisterContents},
     err,
@@ -24,14 +21,16 @@ pub fn init(compiler: &mut Compiler, location: Range) -> Res {
         return Err(Error {
             typ: Box::new(ErrorType::ModuleInitTwice("list".to_string())),
             location,
+            related: vec![],
         });
     }
 
     let slot: u8 = find_pointer_var_slot(&compiler.variables, location)?
         .try_into()
         .unwrap();
-    compiler.module_state.insert(POINTER, Box::from(slot));
-    compiler.module_state.insert(INIT, Box::from(true));
+    // reserve it so no variable or temporary ends up in the same slot
+    compiler.variables[usize::from(slot)] = true;
+    compiler.set_state(List { pointer: slot });
     Ok(())
 }
 
@@ -39,7 +38,7 @@ modul!(add pop get_pointer set_pointer last at);
 
 fn add(compiler: &mut Compiler, call: &Call) -> Res {
     let value = arg_parse(compiler, [Arg::Number("value")], call)?[0];
-    let pointer = *compiler.get_module_state::<u8>(POINTER).unwrap();
+    let pointer = pointer(compiler);
     compiler.eval_expr(value)?;
     if compiler.last_scope().state.b != RegisterContents::Variable(pointer) {
         instr!(compiler, LB, pointer, call.location);
@@ -55,7 +54,7 @@ fn add(compiler: &mut Compiler, call: &Call) -> Res {
 fn pop(compiler: &mut Compiler, call: &Call) -> Res {
     arg_parse(compiler, [], call)?;
 
-    let pointer = *compiler.get_module_state::<u8>(POINTER).unwrap();
+    let pointer = pointer(compiler);
 
     if compiler.last_scope().state.a != RegisterContents::Variable(pointer) {
         instr!(compiler, LA, pointer, call.location);
@@ -71,25 +70,25 @@ fn pop(compiler: &mut Compiler, call: &Call) -> Res {
 
 fn get_pointer(compiler: &mut Compiler, call: &Call) -> Res {
     arg_parse(compiler, [], call)?;
-    let pointer = *compiler.get_module_state(POINTER).unwrap();
-    instr!(compiler, LA, pointer);
+    let pointer = pointer(compiler);
+    instr!(compiler, LA, pointer, call.location);
     Ok(())
 }
 
 fn set_pointer(compiler: &mut Compiler, call: &Call) -> Res {
     let value = arg_parse(compiler, [Arg::Number("value")], call)?[0];
 
-    let pointer = *compiler.get_module_state(POINTER).unwrap();
+    let pointer = pointer(compiler);
     compiler.eval_expr(value)?;
 
-    instr!(compiler, SVA, pointer);
+    instr!(compiler, SVA, pointer, call.location);
     Ok(())
 }
 
 fn last(compiler: &mut Compiler, call: &Call) -> Res {
     arg_parse(compiler, [], call)?;
 
-    let pointer = *compiler.get_module_state(POINTER).unwrap();
+    let pointer = pointer(compiler);
 
     if compiler.last_scope().state.a != RegisterContents::Variable(pointer) {
         instr!(compiler, LA, pointer, call.location);
@@ -132,14 +131,26 @@ fn at(compiler: &mut Compiler, call: &Call) -> Res {
     Ok(())
 }
 
+/// Only there once the module is initialized
+struct List {
+    /// Slot of the variable with the address after the last element
+    pointer: u8,
+}
+
 #[inline]
-fn is_initialized(compiler: &mut Compiler) -> bool {
-    matches!(compiler.get_module_state(INIT), Some(true))
+fn is_initialized(compiler: &Compiler) -> bool {
+    compiler.state::<List>().is_some()
+}
+
+fn pointer(compiler: &Compiler) -> u8 {
+    compiler.state::<List>().unwrap().pointer
 }
 
+/// The last free slot, variables are inserted from the front
 fn find_pointer_var_slot(slots: &[bool; 32], location: Range) -> Res<usize> {
-    slots.iter().rev().position(|slot| !*slot).ok_or(Error {
+    slots.iter().rposition(|slot| !*slot).ok_or(Error {
         typ: Box::new(ErrorType::TooManyVars),
         location,
+        related: vec![],
     })
 }
@@ -0,0 +1,40 @@
+use crate::{
+    backend::{
+        module::{arg_parse, Arg},
+        Compiler,
+    },
+    err,
+    frontend::EqualityOperator,
+    modul,
+};
+
+use super::{Call, ErrorType, Res};
+
+modul!(min max);
+
+fn min(compiler: &mut Compiler, call: &Call) -> Res {
+    pick(compiler, call, EqualityOperator::Less)
+}
+
+fn max(compiler: &mut Compiler, call: &Call) -> Res {
+    pick(compiler, call, EqualityOperator::Greater)
+}
+
+/// Puts whichever argument is `operator` the other one into A
+fn pick(compiler: &mut Compiler, call: &Call, operator: EqualityOperator) -> Res {
+    let [a, b] = arg_parse(compiler, [Arg::Number("a"), Arg::Number("b")], call)?;
+
+    if let (Some(a), Some(b)) = (compiler.try_get_constant(a), compiler.try_get_constant(b)) {
+        let value = if operator == EqualityOperator::Less {
+            a.min(b)
+        } else {
+            a.max(b)
+        };
+        compiler.put_a_number(value, call.location);
+        return Ok(());
+    }
+
+    compiler.put_ab(a, b, true)?;
+    compiler.keep_a_if(operator, call.location);
+    Ok(())
+}
@@ -1,8 +1,11 @@
 mod colorscreen;
+pub mod docs;
 mod io;
 mod list;
+mod math;
 mod ram;
 mod screen;
+mod state;
 
 use crate::{
     err,
@@ -10,6 +13,8 @@ use crate::{
     frontend::{Expression, Range},
 };
 
+pub use state::ModuleState;
+
 use super::{Compiler, ErrorType};
 
 pub fn call(name: &str, compiler: &mut Compiler, call: &Call) -> Res {
@@ -18,16 +23,18 @@ pub fn call(name: &str, compiler: &mut Compiler, call: &Call) -> Res {
         "screen" => screen::module(compiler, call),
         "ram" => ram::module(compiler, call),
         "list" => list::module(compiler, call),
+        "math" => math::module(compiler, call),
         "colorscreen" => colorscreen::module(compiler, call),
         _ => Err(Error {
-            typ: Box::new(ErrorType::NonexistentModule(call.method_name.clone())),
+            typ: Box::new(ErrorType::NonexistentModule(call.method_name.to_string())),
             location: call.location,
+            related: vec![],
         }),
     }
 }
 
 pub fn exist(name: &str) -> bool {
-    matches!(name, "io" | "screen" | "ram" | "list" | "colorscreen")
+    docs::module_doc(name).is_some()
 }
 
 pub fn init(name: &str, compiler: &mut Compiler, location: Range) -> Res {
@@ -38,8 +45,8 @@ pub fn init(name: &str, compiler: &mut Compiler, location: Range) -> Res {
 }
 
 pub struct Call<'a> {
-    pub method_name: &'a String,
-    pub args: &'a Vec<Expression>,
+    pub method_name: &'a str,
+    pub args: &'a [&'a Expression],
     pub location: Range,
 }
 
@@ -69,13 +76,14 @@ fn arg_parse<'a, const COUNT: usize>(
                 None => Err(Error {
                     typ: Box::new(ErrorType::CompileTimeArg(name.to_string())),
                     location: arg.location,
+                    related: vec![],
                 }), // otherwise we error
             },
             Arg::Number(..) => Ok(()),
         })?;
 
     let mut iter = call.args.iter();
-    let res = [(); COUNT].map(|_res| iter.next().unwrap());
+    let res = [(); COUNT].map(|_res| *iter.next().unwrap());
     assert_eq!(res.len(), COUNT);
     Ok(res)
 }
@@ -84,9 +92,9 @@ fn arg_parse<'a, const COUNT: usize>(
 macro_rules! modul {
     ( $($method:ident)* ) => {
         pub fn module(compiler: &mut Compiler, call: &Call) -> Res {
-            match call.method_name.as_str() {
+            match call.method_name {
                 $(stringify!($method) => $method(compiler, call),)*
-                _ => err!(ErrorType::UnknownMethod(call.method_name.clone()), call.location)
+                _ => err!(ErrorType::UnknownMethod(call.method_name.to_string()), call.location)
             }
         }
     }
@@ -39,7 +39,7 @@ fn write(compiler: &mut Compiler, call: &Call) -> Res {
     )?;
 
     match (
-        Compiler::can_put_into_a(value),
+        compiler.can_put_into_a(value),
         Compiler::can_put_into_b(address),
     ) {
         (true, _) => {
@@ -81,33 +81,36 @@ fn read(compiler: &mut Compiler, call: &Call) -> Res {
     Ok(())
 }
 
-/// puts the address in the B register and calls RC if neccessary
+/// puts the address in the B register and calls RC if neccessary,
+/// RC takes the page from B so it has to come after
 fn put_address(compiler: &mut Compiler, address: &Expression, location: Range) -> Res {
     if let Some(value) = compiler.try_get_constant(address) {
-        if compiler.last_scope().state.ram_page != RamPage::ThisOne((value / 16) as u8) {
+        let page_known =
+            compiler.last_scope().state.ram_page == RamPage::ThisOne((value / 16) as u8);
+        compiler.put_b_number(value, location);
+        if !page_known {
             instr!(compiler, RC, location);
         }
-        compiler.put_b_number(value, location);
-    } else {
-        instr!(compiler, RC, location);
-        if Compiler::can_put_into_b(address) {
-            compiler.put_into_b(address)?;
-        } else if Compiler::can_put_into_a(address) {
-            // if can_put_into_b is false and
-            // can_put_into_a is true is must be an assigmnent
-            compiler.put_into_a(address)?;
-            if let ExpressionType::Assignment { ident, value: _ } = &address.typ {
-                instr!(
-                    compiler,
-                    LB,
-                    compiler.get_var(&ident.symbol, location)?,
-                    address.location
-                );
-            }
-        } else {
-            compiler.eval_expr(address)?;
-            compiler.switch(location)?;
+        return Ok(());
+    }
+    if Compiler::can_put_into_b(address) {
+        compiler.put_into_b(address)?;
+    } else if compiler.can_put_into_a(address) {
+        // if can_put_into_b is false and
+        // can_put_into_a is true is must be an assigmnent
+        compiler.put_into_a(address)?;
+        if let ExpressionType::Assignment { ident, value: _ } = &address.typ {
+            instr!(
+                compiler,
+                LB,
+                compiler.get_var(&ident.symbol, location)?,
+                address.location
+            );
         }
+    } else {
+        compiler.eval_expr(address)?;
+        compiler.switch(location)?;
     }
+    instr!(compiler, RC, location);
     Ok(())
 }
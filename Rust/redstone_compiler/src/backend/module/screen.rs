@@ -22,7 +22,7 @@ const SCREENOP_REG: u8 = BASE_OUT_REG + 6;
 const SCREENPOS_REG: u8 = BASE_OUT_REG + 7;
 
 pub fn module(compiler: &mut Compiler, call: &Call) -> Res {
-    match call.method_name.as_str() {
+    match call.method_name {
         "flip" => screen_operation(compiler, call, 1),
         "clear" => screen_operation(compiler, call, 2),
         "set_at" => pixel_operation(compiler, call, 4),
@@ -32,8 +32,9 @@ pub fn module(compiler: &mut Compiler, call: &Call) -> Res {
         "invert" => whole_pixel_operation(compiler, call, 8),
         "off" => whole_pixel_operation(compiler, call, 16),
         _ => Err(Error {
-            typ: Box::new(ErrorType::UnknownMethod(call.method_name.clone())),
+            typ: Box::new(ErrorType::UnknownMethod(call.method_name.to_string())),
             location: call.location,
+            related: vec![],
         }),
     }
 }
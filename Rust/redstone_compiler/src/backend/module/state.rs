@@ -0,0 +1,22 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+/// What modules remember between calls, at most one value of every type
+#[derive(Debug, Default)]
+pub struct ModuleState {
+    values: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl ModuleState {
+    #[must_use]
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.values.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Replaces the value of the same type
+    pub fn insert<T: 'static>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Box::new(value));
+    }
+}
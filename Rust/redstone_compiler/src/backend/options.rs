@@ -0,0 +1,98 @@
+/// How hard the compiler tries to avoid redundant instructions
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    /// Loads every value again, even if it is already in the register
+    O0,
+    /// Tracks register contents to skip redundant loads
+    #[default]
+    O1,
+}
+
+impl OptLevel {
+    pub const ALL: [Self; 2] = [Self::O0, Self::O1];
+}
+
+use super::InstructionVariant;
+
+/// Properties of the computer the program is compiled for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub name: &'static str,
+    /// Instructions per ROM page, jumps to other pages become disc jumps
+    pub page_size: usize,
+    /// Pages of the ROM, at most 256 because disc jumps load the page with `LCL`
+    pub max_pages: usize,
+    pub timing: Timing,
+}
+
+/// How long instructions take to execute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    /// Cycles of instructions that finish within a single cycle
+    pub instant: u8,
+    /// Cycles of the others, like shifts, multiplication and RAM paging
+    pub slow: u8,
+    /// Redstone ticks per cycle, a tick lasts a tenth of a second
+    pub ticks_per_cycle: u32,
+}
+
+impl Timing {
+    #[must_use]
+    pub const fn cycles(self, variant: InstructionVariant) -> u8 {
+        if variant.instant() {
+            self.instant
+        } else {
+            self.slow
+        }
+    }
+
+    #[must_use]
+    pub const fn ticks(self, cycles: u64) -> u64 {
+        cycles * self.ticks_per_cycle as u64
+    }
+
+    /// Estimated wall-clock time in a game running at full speed
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn seconds(self, cycles: u64) -> f64 {
+        self.ticks(cycles) as f64 / 10.0
+    }
+}
+
+impl Target {
+    pub const MCN_16: Self = Self {
+        name: "mcn-16",
+        page_size: 64,
+        max_pages: 256,
+        timing: Timing {
+            instant: 1,
+            slow: 2,
+            ticks_per_cycle: 10,
+        },
+    };
+
+    pub const ALL: [&'static Self; 1] = [&Self::MCN_16];
+
+    #[must_use]
+    pub fn by_name(name: &str) -> Option<&'static Self> {
+        Self::ALL.into_iter().find(|target| target.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompileOptions {
+    pub opt_level: OptLevel,
+    pub target: Target,
+    /// Whether a failing `assert` halts the program, otherwise they generate no code
+    pub assertions: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            opt_level: OptLevel::default(),
+            target: Target::MCN_16,
+            assertions: false,
+        }
+    }
+}
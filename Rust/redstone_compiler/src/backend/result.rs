@@ -0,0 +1,106 @@
+use crate::frontend::Range;
+
+use super::{ComputerState, DebugInfo, Instruction, Symbol};
+
+/// A compiled program with what the compiler found out about it, so tools can look
+/// things up without walking the syntax tree again
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CompilationResult {
+    instructions: Vec<Instruction>,
+    debug_info: DebugInfo,
+}
+
+impl CompilationResult {
+    #[must_use]
+    pub const fn new(instructions: Vec<Instruction>, debug_info: DebugInfo) -> Self {
+        Self {
+            instructions,
+            debug_info,
+        }
+    }
+
+    #[must_use]
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    #[must_use]
+    pub const fn debug_info(&self) -> &DebugInfo {
+        &self.debug_info
+    }
+
+    #[must_use]
+    pub fn into_parts(self) -> (Vec<Instruction>, DebugInfo) {
+        (self.instructions, self.debug_info)
+    }
+
+    /// Every variable with its slot and how deeply its block is nested, in the order
+    /// they were declared
+    #[must_use]
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.debug_info.symbols
+    }
+
+    /// Sorted by name
+    #[must_use]
+    pub fn modules_used(&self) -> &[String] {
+        &self.debug_info.modules
+    }
+
+    /// Addresses jumps can go to, sorted and without duplicates
+    #[must_use]
+    pub fn jump_targets(&self) -> Vec<usize> {
+        let mut targets = self.debug_info.jump_marks.clone();
+        targets.sort_unstable();
+        targets.dedup();
+        targets
+    }
+
+    /// The instruction `offset` instructions after the start of the program
+    #[must_use]
+    pub fn instruction_for_offset(&self, offset: usize) -> Option<&Instruction> {
+        self.instructions.get(offset)
+    }
+
+    /// From the first to the last instruction generated from code inside of `range`
+    ///
+    /// For whole statements that's exactly their code. Parts of a statement can get code
+    /// of the rest of it, like the condition of a loop, which is checked before and after
+    /// its body
+    #[must_use]
+    pub fn instructions_in(&self, range: Range) -> &[Instruction] {
+        let mut addresses = self.debug_info.source_map.addresses_in(range);
+        let Some(first) = addresses.next() else {
+            return &[];
+        };
+        let last = addresses.last().unwrap_or(first);
+        &self.instructions[first..=last]
+    }
+
+    /// What is known about the registers right before the instruction at `address` runs
+    ///
+    /// Runs the instructions since the last jump target in front of it, nothing is known
+    /// where a jump can land
+    #[must_use]
+    pub fn state_before(&self, address: usize) -> ComputerState {
+        let address = address.min(self.instructions.len());
+        let start = self
+            .debug_info
+            .jump_marks
+            .iter()
+            .filter(|&&target| target <= address)
+            .max();
+        match start {
+            Some(&start) => ComputerState::unknown().after(&self.instructions[start..address]),
+            None => ComputerState::default().after(&self.instructions[..address]),
+        }
+    }
+}
+
+impl From<CompilationResult> for Vec<Instruction> {
+    fn from(result: CompilationResult) -> Self {
+        result.instructions
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{error::Error, frontend::Ast};
+
+use super::{
+    compiler::Compiler, incremental::Snapshot, module::ModuleState, CompileOptions, Instruction,
+    Symbol,
+};
+
+/// Compiles a program piece by piece, like a REPL reads it
+///
+/// Variables, inline variables and modules of earlier pieces stay defined and what the
+/// compiler knows about the registers is kept. Every piece is linked as a program of
+/// its own, so it has to run right after the previous one, with the same registers,
+/// slots and RAM, like [`Emulator::load`](crate::emulator::Emulator::load) does
+#[derive(Debug)]
+pub struct Session {
+    options: CompileOptions,
+    before: Snapshot,
+    module_state: ModuleState,
+    symbols: Vec<Symbol>,
+}
+
+impl Session {
+    #[must_use]
+    pub fn new(options: CompileOptions) -> Self {
+        Self {
+            options,
+            before: Compiler::new(&Ast::new(), options).snapshot(),
+            module_state: ModuleState::default(),
+            symbols: vec![],
+        }
+    }
+
+    /// The code of the statements of `ast`, usually the one line that was typed. If
+    /// one of them fails, the session stays as it was
+    ///
+    /// # Errors
+    ///
+    /// on any compiler error
+    pub fn compile_expression(&mut self, ast: &Ast) -> Result<Vec<Instruction>, Vec<Error>> {
+        let mut compiler = Compiler::new(ast, self.options);
+        compiler.restore(&self.before);
+        compiler.set_module_state(std::mem::take(&mut self.module_state));
+        let result = compiler.eval_program();
+        self.module_state = compiler.take_module_state();
+        result?;
+        let before = compiler.snapshot();
+        let (instructions, debug_info) = compiler
+            .link_with_debug_info()
+            .map_err(|error| vec![error])?;
+        self.before = before;
+        self.symbols.extend(debug_info.symbols);
+        Ok(instructions)
+    }
+
+    /// Every variable declared so far, in order
+    #[must_use]
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Names of the modules that are used so far, sorted
+    #[must_use]
+    pub fn modules(&self) -> Vec<&str> {
+        let mut modules: Vec<_> = self.before.modules.iter().map(String::as_str).collect();
+        modules.sort_unstable();
+        modules
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new(CompileOptions::default())
+    }
+}
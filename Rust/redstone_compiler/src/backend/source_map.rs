@@ -0,0 +1,73 @@
+use std::sync::OnceLock;
+
+use crate::frontend::Range;
+
+use super::Instruction;
+
+/// Maps instruction addresses back to the code they were generated from
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceMap {
+    locations: Vec<Range>,
+    /// Addresses sorted by their location, built by the first lookup of a range
+    #[cfg_attr(feature = "serde", serde(skip))]
+    by_location: OnceLock<Vec<usize>>,
+}
+
+impl PartialEq for SourceMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.locations == other.locations
+    }
+}
+
+impl Eq for SourceMap {}
+
+impl SourceMap {
+    #[must_use]
+    pub fn new(instructions: &[Instruction]) -> Self {
+        Self {
+            locations: instructions.iter().map(|i| i.orig_location).collect(),
+            by_location: OnceLock::new(),
+        }
+    }
+
+    /// Source location of every instruction, indexed by address
+    #[must_use]
+    pub fn locations(&self) -> &[Range] {
+        &self.locations
+    }
+
+    #[must_use]
+    pub fn location_of(&self, address: usize) -> Option<Range> {
+        self.locations.get(address).copied()
+    }
+
+    /// Addresses of all instructions generated from code inside of `range`, in order
+    pub fn addresses_in(&self, range: Range) -> impl Iterator<Item = usize> {
+        let by_location = self.by_location.get_or_init(|| {
+            let mut addresses: Vec<_> = (0..self.locations.len()).collect();
+            addresses.sort_by_key(|&address| self.locations[address]);
+            addresses
+        });
+        // only locations that start inside of the range can be inside of it
+        let first = by_location.partition_point(|&address| self.locations[address].0 < range.0);
+        let mut addresses: Vec<_> = by_location[first..]
+            .iter()
+            .take_while(|&&address| self.locations[address].0 <= range.1)
+            .filter(|&&address| self.locations[address].1 <= range.1)
+            .copied()
+            .collect();
+        addresses.sort_unstable();
+        addresses.into_iter()
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+}
@@ -0,0 +1,14 @@
+use crate::frontend::Range;
+
+/// A variable and the slot the compiler gave it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Symbol {
+    pub name: String,
+    pub slot: u8,
+    /// 0 for global variables, every block they are nested in adds one.
+    /// The slots of nested variables are reused after their block
+    pub depth: usize,
+    /// Code that introduced the variable
+    pub location: Range,
+}
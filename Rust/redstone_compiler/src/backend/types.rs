@@ -2,9 +2,11 @@ use std::collections::HashMap;
 
 use crate::backend::Instruction;
 
+/// What the compiler knows about the value in a register
 #[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
-#[allow(unused)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RegisterContents {
+    /// The value of the variable in this RAM slot
     Variable(u8),
     Number(i16),
     RamAddress(i32),
@@ -12,7 +14,9 @@ pub enum RegisterContents {
     Unknown,
 }
 
+/// The RAM page slots are read from and written to
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RamPage {
     ThisOne(u8),
     Unknown,
@@ -24,7 +28,13 @@ impl Default for RamPage {
     }
 }
 
-#[derive(Default, Copy, Clone, Debug)]
+/// What is known about the registers at some point of a program, without running it
+///
+/// Start with [`Self::default`] at the start of the program, where only the RAM page is
+/// known, or with [`Self::unknown`] where jumps land, and step over instructions with
+/// [`Instruction::execute`]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ComputerState {
     pub a: RegisterContents,
     pub b: RegisterContents,
@@ -32,10 +42,76 @@ pub struct ComputerState {
     pub ram_page: RamPage,
 }
 
-#[derive(Debug)]
+impl ComputerState {
+    /// Nothing is known, not even the ram page
+    #[must_use]
+    pub const fn unknown() -> Self {
+        Self {
+            a: RegisterContents::Unknown,
+            b: RegisterContents::Unknown,
+            c: RegisterContents::Unknown,
+            ram_page: RamPage::Unknown,
+        }
+    }
+
+    /// The state after running `instructions` one after another. Jumps aren't followed
+    #[must_use]
+    pub fn after(mut self, instructions: &[Instruction]) -> Self {
+        for instruction in instructions {
+            instruction.execute(&mut self);
+        }
+        self
+    }
+
+    /// What is known on both paths where two paths of the program join
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        let keep_equal = |this: RegisterContents, other| {
+            if this == other {
+                this
+            } else {
+                RegisterContents::Unknown
+            }
+        };
+        Self {
+            a: keep_equal(self.a, other.a),
+            b: keep_equal(self.b, other.b),
+            c: keep_equal(self.c, other.c),
+            ram_page: if self.ram_page == other.ram_page {
+                self.ram_page
+            } else {
+                RamPage::Unknown
+            },
+        }
+    }
+}
+
+/// Code before linking, nested like the scopes that generated it. Jumps still point
+/// to jump marks instead of addresses
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instr {
     Code(Instruction),
-    Scope(Vec<Instr>),
+    Scope(Vec<Self>),
+}
+
+impl Instr {
+    /// Instructions in `scope`, nested ones included
+    #[must_use]
+    pub fn count(scope: &[Self]) -> usize {
+        let mut count = 0;
+        let mut stack = vec![scope.iter()];
+        while let Some(scope) = stack.last_mut() {
+            match scope.next() {
+                Some(Self::Code(_)) => count += 1,
+                Some(Self::Scope(inner)) => stack.push(inner.iter()),
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        count
+    }
 }
 
 #[derive(Debug, Default)]
@@ -44,6 +120,12 @@ pub struct Scope {
     pub(crate) variables: HashMap<String, u8>,
     pub(crate) inline_variables: HashMap<String, i16>,
     pub(crate) instructions: Vec<Instr>,
+    /// Length of `instructions` with the nested scopes flattened
+    pub(crate) len: usize,
+    /// A statement of it never finishes, so the ones after it can't run
+    pub(crate) stopped: bool,
+    /// Index of the warning about the statements after that one, once there are some
+    pub(crate) unreachable: Option<usize>,
 }
 
 impl Scope {
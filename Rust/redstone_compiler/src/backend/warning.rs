@@ -0,0 +1,58 @@
+use crate::{
+    error::{ErrorType, Related},
+    frontend::Range,
+};
+
+/// Code that compiles, but probably doesn't do what it should
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Warning {
+    pub typ: WarningType,
+    pub location: Range,
+    /// Other locations that explain the warning
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub related: Vec<Related>,
+}
+
+impl Warning {
+    #[must_use]
+    pub fn render(&self, code: &str, file: &str) -> String {
+        crate::error::render_warning(
+            &self.typ.get_message(),
+            self.location,
+            &self.related,
+            code,
+            file,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WarningType {
+    /// A `todo`, it halts the program
+    Todo,
+    /// Statements after a `forever` loop or a `todo` in the same block, they generate
+    /// no code
+    Unreachable,
+    /// A module that an earlier `use` already loaded, it isn't loaded again
+    DuplicateUse(String),
+}
+
+impl ErrorType for WarningType {
+    fn get_message(&self) -> String {
+        match self {
+            Self::Todo => "Unfinished code".to_string(),
+            Self::Unreachable => "This code can never run".to_string(),
+            Self::DuplicateUse(module) => format!("Module {module} is already used"),
+        }
+    }
+
+    fn get_code(&self) -> &'static str {
+        match self {
+            Self::Todo => "todo",
+            Self::Unreachable => "unreachable",
+            Self::DuplicateUse(_) => "duplicate-use",
+        }
+    }
+}
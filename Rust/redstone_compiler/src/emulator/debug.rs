@@ -0,0 +1,125 @@
+use crate::{
+    backend::{DebugInfo, SourceMap},
+    frontend::Range,
+};
+
+use super::{Emulator, StopReason, Watch, WatchHit};
+
+impl Emulator {
+    /// Makes [`Self::run`] stop before executing the instruction at `address`
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    /// Breaks where execution enters code inside of `range`, that is on every instruction
+    /// from the range that doesn't follow another one from it.
+    /// Returns the addresses, none means there is no code to break on
+    pub fn add_breakpoint_in(&mut self, range: Range) -> Vec<usize> {
+        let addresses: Vec<_> = self.source_map.addresses_in(range).collect();
+        let entries: Vec<_> = addresses
+            .iter()
+            .enumerate()
+            .filter(|&(i, &address)| i == 0 || addresses[i - 1] + 1 != address)
+            .map(|(_, &address)| address)
+            .collect();
+        self.breakpoints.extend(&entries);
+        entries
+    }
+
+    /// Returns whether there was a breakpoint
+    pub fn remove_breakpoint(&mut self, address: usize) -> bool {
+        self.breakpoints.remove(&address)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Addresses with a breakpoint, in order
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Locations of the instructions, empty if the program was loaded from binary
+    #[must_use]
+    pub const fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Takes the source map of a program loaded from binary, so breakpoints and locations
+    /// work. Returns false and keeps the old one if the info is incompatible or belongs
+    /// to a program of another length
+    pub fn load_debug_info(&mut self, info: &DebugInfo) -> bool {
+        if !info.is_compatible() || info.source_map.len() != self.len() {
+            return false;
+        }
+        self.source_map = info.source_map.clone();
+        true
+    }
+
+    /// Code the next instruction was generated from
+    #[must_use]
+    pub fn current_location(&self) -> Option<Range> {
+        self.source_map.location_of(self.state.pc)
+    }
+
+    /// Executes instructions until the next one starts another line of code or
+    /// a jump goes back to the same line. Instructions spanning several lines, like the
+    /// jumps of loops, belong to no line and are stepped over.
+    /// Stops early for the same reasons as [`Self::run`] except breakpoints
+    pub fn step_statement(&mut self, max_cycles: u64) -> Option<StopReason> {
+        let Some(start) = self.current_location() else {
+            return self.step();
+        };
+        let start_address = self.state.pc;
+        loop {
+            if self.state.cycles >= max_cycles {
+                return Some(StopReason::CycleLimit);
+            }
+            if let Some(reason) = self.step() {
+                return Some(reason);
+            }
+            if let Some(hit) = self.watch_hit.take() {
+                return Some(StopReason::Watchpoint(hit));
+            }
+            // past the end, the next step reports it
+            let location = self.current_location()?;
+            let single_line = location.0 .0 == location.1 .0;
+            if single_line && (location.0 .0 != start.0 .0 || self.state.pc <= start_address) {
+                return None;
+            }
+        }
+    }
+
+    /// Makes [`Self::run`] stop after an instruction changes the value
+    pub fn add_watch(&mut self, watch: Watch) {
+        self.watches.insert(watch);
+    }
+
+    /// Returns whether the value was watched
+    pub fn remove_watch(&mut self, watch: Watch) -> bool {
+        self.watches.remove(&watch)
+    }
+
+    pub fn clear_watches(&mut self) {
+        self.watches.clear();
+    }
+
+    pub fn watches(&self) -> impl Iterator<Item = Watch> + '_ {
+        self.watches.iter().copied()
+    }
+
+    /// Called before the instruction at `address` overwrites `old`
+    pub(super) fn watch_write(&mut self, watch: Watch, old: i16, address: usize) {
+        let new = self.state.a;
+        if old != new && self.watches.contains(&watch) {
+            self.watch_hit = Some(WatchHit {
+                watch,
+                old,
+                new,
+                address,
+                location: self.source_map.location_of(address),
+            });
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    collections::HashSet,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use super::State;
+
+/// Hashes kept before starting over, bounds the memory a long run needs
+const MAX_SEEN: usize = 1 << 16;
+
+/// Notices when execution comes back to a state it has been in before,
+/// from there on it would repeat forever
+#[derive(Debug, Default)]
+pub struct LoopDetector {
+    seen: HashSet<u64>,
+}
+
+impl LoopDetector {
+    /// Records the state, returns whether it was already recorded.
+    /// RAM is represented by the number of writes that changed it,
+    /// so loops that write to RAM are never reported
+    pub fn visit(&mut self, state: &State, ram_writes: u64) -> bool {
+        let mut hasher = DefaultHasher::new();
+        (
+            state.pc,
+            state.a,
+            state.b,
+            state.c,
+            state.slots,
+            state.ram_page,
+            state.inputs,
+            state.outputs,
+            ram_writes,
+        )
+            .hash(&mut hasher);
+        if self.seen.len() >= MAX_SEEN {
+            self.seen.clear();
+        }
+        !self.seen.insert(hasher.finish())
+    }
+
+    pub fn clear(&mut self) {
+        self.seen.clear();
+    }
+}
@@ -0,0 +1,450 @@
+mod debug;
+mod guard;
+mod state;
+
+use std::collections::BTreeSet;
+
+use crate::{
+    backend::{disassemble, Instruction, InstructionVariant, InvalidWord, SourceMap, Target},
+    frontend::Range,
+};
+
+use guard::LoopDetector;
+pub use state::State;
+
+/// Variable slots, the slots after them are the I/O ports
+pub const SLOT_COUNT: usize = 32;
+pub const PORT_COUNT: usize = 8;
+/// Words of RAM, selected in pages of 16 by `RC`
+pub const RAM_SIZE: usize = 4096;
+pub const RAM_PAGE_SIZE: usize = 16;
+
+/// A value written to an output port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OutputWrite {
+    pub port: u8,
+    pub value: i16,
+    /// Cycles spent up to and including the write
+    pub cycle: u64,
+}
+
+/// Why [`Emulator::run`] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum StopReason {
+    /// Reached a `STP`, like the one of a failed `assert` or a `todo`.
+    /// [`Emulator::current_location`] is the code it belongs to
+    Halted,
+    /// Ran past the last instruction
+    EndOfProgram,
+    /// Used up the cycle budget
+    CycleLimit,
+    /// The instruction at this address can't be executed
+    InvalidInstruction(usize),
+    /// About to execute the instruction at a breakpoint, inspect it with [`Emulator::state`]
+    Breakpoint(usize),
+    /// Just changed a watched value
+    Watchpoint(WatchHit),
+    /// Jumped back to this address in a state it was in before, see [`Emulator::detect_loops`]
+    PossibleInfiniteLoop(usize),
+}
+
+/// A variable slot or RAM word that stops [`Emulator::run`] when its value changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum Watch {
+    Slot(u8),
+    Ram(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct WatchHit {
+    pub watch: Watch,
+    pub old: i16,
+    pub new: i16,
+    /// The instruction that wrote the value
+    pub address: usize,
+    pub location: Option<Range>,
+}
+
+/// Consulted when the program reads an input port, `None` keeps the value set with
+/// [`Emulator::set_input`]
+pub type InputHandler = Box<dyn FnMut(u8) -> Option<i16>>;
+/// Called for every write to an output port
+pub type OutputHandler = Box<dyn FnMut(OutputWrite)>;
+
+/// Runs compiled programs the way the computer would
+pub struct Emulator {
+    program: Vec<(InstructionVariant, u8)>,
+    target: Target,
+    state: State,
+    ram: Vec<i16>,
+    /// Writes that changed the RAM
+    ram_writes: u64,
+    loop_detector: Option<LoopDetector>,
+    output_log: Vec<OutputWrite>,
+    on_input: Option<InputHandler>,
+    on_output: Option<OutputHandler>,
+    /// Empty for programs loaded from binary
+    source_map: SourceMap,
+    breakpoints: BTreeSet<usize>,
+    /// Breakpoint [`Self::run`] stopped at, skipped when it continues
+    paused_at: Option<usize>,
+    watches: BTreeSet<Watch>,
+    /// Set by the last step if it changed a watched value
+    watch_hit: Option<WatchHit>,
+}
+
+impl Emulator {
+    #[must_use]
+    pub fn new(program: &[Instruction], target: Target) -> Self {
+        Self {
+            source_map: SourceMap::new(program),
+            ..Self::with_program(
+                program
+                    .iter()
+                    .map(|instr| (instr.variant, instr.arg.unwrap_or(0)))
+                    .collect(),
+                target,
+            )
+        }
+    }
+
+    /// Loads an encoded program like the one in the ROM
+    ///
+    /// # Errors
+    ///
+    /// The first word that isn't an instruction
+    pub fn from_binary(words: &[u16], target: Target) -> Result<Self, InvalidWord> {
+        let program = disassemble(words)?
+            .iter()
+            .map(|instr| (instr.variant, instr.arg.unwrap_or(0)))
+            .collect();
+        Ok(Self::with_program(program, target))
+    }
+
+    fn with_program(program: Vec<(InstructionVariant, u8)>, target: Target) -> Self {
+        Self {
+            program,
+            target,
+            state: State::default(),
+            ram: vec![0; RAM_SIZE],
+            ram_writes: 0,
+            loop_detector: None,
+            output_log: vec![],
+            on_input: None,
+            on_output: None,
+            source_map: SourceMap::default(),
+            breakpoints: BTreeSet::new(),
+            paused_at: None,
+            watches: BTreeSet::new(),
+            watch_hit: None,
+        }
+    }
+
+    /// Replaces the program and starts it from its first instruction. Registers, slots
+    /// and RAM are kept, so the pieces of a [`Session`](crate::backend::Session) can run
+    /// one after the other. Breakpoints are removed, they were for the old program
+    pub fn load(&mut self, program: &[Instruction]) {
+        self.program = program
+            .iter()
+            .map(|instr| (instr.variant, instr.arg.unwrap_or(0)))
+            .collect();
+        self.source_map = SourceMap::new(program);
+        self.state.pc = 0;
+        self.breakpoints.clear();
+        self.paused_at = None;
+        if let Some(detector) = &mut self.loop_detector {
+            detector.clear();
+        }
+    }
+
+    /// Starts over with cleared registers, slots and RAM, inputs and breakpoints are kept
+    pub fn reset(&mut self) {
+        self.state = State {
+            inputs: self.state.inputs,
+            ..State::default()
+        };
+        self.ram.fill(0);
+        self.ram_writes = 0;
+        if let Some(detector) = &mut self.loop_detector {
+            detector.clear();
+        }
+        self.output_log.clear();
+        self.paused_at = None;
+    }
+
+    /// Sets what the program reads from an input port, ports out of range are ignored
+    pub fn set_input(&mut self, port: u8, value: i16) {
+        if let Some(input) = self.state.inputs.get_mut(port as usize) {
+            *input = value;
+        }
+    }
+
+    /// Makes [`Self::run`] stop with [`StopReason::PossibleInfiniteLoop`] when a jump back
+    /// leads to a state seen before. Programs waiting for input in a loop are stopped too,
+    /// unless the input changes
+    pub fn detect_loops(&mut self, enabled: bool) {
+        self.loop_detector = enabled.then(LoopDetector::default);
+    }
+
+    /// Replaces the handler asked for input values, the port is 0-7
+    pub fn on_input(&mut self, handler: impl FnMut(u8) -> Option<i16> + 'static) {
+        self.on_input = Some(Box::new(handler));
+    }
+
+    /// Replaces the handler told about output writes, they are logged either way
+    pub fn on_output(&mut self, handler: impl FnMut(OutputWrite) + 'static) {
+        self.on_output = Some(Box::new(handler));
+    }
+
+    #[must_use]
+    pub const fn state(&self) -> &State {
+        &self.state
+    }
+
+    #[must_use]
+    pub fn ram(&self) -> &[i16] {
+        &self.ram
+    }
+
+    /// Number of instructions in the program
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.program.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.program.is_empty()
+    }
+
+    /// The instruction at `pc` with its argument, 0 if it doesn't take one
+    #[must_use]
+    pub fn current_instruction(&self) -> Option<(InstructionVariant, u8)> {
+        self.program.get(self.state.pc).copied()
+    }
+
+    /// ROM page the next instruction is on
+    #[must_use]
+    pub const fn page(&self) -> usize {
+        self.state.pc / self.target.page_size
+    }
+
+    /// Every output write so far, in order
+    #[must_use]
+    pub fn output_log(&self) -> &[OutputWrite] {
+        &self.output_log
+    }
+
+    #[must_use]
+    pub const fn target(&self) -> Target {
+        self.target
+    }
+
+    #[must_use]
+    pub const fn cycles(&self) -> u64 {
+        self.state.cycles
+    }
+
+    /// Redstone ticks the computer would have needed so far
+    #[must_use]
+    pub const fn ticks(&self) -> u64 {
+        self.target.timing.ticks(self.state.cycles)
+    }
+
+    /// Wall-clock estimate of the time spent so far
+    #[must_use]
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.target.timing.seconds(self.state.cycles)
+    }
+
+    /// Executes instructions until the program stops or `max_cycles` have passed in total.
+    /// Running again after a breakpoint continues past it
+    pub fn run(&mut self, max_cycles: u64) -> StopReason {
+        let mut resumed_at = self.paused_at.take();
+        loop {
+            let pc = self.state.pc;
+            if resumed_at.take() != Some(pc) && self.breakpoints.contains(&pc) {
+                self.paused_at = Some(pc);
+                return StopReason::Breakpoint(pc);
+            }
+            if self.state.cycles >= max_cycles {
+                return StopReason::CycleLimit;
+            }
+            if let Some(reason) = self.step() {
+                return reason;
+            }
+            if let Some(hit) = self.watch_hit.take() {
+                return StopReason::Watchpoint(hit);
+            }
+            if let Some(detector) = &mut self.loop_detector {
+                if self.state.pc <= pc && detector.visit(&self.state, self.ram_writes) {
+                    return StopReason::PossibleInfiniteLoop(self.state.pc);
+                }
+            }
+        }
+    }
+
+    /// Executes one instruction, returns why the program can't continue.
+    /// Stopping doesn't advance `pc`, so stepping again stops the same way
+    pub fn step(&mut self) -> Option<StopReason> {
+        let Some(&(variant, arg)) = self.program.get(self.state.pc) else {
+            return Some(StopReason::EndOfProgram);
+        };
+        let address = self.state.pc;
+        self.paused_at = None;
+        self.watch_hit = None;
+        self.state.cycles += u64::from(self.target.timing.cycles(variant));
+        self.state.pc += 1;
+        let reason = self.execute(variant, arg, address).err();
+        if reason.is_some() {
+            // stay on the instruction that stopped the program
+            self.state.pc = address;
+        }
+        reason
+    }
+
+    fn execute(
+        &mut self,
+        variant: InstructionVariant,
+        arg: u8,
+        address: usize,
+    ) -> Result<(), StopReason> {
+        use InstructionVariant as IV;
+        let invalid = StopReason::InvalidInstruction(address);
+        match variant {
+            IV::STOP => return Err(StopReason::Halted),
+            IV::NON => {}
+            IV::LA => self.state.a = self.read_slot(arg).ok_or(invalid)?,
+            IV::LB => self.state.b = self.read_slot(arg).ok_or(invalid)?,
+            IV::LC => self.state.c = self.read_slot(arg).ok_or(invalid)?,
+            IV::SVA => self.write_slot(arg, address).ok_or(invalid)?,
+            IV::LAL => self.state.a = arg.into(),
+            IV::LAH => self.state.a = set_high_byte(self.state.a, arg),
+            IV::LBL => self.state.b = arg.into(),
+            IV::LBH => self.state.b = set_high_byte(self.state.b, arg),
+            IV::LCL => self.state.c = arg.into(),
+            IV::ADD => self.state.a = self.state.a.wrapping_add(self.state.b),
+            IV::SUB => self.state.a = self.state.a.wrapping_sub(self.state.b),
+            IV::AND => self.state.a &= self.state.b,
+            IV::OR => self.state.a |= self.state.b,
+            IV::XOR => self.state.a ^= self.state.b,
+            IV::MUL => self.state.a = self.state.a.wrapping_mul(self.state.b),
+            IV::SUP => self.state.a = self.state.a.wrapping_shl(arg.into()),
+            IV::SDN => self.state.a = self.state.a.wrapping_shr(arg.into()),
+            IV::RC => self.state.ram_page = (self.state.b as u16 / RAM_PAGE_SIZE as u16) as u8,
+            IV::RR => self.state.a = self.ram[self.ram_address()],
+            IV::RW => {
+                let ram_address = self.ram_address();
+                let old = self.ram[ram_address];
+                if old != self.state.a {
+                    self.watch_write(Watch::Ram(ram_address), old, address);
+                    self.ram_writes += 1;
+                }
+                self.ram[ram_address] = self.state.a;
+            }
+            IV::INB => self.state.b = self.state.b.wrapping_add(1),
+            // encoded, but the computer doesn't implement skips
+            IV::SMP
+            | IV::SE
+            | IV::SNE
+            | IV::SG
+            | IV::SGE
+            | IV::SL
+            | IV::SLE
+            | IV::SMD
+            | IV::SDE
+            | IV::SDNE
+            | IV::SDG
+            | IV::SDGE
+            | IV::SDL
+            | IV::SDLE => return Err(invalid),
+            IV::JMP
+            | IV::JE
+            | IV::JNE
+            | IV::JG
+            | IV::JGE
+            | IV::JL
+            | IV::JLE
+            | IV::JMD
+            | IV::JDE
+            | IV::JDN
+            | IV::JDG
+            | IV::JDGE
+            | IV::JDL
+            | IV::JDLE => {
+                if self.condition(variant) {
+                    let page = if variant.disc_jump() {
+                        self.state.c as usize
+                    } else {
+                        address / self.target.page_size
+                    };
+                    self.state.pc =
+                        page * self.target.page_size + arg as usize % self.target.page_size;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_slot(&mut self, slot: u8) -> Option<i16> {
+        let index = slot as usize;
+        if index < SLOT_COUNT {
+            return Some(self.state.slots[index]);
+        }
+        let port = slot - SLOT_COUNT as u8;
+        let input = self.state.inputs.get_mut(port as usize)?;
+        if let Some(value) = self.on_input.as_mut().and_then(|handler| handler(port)) {
+            *input = value;
+        }
+        Some(*input)
+    }
+
+    fn write_slot(&mut self, slot: u8, address: usize) -> Option<()> {
+        let index = slot as usize;
+        if index < SLOT_COUNT {
+            self.watch_write(Watch::Slot(slot), self.state.slots[index], address);
+            self.state.slots[index] = self.state.a;
+        } else {
+            *self.state.outputs.get_mut(index - SLOT_COUNT)? = self.state.a;
+            let write = OutputWrite {
+                port: slot - SLOT_COUNT as u8,
+                value: self.state.a,
+                cycle: self.state.cycles,
+            };
+            if let Some(handler) = &mut self.on_output {
+                handler(write);
+            }
+            self.output_log.push(write);
+        }
+        Some(())
+    }
+
+    const fn ram_address(&self) -> usize {
+        self.state.ram_page as usize * RAM_PAGE_SIZE
+            + (self.state.b as u16 as usize % RAM_PAGE_SIZE)
+    }
+
+    const fn condition(&self, jump: InstructionVariant) -> bool {
+        match jump.id() {
+            1 => self.state.a == self.state.b,
+            2 => self.state.a != self.state.b,
+            3 => self.state.a > self.state.b,
+            4 => self.state.a >= self.state.b,
+            5 => self.state.a < self.state.b,
+            6 => self.state.a <= self.state.b,
+            _ => true,
+        }
+    }
+}
+
+/// What `LAH` and `LBH` do, the low byte stays
+pub(crate) const fn set_high_byte(value: i16, high: u8) -> i16 {
+    (value & 0xff) | ((high as i16) << 8)
+}
@@ -0,0 +1,36 @@
+use super::{PORT_COUNT, SLOT_COUNT};
+
+/// Registers, slots and ports of the computer, everything but the RAM
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct State {
+    /// Address of the next instruction
+    pub pc: usize,
+    pub a: i16,
+    pub b: i16,
+    pub c: i16,
+    pub slots: [i16; SLOT_COUNT],
+    /// Selected by `RC`, `RR` and `RW` access one of its 16 words
+    pub ram_page: u8,
+    pub inputs: [i16; PORT_COUNT],
+    /// Last value written to each output port
+    pub outputs: [i16; PORT_COUNT],
+    pub cycles: u64,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            pc: 0,
+            a: 0,
+            b: 0,
+            c: 0,
+            slots: [0; SLOT_COUNT],
+            ram_page: 0,
+            inputs: [0; PORT_COUNT],
+            outputs: [0; PORT_COUNT],
+            cycles: 0,
+        }
+    }
+}
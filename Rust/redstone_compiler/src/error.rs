@@ -1,4 +1,4 @@
-use colored::{Colorize, CustomColor};
+use colored::{ColoredString, Colorize, CustomColor};
 use std::fmt::{Debug, Display};
 
 use crate::frontend::Range;
@@ -9,24 +9,71 @@ macro_rules! err {
         Err($crate::error::Error {
             typ: Box::new(ErrorType::$type),
             location: $loc,
+            related: vec![],
         })
     };
     ($type:expr, $loc:expr) => {
         Err($crate::error::Error {
             typ: Box::new($type),
             location: $loc,
+            related: vec![],
         })
     };
 }
 
 #[allow(clippy::module_name_repetitions)]
-pub trait ErrorType {
+/// `Send` so errors can come from compilations on other threads
+pub trait ErrorType: Send {
     fn get_message(&self) -> String;
+    /// Stable identifier of the kind of error, independent of the message
+    fn get_code(&self) -> &'static str;
+    /// Changes an editor can offer to fix the error
+    fn fixes(&self) -> Vec<Fix> {
+        vec![]
+    }
+}
+
+/// A change that fixes an error, applied in the order they are listed
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum Fix {
+    /// Replace a misspelled name inside the location of the error
+    Rename { from: String, to: String },
+    /// Load a module with a `use` statement after the ones at the top of the file
+    AddUse(String),
 }
 
 pub struct Error {
     pub typ: Box<dyn ErrorType>,
     pub location: Range,
+    /// Other locations that explain the error
+    pub related: Vec<Related>,
+}
+
+/// A second location that matters for an error, like where the block that misses its
+/// `end` started
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Related {
+    pub location: Range,
+    pub message: String,
+}
+
+/// Serialized like a diagnostic, with the code, message and fixes of its type
+#[cfg(feature = "serde")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut error = serializer.serialize_struct("Error", 5)?;
+        error.serialize_field("code", self.typ.get_code())?;
+        error.serialize_field("message", &self.typ.get_message())?;
+        error.serialize_field("location", &self.location)?;
+        error.serialize_field("related", &self.related)?;
+        error.serialize_field("fixes", &self.typ.fixes())?;
+        error.end()
+    }
 }
 
 impl Debug for Error {
@@ -53,6 +100,18 @@ const BRIGHT_RED: CustomColor = CustomColor {
     b: 86,
 };
 
+const YELLOW: CustomColor = CustomColor {
+    r: 193,
+    g: 156,
+    b: 0,
+};
+
+const BRIGHT_YELLOW: CustomColor = CustomColor {
+    r: 249,
+    g: 241,
+    b: 165,
+};
+
 const BRIGHT_BLUE: CustomColor = CustomColor {
     r: 59,
     g: 120,
@@ -65,35 +124,102 @@ impl Error {
         Ok(())
     }
 
+    #[must_use]
+    pub fn with_related(mut self, location: Range, message: impl Into<String>) -> Self {
+        self.related.push(Related {
+            location,
+            message: message.into(),
+        });
+        self
+    }
+
     pub fn pretty_print(&self, code: &str, file: &str) {
-        if self.location.0 .0 != self.location.1 .0 {
-            println!("Multi-line errors don't support nice error messages yet\n{self}");
-            return;
-        }
-        let Some(line) = code.split('\n').nth(self.location.0 .0 as usize) else {
-            println!("Compiler crashed, line does not exist in file, apparently\n{self}");
-            return;
-        };
-
-        println!(
-            "{} {}\nat {file}:{:?}",
-            "Error:".custom_color(RED),
-            self.typ.get_message().custom_color(BRIGHT_RED),
-            self.location
+        println!("{}", self.render(code, file));
+    }
+
+    /// The message with the line of code it's in and the location underlined,
+    /// followed by the related locations, colored unless `colored` is told otherwise
+    #[must_use]
+    pub fn render(&self, code: &str, file: &str) -> String {
+        let mut text = render(
+            &"Error:".custom_color(RED),
+            &self.typ.get_message(),
+            BRIGHT_RED,
+            self.location,
+            code,
+            file,
         );
+        render_related(&mut text, &self.related, code, file);
+        text
+    }
+}
+
+/// Like [`Error::render`], for a warning
+pub fn render_warning(
+    message: &str,
+    location: Range,
+    related: &[Related],
+    code: &str,
+    file: &str,
+) -> String {
+    let mut text = render(
+        &"Warning:".custom_color(YELLOW),
+        message,
+        BRIGHT_YELLOW,
+        location,
+        code,
+        file,
+    );
+    render_related(&mut text, related, code, file);
+    text
+}
+
+fn render_related(text: &mut String, related: &[Related], code: &str, file: &str) {
+    for related in related {
+        text.push_str(&render(
+            &"Note:".custom_color(BRIGHT_BLUE),
+            &related.message,
+            BRIGHT_BLUE,
+            related.location,
+            code,
+            file,
+        ));
+    }
+}
 
-        let line_number = format!("{} | ", self.location.0 .0 + 1);
-        let len = line_number.len() - 3;
-
-        println!("{} {} ", " ".repeat(len), "|".custom_color(BRIGHT_BLUE));
-        print!("{}", line_number.as_str().custom_color(BRIGHT_BLUE));
-        println!("{line}");
-        print!("{} {} ", " ".repeat(len), "|".custom_color(BRIGHT_BLUE));
-        println!(
-            "{}{}\n",
-            " ".repeat(self.location.0 .1 as usize - 1),
-            "^".repeat((self.location.1 .1 - self.location.0 .1) as usize + 1)
-                .custom_color(BRIGHT_RED)
+fn render(
+    heading: &ColoredString,
+    message: &str,
+    color: CustomColor,
+    location: Range,
+    code: &str,
+    file: &str,
+) -> String {
+    if location.0 .0 != location.1 .0 {
+        return format!(
+            "Multi-line errors don't support nice error messages yet\n{message} at {location:?}"
         );
     }
+    let Some(line) = code.split('\n').nth(location.0 .0 as usize) else {
+        return format!(
+            "Compiler crashed, line does not exist in file, apparently\n{message} at {location:?}"
+        );
+    };
+
+    let line_number = format!("{} | ", location.0 .0 + 1);
+    let len = line_number.len() - 3;
+    // columns start at 1, errors at the start of a line are at 0
+    let start = location.0 .1.max(1);
+    let width = location.1 .1.saturating_sub(start) + 1;
+    format!(
+        "{heading} {}\nat {file}:{location:?}\n{} {}\n{}{line}\n{} {} {}{}\n",
+        message.custom_color(color),
+        " ".repeat(len),
+        "|".custom_color(BRIGHT_BLUE),
+        line_number.as_str().custom_color(BRIGHT_BLUE),
+        " ".repeat(len),
+        "|".custom_color(BRIGHT_BLUE),
+        " ".repeat(start as usize - 1),
+        "^".repeat(width as usize).custom_color(color)
+    )
 }
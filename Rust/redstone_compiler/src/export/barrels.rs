@@ -0,0 +1,40 @@
+//! Commands that fill the barrels of a ROM that is already built, for CPUs that read
+//! their program from the signal strength of containers instead of solid blocks
+
+use super::{Encoding, Layout, RomLayout};
+
+/// The function as text, see [`commands`]
+#[must_use]
+pub fn write(words: &[u16], page_size: usize, layout: &Layout) -> Vec<u8> {
+    let mut function = commands(words, page_size, layout).join("\n");
+    function.push('\n');
+    function.into_bytes()
+}
+
+/// A `data modify` command for every barrel of `layout` with [`Encoding::Barrels`]
+///
+/// They replace the items of the barrels without touching the blocks, whatever encoding
+/// `layout` has. Positions are relative to the first barrel of the first word, so they
+/// are run from there
+#[must_use]
+pub fn commands(words: &[u16], page_size: usize, layout: &Layout) -> Vec<String> {
+    let layout = Layout {
+        encoding: Encoding::Barrels,
+        ..layout.clone()
+    };
+    layout
+        .place(words, page_size)
+        .into_iter()
+        .filter_map(|block| {
+            let [x, y, z] = block.position;
+            let (_, items) = block
+                .entity?
+                .data
+                .into_iter()
+                .find(|(name, _)| name == "Items")?;
+            Some(format!(
+                "data modify block ~{x} ~{y} ~{z} Items set value {items}"
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+//! A datapack with functions that place the ROM with commands, for servers without
+//! schematic mods. `/function <namespace>:place` builds it where it's run from
+
+use std::collections::HashMap;
+
+use super::{nbt::Tag, zip, Region};
+
+/// The datapack format of Minecraft 1.20.1, the version of [`DATA_VERSION`](super::DATA_VERSION)
+const PACK_FORMAT: u32 = 15;
+
+/// The default `maxCommandChainLength`, more commands in one tick are skipped
+pub const MAX_COMMANDS: usize = 65_536;
+
+/// Commands of a part that don't place blocks
+const OVERHEAD: usize = 4;
+
+/// Zipped datapack of [`files`], it goes into the `datapacks` directory of the world
+#[must_use]
+pub fn write(region: &Region, namespace: &str) -> Vec<u8> {
+    zip::archive(&files(region, namespace))
+}
+
+/// Paths and contents of the files of the datapack. `namespace` has to be a valid
+/// namespace: lowercase letters, digits and `_-.`
+///
+/// The blocks are placed in parts of at most [`MAX_COMMANDS`] commands, one per tick.
+/// Scheduled functions run at the world spawn, so a marker keeps the position the ROM
+/// is placed at
+#[must_use]
+pub fn files(region: &Region, namespace: &str) -> Vec<(String, String)> {
+    let functions = |name: &str| format!("data/{namespace}/functions/{name}.mcfunction");
+    let markers = format!("@e[type=minecraft:marker,tag={namespace}_rom]");
+    let marker = format!("@e[type=minecraft:marker,tag={namespace}_rom,limit=1]");
+    let commands = commands(region);
+    let parts: Vec<_> = commands.chunks(MAX_COMMANDS - OVERHEAD).collect();
+
+    let mut files = vec![
+        (
+            "pack.mcmeta".to_string(),
+            format!(
+                "{{\"pack\":{{\"pack_format\":{PACK_FORMAT},\"description\":\"ROM of {namespace}\"}}}}\n"
+            ),
+        ),
+        (
+            functions("place"),
+            format!(
+                "kill {markers}\n\
+                 summon minecraft:marker ~ ~ ~ {{Tags:[\"{namespace}_rom\"]}}\n\
+                 function {namespace}:part_0\n"
+            ),
+        ),
+    ];
+    for (index, blocks) in parts.iter().enumerate() {
+        let next = if index + 1 == parts.len() {
+            format!("kill {markers}\nsay Placed the ROM of {namespace}\n")
+        } else {
+            format!("schedule function {namespace}:part_{} 1t\n", index + 1)
+        };
+        files.push((
+            functions(&format!("part_{index}")),
+            format!("execute at {marker} run function {namespace}:blocks_{index}\n{next}"),
+        ));
+        files.push((
+            functions(&format!("blocks_{index}")),
+            blocks.join("\n") + "\n",
+        ));
+    }
+    files
+}
+
+/// `setblock` for single blocks and blocks with data, `fill` for rows along x. Air is
+/// left out so it doesn't replace anything around the ROM
+fn commands(region: &Region) -> Vec<String> {
+    let [width, height, length] = region.size;
+    let entities: HashMap<_, _> = region
+        .entities
+        .iter()
+        .map(|(position, entity)| (*position, entity))
+        .collect();
+    let mut commands = vec![];
+    for y in 0..height {
+        for z in 0..length {
+            let row = ((y * length + z) * width) as usize;
+            let mut x = 0;
+            while x < width {
+                let index = region.blocks[row + x as usize];
+                let state = &region.palette[index as usize];
+                if let Some(entity) = entities.get(&[x, y, z]) {
+                    let data = Tag::Compound(entity.data.clone());
+                    commands.push(format!("setblock ~{x} ~{y} ~{z} {state}{data}"));
+                    x += 1;
+                    continue;
+                }
+                let mut end = x;
+                while end + 1 < width
+                    && region.blocks[row + end as usize + 1] == index
+                    && !entities.contains_key(&[end + 1, y, z])
+                {
+                    end += 1;
+                }
+                if index != 0 {
+                    commands.push(if end == x {
+                        format!("setblock ~{x} ~{y} ~{z} {state}")
+                    } else {
+                        format!("fill ~{x} ~{y} ~{z} ~{end} ~{y} ~{z} {state}")
+                    });
+                }
+                x = end + 1;
+            }
+        }
+    }
+    commands
+}
@@ -0,0 +1,36 @@
+use crate::error::ErrorType;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Type {
+    UnknownKey(String),
+    MissingKey(String),
+    InvalidValue(String, &'static str),
+    UnknownEncoding(String),
+    UnknownBitOrder(String),
+}
+
+impl ErrorType for Type {
+    fn get_message(&self) -> String {
+        match self {
+            Self::UnknownKey(key) => format!("{key} is not a layout setting"),
+            Self::MissingKey(key) => format!("The layout needs {key}"),
+            Self::InvalidValue(key, expected) => format!("{key} has to be {expected}"),
+            Self::UnknownEncoding(name) => {
+                format!("There is no encoding called {name}, it can be blocks, torches or barrels")
+            }
+            Self::UnknownBitOrder(name) => {
+                format!("{name} is not a bit order, it can be lsb-first or msb-first")
+            }
+        }
+    }
+
+    fn get_code(&self) -> &'static str {
+        match self {
+            Self::UnknownKey(_) => "unknown-key",
+            Self::MissingKey(_) => "missing-key",
+            Self::InvalidValue(..) => "invalid-value",
+            Self::UnknownEncoding(_) => "unknown-encoding",
+            Self::UnknownBitOrder(_) => "unknown-bit-order",
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Gzip without compression: the deflate stream only has stored blocks. Schematics of a
+//! ROM are small, so it isn't worth a compression library. Reading needs the whole
+//! decoder, files saved by the game or mods are compressed
+
+use crate::backend::checksum::crc32;
+
+use super::{inflate::inflate, ReadError};
+
+/// Largest stored block deflate allows
+const BLOCK_LEN: usize = 0xFFFF;
+
+const TRUNCATED: ReadError = ReadError::Compression("the file ends early");
+
+/// Wraps `data` in a gzip member any gzip reader accepts
+#[must_use]
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    // magic, deflate, no flags, no modification time, no extra flags, unknown OS
+    let mut gzip = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    let mut blocks = data.chunks(BLOCK_LEN).peekable();
+    if blocks.peek().is_none() {
+        gzip.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        gzip.push(u8::from(blocks.peek().is_none()));
+        gzip.extend(len.to_le_bytes());
+        gzip.extend((!len).to_le_bytes());
+        gzip.extend(block);
+    }
+    gzip.extend(crc32(data).to_le_bytes());
+    gzip.extend((data.len() as u32).to_le_bytes());
+    gzip
+}
+
+/// The data in a gzip member, checked against its checksum
+///
+/// # Errors
+///
+/// If it isn't gzip, the deflate stream is broken or the checksum doesn't match
+pub fn decompress(gzip: &[u8]) -> Result<Vec<u8>, ReadError> {
+    const FHCRC: u8 = 2;
+    const FEXTRA: u8 = 4;
+    const FNAME: u8 = 8;
+    const FCOMMENT: u8 = 16;
+    let [0x1f, 0x8b, 8, flags, _, _, _, _, _, _, ..] = *gzip else {
+        return Err(ReadError::Compression("not gzip"));
+    };
+    let mut index = 10;
+    if flags & FEXTRA != 0 {
+        let len = gzip.get(index..index + 2).ok_or(TRUNCATED)?;
+        index += 2 + usize::from(u16::from_le_bytes([len[0], len[1]]));
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = gzip
+                .get(index..)
+                .and_then(|rest| rest.iter().position(|&byte| byte == 0))
+                .ok_or(TRUNCATED)?;
+            index += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        index += 2;
+    }
+    let (data, read) = inflate(gzip.get(index..).ok_or(TRUNCATED)?)?;
+    let trailer = gzip.get(index + read..index + read + 8).ok_or(TRUNCATED)?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    if crc != crc32(&data) {
+        return Err(ReadError::Compression("the checksum doesn't match"));
+    }
+    Ok(data)
+}
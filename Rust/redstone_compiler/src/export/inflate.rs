@@ -0,0 +1,216 @@
+//! Deflate decoder, decoding Huffman codes a bit at a time like zlib's `puff`
+
+use super::ReadError;
+
+const MAX_BITS: usize = 15;
+
+/// Base lengths of the length symbols from 257 and their extra bits
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order the lengths of the code length code are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const fn broken(reason: &'static str) -> ReadError {
+    ReadError::Compression(reason)
+}
+
+/// The decompressed data and how many bytes of `data` the stream took
+///
+/// # Errors
+///
+/// If the stream is broken or ends early
+pub fn inflate(data: &[u8]) -> Result<(Vec<u8>, usize), ReadError> {
+    let mut bits = Bits { data, position: 0 };
+    let mut output = vec![];
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => bits.stored(&mut output)?,
+            1 => {
+                let (lengths, distances) = fixed();
+                bits.codes(&mut output, &lengths, &distances)?;
+            }
+            2 => {
+                let (lengths, distances) = bits.dynamic()?;
+                bits.codes(&mut output, &lengths, &distances)?;
+            }
+            _ => return Err(broken("invalid block type")),
+        }
+        if last {
+            return Ok((output, bits.position.div_ceil(8)));
+        }
+    }
+}
+
+/// Canonical Huffman code: how many codes there are of each length and the symbols
+/// ordered by code
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; MAX_BITS + 1];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[usize::from(offsets[usize::from(len)])] = symbol as u16;
+                offsets[usize::from(len)] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+}
+
+/// The codes of blocks with fixed Huffman codes
+fn fixed() -> (Huffman, Huffman) {
+    let mut lengths = [8; 288];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+/// Reads from the lowest bit of each byte up
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn read(&mut self, count: u8) -> Result<u32, ReadError> {
+        let mut value = 0;
+        for bit in 0..count {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or(broken("the stream ends early"))?;
+            value |= u32::from(byte >> (self.position % 8) & 1) << bit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn decode(&mut self, huffman: &Huffman) -> Result<u16, ReadError> {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &huffman.counts[1..] {
+            code |= self.read(1)? as i32;
+            let count = i32::from(count);
+            if code - count < first {
+                return Ok(huffman.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(broken("invalid Huffman code"))
+    }
+
+    fn stored(&mut self, output: &mut Vec<u8>) -> Result<(), ReadError> {
+        let start = self.position.div_ceil(8);
+        let header = self
+            .data
+            .get(start..start + 4)
+            .ok_or(broken("the stream ends early"))?;
+        let len = u16::from_le_bytes([header[0], header[1]]);
+        if !len != u16::from_le_bytes([header[2], header[3]]) {
+            return Err(broken("stored block length doesn't match its complement"));
+        }
+        let block = self
+            .data
+            .get(start + 4..start + 4 + usize::from(len))
+            .ok_or(broken("the stream ends early"))?;
+        output.extend(block);
+        self.position = (start + 4 + usize::from(len)) * 8;
+        Ok(())
+    }
+
+    fn dynamic(&mut self) -> Result<(Huffman, Huffman), ReadError> {
+        let length_count = self.read(5)? as usize + 257;
+        let distance_count = self.read(5)? as usize + 1;
+        let code_length_count = self.read(4)? as usize + 4;
+        let mut code_lengths = [0; 19];
+        for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_lengths[symbol] = self.read(3)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_lengths);
+        let mut lengths = vec![];
+        while lengths.len() < length_count + distance_count {
+            let (len, repeat) = match self.decode(&code_lengths)? {
+                symbol @ 0..=15 => (symbol as u8, 1),
+                16 => {
+                    let &previous = lengths.last().ok_or(broken("nothing to repeat"))?;
+                    (previous, 3 + self.read(2)?)
+                }
+                17 => (0, 3 + self.read(3)?),
+                _ => (0, 11 + self.read(7)?),
+            };
+            lengths.extend(std::iter::repeat_n(len, repeat as usize));
+        }
+        if lengths.len() > length_count + distance_count {
+            return Err(broken("code lengths repeat too far"));
+        }
+        let (lengths, distances) = lengths.split_at(length_count);
+        Ok((Huffman::new(lengths), Huffman::new(distances)))
+    }
+
+    fn codes(
+        &mut self,
+        output: &mut Vec<u8>,
+        lengths: &Huffman,
+        distances: &Huffman,
+    ) -> Result<(), ReadError> {
+        loop {
+            let symbol = usize::from(self.decode(lengths)?);
+            match symbol {
+                0..=255 => output.push(symbol as u8),
+                256 => return Ok(()),
+                _ => {
+                    let symbol = symbol - 257;
+                    let (&base, &extra) = LENGTH_BASE
+                        .get(symbol)
+                        .zip(LENGTH_EXTRA.get(symbol))
+                        .ok_or(broken("invalid length"))?;
+                    let len = usize::from(base) + self.read(extra)? as usize;
+                    let symbol = usize::from(self.decode(distances)?);
+                    let (&base, &extra) = DISTANCE_BASE
+                        .get(symbol)
+                        .zip(DISTANCE_EXTRA.get(symbol))
+                        .ok_or(broken("invalid distance"))?;
+                    let distance = usize::from(base) + self.read(extra)? as usize;
+                    if distance > output.len() {
+                        return Err(broken("distance goes back too far"));
+                    }
+                    // the copy can overlap what it writes
+                    let start = output.len() - distance;
+                    for index in start..start + len {
+                        output.push(output[index]);
+                    }
+                }
+            }
+        }
+    }
+}
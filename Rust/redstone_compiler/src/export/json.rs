@@ -0,0 +1,93 @@
+//! The JSON tools get a compiled program in, written by `mcnc --emit json` and read by
+//! the web editor
+//!
+//! An object with
+//! - `formatVersion`: [`JSON_FORMAT_VERSION`], check it before reading the rest
+//! - `target`, `pageSize` and `pageCount`
+//! - `instructions`: every instruction with its `address`, `page`, `mnemonic`, `arg`,
+//!   encoded `word`, the `text` of its assembly, its `label` and `comment` if it has
+//!   them and the `location` of the code it's from
+//! - `debugInfo`: the [`DebugInfo`], which has its own version
+//!
+//! Locations are `[[line, column], [line, column]]`, lines start at 0 and columns at 1,
+//! both ends are included. Fields that are `null` can also be missing
+
+use crate::{
+    backend::{DebugInfo, Instruction, Target},
+    frontend::Range,
+};
+
+/// Changes whenever a field of [`ProgramJson`] is renamed, removed or means something
+/// else. New fields don't change it, readers have to ignore the ones they don't know
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ProgramJson {
+    pub format_version: u32,
+    /// Name of the [`Target`]
+    pub target: String,
+    pub page_size: usize,
+    pub page_count: usize,
+    pub instructions: Vec<InstructionJson>,
+    pub debug_info: DebugInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct InstructionJson {
+    pub address: usize,
+    pub page: usize,
+    pub mnemonic: String,
+    pub arg: Option<u8>,
+    /// Encoded like in the ROM
+    pub word: u16,
+    /// In the format the assembler reads, with the label and the comment
+    pub text: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub label: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub comment: Option<String>,
+    pub location: Range,
+}
+
+impl ProgramJson {
+    /// Whether it's in the format this compiler writes
+    #[must_use]
+    pub const fn is_compatible(&self) -> bool {
+        self.format_version == JSON_FORMAT_VERSION
+    }
+}
+
+#[must_use]
+pub fn program_json(
+    instructions: &[Instruction],
+    debug_info: &DebugInfo,
+    target: &Target,
+) -> ProgramJson {
+    let page_size = target.page_size;
+    ProgramJson {
+        format_version: JSON_FORMAT_VERSION,
+        target: target.name.to_string(),
+        page_size,
+        page_count: instructions.len().div_ceil(page_size),
+        instructions: instructions
+            .iter()
+            .enumerate()
+            .map(|(address, instruction)| InstructionJson {
+                address,
+                page: address / page_size,
+                mnemonic: instruction.variant.name().to_string(),
+                arg: instruction.arg,
+                word: instruction.to_bin(),
+                text: format!("{instruction}"),
+                label: instruction.label.clone(),
+                comment: instruction.comment.clone(),
+                location: instruction.orig_location,
+            })
+            .collect(),
+        debug_info: debug_info.clone(),
+    }
+}
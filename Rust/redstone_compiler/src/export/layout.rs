@@ -0,0 +1,447 @@
+use crate::{
+    err,
+    frontend::Range,
+    project::toml::{self, Entry, Kind, Value},
+    Error,
+};
+
+use super::{error::Type as ErrorType, nbt::Tag, BlockEntity, Placed, Position, Region, RomLayout};
+
+/// Where the bits of the ROM are in the world, relative to the first bit of the first
+/// word. Layout files describe it as JSON or TOML, settings they leave out are the ones
+/// of the default
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, rename_all = "camelCase"))]
+pub struct Layout {
+    /// Which bit of a word comes first
+    pub bit_order: BitOrder,
+    /// From one bit of a word to the next
+    pub bit_step: Position,
+    /// From one word of a page to the next
+    pub word_step: Position,
+    /// From one page to the next
+    pub page_step: Position,
+    /// Where the first pages start, for ROMs that aren't evenly spaced. Pages after them
+    /// continue from the last one with `page_step`
+    pub page_offsets: Vec<Position>,
+    /// Pages one ROM unit holds, bigger programs are split into several units that all
+    /// start at the first page. No limit if it's missing
+    pub pages_per_unit: Option<usize>,
+    pub encoding: Encoding,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BitOrder {
+    #[default]
+    LsbFirst,
+    MsbFirst,
+}
+
+/// How bits become blocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "camelCase"))]
+pub enum Encoding {
+    /// A block per bit, depending on whether it is set
+    Blocks { one: String, zero: String },
+    /// A block under every bit, set bits get a redstone torch on top of it
+    Torches { base: String },
+    /// A barrel for every 4 bits with their value as its signal strength, `bit_step`
+    /// goes from one barrel to the next
+    Barrels,
+}
+
+/// Stackable item barrels are filled with
+const BARREL_ITEM: &str = "minecraft:redstone";
+const BARREL_SLOTS: u32 = 27;
+
+impl Default for Layout {
+    /// The same as `layouts/standard.json`: bits go up, words along x and pages along z
+    fn default() -> Self {
+        Self {
+            bit_order: BitOrder::LsbFirst,
+            bit_step: [0, 2, 0],
+            word_step: [2, 0, 0],
+            page_step: [0, 0, 4],
+            page_offsets: vec![],
+            pages_per_unit: None,
+            encoding: Encoding::Blocks {
+                one: "minecraft:redstone_block".to_string(),
+                zero: "minecraft:glass".to_string(),
+            },
+        }
+    }
+}
+
+impl RomLayout for Layout {
+    fn place(&self, words: &[u16], page_size: usize) -> Vec<Placed> {
+        let page_size = page_size.max(1);
+        let mut blocks = Vec::with_capacity(words.len() * 16);
+        for (address, &word) in words.iter().enumerate() {
+            let start = add(
+                self.page_origin(address / page_size),
+                offset(self.word_step, address % page_size),
+            );
+            self.place_word(word, start, &mut blocks);
+        }
+        blocks
+    }
+
+    /// Pages and words going in a negative direction start at the far end of the region
+    /// along that axis, otherwise at the near end
+    fn read(&self, region: &Region, page_size: usize) -> Vec<u16> {
+        let page_size = page_size.max(1);
+        let cells: Vec<_> = (0..self.cells())
+            .map(|index| self.cell([0; 3], index))
+            .collect();
+        let origin = [0, 1, 2].map(|axis| {
+            let backwards = self.word_step[axis] < 0
+                || self.page_step[axis] < 0
+                || self.page_offsets.iter().any(|offset| offset[axis] < 0);
+            if backwards {
+                let last = cells.iter().map(|cell| cell[axis]).max().unwrap_or(0);
+                region.size[axis] as i32 - 1 - last
+            } else {
+                -cells.iter().map(|cell| cell[axis]).min().unwrap_or(0)
+            }
+        });
+        let mut words = vec![];
+        for page in 0.. {
+            for index in 0..page_size {
+                let start = add(
+                    add(origin, self.page_origin(page)),
+                    offset(self.word_step, index),
+                );
+                let Some(word) = self.read_word(region, start) else {
+                    return words;
+                };
+                words.push(word);
+            }
+        }
+        words
+    }
+}
+
+impl Layout {
+    /// Reads a layout file in TOML, the encoding is in its own table:
+    ///
+    /// ```toml
+    /// bit-order = "msb-first"
+    /// bit-step = [0, 2, 0]
+    /// page-offsets = [[0, 0, 0], [0, 0, 6]]
+    /// pages-per-unit = 16
+    ///
+    /// [encoding]
+    /// type = "torches"
+    /// base = "minecraft:stone"
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If it isn't valid TOML or a setting is unknown or has the wrong type
+    pub fn parse_toml(text: &str) -> Result<Self, Error> {
+        let mut layout = Self::default();
+        let mut encoding = None;
+        let mut blocks = vec![];
+        for Entry {
+            key,
+            location,
+            value,
+        } in toml::parse(text)?
+        {
+            match key.as_str() {
+                "bit-order" => {
+                    layout.bit_order = match string(&key, value.clone())?.as_str() {
+                        "lsb-first" => BitOrder::LsbFirst,
+                        "msb-first" => BitOrder::MsbFirst,
+                        name => {
+                            return err!(
+                                ErrorType::UnknownBitOrder(name.to_string()),
+                                value.location
+                            )
+                        }
+                    }
+                }
+                "bit-step" => layout.bit_step = position(&key, &value)?,
+                "word-step" => layout.word_step = position(&key, &value)?,
+                "page-step" => layout.page_step = position(&key, &value)?,
+                "page-offsets" => {
+                    let Kind::Array(values) = value.kind else {
+                        return err!(
+                            ErrorType::InvalidValue(key, "an array of positions"),
+                            value.location
+                        );
+                    };
+                    layout.page_offsets = values
+                        .into_iter()
+                        .map(|value| position(&key, &value))
+                        .collect::<Result<_, _>>()?;
+                }
+                "pages-per-unit" => {
+                    layout.pages_per_unit = match value.kind {
+                        Kind::Integer(pages) if pages > 0 => usize::try_from(pages).ok(),
+                        _ => None,
+                    };
+                    if layout.pages_per_unit.is_none() {
+                        return err!(
+                            ErrorType::InvalidValue(key, "a positive integer"),
+                            value.location
+                        );
+                    }
+                }
+                "encoding.type" => encoding = Some((string(&key, value.clone())?, value.location)),
+                "encoding.one" | "encoding.zero" | "encoding.base" => {
+                    blocks.push((key.clone(), string(&key, value)?, location));
+                }
+                _ => return err!(ErrorType::UnknownKey(key), location),
+            }
+        }
+        let mut block = |name: &str| {
+            let key = format!("encoding.{name}");
+            let index = blocks.iter().position(|(other, ..)| *other == key);
+            index.map_or_else(
+                || err!(ErrorType::MissingKey(key), Range::default()),
+                |index| Ok(blocks.remove(index).1),
+            )
+        };
+        match encoding {
+            Some((name, location)) => {
+                layout.encoding = match name.as_str() {
+                    "blocks" => Encoding::Blocks {
+                        one: block("one")?,
+                        zero: block("zero")?,
+                    },
+                    "torches" => Encoding::Torches {
+                        base: block("base")?,
+                    },
+                    "barrels" => Encoding::Barrels,
+                    _ => return err!(ErrorType::UnknownEncoding(name), location),
+                };
+            }
+            None if !blocks.is_empty() => {
+                return err!(
+                    ErrorType::MissingKey("encoding.type".to_string()),
+                    Range::default()
+                )
+            }
+            None => {}
+        }
+        // blocks the encoding doesn't use
+        if let Some((key, _, location)) = blocks.pop() {
+            return err!(ErrorType::UnknownKey(key), location);
+        }
+        Ok(layout)
+    }
+
+    fn page_origin(&self, page: usize) -> Position {
+        match self.page_offsets.get(page) {
+            Some(&origin) => origin,
+            None => match self.page_offsets.last() {
+                Some(&last) => add(
+                    last,
+                    offset(self.page_step, page + 1 - self.page_offsets.len()),
+                ),
+                None => offset(self.page_step, page),
+            },
+        }
+    }
+
+    /// Blocks a word is stored in, nibbles take one barrel
+    const fn cells(&self) -> usize {
+        match self.encoding {
+            Encoding::Barrels => 4,
+            _ => 16,
+        }
+    }
+
+    /// Where bit or nibble `index` of the word at `start` is
+    fn cell(&self, start: Position, index: usize) -> Position {
+        let index = match self.bit_order {
+            BitOrder::LsbFirst => index,
+            BitOrder::MsbFirst => self.cells() - 1 - index,
+        };
+        add(start, offset(self.bit_step, index))
+    }
+
+    fn place_word(&self, word: u16, start: Position, blocks: &mut Vec<Placed>) {
+        let bit = |index| self.cell(start, index);
+        match &self.encoding {
+            Encoding::Blocks { one, zero } => {
+                for index in 0..16 {
+                    let state = if word >> index & 1 == 1 { one } else { zero };
+                    blocks.push(Placed::block(bit(index), state));
+                }
+            }
+            Encoding::Torches { base } => {
+                for index in 0..16 {
+                    blocks.push(Placed::block(bit(index), base));
+                    if word >> index & 1 == 1 {
+                        let above = add(bit(index), [0, 1, 0]);
+                        blocks.push(Placed::block(above, "minecraft:redstone_torch"));
+                    }
+                }
+            }
+            Encoding::Barrels => {
+                for index in 0..4 {
+                    let strength = (word >> (index * 4) & 0xF) as u8;
+                    blocks.push(Placed {
+                        position: bit(index),
+                        state: "minecraft:barrel[facing=up]".to_string(),
+                        entity: Some(barrel(strength)),
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Layout {
+    /// Blocks that aren't part of the encoding read as 0, `None` if none of them are
+    fn read_word(&self, region: &Region, start: Position) -> Option<u16> {
+        let mut word = 0;
+        let mut found = false;
+        for index in 0..self.cells() {
+            let cell = self.cell(start, index);
+            let value = match &self.encoding {
+                Encoding::Blocks { one, zero } => match region.state(cell) {
+                    Some(state) if matches(state, one) => Some(1),
+                    Some(state) if matches(state, zero) => Some(0),
+                    _ => None,
+                },
+                Encoding::Torches { base } => region
+                    .state(cell)
+                    .filter(|state| matches(state, base))
+                    .map(|_| {
+                        let above = region.state(add(cell, [0, 1, 0]));
+                        u16::from(
+                            above.is_some_and(|state| matches(state, "minecraft:redstone_torch")),
+                        )
+                    }),
+                Encoding::Barrels => region
+                    .state(cell)
+                    .filter(|state| matches(state, "minecraft:barrel"))
+                    .map(|_| region.entity(cell).map_or(0, signal_strength)),
+            };
+            if let Some(value) = value {
+                found = true;
+                word |= value << (index * (16 / self.cells()));
+            }
+        }
+        found.then_some(word)
+    }
+}
+
+/// Whether `state` is the block `expected` with at least the properties it has, the
+/// game saves all of them
+fn matches(state: &str, expected: &str) -> bool {
+    let split = |state: &str| -> (String, Vec<String>) {
+        match state
+            .strip_suffix(']')
+            .and_then(|state| state.split_once('['))
+        {
+            Some((name, properties)) => (
+                name.to_string(),
+                properties
+                    .split(',')
+                    .map(|property| property.trim().to_string())
+                    .collect(),
+            ),
+            None => (state.to_string(), vec![]),
+        }
+    };
+    let (name, properties) = split(state);
+    let (expected_name, expected_properties) = split(expected);
+    name == expected_name
+        && expected_properties
+            .iter()
+            .all(|property| properties.contains(property))
+}
+
+/// What a comparator reads from a container, assuming all items stack to 64
+fn signal_strength(entity: &BlockEntity) -> u16 {
+    let Some((_, Tag::List(items))) = entity.data.iter().find(|(name, _)| name == "Items") else {
+        return 0;
+    };
+    let count: u32 = items
+        .iter()
+        .map(|item| match item.get("Count") {
+            Some(&Tag::Byte(count)) => u32::from(count as u8),
+            Some(&Tag::Int(count)) => count as u32,
+            _ => 0,
+        })
+        .sum();
+    if count == 0 {
+        return 0;
+    }
+    (1 + 14 * count / (BARREL_SLOTS * 64)).min(15) as u16
+}
+
+/// A barrel a comparator reads `strength` from
+fn barrel(strength: u8) -> BlockEntity {
+    let capacity = BARREL_SLOTS * 64;
+    // comparators output 1 + 14 * fullness, rounded down, and 0 only if it's empty
+    let mut count = if strength == 0 {
+        0
+    } else {
+        (capacity * u32::from(strength - 1)).div_ceil(14).max(1)
+    };
+    let mut items = vec![];
+    for slot in 0.. {
+        if count == 0 {
+            break;
+        }
+        let stack = count.min(64);
+        count -= stack;
+        items.push(Tag::compound([
+            ("Slot", Tag::Byte(slot)),
+            ("id", Tag::String(BARREL_ITEM.to_string())),
+            ("Count", Tag::Byte(stack as i8)),
+        ]));
+    }
+    BlockEntity {
+        id: "minecraft:barrel".to_string(),
+        data: vec![("Items".to_string(), Tag::List(items))],
+    }
+}
+
+fn offset(step: Position, times: usize) -> Position {
+    step.map(|axis| axis * times as i32)
+}
+
+const fn add(a: Position, b: Position) -> Position {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn string(key: &str, value: Value) -> Result<String, Error> {
+    match value.kind {
+        Kind::String(string) => Ok(string),
+        _ => err!(
+            ErrorType::InvalidValue(key.to_string(), "a string"),
+            value.location
+        ),
+    }
+}
+
+/// `[x, y, z]`
+fn position(key: &str, value: &Value) -> Result<Position, Error> {
+    let invalid = || {
+        err!(
+            ErrorType::InvalidValue(key.to_string(), "an array of 3 integers"),
+            value.location
+        )
+    };
+    let Kind::Array(values) = &value.kind else {
+        return invalid();
+    };
+    let mut axes = values.iter().map(|value| match value.kind {
+        Kind::Integer(axis) => i32::try_from(axis).ok(),
+        _ => None,
+    });
+    match (axes.next(), axes.next(), axes.next(), axes.next()) {
+        (Some(Some(x)), Some(Some(y)), Some(Some(z)), None) => Ok([x, y, z]),
+        _ => invalid(),
+    }
+}
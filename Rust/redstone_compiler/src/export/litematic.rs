@@ -0,0 +1,95 @@
+//! Litematica schematics, for pasting with the mod instead of `WorldEdit`
+
+use super::{
+    gzip,
+    nbt::{block_state, Tag},
+    Region, AIR, DATA_VERSION,
+};
+
+/// Format version of Litematica for Minecraft 1.20
+const VERSION: i32 = 6;
+
+/// Gzipped NBT of a schematic with `region` as its only region, `time` is when it was
+/// created in milliseconds since the Unix epoch
+#[must_use]
+pub fn write(region: &Region, name: &str, time: i64) -> Vec<u8> {
+    let [width, height, length] = region.size.map(|axis| axis as i32);
+    let volume = width * height * length;
+    let placed = region.blocks.iter().filter(|&&index| index != 0).count();
+    let metadata = Tag::compound([
+        ("Name", Tag::String(name.to_string())),
+        ("Author", Tag::String(String::new())),
+        ("Description", Tag::String(String::new())),
+        ("RegionCount", Tag::Int(1)),
+        ("TotalBlocks", Tag::Int(placed as i32)),
+        ("TotalVolume", Tag::Int(volume)),
+        ("EnclosingSize", vector([width, height, length])),
+        ("TimeCreated", Tag::Long(time)),
+        ("TimeModified", Tag::Long(time)),
+    ]);
+    let rom = Tag::compound([
+        ("Position", vector([0; 3])),
+        ("Size", vector([width, height, length])),
+        (
+            "BlockStatePalette",
+            Tag::List(
+                region
+                    .palette
+                    .iter()
+                    .map(|state| block_state(state))
+                    .collect(),
+            ),
+        ),
+        ("BlockStates", Tag::LongArray(block_states(region))),
+        ("TileEntities", Tag::List(tile_entities(region))),
+        ("Entities", Tag::List(vec![])),
+        ("PendingBlockTicks", Tag::List(vec![])),
+        ("PendingFluidTicks", Tag::List(vec![])),
+    ]);
+    let schematic = Tag::compound([
+        ("MinecraftDataVersion", Tag::Int(DATA_VERSION)),
+        ("Version", Tag::Int(VERSION)),
+        ("Metadata", metadata),
+        ("Regions", Tag::Compound(vec![(name.to_string(), rom)])),
+    ]);
+    gzip::compress(&schematic.to_bytes(""))
+}
+
+fn vector([x, y, z]: [i32; 3]) -> Tag {
+    Tag::compound([("x", Tag::Int(x)), ("y", Tag::Int(y)), ("z", Tag::Int(z))])
+}
+
+/// Palette indices with as few bits as fit the palette, but at least 2, packed into
+/// longs from the lowest bit up. An index can start in one long and end in the next
+fn block_states(region: &Region) -> Vec<i64> {
+    debug_assert_eq!(region.palette.first().map(String::as_str), Some(AIR));
+    let bits = (u32::BITS - (region.palette.len() as u32 - 1).leading_zeros()).max(2) as usize;
+    let mut longs = vec![0u64; (region.blocks.len() * bits).div_ceil(64)];
+    for (position, &index) in region.blocks.iter().enumerate() {
+        let start = position * bits;
+        let (long, offset) = (start / 64, start % 64);
+        longs[long] |= u64::from(index) << offset;
+        if offset + bits > 64 {
+            longs[long + 1] |= u64::from(index) >> (64 - offset);
+        }
+    }
+    longs.into_iter().map(|long| long as i64).collect()
+}
+
+/// Their data with the position in the region
+fn tile_entities(region: &Region) -> Vec<Tag> {
+    region
+        .entities
+        .iter()
+        .map(|([x, y, z], entity)| {
+            let mut tag = vec![
+                ("x".to_string(), Tag::Int(*x as i32)),
+                ("y".to_string(), Tag::Int(*y as i32)),
+                ("z".to_string(), Tag::Int(*z as i32)),
+                ("id".to_string(), Tag::String(entity.id.clone())),
+            ];
+            tag.extend(entity.data.iter().cloned());
+            Tag::Compound(tag)
+        })
+        .collect()
+}
@@ -0,0 +1,183 @@
+//! Gets compiled programs into a Minecraft world: the ROM is built out of blocks
+//! according to a [`RomLayout`] and written in a format the game or its mods can paste
+//!
+//! A [`report::Report`] describes where everything ended up for the editor, programs
+//! too big for one ROM are split into [`units`]
+
+pub mod barrels;
+pub mod datapack;
+mod error;
+mod gzip;
+mod inflate;
+pub mod json;
+mod layout;
+pub mod litematic;
+pub mod nbt;
+pub mod report;
+pub mod schem;
+pub mod structure;
+pub mod units;
+mod zip;
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+use nbt::Tag;
+
+pub use layout::{BitOrder, Encoding, Layout};
+
+/// x, y and z, y points up
+pub type Position = [i32; 3];
+
+/// The version of the game the block states are from, Minecraft 1.20.1. Newer versions
+/// upgrade the blocks when they paste them
+pub const DATA_VERSION: i32 = 3465;
+
+/// Block state of the space around the bits
+pub const AIR: &str = "minecraft:air";
+
+/// Places the blocks of a ROM, the formats only get the [`Region`] with what it placed
+pub trait RomLayout {
+    /// The blocks of the ROM of `words`, pages have `page_size` words
+    fn place(&self, words: &[u16], page_size: usize) -> Vec<Placed>;
+
+    /// The words of a ROM built with this layout, the reverse of [`RomLayout::place`]. It
+    /// ends at the first word that has none of its blocks
+    fn read(&self, region: &Region, page_size: usize) -> Vec<u16>;
+}
+
+/// Why a file couldn't be read back into a [`Region`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadError {
+    /// It isn't gzip or its deflate stream is broken
+    Compression(&'static str),
+    /// The NBT ends early or has a tag that doesn't exist
+    Nbt(&'static str),
+    /// A tag the format needs is missing or has the wrong type
+    MissingTag(&'static str),
+    /// The tags are there, but what's in them doesn't fit together
+    Corrupt(&'static str),
+    UnsupportedVersion(i32),
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compression(reason) => write!(f, "Couldn't decompress it, {reason}"),
+            Self::Nbt(reason) => write!(f, "Invalid NBT, {reason}"),
+            Self::MissingTag(name) => write!(f, "It has no valid {name} tag"),
+            Self::Corrupt(reason) => write!(f, "Its blocks are broken, {reason}"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Version {version} of the format isn't supported")
+            }
+        }
+    }
+}
+
+/// A block of a [`RomLayout`] at its position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placed {
+    pub position: Position,
+    pub state: String,
+    pub entity: Option<BlockEntity>,
+}
+
+impl Placed {
+    #[must_use]
+    pub fn block(position: Position, state: &str) -> Self {
+        Self {
+            position,
+            state: state.to_string(),
+            entity: None,
+        }
+    }
+}
+
+/// The data of a block beyond its state, like the items in a container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockEntity {
+    pub id: String,
+    pub data: Vec<(String, Tag)>,
+}
+
+/// A box of blocks, the smallest one around all the placed blocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// Width, height and length: the size along x, y and z
+    pub size: [u32; 3],
+    /// Block states, [`AIR`] is always the first one
+    pub palette: Vec<String>,
+    /// Index into the palette of every block, x changes fastest and y slowest
+    pub blocks: Vec<u32>,
+    /// Position in the region of the blocks that have them
+    pub entities: Vec<([u32; 3], BlockEntity)>,
+}
+
+impl Region {
+    /// Builds the ROM of `words`, pages have `page_size` words
+    #[must_use]
+    pub fn new(words: &[u16], page_size: usize, layout: &dyn RomLayout) -> Self {
+        let placed = layout.place(words, page_size);
+        let min = placed.iter().fold([i32::MAX; 3], |min, block| {
+            [0, 1, 2].map(|axis| min[axis].min(block.position[axis]))
+        });
+        let max = placed.iter().fold([i32::MIN; 3], |max, block| {
+            [0, 1, 2].map(|axis| max[axis].max(block.position[axis]))
+        });
+        let size = if placed.is_empty() {
+            [1; 3]
+        } else {
+            [0, 1, 2].map(|axis| (max[axis] - min[axis] + 1) as u32)
+        };
+
+        let mut palette = vec![AIR.to_string()];
+        let mut indices = HashMap::from([(AIR.to_string(), 0)]);
+        let mut blocks = vec![0; size.iter().product::<u32>() as usize];
+        let mut entities = vec![];
+        for block in placed {
+            let index = *indices.entry(block.state.clone()).or_insert_with(|| {
+                palette.push(block.state);
+                palette.len() as u32 - 1
+            });
+            let position = [0, 1, 2].map(|axis| (block.position[axis] - min[axis]) as u32);
+            let [x, y, z] = position;
+            blocks[((y * size[2] + z) * size[0] + x) as usize] = index;
+            if let Some(entity) = block.entity {
+                entities.push((position, entity));
+            }
+        }
+        Self {
+            size,
+            palette,
+            blocks,
+            entities,
+        }
+    }
+    /// The block state at `position`, if it's inside the region
+    #[must_use]
+    pub fn state(&self, position: Position) -> Option<&str> {
+        let index = self.index(position)?;
+        Some(&self.palette[self.blocks[index] as usize])
+    }
+
+    #[must_use]
+    pub fn entity(&self, position: Position) -> Option<&BlockEntity> {
+        self.index(position)?;
+        let position = position.map(|axis| axis as u32);
+        self.entities
+            .iter()
+            .find(|(other, _)| *other == position)
+            .map(|(_, entity)| entity)
+    }
+
+    fn index(&self, position: Position) -> Option<usize> {
+        let [x, y, z] = position;
+        let [width, height, length] = self.size.map(|axis| axis as i32);
+        if !(0..width).contains(&x) || !(0..height).contains(&y) || !(0..length).contains(&z) {
+            return None;
+        }
+        Some(((y * length + z) * width + x) as usize)
+    }
+}
@@ -0,0 +1,304 @@
+//! Minecraft's Named Binary Tag format, big-endian like Java edition writes it
+
+use std::fmt;
+
+use super::ReadError;
+
+/// Compounds and lists in each other, deeper ones are rejected like the game does
+const MAX_DEPTH: usize = 512;
+
+/// A value with its type, compounds keep the order of their entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    ByteArray(Vec<u8>),
+    String(String),
+    /// All elements have the same type, empty lists are written as lists of nothing
+    List(Vec<Self>),
+    Compound(Vec<(String, Self)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    /// Compound of `entries`, saves converting every key
+    #[must_use]
+    pub fn compound<const N: usize>(entries: [(&str, Self); N]) -> Self {
+        Self::Compound(
+            entries
+                .into_iter()
+                .map(|(key, tag)| (key.to_string(), tag))
+                .collect(),
+        )
+    }
+
+    #[must_use]
+    pub const fn id(&self) -> u8 {
+        match self {
+            Self::Byte(_) => 1,
+            Self::Short(_) => 2,
+            Self::Int(_) => 3,
+            Self::Long(_) => 4,
+            Self::ByteArray(_) => 7,
+            Self::String(_) => 8,
+            Self::List(_) => 9,
+            Self::Compound(_) => 10,
+            Self::IntArray(_) => 11,
+            Self::LongArray(_) => 12,
+        }
+    }
+
+    /// The file format: this tag with a name, it has to be a compound to be read by the game
+    #[must_use]
+    pub fn to_bytes(&self, name: &str) -> Vec<u8> {
+        let mut bytes = vec![self.id()];
+        write_string(&mut bytes, name);
+        self.write_payload(&mut bytes);
+        bytes
+    }
+
+    /// Reads a named tag in the format [`Tag::to_bytes`] writes
+    ///
+    /// # Errors
+    ///
+    /// If it ends early or has a tag id that doesn't exist
+    pub fn from_bytes(bytes: &[u8]) -> Result<(String, Self), ReadError> {
+        let mut reader = Reader { bytes, index: 0 };
+        let id = reader.take::<1>()?[0];
+        let name = reader.string()?;
+        Ok((name, reader.payload(id, 0)?))
+    }
+
+    /// The entry called `name`, if this is a compound
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Self> {
+        match self {
+            Self::Compound(entries) => entries
+                .iter()
+                .find(|(other, _)| other == name)
+                .map(|(_, tag)| tag),
+            _ => None,
+        }
+    }
+
+    fn write_payload(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Self::Byte(value) => bytes.extend(value.to_be_bytes()),
+            Self::Short(value) => bytes.extend(value.to_be_bytes()),
+            Self::Int(value) => bytes.extend(value.to_be_bytes()),
+            Self::Long(value) => bytes.extend(value.to_be_bytes()),
+            Self::ByteArray(values) => {
+                bytes.extend((values.len() as i32).to_be_bytes());
+                bytes.extend(values);
+            }
+            Self::String(value) => write_string(bytes, value),
+            Self::List(values) => {
+                bytes.push(values.first().map_or(0, Self::id));
+                bytes.extend((values.len() as i32).to_be_bytes());
+                for value in values {
+                    value.write_payload(bytes);
+                }
+            }
+            Self::Compound(entries) => {
+                for (name, value) in entries {
+                    bytes.push(value.id());
+                    write_string(bytes, name);
+                    value.write_payload(bytes);
+                }
+                bytes.push(0);
+            }
+            Self::IntArray(values) => {
+                bytes.extend((values.len() as i32).to_be_bytes());
+                bytes.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+            }
+            Self::LongArray(values) => {
+                bytes.extend((values.len() as i32).to_be_bytes());
+                bytes.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+            }
+        }
+    }
+}
+
+/// `minecraft:barrel[facing=up]` as a name with properties, like the game saves them
+#[must_use]
+pub fn block_state(state: &str) -> Tag {
+    let Some((name, properties)) = state
+        .strip_suffix(']')
+        .and_then(|state| state.split_once('['))
+    else {
+        return Tag::compound([("Name", Tag::String(state.to_string()))]);
+    };
+    let properties = properties
+        .split(',')
+        .filter_map(|property| property.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.trim().to_string(),
+                Tag::String(value.trim().to_string()),
+            )
+        })
+        .collect();
+    Tag::compound([
+        ("Name", Tag::String(name.to_string())),
+        ("Properties", Tag::Compound(properties)),
+    ])
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ReadError> {
+        let bytes = self
+            .bytes
+            .get(self.index..self.index + N)
+            .ok_or(ReadError::Nbt("it ends early"))?;
+        self.index += N;
+        Ok(bytes.try_into().unwrap_or([0; N]))
+    }
+
+    /// A length, negative ones are treated as empty
+    fn len(&mut self) -> Result<usize, ReadError> {
+        Ok(usize::try_from(i32::from_be_bytes(self.take()?)).unwrap_or(0))
+    }
+
+    fn string(&mut self) -> Result<String, ReadError> {
+        let len = usize::from(u16::from_be_bytes(self.take()?));
+        let bytes = self
+            .bytes
+            .get(self.index..self.index + len)
+            .ok_or(ReadError::Nbt("it ends early"))?;
+        self.index += len;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> Result<Tag, ReadError> {
+        if depth > MAX_DEPTH {
+            return Err(ReadError::Nbt("it is nested too deep"));
+        }
+        Ok(match id {
+            1 => Tag::Byte(i8::from_be_bytes(self.take()?)),
+            2 => Tag::Short(i16::from_be_bytes(self.take()?)),
+            // floats and doubles aren't in any block this writes, they are read as their bits
+            3 | 5 => Tag::Int(i32::from_be_bytes(self.take()?)),
+            4 | 6 => Tag::Long(i64::from_be_bytes(self.take()?)),
+            7 => {
+                let len = self.len()?;
+                let bytes = self
+                    .bytes
+                    .get(self.index..self.index + len)
+                    .ok_or(ReadError::Nbt("it ends early"))?;
+                self.index += len;
+                Tag::ByteArray(bytes.to_vec())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let id = self.take::<1>()?[0];
+                let len = self.len()?;
+                let mut values = vec![];
+                for _ in 0..len {
+                    values.push(self.payload(id, depth + 1)?);
+                }
+                Tag::List(values)
+            }
+            10 => {
+                let mut entries = vec![];
+                loop {
+                    let id = self.take::<1>()?[0];
+                    if id == 0 {
+                        break Tag::Compound(entries);
+                    }
+                    let name = self.string()?;
+                    entries.push((name, self.payload(id, depth + 1)?));
+                }
+            }
+            11 => {
+                let len = self.len()?;
+                let mut values = vec![];
+                for _ in 0..len {
+                    values.push(i32::from_be_bytes(self.take()?));
+                }
+                Tag::IntArray(values)
+            }
+            12 => {
+                let len = self.len()?;
+                let mut values = vec![];
+                for _ in 0..len {
+                    values.push(i64::from_be_bytes(self.take()?));
+                }
+                Tag::LongArray(values)
+            }
+            _ => return Err(ReadError::Nbt("unknown tag id")),
+        })
+    }
+}
+
+/// Length-prefixed, the game reads modified UTF-8 which only differs for `\0` and
+/// characters outside of the BMP, neither of which are in block states
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    bytes.extend((string.len() as u16).to_be_bytes());
+    bytes.extend(string.as_bytes());
+}
+
+/// Stringified NBT, the way commands take data
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Byte(value) => write!(f, "{value}b"),
+            Self::Short(value) => write!(f, "{value}s"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Long(value) => write!(f, "{value}L"),
+            Self::ByteArray(values) => write_list(
+                f,
+                "B;",
+                values.iter().map(|value| format!("{}b", *value as i8)),
+            ),
+            Self::String(value) => write_quoted(f, value),
+            Self::List(values) => write_list(f, "", values.iter().map(ToString::to_string)),
+            Self::Compound(entries) => {
+                f.write_str("{")?;
+                for (index, (name, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    if name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "_-.+".contains(c))
+                    {
+                        f.write_str(name)?;
+                    } else {
+                        write_quoted(f, name)?;
+                    }
+                    write!(f, ":{value}")?;
+                }
+                f.write_str("}")
+            }
+            Self::IntArray(values) => write_list(f, "I;", values.iter().map(ToString::to_string)),
+            Self::LongArray(values) => {
+                write_list(f, "L;", values.iter().map(|value| format!("{value}L")))
+            }
+        }
+    }
+}
+
+fn write_list(
+    f: &mut fmt::Formatter<'_>,
+    prefix: &str,
+    values: impl Iterator<Item = String>,
+) -> fmt::Result {
+    let values: Vec<_> = values.collect();
+    write!(f, "[{prefix}{}]", values.join(","))
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(
+        f,
+        "\"{}\"",
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
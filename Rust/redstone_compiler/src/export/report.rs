@@ -0,0 +1,101 @@
+//! Everything the editor needs to draw the control flow and paging of a program: where
+//! each instruction is, when it runs and where jumps go
+
+use crate::{
+    backend::{Instruction, InstructionVariant, Target},
+    frontend::Range,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Report {
+    pub target: &'static str,
+    pub page_size: usize,
+    pub page_count: usize,
+    /// Cycles of all instructions once, without jumps
+    pub cycles: u64,
+    /// How long the cycles take in a game running at full speed
+    pub seconds: f64,
+    pub instructions: Vec<ReportInstruction>,
+    pub jumps: Vec<Jump>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ReportInstruction {
+    pub address: usize,
+    pub page: usize,
+    pub text: String,
+    pub word: u16,
+    pub cycles: u8,
+    /// The cycle it starts at when the program runs straight through from the start
+    pub cycle: u64,
+    pub location: Range,
+}
+
+/// A jump from one instruction to another, also ones that don't always jump
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Jump {
+    pub from: usize,
+    /// `None` for disc jumps to a page that isn't loaded right before them
+    pub to: Option<usize>,
+    pub conditional: bool,
+    /// Goes to another page
+    pub disc: bool,
+}
+
+#[must_use]
+pub fn report(instructions: &[Instruction], target: &Target) -> Report {
+    let page_size = target.page_size;
+    let mut cycle = 0;
+    let mut report_instructions = Vec::with_capacity(instructions.len());
+    let mut jumps = vec![];
+    for (address, instruction) in instructions.iter().enumerate() {
+        let variant = instruction.variant;
+        let cycles = target.timing.cycles(variant);
+        report_instructions.push(ReportInstruction {
+            address,
+            page: address / page_size,
+            text: format!("{instruction}"),
+            word: instruction.to_bin(),
+            cycles,
+            cycle,
+            location: instruction.orig_location,
+        });
+        cycle += u64::from(cycles);
+        if !variant.is_jump() {
+            continue;
+        }
+        let arg = usize::from(instruction.arg.unwrap_or_default()) % page_size;
+        // the page of a disc jump is loaded into C right before it
+        let page = if variant.disc_jump() {
+            address
+                .checked_sub(1)
+                .and_then(|previous| instructions.get(previous))
+                .filter(|previous| previous.variant == InstructionVariant::LCL)
+                .and_then(|previous| previous.arg)
+                .map(usize::from)
+        } else {
+            Some(address / page_size)
+        };
+        jumps.push(Jump {
+            from: address,
+            to: page.map(|page| page * page_size + arg),
+            conditional: !matches!(variant, InstructionVariant::JMP | InstructionVariant::JMD),
+            disc: variant.disc_jump(),
+        });
+    }
+    Report {
+        target: target.name,
+        page_size,
+        page_count: instructions.len().div_ceil(page_size),
+        cycles: cycle,
+        seconds: target.timing.seconds(cycle),
+        instructions: report_instructions,
+        jumps,
+    }
+}
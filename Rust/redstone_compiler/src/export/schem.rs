@@ -0,0 +1,223 @@
+//! Sponge schematics, the format that `WorldEdit` pastes
+
+use std::collections::HashMap;
+
+use super::{gzip, nbt::Tag, BlockEntity, ReadError, Region, AIR, DATA_VERSION};
+
+/// Version 3 is read by `WorldEdit` 7.3 and newer, version 2 by older ones too
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    #[default]
+    V2,
+    V3,
+}
+
+/// Gzipped NBT of a schematic with the blocks of `region`
+#[must_use]
+pub fn write(region: &Region, version: Version) -> Vec<u8> {
+    let [width, height, length] = region.size;
+    let mut schematic = vec![
+        ("Version".to_string(), Tag::Int(version as i32 + 2)),
+        ("DataVersion".to_string(), Tag::Int(DATA_VERSION)),
+        ("Width".to_string(), Tag::Short(width as i16)),
+        ("Height".to_string(), Tag::Short(height as i16)),
+        ("Length".to_string(), Tag::Short(length as i16)),
+        ("Offset".to_string(), Tag::IntArray(vec![0, 0, 0])),
+    ];
+    let palette = Tag::Compound(
+        region
+            .palette
+            .iter()
+            .enumerate()
+            .map(|(index, block)| (block.clone(), Tag::Int(index as i32)))
+            .collect(),
+    );
+    let data = Tag::ByteArray(block_data(region));
+    let entities = Tag::List(block_entities(region, version));
+    let bytes = match version {
+        Version::V2 => {
+            schematic.extend([
+                (
+                    "PaletteMax".to_string(),
+                    Tag::Int(region.palette.len() as i32),
+                ),
+                ("Palette".to_string(), palette),
+                ("BlockData".to_string(), data),
+                ("BlockEntities".to_string(), entities),
+            ]);
+            Tag::Compound(schematic).to_bytes("Schematic")
+        }
+        Version::V3 => {
+            let blocks = Tag::compound([
+                ("Palette", palette),
+                ("Data", data),
+                ("BlockEntities", entities),
+            ]);
+            schematic.push(("Blocks".to_string(), blocks));
+            Tag::compound([("Schematic", Tag::Compound(schematic))]).to_bytes("")
+        }
+    };
+    gzip::compress(&bytes)
+}
+
+/// Palette indices as varints, x changes fastest and y slowest
+fn block_data(region: &Region) -> Vec<u8> {
+    let mut data = Vec::with_capacity(region.blocks.len());
+    for &index in &region.blocks {
+        let mut index = index;
+        while index >= 0x80 {
+            data.push(index as u8 | 0x80);
+            index >>= 7;
+        }
+        data.push(index as u8);
+    }
+    data
+}
+
+/// Version 2 has the data next to the position and id, version 3 in its own compound
+fn block_entities(region: &Region, version: Version) -> Vec<Tag> {
+    region
+        .entities
+        .iter()
+        .map(|(position, entity)| {
+            let mut tag = vec![
+                (
+                    "Pos".to_string(),
+                    Tag::IntArray(position.map(|axis| axis as i32).to_vec()),
+                ),
+                ("Id".to_string(), Tag::String(entity.id.clone())),
+            ];
+            match version {
+                Version::V2 => tag.extend(entity.data.iter().cloned()),
+                Version::V3 => tag.push(("Data".to_string(), Tag::Compound(entity.data.clone()))),
+            }
+            Tag::Compound(tag)
+        })
+        .collect()
+}
+
+/// The blocks of a schematic in any version, like ones saved by `WorldEdit`
+///
+/// # Errors
+///
+/// If it isn't a gzipped schematic, a tag is missing or the blocks don't fit the
+/// palette and size
+pub fn read(bytes: &[u8]) -> Result<Region, ReadError> {
+    let (_, root) = Tag::from_bytes(&gzip::decompress(bytes)?)?;
+    // version 3 wraps it in an unnamed compound
+    let schematic = root.get("Schematic").unwrap_or(&root);
+    let Some(&Tag::Int(version)) = schematic.get("Version") else {
+        return Err(ReadError::MissingTag("Version"));
+    };
+    let (blocks, palette, data_tag, entities) = match version {
+        1 | 2 => (schematic, "Palette", "BlockData", "BlockEntities"),
+        3 => (
+            schematic
+                .get("Blocks")
+                .ok_or(ReadError::MissingTag("Blocks"))?,
+            "Palette",
+            "Data",
+            "BlockEntities",
+        ),
+        _ => return Err(ReadError::UnsupportedVersion(version)),
+    };
+    let mut size = [0; 3];
+    for (axis, name) in ["Width", "Height", "Length"].into_iter().enumerate() {
+        let Some(&Tag::Short(len)) = schematic.get(name) else {
+            return Err(ReadError::MissingTag(name));
+        };
+        size[axis] = u32::from(len as u16);
+    }
+
+    // air comes first in regions, the indices of the schematic are moved to make room
+    let Some(Tag::Compound(states)) = blocks.get(palette) else {
+        return Err(ReadError::MissingTag("Palette"));
+    };
+    let mut region_palette = vec![AIR.to_string()];
+    let mut indices = HashMap::new();
+    for (state, index) in states {
+        let &Tag::Int(index) = index else {
+            return Err(ReadError::MissingTag("Palette"));
+        };
+        let region_index = if state == AIR {
+            0
+        } else {
+            region_palette.push(state.clone());
+            region_palette.len() as u32 - 1
+        };
+        indices.insert(index as u32, region_index);
+    }
+    let Some(Tag::ByteArray(data)) = blocks.get(data_tag) else {
+        return Err(ReadError::MissingTag(data_tag));
+    };
+    // every block takes at least a byte, so broken sizes are caught before allocating
+    let volume = size
+        .iter()
+        .try_fold(1_usize, |volume, &len| volume.checked_mul(len as usize))
+        .filter(|&volume| volume <= data.len())
+        .ok_or(ReadError::Corrupt(
+            "the number of blocks doesn't match the size",
+        ))?;
+    let mut region_blocks = Vec::with_capacity(volume);
+    let mut varint = (0, 0);
+    for &byte in data {
+        varint.0 |= u32::from(byte & 0x7f) << varint.1;
+        varint.1 += 7;
+        if byte & 0x80 == 0 {
+            let index = indices
+                .get(&varint.0)
+                .ok_or(ReadError::Corrupt("a block isn't in the palette"))?;
+            region_blocks.push(*index);
+            varint = (0, 0);
+        } else if varint.1 > 28 {
+            return Err(ReadError::Corrupt("a palette index is longer than 5 bytes"));
+        }
+    }
+    if region_blocks.len() != volume {
+        return Err(ReadError::Corrupt(
+            "the number of blocks doesn't match the size",
+        ));
+    }
+
+    let entities = match blocks.get(entities) {
+        Some(Tag::List(entities)) => entities
+            .iter()
+            .map(|entity| block_entity(entity, version))
+            .collect::<Result<_, _>>()?,
+        _ => vec![],
+    };
+    Ok(Region {
+        size,
+        palette: region_palette,
+        blocks: region_blocks,
+        entities,
+    })
+}
+
+fn block_entity(tag: &Tag, version: i32) -> Result<([u32; 3], BlockEntity), ReadError> {
+    let (Some(Tag::IntArray(position)), Some(Tag::String(id)), Tag::Compound(entries)) =
+        (tag.get("Pos"), tag.get("Id"), tag)
+    else {
+        return Err(ReadError::MissingTag("BlockEntities"));
+    };
+    let &[x, y, z] = &position[..] else {
+        return Err(ReadError::MissingTag("Pos"));
+    };
+    let data = if version == 3 {
+        match tag.get("Data") {
+            Some(Tag::Compound(data)) => data.clone(),
+            _ => vec![],
+        }
+    } else {
+        entries
+            .iter()
+            .filter(|(name, _)| name != "Pos" && name != "Id")
+            .cloned()
+            .collect()
+    };
+    let entity = BlockEntity {
+        id: id.clone(),
+        data,
+    };
+    Ok(([x, y, z].map(|axis| axis as u32), entity))
+}
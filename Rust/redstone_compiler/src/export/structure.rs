@@ -0,0 +1,64 @@
+//! Structure files, the format of structure blocks and `/place template`. They work
+//! without mods: in a datapack under `data/<namespace>/structures` or in the
+//! `generated` directory of the world
+
+use super::{
+    gzip,
+    nbt::{block_state, Tag},
+    Region, DATA_VERSION,
+};
+
+/// Gzipped NBT of a structure with the blocks of `region`
+///
+/// Air is left out, so it's a structure void and doesn't replace anything around the
+/// ROM. Structure blocks only load up to 48 blocks along each axis, `/place template`
+/// loads any size
+#[must_use]
+pub fn write(region: &Region) -> Vec<u8> {
+    let [width, height, length] = region.size;
+    let mut blocks = vec![];
+    for y in 0..height {
+        for z in 0..length {
+            for x in 0..width {
+                let state = region.blocks[((y * length + z) * width + x) as usize];
+                if state == 0 {
+                    continue;
+                }
+                let mut block = vec![
+                    ("state".to_string(), Tag::Int(state as i32 - 1)),
+                    ("pos".to_string(), ints([x, y, z])),
+                ];
+                if let Some((_, entity)) = region
+                    .entities
+                    .iter()
+                    .find(|(position, _)| *position == [x, y, z])
+                {
+                    let mut nbt = vec![("id".to_string(), Tag::String(entity.id.clone()))];
+                    nbt.extend(entity.data.iter().cloned());
+                    block.push(("nbt".to_string(), Tag::Compound(nbt)));
+                }
+                blocks.push(Tag::Compound(block));
+            }
+        }
+    }
+    let structure = Tag::compound([
+        ("DataVersion", Tag::Int(DATA_VERSION)),
+        ("size", ints(region.size)),
+        (
+            "palette",
+            Tag::List(
+                region.palette[1..]
+                    .iter()
+                    .map(|state| block_state(state))
+                    .collect(),
+            ),
+        ),
+        ("blocks", Tag::List(blocks)),
+        ("entities", Tag::List(vec![])),
+    ]);
+    gzip::compress(&structure.to_bytes(""))
+}
+
+fn ints(values: [u32; 3]) -> Tag {
+    Tag::List(values.iter().map(|&value| Tag::Int(value as i32)).collect())
+}
@@ -0,0 +1,124 @@
+//! Splits programs that don't fit into one ROM unit of a [`Layout`](super::Layout)
+//!
+//! Units are cut between pages, where the compiler already put disc jumps, and an index
+//! [`Manifest`] says which pages every unit has
+
+use crate::backend::{Checksums, Instruction, Target};
+
+use super::report::report;
+
+/// Consecutive pages of a program that are built as one ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unit {
+    /// Page of the program the unit starts with
+    pub first_page: usize,
+    pub words: Vec<u16>,
+    /// Other units the disc jumps in this one go to, sorted
+    pub jumps_to: Vec<usize>,
+}
+
+impl Unit {
+    #[must_use]
+    pub fn page_count(&self, page_size: usize) -> usize {
+        self.words.len().div_ceil(page_size.max(1))
+    }
+}
+
+/// Units of at most `pages_per_unit` pages, a single unit if it's `None` or the program
+/// fits into one
+#[must_use]
+pub fn split(
+    instructions: &[Instruction],
+    target: &Target,
+    pages_per_unit: Option<usize>,
+) -> Vec<Unit> {
+    let page_size = target.page_size.max(1);
+    let words: Vec<_> = instructions.iter().map(Instruction::to_bin).collect();
+    let unit_size = pages_per_unit.map_or(usize::MAX, |pages| pages.max(1) * page_size);
+    let chunks: Vec<&[u16]> = if words.is_empty() {
+        vec![&[]]
+    } else {
+        words.chunks(unit_size).collect()
+    };
+    let mut units: Vec<_> = chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, words)| Unit {
+            first_page: index.saturating_mul(unit_size) / page_size,
+            words: words.to_vec(),
+            jumps_to: vec![],
+        })
+        .collect();
+
+    let unit_of = |address: usize| address / unit_size;
+    for jump in report(instructions, target).jumps {
+        let Some(to) = jump.to else { continue };
+        let (from, to) = (unit_of(jump.from), unit_of(to));
+        if from != to && !units[from].jumps_to.contains(&to) {
+            units[from].jumps_to.push(to);
+        }
+    }
+    for unit in &mut units {
+        unit.jumps_to.sort_unstable();
+    }
+    units
+}
+
+/// Index of the files a split program was written to
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Manifest {
+    pub target: &'static str,
+    pub page_size: usize,
+    pub pages_per_unit: Option<usize>,
+    pub instruction_count: usize,
+    pub units: Vec<ManifestUnit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ManifestUnit {
+    pub file: String,
+    pub first_page: usize,
+    pub page_count: usize,
+    /// Address of its first word in the program
+    pub first_address: usize,
+    pub instruction_count: usize,
+    pub jumps_to: Vec<usize>,
+    /// Of the words in the unit, pages are counted from its start
+    pub checksums: Checksums,
+}
+
+impl Manifest {
+    /// `files` has the name every unit was written to, in the same order
+    #[must_use]
+    pub fn new(
+        units: &[Unit],
+        files: Vec<String>,
+        target: &Target,
+        pages_per_unit: Option<usize>,
+    ) -> Self {
+        let page_size = target.page_size;
+        Self {
+            target: target.name,
+            page_size,
+            pages_per_unit,
+            instruction_count: units.iter().map(|unit| unit.words.len()).sum(),
+            units: units
+                .iter()
+                .zip(files)
+                .map(|(unit, file)| ManifestUnit {
+                    file,
+                    first_page: unit.first_page,
+                    page_count: unit.page_count(page_size),
+                    first_address: unit.first_page * page_size,
+                    instruction_count: unit.words.len(),
+                    jumps_to: unit.jumps_to.clone(),
+                    checksums: Checksums::new(&unit.words, page_size),
+                })
+                .collect(),
+        }
+    }
+}
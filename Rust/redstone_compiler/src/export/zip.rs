@@ -0,0 +1,56 @@
+//! Zip archives without compression, like the gzip files
+
+use crate::backend::checksum::crc32;
+
+/// 1980-01-01, the earliest date zip can store
+const DATE: u16 = 0x21;
+
+/// Archive with every file stored as is, directories are part of the paths
+#[must_use]
+pub fn archive(files: &[(String, String)]) -> Vec<u8> {
+    let mut zip = vec![];
+    let mut directory = vec![];
+    for (path, contents) in files {
+        let offset = zip.len() as u32;
+        let header = header(path, contents.as_bytes());
+        // local file header
+        zip.extend(0x0403_4b50_u32.to_le_bytes());
+        zip.extend(&header);
+        zip.extend(path.as_bytes());
+        zip.extend(contents.as_bytes());
+        // central directory header, made by and needing version 2.0
+        directory.extend(0x0201_4b50_u32.to_le_bytes());
+        directory.extend(20_u16.to_le_bytes());
+        directory.extend(&header);
+        // comment length, disk, internal and external attributes
+        directory.extend([0; 10]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(path.as_bytes());
+    }
+    let count = (files.len() as u16).to_le_bytes();
+    let start = (zip.len() as u32).to_le_bytes();
+    let len = (directory.len() as u32).to_le_bytes();
+    zip.extend(directory);
+    zip.extend(0x0605_4b50_u32.to_le_bytes());
+    zip.extend([0; 4]);
+    zip.extend(count);
+    zip.extend(count);
+    zip.extend(len);
+    zip.extend(start);
+    zip.extend([0; 2]);
+    zip
+}
+
+/// What both headers have in common: version, flags, method, time, checksum, sizes and
+/// the length of the name without extra fields
+fn header(path: &str, contents: &[u8]) -> Vec<u8> {
+    let size = (contents.len() as u32).to_le_bytes();
+    let mut header = vec![20, 0, 0, 0, 0, 0, 0, 0];
+    header.extend(DATE.to_le_bytes());
+    header.extend(crc32(contents).to_le_bytes());
+    header.extend(size);
+    header.extend(size);
+    header.extend((path.len() as u16).to_le_bytes());
+    header.extend([0; 2]);
+    header
+}
@@ -1,70 +1,188 @@
+use std::ops::Index;
+
 use vec1::Vec1;
 
 use super::Range;
 
 #[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ident {
     pub symbol: String,
     pub location: Range,
 }
 
+/// Index of an [`Expression`] in the [`Ast`] it was parsed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ExprId(u32);
+
+/// A parsed program. Expressions refer to their children by [`ExprId`] instead of
+/// owning them, so all of them are in one `Vec`
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ast {
+    expressions: Vec<Expression>,
+    /// The top-level statements
+    body: Vec<ExprId>,
+}
+
+impl Ast {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an expression whose children are already in the tree
+    ///
+    /// # Panics
+    ///
+    /// If there are more than `u32::MAX` expressions
+    pub fn push(&mut self, expression: Expression) -> ExprId {
+        let id = ExprId(
+            self.expressions
+                .len()
+                .try_into()
+                .expect("Too many expressions"),
+        );
+        self.expressions.push(expression);
+        id
+    }
+
+    /// Adds a top-level statement
+    pub fn push_statement(&mut self, expression: Expression) -> ExprId {
+        let id = self.push(expression);
+        self.body.push(id);
+        id
+    }
+
+    /// Removes the expressions pushed after the first `len`, they mustn't be in the
+    /// body or children of the rest
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.expressions.truncate(len);
+    }
+
+    #[must_use]
+    pub fn body(&self) -> &[ExprId] {
+        &self.body
+    }
+
+    /// The top-level statements
+    #[must_use]
+    pub fn statements(&self) -> impl ExactSizeIterator<Item = &Expression> {
+        self.body.iter().map(|&id| &self[id])
+    }
+
+    /// All expressions, children before their parents
+    #[must_use]
+    pub fn expressions(&self) -> impl ExactSizeIterator<Item = &Expression> {
+        self.expressions.iter()
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.expressions.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.expressions.is_empty()
+    }
+}
+
+impl Index<ExprId> for Ast {
+    type Output = Expression;
+
+    fn index(&self, id: ExprId) -> &Expression {
+        &self.expressions[id.0 as usize]
+    }
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExpressionType {
     InlineDeclaration {
         ident: Ident,
-        value: Box<Expression>,
+        value: ExprId,
     },
     Use(Vec1<Ident>),
     Conditional {
-        condition: Box<Expression>,
-        body: Vec<Expression>,
-        paths: Vec<(Expression, Vec<Expression>)>,
-        alternate: Option<Vec<Expression>>,
+        condition: ExprId,
+        body: Vec<ExprId>,
+        paths: Vec<(ExprId, Vec<ExprId>)>,
+        alternate: Option<Vec<ExprId>>,
     },
     EndlessLoop {
-        body: Vec<Expression>,
+        body: Vec<ExprId>,
     },
     WhileLoop {
-        condition: Box<Expression>,
-        body: Vec<Expression>,
+        condition: ExprId,
+        body: Vec<ExprId>,
     },
     #[default]
     Pass,
+    /// Code that isn't written yet, halts and warns about it
+    Todo,
     BinaryExpr {
-        left: Box<Expression>,
-        right: Box<Expression>,
+        left: ExprId,
+        right: ExprId,
         operator: Operator,
     },
     EqExpr {
-        left: Box<Expression>,
-        right: Box<Expression>,
+        left: ExprId,
+        right: ExprId,
         operator: EqualityOperator,
     },
+    UnaryExpr {
+        value: ExprId,
+        operator: UnaryOperator,
+    },
     Identifier(String),
     NumericLiteral(i16),
     Assignment {
         ident: Ident,
-        value: Box<Expression>,
+        value: ExprId,
     },
+    /// `variable` is always an [`ExpressionType::Identifier`]
     IAssignment {
-        ident: Ident,
-        value: Box<Expression>,
+        variable: ExprId,
+        value: ExprId,
         operator: Operator,
     },
     VarDeclaration {
         ident: Ident,
     },
+    /// Only generates code if [`CompileOptions::assertions`](crate::backend::CompileOptions::assertions)
+    /// is set
+    Assert {
+        condition: ExprId,
+    },
+    /// Checked at compile-time
+    StaticAssert {
+        condition: ExprId,
+        message: Option<String>,
+    },
+    /// Redstone ticks known at compile-time
+    Wait {
+        ticks: ExprId,
+    },
+    /// Both are always [`ExpressionType::Identifier`]s
+    Swap {
+        left: ExprId,
+        right: ExprId,
+    },
     Member {
-        object: Box<Expression>,
+        object: ExprId,
         property: Ident,
     },
     Call {
-        args: Vec<Expression>,
-        function: Box<Expression>,
+        args: Vec<ExprId>,
+        function: ExprId,
     },
     Debug,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Expression {
     pub typ: ExpressionType,
     pub location: Range,
@@ -80,7 +198,8 @@ impl std::fmt::Debug for Expression {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operator {
     Plus,
     Minus,
@@ -88,17 +207,55 @@ pub enum Operator {
     And,
     Or,
     Xor,
+    /// Only at compile-time
+    Div,
+    /// Only at compile-time
+    Mod,
+    /// The distance has to be known at compile-time
+    ShiftLeft,
+    /// Keeps the sign, the distance has to be known at compile-time
+    ShiftRight,
 }
 
 impl Operator {
     #[inline]
     #[must_use]
     pub const fn is_commutative(self) -> bool {
-        !matches!(self, Self::Minus)
+        matches!(
+            self,
+            Self::Plus | Self::Mult | Self::And | Self::Or | Self::Xor
+        )
+    }
+
+    /// How it's written in the code
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Plus => "+",
+            Self::Minus => "-",
+            Self::Mult => "*",
+            Self::And => "&",
+            Self::Or => "|",
+            Self::Xor => "^",
+            Self::Div => "/",
+            Self::Mod => "%",
+            Self::ShiftLeft => "<<",
+            Self::ShiftRight => ">>",
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnaryOperator {
+    /// `-`
+    Negate,
+    /// `~`, flips every bit
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EqualityOperator {
     EqualTo,
     NotEqual,
@@ -109,6 +266,19 @@ pub enum EqualityOperator {
 }
 
 impl EqualityOperator {
+    /// How it's written in the code
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::EqualTo => "==",
+            Self::NotEqual => "!=",
+            Self::Greater => ">",
+            Self::GreaterEq => ">=",
+            Self::Less => "<",
+            Self::LessEq => "<=",
+        }
+    }
+
     #[must_use]
     pub const fn opposite(self) -> Self {
         match self {
@@ -121,6 +291,19 @@ impl EqualityOperator {
         }
     }
 
+    /// Whether `left self right` is true
+    #[must_use]
+    pub const fn holds(self, left: i16, right: i16) -> bool {
+        match self {
+            Self::EqualTo => left == right,
+            Self::NotEqual => left != right,
+            Self::Greater => left > right,
+            Self::GreaterEq => left >= right,
+            Self::Less => left < right,
+            Self::LessEq => left <= right,
+        }
+    }
+
     #[must_use]
     pub const fn turnaround(self) -> Self {
         match self {
@@ -141,6 +324,8 @@ pub const fn operator(symbol: char) -> Option<Operator> {
         '+' => Some(O::Plus),
         '-' => Some(O::Minus),
         '*' => Some(O::Mult),
+        '/' => Some(O::Div),
+        '%' => Some(O::Mod),
         '&' => Some(O::And),
         '|' => Some(O::Or),
         '^' => Some(O::Xor),
@@ -7,6 +7,8 @@ pub enum Type {
     InvalidDeclartion,
     InvalidAssignment,
     MissingEquals,
+    MissingComma,
+    ExpectedText,
     FunctionChaining,
     MissingOpenParen,
     MissingClosingParen,
@@ -14,6 +16,7 @@ pub enum Type {
     Eof,
     UnexpectedOther,
     ExpectedParen,
+    NestingTooDeep,
 }
 
 impl ErrorType for Type {
@@ -25,6 +28,8 @@ impl ErrorType for Type {
             Self::InvalidDeclartion => "Expected identifier",
             Self::InvalidAssignment => "Can only assign to identifiers",
             Self::MissingEquals => "Expected equals following identifier",
+            Self::MissingComma => "Expected ','",
+            Self::ExpectedText => "Expected a message in double quotes",
             Self::FunctionChaining => {
                 "You can't chain functions, what do you think this is, Python?"
             }
@@ -34,7 +39,29 @@ impl ErrorType for Type {
             Self::Eof => "Unexpected EOF while parsing",
             Self::UnexpectedOther => "Unexpected token found",
             Self::ExpectedParen => "Unexpected token, expected ')'",
+            Self::NestingTooDeep => "This is nested too deep, split it up",
         }
         .to_string()
     }
+
+    fn get_code(&self) -> &'static str {
+        match self {
+            Self::EmptyBlock => "empty-block",
+            Self::MissingEnd => "missing-end",
+            Self::InvalidModuleName => "invalid-module-name",
+            Self::InvalidDeclartion => "invalid-declaration",
+            Self::InvalidAssignment => "invalid-assignment",
+            Self::MissingEquals => "missing-equals",
+            Self::MissingComma => "missing-comma",
+            Self::ExpectedText => "expected-text",
+            Self::FunctionChaining => "function-chaining",
+            Self::MissingOpenParen => "missing-open-paren",
+            Self::MissingClosingParen => "missing-closing-paren",
+            Self::InvalidDot => "invalid-dot",
+            Self::Eof => "unexpected-eof",
+            Self::UnexpectedOther => "unexpected-token",
+            Self::ExpectedParen => "expected-paren",
+            Self::NestingTooDeep => "nesting-too-deep",
+        }
+    }
 }
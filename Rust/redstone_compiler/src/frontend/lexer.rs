@@ -1,13 +1,17 @@
-use std::{fmt::Debug, iter::Peekable};
+use std::fmt::Debug;
 
 use crate::{err, error::Error};
 
 use super::{eq_operator, operator, EqualityOperator as EqOp, Location, Operator, Range};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub enum TokenType {
+/// Identifiers are slices of the source code, so tokens can be copied freely
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenType<'a> {
     Number(i16),
-    Identifier(String),
+    Identifier(&'a str),
+    /// Between double quotes, which aren't included
+    Text(&'a str),
     Equals,
     OpenParen,
     OpenFuncParen,
@@ -16,7 +20,11 @@ pub enum TokenType {
     Dot,
     BinaryOperator(Operator),
     IOperator(Operator),
+    /// `++` or `--`
+    Step(Operator),
     EqOperator(EqOp),
+    /// `~`
+    Not,
     Inline,
     If,
     Elif,
@@ -28,24 +36,32 @@ pub enum TokenType {
     Debug,
     Use,
     Var,
+    Swap,
+    Assert,
+    StaticAssert,
+    Todo,
+    Wait,
+    #[default]
     Eof,
 }
 
-#[derive(Clone, PartialEq, Eq)]
-pub struct Token {
-    pub typ: TokenType,
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Token<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub typ: TokenType<'a>,
     pub location: Range,
 }
 
-impl Token {
-    fn from_char(typ: TokenType, location: Location) -> Self {
+impl<'a> Token<'a> {
+    const fn from_char(typ: TokenType<'a>, location: Location) -> Self {
         Self {
             typ,
             location: Range::single_char(location),
         }
     }
 
-    fn with_len(typ: TokenType, location: Location, len: u16) -> Self {
+    const fn with_len(typ: TokenType<'a>, location: Location, len: u16) -> Self {
         Self {
             typ,
             location: Range(location, Location(location.0, location.1 + len - 1)),
@@ -53,7 +69,7 @@ impl Token {
     }
 }
 
-impl Debug for Token {
+impl Debug for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?} at {:?}", self.typ, self.location)
     }
@@ -64,6 +80,7 @@ enum ErrorType {
     Eof,
     InvalidChar(String),
     TabIndent,
+    UnterminatedText,
 }
 
 impl crate::error::ErrorType for ErrorType {
@@ -75,12 +92,44 @@ impl crate::error::ErrorType for ErrorType {
             Self::TabIndent => {
                 "Pleas only format using spaces, tabs break the formatting".to_string()
             }
+            Self::UnterminatedText => "Missing '\"' before the end of the line".to_string(),
+        }
+    }
+
+    fn get_code(&self) -> &'static str {
+        match self {
+            Self::InvalidNumber(_) => "invalid-number",
+            Self::Eof => "unexpected-eof",
+            Self::InvalidChar(_) => "invalid-char",
+            Self::TabIndent => "tab-indent",
+            Self::UnterminatedText => "unterminated-text",
         }
     }
 }
 
-fn keyword(string: String) -> TokenType {
-    match string.as_str() {
+/// Every word `keyword` doesn't treat as an identifier
+pub const KEYWORDS: [&str; 17] = [
+    "inline",
+    "if",
+    "elif",
+    "elseif",
+    "else",
+    "end",
+    "forever",
+    "while",
+    "pass",
+    "use",
+    "var",
+    "debug",
+    "swap",
+    "assert",
+    "static_assert",
+    "todo",
+    "wait",
+];
+
+fn keyword(string: &str) -> TokenType<'_> {
+    match string {
         "inline" => TokenType::Inline,
         "if" => TokenType::If,
         "elif" | "elseif" => TokenType::Elif,
@@ -92,6 +141,11 @@ fn keyword(string: String) -> TokenType {
         "use" => TokenType::Use,
         "var" => TokenType::Var,
         "debug" => TokenType::Debug,
+        "swap" => TokenType::Swap,
+        "assert" => TokenType::Assert,
+        "static_assert" => TokenType::StaticAssert,
+        "todo" => TokenType::Todo,
+        "wait" => TokenType::Wait,
         _ => TokenType::Identifier(string),
     }
 }
@@ -100,231 +154,260 @@ const fn is_skippable(c: char) -> bool {
     matches!(c, ' ' | '\n' | '\t' | '\r' | ';')
 }
 
-fn next(iter: &mut impl Iterator<Item = char>, location: &mut Location) -> Option<char> {
-    let n = iter.next();
-    if let Some(char) = n {
+/// Walks over the source code, keeping track of the location of the last character
+struct Scanner<'a> {
+    source: &'a str,
+    /// Byte offset of the next character
+    offset: usize,
+    location: Location,
+}
+
+impl<'a> Scanner<'a> {
+    const fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            offset: 0,
+            location: Location(0, 0),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.offset += char.len_utf8();
         match char {
-            '\n' => *location = Location(location.0 + 1, 0),
+            '\n' => self.location = Location(self.location.0.saturating_add(1), 0),
             '\r' => {}
-            _ => location.1 += 1,
+            _ => self.location.1 = self.location.1.saturating_add(1),
         }
+        Some(char)
+    }
+
+    /// Takes characters as long as they match
+    fn skip_while(&mut self, matches: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&matches) {
+            self.next();
+        }
+    }
+
+    /// The source from `start` up to the next character
+    fn since(&self, start: usize) -> &'a str {
+        &self.source[start..self.offset]
     }
-    n
 }
 
 use Token as T;
 use TokenType as Tt;
 
-/// Transform source code into Tokens
+/// Transform source code into Tokens, identifiers borrow from it
 ///
 /// # Errors
 ///
 /// This function will return an error if there is an invalid character
-pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
+pub fn tokenize(source_code: &str) -> Result<Vec<Token<'_>>, Error> {
+    phase!("tokenize", bytes = source_code.len());
     let mut tokens: Vec<Token> = vec![];
-    let mut src = source_code.chars().peekable();
-    let mut current_location = Location(0, 0);
-    let Some(mut char) = next(&mut src, &mut current_location) else {
+    let mut src = Scanner::new(source_code);
+    let Some(mut char) = src.next() else {
         return Ok(vec![]);
     };
     let mut prev = ' ';
     loop {
         match char {
-            '(' => tokens.push(if prev.is_whitespace() | is_skippable(prev) {
-                T::from_char(Tt::OpenParen, current_location)
+            // calls directly follow a name or another call
+            '(' => tokens.push(if prev.is_alphanumeric() || matches!(prev, '_' | ')') {
+                T::from_char(Tt::OpenFuncParen, src.location)
             } else {
-                T::from_char(Tt::OpenFuncParen, current_location)
+                T::from_char(Tt::OpenParen, src.location)
             }),
-            ')' => tokens.push(T::from_char(Tt::CloseParen, current_location)),
-            '+' | '*' | '&' | '|' | '^' => {
-                let equals_after = matches!(src.peek(), Some('='));
+            ')' => tokens.push(T::from_char(Tt::CloseParen, src.location)),
+            '+' if src.peek() == Some('+') => {
+                tokens.push(T::with_len(Tt::Step(Operator::Plus), src.location, 2));
+                src.next();
+            }
+            '+' | '*' | '/' | '%' | '&' | '|' | '^' => {
+                let equals_after = src.peek() == Some('=');
 
                 if let Some(operator) = operator(char) {
                     tokens.push(if equals_after {
-                        T::with_len(Tt::IOperator(operator), current_location, 2)
+                        T::with_len(Tt::IOperator(operator), src.location, 2)
                     } else {
-                        T::from_char(Tt::BinaryOperator(operator), current_location)
+                        T::from_char(Tt::BinaryOperator(operator), src.location)
                     });
                 }
 
                 if equals_after {
-                    next(&mut src, &mut current_location);
+                    src.next();
                 }
             }
-            '-' => tokens.push(read_hyphen(&mut src, &mut current_location)?),
-            ',' => tokens.push(T::from_char(Tt::Comma, current_location)),
-            '.' => tokens.push(T::from_char(Tt::Dot, current_location)),
+            '-' => tokens.push(read_hyphen(&mut src)?),
+            ',' => tokens.push(T::from_char(Tt::Comma, src.location)),
+            '.' => tokens.push(T::from_char(Tt::Dot, src.location)),
 
             '=' => match src.peek() {
                 Some('=') => {
-                    next(&mut src, &mut current_location);
-                    tokens.push(T::from_char(
-                        Tt::EqOperator(EqOp::EqualTo),
-                        current_location,
-                    ));
+                    tokens.push(T::with_len(Tt::EqOperator(EqOp::EqualTo), src.location, 2));
+                    src.next();
                 }
-                _ => tokens.push(T::from_char(Tt::Equals, current_location)),
+                _ => tokens.push(T::from_char(Tt::Equals, src.location)),
             },
+            '<' | '>' if src.peek() == Some(char) => tokens.push(read_shift(char, &mut src)),
             '>' | '<' | '!' => {
-                let equals_after = matches!(src.peek(), Some('='));
+                let equals_after = src.peek() == Some('=');
 
                 if let Some(token) = eq_operator(char, equals_after) {
                     tokens.push(T::with_len(
                         Tt::EqOperator(token),
-                        current_location,
+                        src.location,
                         if equals_after { 2 } else { 1 },
                     ));
-                    next(&mut src, &mut current_location);
+                    src.next();
                 }
             }
-            '#' => while !matches!(next(&mut src, &mut current_location), Some('\n') | None) {},
-            '\t' => return err!(TabIndent, Range(current_location, current_location)),
+            '~' => tokens.push(T::from_char(Tt::Not, src.location)),
+            '"' => tokens.push(read_text(&mut src)?),
+            '#' => while !matches!(src.next(), Some('\n') | None) {},
+            '\t' => return err!(TabIndent, Range(src.location, src.location)),
             _ => {
                 if char.is_ascii_digit() {
-                    let start = current_location;
-                    let num = read_num(char, &mut src, &mut current_location)?;
+                    let start = src.location;
+                    let num = read_num(char, &mut src)?;
 
                     tokens.push(T {
                         typ: Tt::Number(num),
-                        location: Range(start, current_location),
+                        location: Range(start, src.location),
                     });
                 } else if char.is_alphabetic() {
-                    read_identifier(char, &mut src, &mut current_location, &mut tokens);
+                    tokens.push(read_identifier(char, &mut src));
                 } else if !is_skippable(char) {
                     return err!(
                         ErrorType::InvalidChar(char.to_string()),
-                        Range(current_location, current_location)
+                        Range(src.location, src.location)
                     );
                 }
             }
         }
         prev = char;
-        char = match next(&mut src, &mut current_location) {
+        char = match src.next() {
             Some(c) => c,
             None => break,
         };
     }
-    tokens.push(T::from_char(Tt::Eof, current_location));
+    tokens.push(T::from_char(Tt::Eof, src.location));
 
     Ok(tokens)
 }
 
-fn read_hyphen(
-    src: &mut Peekable<std::str::Chars<'_>>,
-    current_location: &mut Location,
-) -> Result<Token, Error> {
+fn read_hyphen<'a>(src: &mut Scanner<'a>) -> Result<Token<'a>, Error> {
     Ok(match src.peek() {
-        None => T::from_char(Tt::BinaryOperator(Operator::Minus), *current_location),
+        None => T::from_char(Tt::BinaryOperator(Operator::Minus), src.location),
         Some(c) => match c {
             '=' => {
-                let t = T::with_len(Tt::IOperator(Operator::Minus), *current_location, 2);
-                next(src, current_location);
+                let t = T::with_len(Tt::IOperator(Operator::Minus), src.location, 2);
+                src.next();
+                t
+            }
+            '-' => {
+                let t = T::with_len(Tt::Step(Operator::Minus), src.location, 2);
+                src.next();
                 t
             }
             '0'..='9' => {
-                let start = *current_location;
-                let num = -read_num(
-                    next(src, current_location).ok_or(<Result<i16, Error>>::unwrap_err(err!(
-                        Eof,
-                        Range(start, *current_location)
-                    )))?,
-                    src,
-                    current_location,
-                )?;
+                let start = src.location;
+                let first = src.next().ok_or_else(|| {
+                    <Result<i16, Error>>::unwrap_err(err!(Eof, Range(start, src.location)))
+                })?;
+                let num = -read_num(first, src)?;
                 T {
                     typ: Tt::Number(num),
-                    location: Range(start, *current_location),
+                    location: Range(start, src.location),
                 }
             }
-            _ => T::from_char(Tt::BinaryOperator(Operator::Minus), *current_location),
+            _ => T::from_char(Tt::BinaryOperator(Operator::Minus), src.location),
         },
     })
 }
 
-fn read_num(
-    first: char,
-    src: &mut Peekable<std::str::Chars<'_>>,
-    current_location: &mut Location,
-) -> Result<i16, Error> {
-    let mut c = src.peek();
-
-    if first == '0' {
-        match c {
-            Some('b') => return read_n_num(src, current_location, 2),
-            Some('x') => return read_n_num(src, current_location, 16),
-            _ => {}
-        }
+/// Text can't span several lines
+fn read_text<'a>(src: &mut Scanner<'a>) -> Result<Token<'a>, Error> {
+    let start = src.location;
+    let offset = src.offset;
+    src.skip_while(|c| !matches!(c, '"' | '\n'));
+    let text = src.since(offset);
+    if src.next() != Some('"') {
+        return err!(UnterminatedText, Range(start, src.location));
     }
+    Ok(T {
+        typ: Tt::Text(text),
+        location: Range(start, src.location),
+    })
+}
 
-    let mut num = String::new();
-    num.push(first);
-
-    loop {
-        let Some(n) = c else {
-            break;
-        };
-        if !n.is_ascii_digit() {
-            break;
-        }
-        num.push(*n);
-        next(src, current_location);
-        c = src.peek();
+/// `<<`, `>>`, `<<=` or `>>=`
+fn read_shift<'a>(char: char, src: &mut Scanner) -> Token<'a> {
+    let start = src.location;
+    let operator = if char == '<' {
+        Operator::ShiftLeft
+    } else {
+        Operator::ShiftRight
+    };
+    src.next();
+    if src.peek() == Some('=') {
+        src.next();
+        T::with_len(Tt::IOperator(operator), start, 3)
+    } else {
+        T::with_len(Tt::BinaryOperator(operator), start, 2)
     }
-    Ok(num.parse().unwrap())
 }
 
-fn read_identifier(
-    char: char,
-    src: &mut Peekable<std::str::Chars<'_>>,
-    current_location: &mut Location,
-    tokens: &mut Vec<Token>,
-) {
-    let start = *current_location;
-    let mut identifier = String::new();
-    identifier.push(char);
-    let mut c = src.peek();
+fn read_num(first: char, src: &mut Scanner) -> Result<i16, Error> {
+    let start = src.location;
 
-    loop {
-        let Some(a) = c else {
-            break;
-        };
-        if !a.is_alphanumeric() && *a != '_' {
-            break;
+    if first == '0' {
+        match src.peek() {
+            Some('b') => return read_n_num(src, 2),
+            Some('x') => return read_n_num(src, 16),
+            _ => {}
         }
-        identifier.push(*a);
-        next(src, current_location);
-        c = src.peek();
     }
-    let len = identifier.len() as u16;
-    tokens.push(T::with_len(keyword(identifier), start, len));
+
+    let offset = src.offset - first.len_utf8();
+    src.skip_while(|c| c.is_ascii_digit());
+    let num = src.since(offset);
+    num.parse().map_or_else(
+        |_| {
+            err!(
+                ErrorType::InvalidNumber(num.to_string()),
+                Range(start, src.location)
+            )
+        },
+        Ok,
+    )
 }
 
-fn read_n_num(
-    src: &mut Peekable<std::str::Chars<'_>>,
-    current_location: &mut Location,
-    radix: u32,
-) -> Result<i16, Error> {
-    let start = *current_location;
-    next(src, current_location);
-    let mut c = src.peek();
-    let mut num = String::new();
+fn read_identifier<'a>(char: char, src: &mut Scanner<'a>) -> Token<'a> {
+    let start = src.location;
+    let offset = src.offset - char.len_utf8();
+    src.skip_while(|c| c.is_alphanumeric() || c == '_');
+    let identifier = src.since(offset);
+    let len = identifier.len() as u16;
+    T::with_len(keyword(identifier), start, len)
+}
 
-    loop {
-        let Some(n) = c else {
-            break;
-        };
-        if !n.is_ascii_hexdigit() {
-            break;
-        }
-        num.push(*n);
-        next(src, current_location);
-        c = src.peek();
-    }
-    u16::from_str_radix(num.as_str(), radix).map_or_else(
+fn read_n_num(src: &mut Scanner, radix: u32) -> Result<i16, Error> {
+    let start = src.location;
+    src.next();
+    let offset = src.offset;
+    src.skip_while(|c| c.is_ascii_hexdigit());
+    let num = src.since(offset);
+    u16::from_str_radix(num, radix).map_or_else(
         |_| {
             err!(
-                ErrorType::InvalidNumber(num),
-                Range(start, *current_location)
+                ErrorType::InvalidNumber(num.to_string()),
+                Range(start, src.location)
             )
         },
         |u| Ok(u as i16),
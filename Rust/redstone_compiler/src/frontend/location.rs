@@ -1,18 +1,50 @@
 use std::fmt::Debug;
-use std::ops::Add;
+use std::ops::{self, Add};
 
 // (line, column)
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location(pub u16, pub u16);
 
+impl Location {
+    /// Byte offset of the char at this location in `code`, or of the end of its line if
+    /// it's right after it. Column 0 is the start of the line
+    #[must_use]
+    pub fn offset(self, code: &str) -> Option<usize> {
+        let mut line_start = 0;
+        for _ in 0..self.0 {
+            line_start += code[line_start..].find('\n')? + 1;
+        }
+        let line = code[line_start..].split('\n').next().unwrap_or_default();
+        let column = usize::from(self.1.saturating_sub(1));
+        line.char_indices()
+            .map(|(offset, _)| offset)
+            .chain([line.len()])
+            .nth(column)
+            .map(|offset| line_start + offset)
+    }
+
+    /// Location of the char at a byte offset in `code`, `None` if the offset is past the
+    /// end or inside of a char
+    #[must_use]
+    pub fn from_offset(code: &str, offset: usize) -> Option<Self> {
+        let before = code.get(..offset)?;
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        let line = before.matches('\n').count();
+        let column = before[line_start..].chars().count() + 1;
+        Some(Self(line.try_into().ok()?, column.try_into().ok()?))
+    }
+}
+
 impl Debug for Location {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}:{}", self.0 + 1, self.1)
     }
 }
 
-/// [from, to], both inclusive
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// [from, to], both inclusive. Ordered by start, then by end
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Range(pub Location, pub Location);
 
 impl Range {
@@ -20,6 +52,46 @@ impl Range {
     pub const fn single_char(location: Location) -> Self {
         Self(location, location)
     }
+
+    #[must_use]
+    pub fn contains(self, location: Location) -> bool {
+        self.0 <= location && location <= self.1
+    }
+
+    /// Whether all of `other` is inside of it
+    #[must_use]
+    pub fn contains_range(self, other: Self) -> bool {
+        self.0 <= other.0 && other.1 <= self.1
+    }
+
+    /// Whether they have a char in common
+    #[must_use]
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 <= other.1 && other.0 <= self.1
+    }
+
+    /// Byte offsets of the chars it covers in `code`, the end is exclusive
+    #[must_use]
+    pub fn byte_range(self, code: &str) -> Option<ops::Range<usize>> {
+        let start = self.0.offset(code)?;
+        let end = self.1.offset(code)?;
+        let end = end + code[end..].chars().next().map_or(0, char::len_utf8);
+        Some(start..end.max(start))
+    }
+
+    /// Range of the chars between two byte offsets in `code`, an empty one is the char
+    /// at its start
+    #[must_use]
+    pub fn from_byte_range(code: &str, range: ops::Range<usize>) -> Option<Self> {
+        let from = range.start;
+        let start = Location::from_offset(code, from)?;
+        let last = code
+            .get(range)?
+            .char_indices()
+            .next_back()
+            .map_or(from, |(offset, _)| from + offset);
+        Some(Self(start, Location::from_offset(code, last)?))
+    }
 }
 
 impl Add for Range {
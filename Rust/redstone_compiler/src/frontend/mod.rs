@@ -3,10 +3,12 @@ pub mod error;
 pub mod lexer;
 pub mod location;
 pub mod parser;
+pub mod unparse;
 
 pub use ast::*;
 pub use lexer::*;
 pub use location::*;
 pub use parser::*;
+pub use unparse::*;
 
 use error::Type as ErrorType;
@@ -1,20 +1,41 @@
-use std::collections::VecDeque;
-
 use crate::{
     err,
     error::Error,
     frontend::{ErrorType, Range},
 };
 
-use super::{EqualityOperator, Expression, ExpressionType, Ident, Operator, Token, TokenType};
+use super::{
+    Ast, EqualityOperator, ExprId, Expression, ExpressionType, Ident, Operator, Token, TokenType,
+    UnaryOperator,
+};
+
+/// How deep blocks and expressions can be nested, parsing and compiling them
+/// recurses and the stack is small on wasm
+pub const MAX_NESTING: usize = 64;
 
 #[derive(Default)]
-pub struct Parser {
-    tokens: VecDeque<Token>,
+pub struct Parser<'a> {
+    tokens: &'a [Token<'a>],
+    /// Index of the next token
+    position: usize,
+    /// Stands in for the final Eof if the tokens don't end with one
+    eof: Token<'a>,
+    /// The tree being built, parsed expressions are only added once they are a child
+    /// or a statement
+    ast: Ast,
+    /// Statements and expressions that are being parsed
+    depth: usize,
 }
 
 type Res<T = Expression, E = Error> = Result<T, E>;
 
+/// Where the parser was, see [`Parser::rewind`]
+#[derive(Clone, Copy)]
+struct Checkpoint {
+    position: usize,
+    expressions: usize,
+}
+
 macro_rules! match_fn {
     ($pattern:pat $(if $guard:expr)? $(,)?) => {
         |value| match value {
@@ -24,43 +45,78 @@ macro_rules! match_fn {
     };
 }
 
-impl Parser {
+impl<'a> Parser<'a> {
     #[must_use]
     pub fn new() -> Self {
         Self::default()
     }
 
-    fn eat(&mut self) -> Token {
-        self.tokens.pop_front().expect("Eof before Stream ends")
+    /// Takes the next token, the final Eof stays so malformed code can't eat past it
+    fn eat(&mut self) -> Token<'a> {
+        let token = *self.at();
+        if token.typ != TokenType::Eof {
+            self.position += 1;
+        }
+        token
     }
 
-    fn at(&self) -> &Token {
-        self.tokens.front().expect("Eof before Stream ends")
+    fn at(&self) -> &Token<'a> {
+        self.peek_n(0)
     }
 
-    fn eat_if_or<F>(&mut self, validator: F, err: ErrorType, location: Range) -> Res<Token>
-    where
-        F: Fn(&TokenType) -> bool,
-    {
+    /// The token `n` after the next one, without eating anything
+    fn peek_n(&self, n: usize) -> &Token<'a> {
+        self.tokens.get(self.position + n).unwrap_or(&self.eof)
+    }
+
+    const fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            position: self.position,
+            expressions: self.ast.len(),
+        }
+    }
+
+    /// Goes back to `checkpoint`, forgetting what was parsed since
+    fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.position = checkpoint.position;
+        self.ast.truncate(checkpoint.expressions);
+    }
+
+    /// Skips the rest of the lines a failed statement is on, so one mistake doesn't
+    /// turn the rest of its line into more errors
+    fn recover(&mut self, start: Checkpoint, error: &Error) {
+        let line = self.at().location.0 .0;
+        self.rewind(start);
+        let last_line = line.max(error.location.0 .0);
+        while self.at().typ != TokenType::Eof && self.at().location.0 .0 <= last_line {
+            self.eat();
+        }
+    }
+
+    /// The `end` of a block, a missing one is reported where it was expected
+    fn eat_end(&mut self, start: Range, block: &str) -> Res<Token<'a>> {
         let token = self.eat();
-        if !validator(&token.typ) {
+        if token.typ != TokenType::End {
             return Err(Error {
-                typ: Box::new(err),
-                location,
-            });
+                typ: Box::new(ErrorType::MissingEnd),
+                location: token.location,
+                related: vec![],
+            }
+            .with_related(start, format!("{block} starts here")));
         }
         Ok(token)
     }
 
-    fn eat_if<F>(&mut self, validator: F, err: ErrorType) -> Res<Token>
+    fn eat_if<F>(&mut self, validator: F, err: ErrorType) -> Res<Token<'a>>
     where
-        F: Fn(&TokenType) -> bool,
+        F: Fn(&TokenType<'a>) -> bool,
     {
         let token = self.eat();
         if !validator(&token.typ) {
             return Err(Error {
                 typ: Box::new(err),
                 location: token.location,
+                related: vec![],
             });
         }
         Ok(token)
@@ -71,25 +127,56 @@ impl Parser {
     /// # Errors
     ///
     /// when any error occurs
-    pub fn produce_ast(&mut self, tokens: Vec<Token>) -> Res<Vec<Expression>, Vec<Error>> {
-        self.tokens = VecDeque::from(tokens);
+    pub fn produce_ast(&mut self, tokens: &'a [Token<'a>]) -> Res<Ast, Vec<Error>> {
+        phase!("parse", tokens = tokens.len());
+        self.tokens = tokens;
+        self.position = 0;
+        self.eof = Token {
+            typ: TokenType::Eof,
+            location: tokens
+                .last()
+                .map(|token| Range::single_char(token.location.1))
+                .unwrap_or_default(),
+        };
 
-        let mut body = vec![];
+        self.ast = Ast::new();
+        self.depth = 0;
         let mut errors = vec![];
 
-        while !self.tokens.is_empty() && self.at().typ != TokenType::Eof {
+        while self.at().typ != TokenType::Eof {
+            let start = self.checkpoint();
             match self.parse_statement() {
-                Ok(expr) => body.push(expr),
-                Err(err) => errors.push(err),
+                Ok(expr) => {
+                    self.ast.push_statement(expr);
+                }
+                Err(err) => {
+                    self.recover(start, &err);
+                    errors.push(err);
+                }
             }
         }
         if !errors.is_empty() {
             return Err(errors);
         }
-        Ok(body)
+        Ok(std::mem::take(&mut self.ast))
+    }
+
+    /// Runs `parse` one level deeper, errors instead if that's too deep
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Res<T>) -> Res<T> {
+        if self.depth >= MAX_NESTING {
+            return err!(NestingTooDeep, self.at().location);
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
     }
 
     fn parse_statement(&mut self) -> Res {
+        self.nested(Self::parse_unnested_statement)
+    }
+
+    fn parse_unnested_statement(&mut self) -> Res {
         let current = self.at();
         Ok(match current.typ {
             TokenType::Inline => self.parse_inline_declaration()?,
@@ -101,21 +188,58 @@ impl Parser {
                     location: token.location,
                 }
             }
+            TokenType::Todo => {
+                let token = self.eat();
+                Expression {
+                    typ: ExpressionType::Todo,
+                    location: token.location,
+                }
+            }
             TokenType::Use => self.parse_use_statement()?,
             TokenType::Var => self.parse_var_declaration()?,
+            TokenType::Swap => self.parse_swap()?,
+            TokenType::StaticAssert => self.parse_static_assert()?,
+            TokenType::Assert => {
+                let start = self.eat().location;
+                let condition = self.parse_expression()?;
+                let location = start + condition.location;
+                Expression {
+                    typ: ExpressionType::Assert {
+                        condition: self.ast.push(condition),
+                    },
+                    location,
+                }
+            }
+            TokenType::Wait => {
+                let start = self.eat().location;
+                let ticks = self.parse_expression()?;
+                let location = start + ticks.location;
+                Expression {
+                    typ: ExpressionType::Wait {
+                        ticks: self.ast.push(ticks),
+                    },
+                    location,
+                }
+            }
             TokenType::Forever => self.parse_endless()?,
             TokenType::While => self.parse_while()?,
             _ => self.parse_expression()?,
         })
     }
 
+    /// A statement inside of a block, already added to the tree
+    fn parse_block_statement(&mut self) -> Res<ExprId> {
+        let statement = self.parse_statement()?;
+        Ok(self.ast.push(statement))
+    }
+
     fn parse_conditional(&mut self) -> Res {
         let start = self.eat().location;
         let (condition, body) = self.parse_conditional_branch()?;
         // self.at is now elif, else or end
         let mut paths = vec![];
 
-        while matches!(self.at().typ, TokenType::Elif | TokenType::Eof) {
+        while matches!(self.at().typ, TokenType::Elif) {
             self.eat();
             paths.push(self.parse_conditional_branch()?);
         }
@@ -125,7 +249,7 @@ impl Parser {
                 self.eat();
                 let mut body = vec![];
                 while !matches!(self.at().typ, TokenType::End | TokenType::Eof) {
-                    body.push(self.parse_statement()?);
+                    body.push(self.parse_block_statement()?);
                 }
                 if body.is_empty() {
                     return err!(EmptyBlock, start + self.at().location);
@@ -136,12 +260,10 @@ impl Parser {
             None
         };
 
-        let end = self
-            .eat_if_or(match_fn!(TokenType::End), ErrorType::MissingEnd, start)?
-            .location;
+        let end = self.eat_end(start, "`if` block")?.location;
         Ok(Expression {
             typ: ExpressionType::Conditional {
-                condition: Box::new(condition),
+                condition,
                 body,
                 paths,
                 alternate,
@@ -150,15 +272,16 @@ impl Parser {
         })
     }
 
-    fn parse_conditional_branch(&mut self) -> Res<(Expression, Vec<Expression>)> {
+    fn parse_conditional_branch(&mut self) -> Res<(ExprId, Vec<ExprId>)> {
         let condition = self.parse_expression()?;
+        let condition = self.ast.push(condition);
         let start = self.at().location;
         let mut body = vec![];
         while !matches!(
             self.at().typ,
             TokenType::Elif | TokenType::Else | TokenType::End | TokenType::Eof
         ) {
-            body.push(self.parse_statement()?);
+            body.push(self.parse_block_statement()?);
         }
         if body.is_empty() {
             return err!(EmptyBlock, start + self.at().location);
@@ -171,11 +294,9 @@ impl Parser {
         let start = self.eat().location;
         let mut body = vec![];
         while !matches!(self.at().typ, T::End | T::Eof) {
-            body.push(self.parse_statement()?);
+            body.push(self.parse_block_statement()?);
         }
-        let end = self
-            .eat_if_or(match_fn!(TokenType::End), ErrorType::MissingEnd, start)?
-            .location;
+        let end = self.eat_end(start, "`forever` loop")?.location;
         if body.is_empty() {
             return err!(EmptyBlock, start + self.at().location);
         }
@@ -189,19 +310,17 @@ impl Parser {
         use TokenType as T;
         let start = self.eat().location;
         let condition = self.parse_expression()?;
+        let condition = self.ast.push(condition);
         let mut body = vec![];
         while !matches!(self.at().typ, T::End | T::Eof) {
-            body.push(self.parse_statement()?);
+            body.push(self.parse_block_statement()?);
         }
-        let end = self.eat_if_or(match_fn!(T::End), ErrorType::MissingEnd, start)?;
+        let end = self.eat_end(start, "`while` loop")?;
         if body.is_empty() {
             return err!(EmptyBlock, start + self.at().location);
         }
         Ok(Expression {
-            typ: ExpressionType::WhileLoop {
-                condition: Box::from(condition),
-                body,
-            },
+            typ: ExpressionType::WhileLoop { condition, body },
             location: start + end.location,
         })
     }
@@ -212,7 +331,7 @@ impl Parser {
         let token = self.eat();
         let mut imports = vec1::vec1!(match token.typ {
             T::Identifier(symbol) => Ident {
-                symbol,
+                symbol: symbol.to_string(),
                 location: token.location,
             },
             _ => return err!(InvalidModuleName, token.location),
@@ -222,11 +341,11 @@ impl Parser {
             let token = self.eat();
             match token.typ {
                 T::Identifier(symbol) => imports.push(Ident {
-                    symbol,
+                    symbol: symbol.to_string(),
                     location: token.location,
                 }),
                 _ => return err!(InvalidModuleName, token.location),
-            };
+            }
         }
         Ok(Expression {
             location: start + imports.last().location,
@@ -242,7 +361,7 @@ impl Parser {
             T::Identifier(symbol) => Ok(Expression {
                 typ: ExpressionType::VarDeclaration {
                     ident: Ident {
-                        symbol,
+                        symbol: symbol.to_string(),
                         location: token.location,
                     },
                 },
@@ -252,6 +371,61 @@ impl Parser {
         }
     }
 
+    /// `static_assert(condition)` or `static_assert(condition, "message")`
+    fn parse_static_assert(&mut self) -> Res {
+        let start = self.eat().location;
+        self.eat_if(
+            match_fn!(TokenType::OpenFuncParen),
+            ErrorType::MissingOpenParen,
+        )?;
+        let condition = self.parse_expression()?;
+        let message = if matches!(self.at().typ, TokenType::Comma) {
+            self.eat();
+            let token = self.eat();
+            let TokenType::Text(message) = token.typ else {
+                return err!(ExpectedText, token.location);
+            };
+            Some(message.to_string())
+        } else {
+            None
+        };
+        let end = self.eat_if(
+            match_fn!(TokenType::CloseParen),
+            ErrorType::MissingClosingParen,
+        )?;
+        Ok(Expression {
+            typ: ExpressionType::StaticAssert {
+                condition: self.ast.push(condition),
+                message,
+            },
+            location: start + end.location,
+        })
+    }
+
+    fn parse_swap(&mut self) -> Res {
+        let start = self.eat().location;
+        let left = self.parse_swapped()?;
+        self.eat_if(match_fn!(TokenType::Comma), ErrorType::MissingComma)?;
+        let right = self.parse_swapped()?;
+        let location = start + self.ast[right].location;
+        Ok(Expression {
+            typ: ExpressionType::Swap { left, right },
+            location,
+        })
+    }
+
+    /// A variable in a swap statement
+    fn parse_swapped(&mut self) -> Res<ExprId> {
+        let token = self.eat();
+        let TokenType::Identifier(name) = token.typ else {
+            return err!(InvalidDeclartion, token.location);
+        };
+        Ok(self.ast.push(Expression {
+            typ: ExpressionType::Identifier(name.to_string()),
+            location: token.location,
+        }))
+    }
+
     fn parse_inline_declaration(&mut self) -> Res {
         let start = self.eat().location;
         let token = self.eat();
@@ -266,17 +440,17 @@ impl Parser {
         Ok(Expression {
             typ: ExpressionType::InlineDeclaration {
                 ident: Ident {
-                    symbol: ident,
+                    symbol: ident.to_string(),
                     location: token.location,
                 },
-                value: Box::new(value),
+                value: self.ast.push(value),
             },
             location: start + end,
         })
     }
 
     fn parse_expression(&mut self) -> Res {
-        self.parse_assignment()
+        self.nested(Self::parse_assignment)
     }
 
     fn parse_assignment(&mut self) -> Res {
@@ -287,7 +461,7 @@ impl Parser {
                 return err!(InvalidAssignment, self.at().location);
             };
             self.eat();
-            let value = self.parse_assignment()?;
+            let value = self.nested(Self::parse_assignment)?;
             let end = value.location;
             return Ok(Expression {
                 typ: ExpressionType::Assignment {
@@ -295,7 +469,7 @@ impl Parser {
                         symbol: name,
                         location: left.location,
                     },
-                    value: Box::new(value),
+                    value: self.ast.push(value),
                 },
                 location: left.location + end,
             });
@@ -307,20 +481,37 @@ impl Parser {
     fn parse_i_assignment(&mut self) -> Res {
         let left = self.parse_eq_expression()?;
 
+        // `x++` is `x += 1`
+        if let TokenType::Step(operator) = self.at().typ {
+            if !matches!(left.typ, ExpressionType::Identifier(_)) {
+                return err!(InvalidAssignment, left.location);
+            }
+            let step = self.eat().location;
+            let location = left.location + step;
+            return Ok(Expression {
+                typ: ExpressionType::IAssignment {
+                    variable: self.ast.push(left),
+                    value: self.ast.push(Expression {
+                        typ: ExpressionType::NumericLiteral(1),
+                        location: step,
+                    }),
+                    operator,
+                },
+                location,
+            });
+        }
+
         if let TokenType::IOperator(operator) = self.at().typ {
-            let ExpressionType::Identifier(ref name) = left.typ else {
+            if !matches!(left.typ, ExpressionType::Identifier(_)) {
                 return err!(InvalidAssignment, left.location);
-            };
+            }
             self.eat();
-            let value = self.parse_i_assignment()?;
+            let value = self.nested(Self::parse_i_assignment)?;
             let location = left.location + value.location;
             return Ok(Expression {
                 typ: ExpressionType::IAssignment {
-                    ident: Ident {
-                        symbol: name.clone(),
-                        location: left.location,
-                    },
-                    value: Box::new(value),
+                    variable: self.ast.push(left),
+                    value: self.ast.push(value),
                     operator,
                 },
                 location,
@@ -331,7 +522,7 @@ impl Parser {
     }
 
     fn parse_eq_expression(&mut self) -> Res {
-        let mut left = self.parse_additive()?;
+        let mut left = self.parse_bitwise()?;
 
         let mut operator = EqualityOperator::EqualTo; // default, gets overwritten
 
@@ -345,12 +536,12 @@ impl Parser {
             }
         } {
             self.eat();
-            let right = self.parse_additive()?;
+            let right = self.parse_bitwise()?;
             let location = left.location + right.location;
             left = Expression {
                 typ: ExpressionType::EqExpr {
-                    left: Box::from(left),
-                    right: Box::from(right),
+                    left: self.ast.push(left),
+                    right: self.ast.push(right),
                     operator,
                 },
                 location,
@@ -360,57 +551,49 @@ impl Parser {
         Ok(left)
     }
 
-    fn parse_additive(&mut self) -> Res {
-        let mut left = self.parse_multiplicative()?;
-
-        let mut operator = Operator::Plus; // default, gets overwritten
+    fn parse_bitwise(&mut self) -> Res {
+        self.parse_binary(
+            Self::parse_shift,
+            &[Operator::And, Operator::Or, Operator::Xor],
+        )
+    }
 
-        while {
-            match self.at().typ {
-                TokenType::BinaryOperator(op) => {
-                    operator = op;
-                    op == Operator::Plus || op == Operator::Minus
-                }
-                _ => false,
-            }
-        } {
-            self.eat();
-            let right = self.parse_multiplicative()?;
-            let location = left.location + right.location;
-            left = Expression {
-                typ: ExpressionType::BinaryExpr {
-                    left: Box::from(left),
-                    right: Box::from(right),
-                    operator,
-                },
-                location,
-            };
-        }
+    fn parse_shift(&mut self) -> Res {
+        self.parse_binary(
+            Self::parse_additive,
+            &[Operator::ShiftLeft, Operator::ShiftRight],
+        )
+    }
 
-        Ok(left)
+    fn parse_additive(&mut self) -> Res {
+        self.parse_binary(
+            Self::parse_multiplicative,
+            &[Operator::Plus, Operator::Minus],
+        )
     }
 
     fn parse_multiplicative(&mut self) -> Res {
-        let mut left = self.parse_call_member()?;
+        self.parse_binary(
+            Self::parse_unary,
+            &[Operator::Mult, Operator::Div, Operator::Mod],
+        )
+    }
 
-        let mut operator = Operator::Plus; // default, gets overwritten
+    /// Operands are parsed with `operand`, `operators` are left-associative
+    fn parse_binary(&mut self, operand: fn(&mut Self) -> Res, operators: &[Operator]) -> Res {
+        let mut left = operand(self)?;
 
-        while {
-            match self.at().typ {
-                TokenType::BinaryOperator(op) => {
-                    operator = op;
-                    op == Operator::Mult
-                }
-                _ => false,
+        while let TokenType::BinaryOperator(operator) = self.at().typ {
+            if !operators.contains(&operator) {
+                break;
             }
-        } {
             self.eat();
-            let right = self.parse_call_member()?;
+            let right = operand(self)?;
             let location = left.location + right.location;
             left = Expression {
                 typ: ExpressionType::BinaryExpr {
-                    left: Box::from(left),
-                    right: Box::from(right),
+                    left: self.ast.push(left),
+                    right: self.ast.push(right),
                     operator,
                 },
                 location,
@@ -420,6 +603,24 @@ impl Parser {
         Ok(left)
     }
 
+    fn parse_unary(&mut self) -> Res {
+        let operator = match self.at().typ {
+            TokenType::BinaryOperator(Operator::Minus) => UnaryOperator::Negate,
+            TokenType::Not => UnaryOperator::Not,
+            _ => return self.parse_call_member(),
+        };
+        let start = self.eat().location;
+        let value = self.nested(Self::parse_unary)?;
+        let location = start + value.location;
+        Ok(Expression {
+            typ: ExpressionType::UnaryExpr {
+                value: self.ast.push(value),
+                operator,
+            },
+            location,
+        })
+    }
+
     fn parse_call_member(&mut self) -> Res {
         let member = self.parse_member()?;
 
@@ -440,13 +641,13 @@ impl Parser {
         Ok(Expression {
             typ: ExpressionType::Call {
                 args,
-                function: Box::new(caller),
+                function: self.ast.push(caller),
             },
             location,
         })
     }
 
-    fn parse_args(&mut self) -> Result<(Vec<Expression>, Range), Error> {
+    fn parse_args(&mut self) -> Result<(Vec<ExprId>, Range), Error> {
         let start = self
             .eat_if(
                 match_fn!(TokenType::OpenFuncParen),
@@ -470,33 +671,38 @@ impl Parser {
         Ok((args, start + end))
     }
 
-    fn parse_arguments_list(&mut self) -> Result<Vec<Expression>, Error> {
-        let mut args = vec![self.parse_expression()?];
+    fn parse_arguments_list(&mut self) -> Result<Vec<ExprId>, Error> {
+        let mut args = vec![self.parse_argument()?];
 
         while matches!(self.at().typ, TokenType::Comma) {
             self.eat();
-            args.push(self.parse_expression()?);
+            args.push(self.parse_argument()?);
         }
         Ok(args)
     }
 
+    fn parse_argument(&mut self) -> Res<ExprId> {
+        let argument = self.parse_expression()?;
+        Ok(self.ast.push(argument))
+    }
+
     fn parse_member(&mut self) -> Res {
         let mut object = self.parse_primary()?;
 
         while matches!(self.at().typ, TokenType::Dot) {
-            let dot = self.eat().location;
-            let property = self.parse_primary()?;
-
-            let ExpressionType::Identifier(name) = property.typ else {
-                return err!(InvalidDot, dot);
+            // only a name can follow, anything else isn't parsed
+            let TokenType::Identifier(name) = self.peek_n(1).typ else {
+                return err!(InvalidDot, self.at().location);
             };
+            self.eat();
+            let property = self.eat();
 
             let location = object.location + property.location;
             object = Expression {
                 typ: ExpressionType::Member {
-                    object: Box::new(object),
+                    object: self.ast.push(object),
                     property: Ident {
-                        symbol: name,
+                        symbol: name.to_string(),
                         location: property.location,
                     },
                 },
@@ -512,7 +718,7 @@ impl Parser {
 
         Ok(match token.typ {
             TokenType::Identifier(name) => Expression {
-                typ: ExpressionType::Identifier(name),
+                typ: ExpressionType::Identifier(name.to_string()),
                 location: token.location,
             },
             TokenType::Number(value) => Expression {
@@ -0,0 +1,288 @@
+//! Prints a syntax tree as source code again
+//!
+//! The code is canonical: blocks are indented by two spaces, operators have spaces around
+//! them and only the parentheses the parser needs are kept. Comments and blank lines
+//! aren't in the tree, so they're lost
+
+use std::fmt::Write;
+
+use super::{Ast, ExprId, Expression, ExpressionType, Operator, UnaryOperator};
+
+const INDENT: &str = "  ";
+
+/// The statements of a block, one per line
+///
+/// Parsing the code again gives the same tree, except for the locations, `x++`, which is
+/// written `x += 1`, and a negated number, which is parsed as a negative number
+#[must_use]
+pub fn unparse(ast: &Ast, statements: &[ExprId]) -> String {
+    let mut printer = Printer {
+        ast,
+        code: String::new(),
+        indent: 0,
+    };
+    printer.block(statements);
+    printer.code
+}
+
+/// An expression without a line break after it. Blocks inside of it are indented
+/// from the start of the line
+#[must_use]
+pub fn unparse_expression(ast: &Ast, expression: &Expression) -> String {
+    let mut printer = Printer {
+        ast,
+        code: String::new(),
+        indent: 0,
+    };
+    printer.expression(expression, Precedence::Assignment);
+    printer.code
+}
+
+/// How tightly an expression binds, from loosest to tightest
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Assignment,
+    IAssignment,
+    Comparison,
+    Bitwise,
+    Shift,
+    Additive,
+    Multiplicative,
+    Unary,
+    /// Members, calls and everything that can't be split
+    Primary,
+}
+
+impl Precedence {
+    const fn of(expression: &Expression) -> Self {
+        match &expression.typ {
+            ExpressionType::IAssignment { .. } => Self::IAssignment,
+            ExpressionType::EqExpr { .. } => Self::Comparison,
+            ExpressionType::BinaryExpr { operator, .. } => match operator {
+                Operator::And | Operator::Or | Operator::Xor => Self::Bitwise,
+                Operator::ShiftLeft | Operator::ShiftRight => Self::Shift,
+                Operator::Plus | Operator::Minus => Self::Additive,
+                Operator::Mult | Operator::Div | Operator::Mod => Self::Multiplicative,
+            },
+            ExpressionType::UnaryExpr { .. } => Self::Unary,
+            ExpressionType::Identifier(_)
+            | ExpressionType::NumericLiteral(_)
+            | ExpressionType::Member { .. }
+            | ExpressionType::Call { .. }
+            | ExpressionType::Debug => Self::Primary,
+            // statements can't be nested, so they're as loose as possible
+            _ => Self::Assignment,
+        }
+    }
+
+    /// Binary operators are left-associative, so their right side binds tighter
+    const fn tighter(self) -> Self {
+        match self {
+            Self::Assignment => Self::IAssignment,
+            Self::IAssignment => Self::Comparison,
+            Self::Comparison => Self::Bitwise,
+            Self::Bitwise => Self::Shift,
+            Self::Shift => Self::Additive,
+            Self::Additive => Self::Multiplicative,
+            Self::Multiplicative => Self::Unary,
+            Self::Unary | Self::Primary => Self::Primary,
+        }
+    }
+}
+
+struct Printer<'a> {
+    ast: &'a Ast,
+    code: String,
+    /// Depth of the block that is printed
+    indent: usize,
+}
+
+impl Printer<'_> {
+    fn block(&mut self, statements: &[ExprId]) {
+        for &statement in statements {
+            self.code.push_str(&INDENT.repeat(self.indent));
+            self.expression(&self.ast[statement], Precedence::Assignment);
+            self.code.push('\n');
+        }
+    }
+
+    fn nested_block(&mut self, statements: &[ExprId]) {
+        self.code.push('\n');
+        self.indent += 1;
+        self.block(statements);
+        self.indent -= 1;
+        self.code.push_str(&INDENT.repeat(self.indent));
+    }
+
+    /// In parentheses if it binds looser than `min`
+    fn expression(&mut self, expression: &Expression, min: Precedence) {
+        let parens = Precedence::of(expression) < min;
+        if parens {
+            self.code.push('(');
+        }
+        self.unparenthesized(expression);
+        if parens {
+            self.code.push(')');
+        }
+    }
+
+    fn child(&mut self, id: ExprId, min: Precedence) {
+        self.expression(&self.ast[id], min);
+    }
+
+    fn unparenthesized(&mut self, expression: &Expression) {
+        match &expression.typ {
+            ExpressionType::InlineDeclaration { ident, value } => {
+                let _ = write!(self.code, "inline {} = ", ident.symbol);
+                self.child(*value, Precedence::Assignment);
+            }
+            ExpressionType::Use(modules) => {
+                let names: Vec<_> = modules.iter().map(|ident| ident.symbol.as_str()).collect();
+                let _ = write!(self.code, "use {}", names.join("."));
+            }
+            ExpressionType::Conditional {
+                condition,
+                body,
+                paths,
+                alternate,
+            } => self.conditional(*condition, body, paths, alternate.as_deref()),
+            ExpressionType::EndlessLoop { body } => {
+                self.code.push_str("forever");
+                self.nested_block(body);
+                self.code.push_str("end");
+            }
+            ExpressionType::WhileLoop { condition, body } => {
+                self.code.push_str("while ");
+                self.child(*condition, Precedence::Assignment);
+                self.nested_block(body);
+                self.code.push_str("end");
+            }
+            ExpressionType::Pass => self.code.push_str("pass"),
+            ExpressionType::Todo => self.code.push_str("todo"),
+            ExpressionType::BinaryExpr {
+                left,
+                right,
+                operator,
+            } => {
+                let precedence = Precedence::of(expression);
+                self.child(*left, precedence);
+                let _ = write!(self.code, " {} ", operator.symbol());
+                self.child(*right, precedence.tighter());
+            }
+            ExpressionType::EqExpr {
+                left,
+                right,
+                operator,
+            } => {
+                self.child(*left, Precedence::Comparison);
+                let _ = write!(self.code, " {} ", operator.symbol());
+                self.child(*right, Precedence::Comparison.tighter());
+            }
+            ExpressionType::UnaryExpr { value, operator } => self.unary(*value, *operator),
+            ExpressionType::Identifier(name) => self.code.push_str(name),
+            // its absolute value doesn't fit
+            ExpressionType::NumericLiteral(i16::MIN) => self.code.push_str("0x8000"),
+            ExpressionType::NumericLiteral(value) => self.code.push_str(&value.to_string()),
+            ExpressionType::Assignment { ident, value } => {
+                let _ = write!(self.code, "{} = ", ident.symbol);
+                self.child(*value, Precedence::Assignment);
+            }
+            ExpressionType::IAssignment {
+                variable,
+                value,
+                operator,
+            } => {
+                self.child(*variable, Precedence::Primary);
+                let _ = write!(self.code, " {}= ", operator.symbol());
+                self.child(*value, Precedence::IAssignment);
+            }
+            ExpressionType::VarDeclaration { ident } => {
+                let _ = write!(self.code, "var {}", ident.symbol);
+            }
+            ExpressionType::Assert { condition } => {
+                self.code.push_str("assert ");
+                self.child(*condition, Precedence::Assignment);
+            }
+            ExpressionType::StaticAssert { condition, message } => {
+                self.code.push_str("static_assert(");
+                self.child(*condition, Precedence::Assignment);
+                if let Some(message) = message {
+                    let _ = write!(self.code, ", \"{message}\"");
+                }
+                self.code.push(')');
+            }
+            ExpressionType::Wait { ticks } => {
+                self.code.push_str("wait ");
+                self.child(*ticks, Precedence::Assignment);
+            }
+            ExpressionType::Swap { left, right } => {
+                self.code.push_str("swap ");
+                self.child(*left, Precedence::Primary);
+                self.code.push_str(", ");
+                self.child(*right, Precedence::Primary);
+            }
+            ExpressionType::Member { object, property } => {
+                self.child(*object, Precedence::Primary);
+                let _ = write!(self.code, ".{}", property.symbol);
+            }
+            ExpressionType::Call { args, function } => self.call(*function, args),
+            ExpressionType::Debug => self.code.push_str("debug"),
+        }
+    }
+
+    fn conditional(
+        &mut self,
+        condition: ExprId,
+        body: &[ExprId],
+        paths: &[(ExprId, Vec<ExprId>)],
+        alternate: Option<&[ExprId]>,
+    ) {
+        self.code.push_str("if ");
+        self.child(condition, Precedence::Assignment);
+        self.nested_block(body);
+        for (condition, body) in paths {
+            self.code.push_str("elif ");
+            self.child(*condition, Precedence::Assignment);
+            self.nested_block(body);
+        }
+        if let Some(alternate) = alternate {
+            self.code.push_str("else");
+            self.nested_block(alternate);
+        }
+        self.code.push_str("end");
+    }
+
+    fn call(&mut self, function: ExprId, args: &[ExprId]) {
+        self.child(function, Precedence::Primary);
+        self.code.push('(');
+        for (index, &arg) in args.iter().enumerate() {
+            if index > 0 {
+                self.code.push_str(", ");
+            }
+            self.child(arg, Precedence::Assignment);
+        }
+        self.code.push(')');
+    }
+
+    fn unary(&mut self, value: ExprId, operator: UnaryOperator) {
+        let mut operand = Printer {
+            ast: self.ast,
+            code: String::new(),
+            indent: self.indent,
+        };
+        operand.child(value, Precedence::Unary);
+        let value = operand.code;
+        match operator {
+            // `--` is a step and `-5` a number
+            UnaryOperator::Negate if value.starts_with('-') => {
+                let _ = write!(self.code, "-({value})");
+            }
+            UnaryOperator::Negate => {
+                let _ = write!(self.code, "-{value}");
+            }
+            UnaryOperator::Not => {
+                let _ = write!(self.code, "~{value}");
+            }
+        }
+    }
+}
@@ -1,5 +1,13 @@
+#[macro_use]
+mod trace;
+
 pub mod backend;
+pub mod emulator;
 mod error;
+pub mod export;
 pub mod frontend;
+pub mod project;
+
+pub use error::{Error, ErrorType, Fix, Related};
 
-pub use error::Error;
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -1,6 +1,7 @@
 use std::{
     collections::VecDeque,
     env,
+    fmt::Write as _,
     fs::{self, create_dir_all, File},
     io::{self, Read, Write},
 };
@@ -56,6 +57,9 @@ fn main() -> io::Result<()> {
     args.pop_front();
 
     let debug = has_arg(&mut args, "--dbg");
+    if has_arg(&mut args, "--verbose") {
+        init_tracing();
+    }
 
     let program = match args.pop_front() {
         None => input("Enter program or leave empty for repl: ")?,
@@ -98,11 +102,12 @@ fn main() -> io::Result<()> {
     }
 
     let mut parser = Parser::new();
-    let ast = match parser.produce_ast(tokens) {
+    let ast = match parser.produce_ast(&tokens) {
         Ok(ast) => ast,
         Err(errs) => {
-            errs.into_iter()
-                .for_each(|err| err.pretty_print(code.as_str(), path.as_str()));
+            for err in errs {
+                err.pretty_print(code.as_str(), path.as_str());
+            }
             return Ok(());
         }
     };
@@ -110,7 +115,7 @@ fn main() -> io::Result<()> {
         println!("{ast:#?}");
     }
 
-    let assembly = match compile_program(ast) {
+    let assembly = match compile_program(&ast) {
         Ok(assembly) => assembly,
         Err(errs) => {
             for err in errs {
@@ -142,14 +147,14 @@ fn main() -> io::Result<()> {
         for instr in &assembly {
             let line_s = (instr.orig_location.0 .0, instr.orig_location.1 .0);
             if last != Some(line_s) {
-                locations.push_str(&if line_s.0 == line_s.1 {
-                    format!("{}:\n", line_s.0 + 1)
+                if line_s.0 == line_s.1 {
+                    let _ = writeln!(locations, "{}:", line_s.0 + 1);
                 } else {
-                    format!("{}-{}:\n", line_s.0 + 1, line_s.1 + 1)
-                });
+                    let _ = writeln!(locations, "{}-{}:", line_s.0 + 1, line_s.1 + 1);
+                }
                 last = Some(line_s);
             }
-            locations.push_str(&format!("\t{instr}\n"));
+            let _ = writeln!(locations, "\t{instr}");
         }
         fs::write(format!("{dir}/{program}.loc"), locations)?;
     }
@@ -164,6 +169,23 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+/// Prints how long each phase of the compiler took to stderr
+#[cfg(feature = "verbose")]
+fn init_tracing() {
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    tracing_subscriber::fmt()
+        .with_span_events(FmtSpan::CLOSE)
+        .with_target(false)
+        .with_writer(io::stderr)
+        .init();
+}
+
+#[cfg(not(feature = "verbose"))]
+fn init_tracing() {
+    eprintln!("--verbose needs the compiler to be built with the verbose feature");
+}
+
 fn input(prompt: &str) -> Result<String, io::Error> {
     let mut contents = String::new();
     print!("{prompt}");
@@ -173,7 +195,6 @@ fn input(prompt: &str) -> Result<String, io::Error> {
 }
 
 fn repl() -> io::Result<()> {
-    let mut parser = Parser::new();
     println!("Repl v{VERSION}");
     loop {
         let line = input("> ")?;
@@ -191,19 +212,20 @@ fn repl() -> io::Result<()> {
         };
         println!("{tokens:#?}");
 
-        let parser_result = parser.produce_ast(tokens);
+        let parser_result = Parser::new().produce_ast(&tokens);
 
         let ast = match parser_result {
             Ok(ast) => ast,
             Err(errs) => {
-                errs.into_iter()
-                    .for_each(|err| err.pretty_print(&line, "Repl"));
+                for err in errs {
+                    err.pretty_print(&line, "Repl");
+                }
                 continue;
             }
         };
         println!("{ast:#?}");
 
-        let code = compile_program(ast);
+        let code = compile_program(&ast);
         match code {
             Ok(code) => println!("{code:#?}"),
             Err(err) => err.into_iter().for_each(|err| {
@@ -0,0 +1,59 @@
+use crate::error::ErrorType;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Type {
+    ExpectedKey,
+    Expected(char),
+    ExpectedValue,
+    UnexpectedCharacter(char),
+    UnclosedString,
+    InvalidEscape(char),
+    InvalidInteger(String),
+    DuplicateKey(String),
+    UnknownKey(String),
+    MissingKey(String),
+    InvalidValue(String, &'static str),
+    UnknownTarget(String),
+    InvalidOptLevel(i64),
+    InvalidPageSize(i64),
+}
+
+impl ErrorType for Type {
+    fn get_message(&self) -> String {
+        match self {
+            Self::ExpectedKey => "Expected a key or a [table]".to_string(),
+            Self::Expected(char) => format!("Expected {char:?}"),
+            Self::ExpectedValue => "Expected a string, number, boolean or array".to_string(),
+            Self::UnexpectedCharacter(char) => format!("Unexpected {char:?}"),
+            Self::UnclosedString => "The string isn't closed".to_string(),
+            Self::InvalidEscape(char) => format!("\\{char} is not a valid escape"),
+            Self::InvalidInteger(text) => format!("{text} is not a valid integer"),
+            Self::DuplicateKey(key) => format!("{key} is already defined"),
+            Self::UnknownKey(key) => format!("{key} is not a manifest setting"),
+            Self::MissingKey(key) => format!("The manifest needs {key}"),
+            Self::InvalidValue(key, expected) => format!("{key} has to be {expected}"),
+            Self::UnknownTarget(name) => format!("There is no target called {name}"),
+            Self::InvalidOptLevel(level) => format!("{level} is not an optimization level"),
+            Self::InvalidPageSize(size) => format!("{size} is not a valid page size"),
+        }
+    }
+
+    fn get_code(&self) -> &'static str {
+        match self {
+            Self::ExpectedKey => "expected-key",
+            Self::Expected(_) => "expected-character",
+            Self::ExpectedValue => "expected-value",
+            Self::UnexpectedCharacter(_) => "unexpected-character",
+            Self::UnclosedString => "unclosed-string",
+            Self::InvalidEscape(_) => "invalid-escape",
+            Self::InvalidInteger(_) => "invalid-integer",
+            Self::DuplicateKey(_) => "duplicate-key",
+            Self::UnknownKey(_) => "unknown-key",
+            Self::MissingKey(_) => "missing-key",
+            Self::InvalidValue(..) => "invalid-value",
+            Self::UnknownTarget(_) => "unknown-target",
+            Self::InvalidOptLevel(_) => "invalid-opt-level",
+            Self::InvalidPageSize(_) => "invalid-page-size",
+        }
+    }
+}
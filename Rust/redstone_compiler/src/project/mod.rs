@@ -0,0 +1,176 @@
+//! The `mcn.toml` manifest of a project, read by the command-line compiler and the
+//! language server so both compile the same way
+
+mod error;
+pub(crate) mod toml;
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::{
+    backend::{CompileOptions, OptLevel, Target},
+    err,
+    frontend::Range,
+    Error,
+};
+
+use error::Type as ErrorType;
+use toml::{Entry, Kind, Value};
+
+/// Name of the manifest in the root directory of a project
+pub const MANIFEST_FILE: &str = "mcn.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub name: String,
+    pub target: &'static Target,
+    /// The program that gets compiled, relative to the manifest
+    pub entry: String,
+    /// More files and directories with programs of the project, relative to the manifest.
+    /// They are checked together with the entry
+    pub includes: Vec<String>,
+    pub opt_level: OptLevel,
+    /// Overrides the page size of the target
+    pub page_size: Option<usize>,
+}
+
+impl Manifest {
+    /// The manifest `mcnc new` creates
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            target: &Target::MCN_16,
+            entry: "src/main.mcn".to_string(),
+            includes: vec!["src".to_string()],
+            opt_level: OptLevel::default(),
+            page_size: None,
+        }
+    }
+
+    /// # Errors
+    ///
+    /// If it isn't valid TOML, a setting is unknown or has the wrong type, or the name
+    /// is missing
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let entries = toml::parse(text)?;
+        let mut manifest = Self::new(String::new());
+        let mut has_name = false;
+        for Entry {
+            key,
+            location,
+            value,
+        } in entries
+        {
+            match key.as_str() {
+                "project.name" => {
+                    manifest.name = string(&key, value)?;
+                    has_name = true;
+                }
+                "project.target" => {
+                    let name = string(&key, value.clone())?;
+                    manifest.target = Target::by_name(&name)
+                        .map_or_else(|| err!(ErrorType::UnknownTarget(name), value.location), Ok)?;
+                }
+                "project.entry" => manifest.entry = string(&key, value)?,
+                "project.includes" => {
+                    let Kind::Array(values) = value.kind else {
+                        return err!(
+                            ErrorType::InvalidValue(key, "an array of strings"),
+                            value.location
+                        );
+                    };
+                    manifest.includes = values
+                        .into_iter()
+                        .map(|value| string(&key, value))
+                        .collect::<Result<_, _>>()?;
+                }
+                "options.opt-level" => {
+                    manifest.opt_level = match integer(&key, &value)? {
+                        0 => OptLevel::O0,
+                        1 => OptLevel::O1,
+                        level => return err!(ErrorType::InvalidOptLevel(level), value.location),
+                    };
+                }
+                "options.page-size" => {
+                    let size = integer(&key, &value)?;
+                    manifest.page_size = Some(
+                        usize::try_from(size)
+                            .ok()
+                            .filter(|size| (1..=256).contains(size))
+                            .map_or_else(
+                                || err!(ErrorType::InvalidPageSize(size), value.location),
+                                Ok,
+                            )?,
+                    );
+                }
+                _ => return err!(ErrorType::UnknownKey(key), location),
+            }
+        }
+        if !has_name {
+            return err!(
+                ErrorType::MissingKey("project.name".to_string()),
+                Range::default()
+            );
+        }
+        Ok(manifest)
+    }
+
+    #[must_use]
+    pub fn compile_options(&self) -> CompileOptions {
+        CompileOptions {
+            opt_level: self.opt_level,
+            target: Target {
+                page_size: self.page_size.unwrap_or(self.target.page_size),
+                ..*self.target
+            },
+            ..CompileOptions::default()
+        }
+    }
+}
+
+fn string(key: &str, value: Value) -> Result<String, Error> {
+    match value.kind {
+        Kind::String(string) => Ok(string),
+        _ => err!(
+            ErrorType::InvalidValue(key.to_string(), "a string"),
+            value.location
+        ),
+    }
+}
+
+fn integer(key: &str, value: &Value) -> Result<i64, Error> {
+    match value.kind {
+        Kind::Integer(integer) => Ok(integer),
+        _ => err!(
+            ErrorType::InvalidValue(key.to_string(), "an integer"),
+            value.location
+        ),
+    }
+}
+
+/// Writes it in the format [`Manifest::parse`] reads
+impl Display for Manifest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[project]")?;
+        writeln!(f, "name = {:?}", self.name)?;
+        writeln!(f, "target = {:?}", self.target.name)?;
+        writeln!(f, "entry = {:?}", self.entry)?;
+        let includes: Vec<_> = self
+            .includes
+            .iter()
+            .map(|include| format!("{include:?}"))
+            .collect();
+        writeln!(f, "includes = [{}]", includes.join(", "))?;
+        writeln!(f)?;
+        writeln!(f, "[options]")?;
+        let level = match self.opt_level {
+            OptLevel::O0 => 0,
+            OptLevel::O1 => 1,
+        };
+        writeln!(f, "opt-level = {level}")?;
+        if let Some(size) = self.page_size {
+            writeln!(f, "page-size = {size}")?;
+        }
+        Ok(())
+    }
+}
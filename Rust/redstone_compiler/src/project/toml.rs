@@ -0,0 +1,237 @@
+//! The subset of TOML manifests are written in: tables and keys with strings, integers,
+//! booleans or arrays of them, each on a single line
+
+use crate::{
+    err,
+    frontend::{Location, Range},
+    Error,
+};
+
+use super::ErrorType;
+
+type Res<T> = Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Value {
+    pub kind: Kind,
+    pub location: Range,
+}
+
+/// A key with the table it is in, like `options.opt-level`
+#[derive(Debug)]
+pub struct Entry {
+    pub key: String,
+    pub location: Range,
+    pub value: Value,
+}
+
+/// Every key in the order it appears
+///
+/// # Errors
+///
+/// If the text is not in the supported subset or a key is defined twice
+pub fn parse(text: &str) -> Res<Vec<Entry>> {
+    let mut entries: Vec<Entry> = vec![];
+    let mut tables = vec![];
+    let mut table = String::new();
+    for (index, line) in text.lines().enumerate() {
+        let mut line = Line {
+            chars: line.chars().collect(),
+            index: 0,
+            number: index as u16,
+        };
+        line.skip_whitespace();
+        match line.peek() {
+            None | Some('#') => continue,
+            Some('[') => {
+                line.index += 1;
+                line.skip_whitespace();
+                let (name, location) = line.key()?;
+                line.skip_whitespace();
+                line.expect(']')?;
+                if tables.contains(&name) {
+                    return err!(ErrorType::DuplicateKey(name), location);
+                }
+                tables.push(name.clone());
+                table = name;
+            }
+            Some(_) => {
+                let (name, location) = line.key()?;
+                line.skip_whitespace();
+                line.expect('=')?;
+                line.skip_whitespace();
+                let value = line.value()?;
+                let key = if table.is_empty() {
+                    name
+                } else {
+                    format!("{table}.{name}")
+                };
+                if entries.iter().any(|entry| entry.key == key) {
+                    return err!(ErrorType::DuplicateKey(key), location);
+                }
+                entries.push(Entry {
+                    key,
+                    location,
+                    value,
+                });
+            }
+        }
+        line.skip_whitespace();
+        match line.peek() {
+            None | Some('#') => {}
+            Some(char) => return err!(ErrorType::UnexpectedCharacter(char), line.here()),
+        }
+    }
+    Ok(entries)
+}
+
+struct Line {
+    chars: Vec<char>,
+    index: usize,
+    number: u16,
+}
+
+impl Line {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.peek();
+        self.index += 1;
+        char
+    }
+
+    const fn location(&self, index: usize) -> Location {
+        Location(self.number, index as u16 + 1)
+    }
+
+    const fn here(&self) -> Range {
+        Range::single_char(self.location(self.index))
+    }
+
+    /// From `start` to the last character that was read
+    fn since(&self, start: usize) -> Range {
+        Range(
+            self.location(start),
+            self.location(self.index.max(start + 1) - 1),
+        )
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|char| char == ' ' || char == '\t') {
+            self.index += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Res<()> {
+        if self.peek() != Some(expected) {
+            return err!(ErrorType::Expected(expected), self.here());
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let start = self.index;
+        while self.peek().is_some_and(&predicate) {
+            self.index += 1;
+        }
+        self.chars[start..self.index].iter().collect()
+    }
+
+    /// A bare key of letters, digits, `-` and `_`
+    fn key(&mut self) -> Res<(String, Range)> {
+        let start = self.index;
+        let key =
+            self.take_while(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_');
+        if key.is_empty() {
+            return err!(ErrorType::ExpectedKey, self.here());
+        }
+        Ok((key, self.since(start)))
+    }
+
+    fn value(&mut self) -> Res<Value> {
+        let start = self.index;
+        let kind = match self.peek() {
+            Some('"') => Kind::String(self.string()?),
+            Some('[') => Kind::Array(self.array()?),
+            Some(char) if char.is_ascii_digit() || char == '+' || char == '-' => {
+                let text = self
+                    .take_while(|char| char.is_ascii_digit() || matches!(char, '+' | '-' | '_'));
+                let integer = text.replace('_', "").parse().map_err(|_| Error {
+                    typ: Box::new(ErrorType::InvalidInteger(text.clone())),
+                    location: self.since(start),
+                    related: vec![],
+                })?;
+                Kind::Integer(integer)
+            }
+            Some(char) if char.is_ascii_alphabetic() => {
+                match self
+                    .take_while(|char| char.is_ascii_alphanumeric())
+                    .as_str()
+                {
+                    "true" => Kind::Boolean(true),
+                    "false" => Kind::Boolean(false),
+                    _ => return err!(ErrorType::ExpectedValue, self.since(start)),
+                }
+            }
+            _ => return err!(ErrorType::ExpectedValue, self.here()),
+        };
+        Ok(Value {
+            kind,
+            location: self.since(start),
+        })
+    }
+
+    fn string(&mut self) -> Res<String> {
+        let start = self.index;
+        self.index += 1;
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(match self.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(char) => {
+                        self.index -= 1;
+                        return err!(ErrorType::InvalidEscape(char), self.since(self.index - 1));
+                    }
+                    None => break,
+                }),
+                Some(char) => string.push(char),
+                None => break,
+            }
+        }
+        self.index = self.chars.len();
+        err!(ErrorType::UnclosedString, self.since(start))
+    }
+
+    fn array(&mut self) -> Res<Vec<Value>> {
+        self.index += 1;
+        let mut values = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(']') {
+                self.index += 1;
+                return Ok(values);
+            }
+            values.push(self.value()?);
+            self.skip_whitespace();
+            if self.peek() != Some(']') {
+                self.expect(',')?;
+            }
+        }
+    }
+}
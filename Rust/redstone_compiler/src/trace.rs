@@ -0,0 +1,10 @@
+//! Spans for the phases of the compiler with the `tracing` feature, a subscriber gets
+//! how long each of them took. Without the feature they compile to nothing
+
+/// Enters a span for a phase until the end of the enclosing block
+macro_rules! phase {
+    ($name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _phase = tracing::info_span!($name $(, $($fields)*)?).entered();
+    };
+}
@@ -0,0 +1,102 @@
+mod assembly_tests {
+    use redstone_compiler::{
+        backend::{
+            assemble, assemble_with, compile_program, disassemble, parse_assembly, Instruction,
+            InvalidWord, Target,
+        },
+        emulator::{Emulator, StopReason},
+        frontend::{tokenize, Location, Parser, Range},
+    };
+
+    fn compile(code: &str) -> Vec<Instruction> {
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        compile_program(&ast).expect("Code to compile")
+    }
+
+    fn parts(instructions: &[Instruction]) -> Vec<(&'static str, Option<u8>)> {
+        instructions
+            .iter()
+            .map(|i| (i.variant.name(), i.arg))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let instructions = compile("use io\na = 300\nwhile a > 2\n  io.write(a, 1)\nend");
+        let lines: Vec<_> = instructions.iter().map(ToString::to_string).collect();
+        let text = lines.join("\n");
+        let parsed = parse_assembly(&text).expect("Assembly to parse");
+        assert_eq!(parts(&parsed), parts(&instructions));
+
+        let binary: Vec<_> = instructions.iter().map(Instruction::to_bin).collect();
+        let decoded = disassemble(&binary).expect("Binary to decode");
+        assert_eq!(parts(&decoded), parts(&instructions));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            disassemble(&[0x0106, 0x0001]),
+            Err(InvalidWord {
+                address: 1,
+                word: 0x0001
+            })
+        );
+
+        let errors = parse_assembly("lal 0x10 ; comment\n\nFOO\nADD 1\nLA 256").unwrap_err();
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|error| (error.typ.get_code(), error.location))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("unknown-mnemonic", Range(Location(2, 1), Location(2, 3))),
+                ("unexpected-arg", Range(Location(3, 5), Location(3, 5))),
+                ("invalid-arg", Range(Location(4, 4), Location(4, 6))),
+            ]
+        );
+    }
+
+    #[test]
+    fn labels() {
+        let code = "  LAL 3\nloop: SVA 0\n  LBL 1\n  SUB\n  LBL 0\n  JG loop\ndone:\n  SVA 33";
+        let target = Target {
+            page_size: 4,
+            ..Target::MCN_16
+        };
+        let instructions = assemble_with(code, target).expect("Assembly to assemble");
+        assert_eq!(
+            parts(&instructions)[5..],
+            [("LCL", Some(0)), ("JDG", Some(1)), ("SVA", Some(33))]
+        );
+        assert_eq!(format!("{}", instructions[1]), "loop: SVA 0");
+        assert_eq!(format!("{}", instructions[6]), "JDG 1 ; loop");
+        assert_eq!(instructions[7].label.as_deref(), Some("done"));
+
+        let mut emulator = Emulator::new(&instructions, target);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        assert_eq!(emulator.state().outputs[1], 0);
+        assert_eq!(emulator.state().slots[0], 1);
+
+        let errors = assemble("a: JMP b\na: NON\n1x: JE 0x").unwrap_err();
+        assert_eq!(
+            errors[0].related[0].location,
+            Range(Location(0, 1), Location(0, 2))
+        );
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|error| (error.typ.get_code(), error.location))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("duplicate-label", Range(Location(1, 1), Location(1, 2))),
+                ("invalid-label", Range(Location(2, 1), Location(2, 3))),
+                ("invalid-arg", Range(Location(2, 8), Location(2, 9))),
+                ("unknown-label", Range(Location(0, 8), Location(0, 8))),
+            ]
+        );
+    }
+}
@@ -0,0 +1,644 @@
+mod compiler_tests {
+    use std::collections::HashMap;
+
+    use redstone_compiler::{
+        backend::{
+            compile_program, compile_program_ir, compile_program_with,
+            compile_program_with_debug_info, compile_program_with_symbols, eval_constant, Cache,
+            CompileOptions, ComputerState, Instr, Instruction, InstructionVariant, OptLevel,
+            RamPage, RegisterContents, SourceMap, Target, Warning, WarningType,
+        },
+        frontend::{tokenize, unparse, Ast, Expression, ExpressionType, Location, Parser, Range},
+        Fix, Related,
+    };
+
+    fn compile(code: &str) -> Vec<Instruction> {
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        compile_program(&ast).expect("Code to compile")
+    }
+
+    fn variants(instructions: &[Instruction]) -> Vec<&'static str> {
+        instructions.iter().map(|i| i.variant.name()).collect()
+    }
+
+    #[test]
+    fn opt_levels() {
+        let code = "a = 5\nb = a";
+        assert_eq!(variants(&compile(code)), ["LAL", "SVA", "SVA"]);
+
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let options = CompileOptions {
+            opt_level: OptLevel::O0,
+            ..CompileOptions::default()
+        };
+        let unoptimized = compile_program_with(&ast, options).expect("Code to compile");
+        assert_eq!(variants(&unoptimized), ["LAL", "SVA", "LA", "SVA"]);
+    }
+
+    #[test]
+    fn ir_without_jumps_is_linked_code() {
+        fn flatten(ir: &[Instr], into: &mut Vec<&'static str>) {
+            for instr in ir {
+                match instr {
+                    Instr::Code(instruction) => into.push(instruction.variant.name()),
+                    Instr::Scope(scope) => flatten(scope, into),
+                }
+            }
+        }
+
+        let code = "a = 5\nb = a + 3";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let ir = compile_program_ir(&ast, CompileOptions::default()).expect("Code to compile");
+        let mut names = vec![];
+        flatten(&ir, &mut names);
+        assert_eq!(names, variants(&compile(code)));
+        assert_eq!(Instr::count(&ir), names.len());
+    }
+
+    #[test]
+    fn source_map_block() {
+        let code = "a = 1\nforever\n  a += 2\nend\nb = 3";
+        let instructions = compile(code);
+        let map = SourceMap::new(&instructions);
+        assert_eq!(map.len(), instructions.len());
+
+        let block = Range(Location(1, 1), Location(3, 3));
+        let addresses: Vec<_> = map.addresses_in(block).collect();
+        let variants: Vec<_> = addresses
+            .iter()
+            .map(|&address| instructions[address].variant.name())
+            .collect();
+        assert_eq!(variants, ["LA", "LBL", "ADD", "SVA", "JMP"]);
+    }
+
+    #[test]
+    fn symbols() {
+        let code = "a = 1\nforever\n  b = a\nend";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let (_, symbols) =
+            compile_program_with_symbols(&ast, CompileOptions::default()).expect("Code to compile");
+        let symbols: Vec<_> = symbols
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.slot, symbol.depth))
+            .collect();
+        assert_eq!(symbols, [("a", 0, 0), ("b", 1, 1)]);
+    }
+
+    #[test]
+    fn compilation_result() {
+        let code = "use io\na = 1\nwhile a < 3\n  b = a\n  a += 1\nend\nio.write(a, 0)";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let result = redstone_compiler::backend::compile(&ast, CompileOptions::default())
+            .expect("Code to compile");
+        let symbols: Vec<_> = result
+            .symbols()
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.slot, symbol.depth))
+            .collect();
+        assert_eq!(symbols, [("a", 0, 0), ("b", 1, 1)]);
+        assert_eq!(result.modules_used(), ["io"]);
+        // the start of the loop's body and the code after the loop
+        assert_eq!(result.jump_targets(), [4, 12]);
+        let after_loop = result
+            .instruction_for_offset(12)
+            .expect("Code after the loop");
+        assert_eq!(after_loop.variant.name(), "SVA");
+        let last = result.instructions().len() - 1;
+        assert_eq!(
+            result.instruction_for_offset(last),
+            result.instructions().last()
+        );
+        assert_eq!(result.instruction_for_offset(last + 1), None);
+    }
+
+    #[test]
+    fn computer_state() {
+        let code = "a = 5\nb = a + 2\nwhile b > 0\n  b -= 1\nend";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let result = redstone_compiler::backend::compile(&ast, CompileOptions::default())
+            .expect("Code to compile");
+        let state = result.state_before(4);
+        assert_eq!(state.a, RegisterContents::Unknown);
+        assert_eq!(state.b, RegisterContents::Number(2));
+        assert_eq!(state.ram_page, RamPage::ThisOne(0));
+        // the comparison of the loop
+        assert_eq!(result.state_before(6).a, RegisterContents::Variable(1));
+        assert_eq!(result.state_before(6).b, RegisterContents::Number(0));
+        // the body of the loop is a jump target
+        assert_eq!(result.state_before(7), ComputerState::unknown());
+        assert_eq!(result.state_before(9).b, RegisterContents::Number(1));
+
+        let mut state = ComputerState::default();
+        Instruction::new(InstructionVariant::LAL, Some(3), Range::default()).execute(&mut state);
+        assert_eq!(state.a, RegisterContents::Number(3));
+        Instruction::new(InstructionVariant::SVA, Some(2), Range::default()).execute(&mut state);
+        assert_eq!(state.a, RegisterContents::Variable(2));
+
+        // like the emulator: the high byte is replaced and B wraps around
+        let program = [
+            Instruction::new(InstructionVariant::LAL, Some(52), Range::default()),
+            Instruction::new(InstructionVariant::LAH, Some(18), Range::default()),
+            Instruction::new(InstructionVariant::LAH, Some(86), Range::default()),
+            Instruction::new(InstructionVariant::LBL, Some(255), Range::default()),
+            Instruction::new(InstructionVariant::LBH, Some(127), Range::default()),
+            Instruction::new(InstructionVariant::INB, None, Range::default()),
+        ];
+        let state = ComputerState::default().after(&program);
+        assert_eq!(state.a, RegisterContents::Number(22068));
+        assert_eq!(state.b, RegisterContents::Number(i16::MIN));
+    }
+
+    #[test]
+    fn instructions_in() {
+        let code = "a = 5\nb = a + 2\nwhile b > 0\n  b -= 1\nend";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let result = redstone_compiler::backend::compile(&ast, CompileOptions::default())
+            .expect("Code to compile");
+        let body = Range(Location(3, 1), Location(3, u16::MAX));
+        assert_eq!(
+            variants(result.instructions_in(body)),
+            ["LA", "LBL", "SUB", "SVA"]
+        );
+        let whole = Range(Location(0, 1), Location(4, u16::MAX));
+        assert_eq!(result.instructions_in(whole), result.instructions());
+        let after = Range(Location(5, 1), Location(5, u16::MAX));
+        assert!(result.instructions_in(after).is_empty());
+        let source_map = &result.debug_info().source_map;
+        for line in 0..5 {
+            let range = Range(Location(line, 1), Location(line, u16::MAX));
+            let scanned: Vec<_> = (0..source_map.len())
+                .filter(|&address| range.contains_range(source_map.location_of(address).unwrap()))
+                .collect();
+            assert_eq!(source_map.addresses_in(range).collect::<Vec<_>>(), scanned);
+        }
+    }
+
+    #[test]
+    fn annotations() {
+        let code = "a = 1\nwhile a < 3\n  a += 1\nend";
+        let lines: Vec<_> = compile(code).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "LAL 1 ; a = 1",
+                "SVA 0 ; a",
+                "LBL 3 ; while a < 3",
+                "JGE 10 ; end",
+                "L4: LA 0 ; a += 1",
+                "LBL 1",
+                "ADD",
+                "SVA 0 ; a",
+                "LBL 3",
+                "JL 4 ; L4",
+            ]
+        );
+    }
+
+    #[test]
+    fn ast_arena() {
+        let code = "a = 1 + 2\nwhile a > 0\n  a -= 1\nend";
+        let ast = Parser::new()
+            .produce_ast(&tokenize(code).expect("Code to tokenize"))
+            .expect("Code to parse");
+        assert_eq!(ast.body().len(), 2);
+        // 1, 2, 1 + 2, the assignment, a, 0, a > 0, a, 1, a -= 1 and the loop
+        assert_eq!(ast.len(), 11);
+        let ExpressionType::WhileLoop { condition, body } = &ast[ast.body()[1]].typ else {
+            panic!("Expected a loop");
+        };
+        assert!(matches!(ast[*condition].typ, ExpressionType::EqExpr { .. }));
+        assert_eq!(body.len(), 1);
+
+        // it's only borrowed, so it can be compiled again
+        let optimized = compile_program(&ast).expect("Code to compile");
+        let options = CompileOptions {
+            opt_level: OptLevel::O0,
+            ..CompileOptions::default()
+        };
+        let unoptimized = compile_program_with(&ast, options).expect("Code to compile");
+        assert!(optimized.len() < unoptimized.len());
+    }
+
+    #[test]
+    fn incremental() {
+        let versions = [
+            "use list\na = 1\nif a > 0\n  a += 2\nend\nwhile a > 0\n  list.add(a)\nend",
+            // only the body of the conditional changed
+            "use list\na = 1\nif a > 0\n  a += 3\n  b = a\nend\nwhile a > 0\n  list.add(a)\nend",
+            // everything after the new line moved
+            "use list\n\na = 1\nif a > 0\n  a += 3\n  b = a\nend\nwhile a > 0\n  list.add(a)\nend",
+        ];
+        let mut cache = Cache::new();
+        let mut reused = vec![];
+        for code in versions {
+            let tokens = tokenize(code).expect("Code to tokenize");
+            let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+            let incremental = cache
+                .compile(&ast, &tokens, CompileOptions::default())
+                .expect("Code to compile");
+            let fresh = compile_program_with_debug_info(&ast, CompileOptions::default())
+                .expect("Code to compile");
+            assert_eq!(incremental, fresh);
+            reused.push(cache.reused());
+        }
+        // the loop after the changed conditional isn't reused, A holds another variable there
+        assert_eq!(reused, [0, 1, 3]);
+    }
+
+    #[test]
+    fn nesting_too_deep() {
+        let parse = |code: &str| {
+            let tokens = tokenize(code).expect("Code to tokenize");
+            Parser::new()
+                .produce_ast(&tokens)
+                .map(|_| ())
+                .map_err(|errors| errors[0].typ.get_code())
+        };
+        assert_eq!(
+            parse(&format!("a = {}1{}", "( ".repeat(20), " )".repeat(20))),
+            Ok(())
+        );
+        let parens = format!("a = {}1{}", "( ".repeat(1000), " )".repeat(1000));
+        assert_eq!(parse(&parens), Err("nesting-too-deep"));
+        let blocks = format!("{}pass\n{}", "forever\n".repeat(1000), "end\n".repeat(1000));
+        assert_eq!(parse(&blocks), Err("nesting-too-deep"));
+    }
+
+    #[test]
+    fn missing_end() {
+        let tokens = tokenize("a = 1\nwhile a\n  a--\n").expect("Code to tokenize");
+        let errors = Parser::new()
+            .produce_ast(&tokens)
+            .expect_err("The loop to miss its end");
+        assert_eq!(errors[0].typ.get_code(), "missing-end");
+        assert_eq!(errors[0].location.0 .0, 3);
+        assert_eq!(
+            errors[0].related[0].location,
+            Range(Location(1, 1), Location(1, 5))
+        );
+
+        let tokens = tokenize("if 1\n  pass\nelif 2\n  pass").expect("Code to tokenize");
+        let errors = Parser::new()
+            .produce_ast(&tokens)
+            .expect_err("The if to miss its end");
+        assert_eq!(errors[0].typ.get_code(), "missing-end");
+        assert_eq!(
+            errors[0].related[0].location,
+            Range(Location(0, 1), Location(0, 2))
+        );
+    }
+
+    #[test]
+    fn module_fixes() {
+        let fixes = |code: &str| {
+            let tokens = tokenize(code).expect("Code to tokenize");
+            let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+            let errors = compile_program(&ast).expect_err("Code not to compile");
+            errors[0].typ.fixes()
+        };
+        assert_eq!(fixes("screen.clear()"), [Fix::AddUse("screen".to_string())]);
+        assert_eq!(
+            fixes("use io\nscren.clear()"),
+            [
+                Fix::Rename {
+                    from: "scren".to_string(),
+                    to: "screen".to_string()
+                },
+                Fix::AddUse("screen".to_string())
+            ]
+        );
+        assert_eq!(
+            fixes("use scren"),
+            [Fix::Rename {
+                from: "scren".to_string(),
+                to: "screen".to_string()
+            }]
+        );
+        assert_eq!(fixes("use keyboard"), []);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use redstone_compiler::frontend::{Ast, Token};
+
+        let code = "use io\ninline W = 4\nforever\n  a = in(1) * W\n  io.write(a, 2)\nend";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let json = serde_json::to_string(&tokens).unwrap();
+        let deserialized: Vec<Token> = serde_json::from_str(&json).unwrap();
+        assert!(deserialized == tokens);
+
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let json = serde_json::to_string(&ast).unwrap();
+        let deserialized: Ast = serde_json::from_str(&json).unwrap();
+        let instructions = compile_program(&ast).expect("Code to compile");
+        assert_eq!(compile_program(&deserialized).unwrap(), instructions);
+
+        let json = serde_json::to_string(&instructions).unwrap();
+        let deserialized: Vec<Instruction> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, instructions);
+
+        let tokens = tokenize("screen.clear()").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let errors = compile_program(&ast).expect_err("Code not to compile");
+        let json = serde_json::to_value(&errors[0]).unwrap();
+        assert_eq!(json["code"], "unloaded-module");
+        assert_eq!(json["fixes"][0]["addUse"], "screen");
+    }
+
+    #[test]
+    fn steps() {
+        let words =
+            |code: &str| -> Vec<u16> { compile(code).iter().map(Instruction::to_bin).collect() };
+        assert_eq!(
+            words("a = 5\na++\nb = 2\nb--"),
+            words("a = 5\na += 1\nb = 2\nb -= 1")
+        );
+        let tokens = tokenize("5++").expect("Code to tokenize");
+        let errors = Parser::new()
+            .produce_ast(&tokens)
+            .expect_err("Only variables to step");
+        assert_eq!(errors[0].typ.get_code(), "invalid-assignment");
+    }
+
+    #[test]
+    fn eval_constants() {
+        let tokens = tokenize("a = W * 2 + 1\nb = in(1) + 1").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let value = |statement: &Expression| match statement.typ {
+            ExpressionType::Assignment { value, .. } => &ast[value],
+            _ => unreachable!("Only assignments in the program"),
+        };
+        let statements: Vec<_> = ast.statements().collect();
+        let inline_variables = HashMap::from([("W".to_string(), 4)]);
+        assert_eq!(
+            eval_constant(&ast, value(statements[0]), &inline_variables).ok(),
+            Some(9)
+        );
+        assert!(eval_constant(&ast, value(statements[0]), &HashMap::new()).is_err());
+        assert!(eval_constant(&ast, value(statements[1]), &inline_variables).is_err());
+    }
+
+    #[test]
+    fn constant_operators() {
+        let words =
+            |code: &str| -> Vec<u16> { compile(code).iter().map(Instruction::to_bin).collect() };
+        let folded = "inline a = -(7 / 2) % 2 + (1 << 4 | 0xf0 >> 4 & ~0) + (3 > 2)\nb = a";
+        assert_eq!(words(folded), words("b = 31"));
+        assert_eq!(words("b = 7 / 2"), words("b = 3"));
+        // the remainder fits even where the quotient doesn't
+        assert_eq!(
+            words("inline A = -32767 - 1\ninline B = A % -1\nb = B"),
+            words("b = 0")
+        );
+
+        let compile_error = |code: &str| {
+            let tokens = tokenize(code).expect("Code to tokenize");
+            let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+            let errors = compile_program(&ast).expect_err("Code not to compile");
+            (errors[0].typ.get_code(), errors[0].location)
+        };
+        let at = |start, end| Range(Location(0, start), Location(0, end));
+        assert_eq!(
+            compile_error("inline a = 2 + 1 / (3 - 3)"),
+            ("division-by-zero", at(16, 25))
+        );
+        assert_eq!(
+            compile_error("inline a = -(-32767 - 1)"),
+            ("constant-overflow", at(12, 23))
+        );
+        // the sub-expression that overflows, not the whole one
+        assert_eq!(
+            compile_error("inline a = 1 + 32767 * 2"),
+            ("constant-overflow", at(16, 24))
+        );
+        assert_eq!(
+            compile_error("inline a = (32767 + 1) - 2"),
+            ("constant-overflow", at(13, 21))
+        );
+        assert_eq!(
+            compile_error("inline a = -32767 - 2"),
+            ("constant-overflow", at(12, 21))
+        );
+        assert_eq!(
+            compile_error("inline a = (-32767 - 1) / -1"),
+            ("constant-overflow", at(13, 28))
+        );
+        assert_eq!(
+            compile_error("inline a = 1 << 16"),
+            ("shift-too-far", at(12, 18))
+        );
+        assert_eq!(compile_error("a = 1\nb = a / 2").0, "runtime-division");
+        assert_eq!(compile_error("a = 1\nb = 1 << a").0, "compile-time-arg");
+    }
+
+    #[test]
+    fn static_assert() {
+        let compile_error = |code: &str| {
+            let tokens = tokenize(code).expect("Code to tokenize");
+            let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+            compile_program(&ast)
+                .err()
+                .map(|errors| errors[0].typ.get_message())
+        };
+        let code = "inline WIDTH = 16\ninline HEIGHT = 16\n";
+        assert_eq!(
+            compile_error(&format!(
+                "{code}static_assert(WIDTH * HEIGHT <= 256, \"screen too big\")"
+            )),
+            None
+        );
+        assert_eq!(
+            compile_error(&format!(
+                "{code}static_assert(WIDTH * HEIGHT < 256, \"screen too big\")"
+            )),
+            Some("Static assert failed: screen too big".to_string())
+        );
+
+        let tokens = tokenize("a = 1\nstatic_assert(a)").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let errors = compile_program(&ast).expect_err("Only constants to be asserted");
+        assert_eq!(errors[0].typ.get_code(), "forbidden-inline");
+    }
+
+    #[test]
+    fn unreachable() {
+        let code = "a = in(0)\nif a == 0\n  todo\n  a = 2\nelse\n  forever\n    pass\n  end\nend\nb = a\nc = b";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let (instructions, debug_info) =
+            compile_program_with_debug_info(&ast, CompileOptions::default())
+                .expect("Code to compile");
+        let unreachable: Vec<_> = debug_info
+            .warnings
+            .iter()
+            .filter(|warning| warning.typ == WarningType::Unreachable)
+            .map(|warning| warning.location)
+            .collect();
+        assert_eq!(
+            unreachable,
+            [
+                Range(Location(3, 3), Location(3, 7)),
+                Range(Location(9, 1), Location(10, 5)),
+            ]
+        );
+        // only `a` is ever stored
+        assert!(instructions
+            .iter()
+            .filter(|instruction| instruction.variant.name() == "SVA")
+            .all(|instruction| instruction.arg == Some(0)));
+
+        let mut cache = Cache::new();
+        let incremental = cache
+            .compile(&ast, &tokens, CompileOptions::default())
+            .expect("Code to compile");
+        assert_eq!(incremental.1.warnings, debug_info.warnings);
+        // the conditional is reused and still stops the code after it
+        let incremental = cache
+            .compile(&ast, &tokens, CompileOptions::default())
+            .expect("Code to compile");
+        assert_eq!(cache.reused(), 2);
+        assert_eq!(incremental, (instructions, debug_info));
+    }
+
+    #[test]
+    fn duplicate_use() {
+        let code = "use list\nuse io.list\nlist.add(1)";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let (instructions, debug_info) =
+            compile_program_with_debug_info(&ast, CompileOptions::default())
+                .expect("Code to compile");
+        assert_eq!(
+            debug_info.warnings,
+            [Warning {
+                typ: WarningType::DuplicateUse("list".to_string()),
+                location: Range(Location(1, 8), Location(1, 11)),
+                related: vec![Related {
+                    location: Range(Location(0, 5), Location(0, 8)),
+                    message: "list is first used here".to_string(),
+                }],
+            }]
+        );
+        // the list is only set up once
+        assert_eq!(instructions, compile("use list\nuse io\nlist.add(1)"));
+        assert_eq!(debug_info.modules, ["io", "list"]);
+
+        let mut cache = Cache::new();
+        let (_, incremental) = cache
+            .compile(&ast, &tokens, CompileOptions::default())
+            .expect("Code to compile");
+        assert_eq!(incremental.warnings, debug_info.warnings);
+    }
+
+    #[test]
+    fn program_too_large() {
+        let options = CompileOptions {
+            target: Target {
+                page_size: 4,
+                max_pages: 2,
+                ..Target::MCN_16
+            },
+            ..CompileOptions::default()
+        };
+        let compile_error = |code: &str| {
+            let tokens = tokenize(code).expect("Code to tokenize");
+            let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+            let errors = compile_program_with(&ast, options).expect_err("Code not to fit");
+            (errors[0].typ.get_code(), errors[0].location)
+        };
+        // the jump over the body ends up on the third page
+        assert_eq!(
+            compile_error("a = 1\nwhile a < 9\n  a += 1\nend"),
+            ("program-too-large", Range(Location(1, 1), Location(3, 3)))
+        );
+        // no jumps, the code of the last line just doesn't fit
+        assert_eq!(
+            compile_error("a = 1\nb = 2\nc = 3\nd = 4\ne = 5"),
+            ("program-too-large", Range(Location(4, 5), Location(4, 5)))
+        );
+        let tokens = tokenize("a = 1\nb = 2").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        assert!(compile_program_with(&ast, options).is_ok());
+    }
+
+    #[test]
+    fn todo() {
+        let code = "a = in(0)\nif a == 0\n  todo\nend";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let (instructions, debug_info) =
+            compile_program_with_debug_info(&ast, CompileOptions::default())
+                .expect("Code to compile");
+        assert!(variants(&instructions).contains(&"STP"));
+        assert_eq!(
+            debug_info.warnings,
+            [Warning {
+                typ: WarningType::Todo,
+                location: Range(Location(2, 3), Location(2, 6)),
+                related: vec![],
+            }]
+        );
+
+        // reused statements keep their warnings
+        let mut cache = Cache::new();
+        cache
+            .compile(&ast, &tokens, CompileOptions::default())
+            .expect("Code to compile");
+        let (_, cached) = cache
+            .compile(&ast, &tokens, CompileOptions::default())
+            .expect("Code to compile");
+        assert_eq!(cache.reused(), 2);
+        assert_eq!(cached.warnings, debug_info.warnings);
+    }
+
+    #[test]
+    fn unparse_round_trip() {
+        let parse = |code: &str| -> Ast {
+            let tokens = tokenize(code).expect("Code to tokenize");
+            Parser::new().produce_ast(&tokens).expect("Code to parse")
+        };
+        let canonical = |code: &str| {
+            let ast = parse(code);
+            unparse(&ast, ast.body())
+        };
+        let messy = "use io\nif (a==1)# comment\n    io.write( ( a+1 )*2,0)\nelif a> 1\n a++\nelse\n  pass\nend";
+        assert_eq!(
+            canonical(messy),
+            "use io\nif a == 1\n  io.write((a + 1) * 2, 0)\nelif a > 1\n  a += 1\nelse\n  pass\nend\n"
+        );
+        assert_eq!(canonical("a = (b = 1) + 0x8000"), "a = (b = 1) + 0x8000\n");
+        assert_eq!(
+            canonical("a = -(-b) - -5 - (1 - 2)"),
+            "a = -(-b) - -5 - (1 - 2)\n"
+        );
+
+        let programs = [
+            include_str!("programs/conditionals.mcn"),
+            include_str!("programs/list.mcn"),
+            include_str!("programs/loops.mcn"),
+            "inline W = 4\nvar b\na = 1\nb = W * 2 << 1 & 3 ^ ~W % 3\nswap a, b\nwait W * 10 + 20",
+            "inline A = 1\ninline C = (A == 2) > (A < 2)\na = C\na <<= 2\nb = 3\nb -= a -= 1\nstatic_assert(2 > 1, \"msg\")",
+            "a = 7\nb = a - (a - 1) * -(a + 1)\nforever\n  assert a != 0\n  a = b = a + 1\nend",
+        ];
+        for code in programs {
+            let unparsed = canonical(code);
+            assert_eq!(canonical(&unparsed), unparsed, "{unparsed}");
+            let words = |ast: &Ast| -> Vec<u16> {
+                compile_program(ast)
+                    .expect("Code to compile")
+                    .iter()
+                    .map(Instruction::to_bin)
+                    .collect()
+            };
+            assert_eq!(words(&parse(code)), words(&parse(&unparsed)), "{unparsed}");
+        }
+    }
+}
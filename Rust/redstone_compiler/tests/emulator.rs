@@ -0,0 +1,413 @@
+mod emulator_tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use redstone_compiler::{
+        backend::{
+            compile_program, compile_program_with, compile_program_with_debug_info, CompileOptions,
+            Instruction, InstructionVariant, OptLevel, Session, Target,
+        },
+        emulator::{Emulator, OutputWrite, State, StopReason, Watch},
+        frontend::{tokenize, Location, Parser, Range},
+    };
+
+    fn compile(code: &str) -> Vec<Instruction> {
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        compile_program(&ast).expect("Code to compile")
+    }
+
+    #[test]
+    fn writes_outputs() {
+        let code = "use io\na = 1\nb = a + 2\nio.write(b, 2)\nio.write(a * b + 1, 7)";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+
+        let values: Vec<_> = emulator
+            .output_log()
+            .iter()
+            .map(|write| (write.port, write.value))
+            .collect();
+        assert_eq!(values, [(2, 3), (7, 4)]);
+    }
+
+    #[test]
+    fn session() {
+        let mut session = Session::default();
+        let mut emulator = Emulator::new(&[], Target::MCN_16);
+        let mut run = |session: &mut Session, line: &str| {
+            let tokens = tokenize(line).expect("Code to tokenize");
+            let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+            let code = session.compile_expression(&ast)?;
+            emulator.load(&code);
+            assert_eq!(emulator.run(1000), StopReason::EndOfProgram);
+            Ok::<_, Vec<_>>(
+                emulator
+                    .output_log()
+                    .iter()
+                    .map(|write| write.value)
+                    .collect::<Vec<_>>(),
+            )
+        };
+        run(&mut session, "use io").unwrap();
+        run(&mut session, "a = 4").unwrap();
+        run(&mut session, "inline N = 3").unwrap();
+        run(&mut session, "b = a * N").unwrap();
+        assert!(run(&mut session, "c = d").is_err());
+        // the failed line didn't declare anything
+        run(&mut session, "while a > 0\n  a -= 1\n  b += 1\nend").unwrap();
+        let written = run(&mut session, "io.write(b, 0)").unwrap();
+        assert_eq!(written, [16]);
+
+        let names: Vec<_> = session
+            .symbols()
+            .iter()
+            .map(|symbol| symbol.name.as_str())
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(session.modules(), ["io"]);
+    }
+
+    #[test]
+    fn long_program() {
+        // the loop jumps over and back across more than 255 instructions and many pages
+        let code = format!(
+            "use io\ni = 0\ns = 0\nwhile i < 2\n{}  i += 1\nend\nio.write(s, 0)",
+            "  s += 1\n".repeat(500)
+        );
+        let program = compile(&code);
+        assert!(program.len() > 1000);
+        let mut emulator = Emulator::new(&program, Target::MCN_16);
+        assert_eq!(emulator.run(100_000), StopReason::EndOfProgram);
+        let values: Vec<_> = emulator
+            .output_log()
+            .iter()
+            .map(|write| write.value)
+            .collect();
+        assert_eq!(values, [1000]);
+    }
+
+    #[test]
+    fn many_jumps() {
+        // every block needs two jump marks, more than fit into an arg
+        let code = format!(
+            "use io\na = 0\ns = 0\n{}io.write(s, 0)",
+            "if a == 0\n  s += 1\nelse\n  s += 2\nend\n".repeat(200)
+        );
+        let program = compile(&code);
+        let mut emulator = Emulator::new(&program, Target::MCN_16);
+        assert_eq!(emulator.run(100_000), StopReason::EndOfProgram);
+        let values: Vec<_> = emulator
+            .output_log()
+            .iter()
+            .map(|write| write.value)
+            .collect();
+        assert_eq!(values, [200]);
+        // jumps go to an address inside of their page
+        assert!(program
+            .iter()
+            .filter(|instruction| instruction.variant.is_jump())
+            .all(|instruction| usize::from(instruction.arg.unwrap()) < Target::MCN_16.page_size));
+    }
+
+    #[test]
+    fn nested_jumps_across_pages() {
+        // jumps land on the `LCL` of disc jumps and the pages of the `LCL`s move
+        let code = "use io\ni = 0\ns = 0\nwhile i < 12\n  if i < 6\n    if i < 3\n      s += 1\n    else\n      s += 2\n    end\n  else\n    if i < 9\n      s += 3\n    else\n      s += 4\n    end\n  end\n  i += 1\nend\nio.write(s, 0)";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        for page_size in [4, 8, 16, 64] {
+            for opt_level in [OptLevel::O0, OptLevel::O1] {
+                let target = Target {
+                    page_size,
+                    ..Target::MCN_16
+                };
+                let options = CompileOptions {
+                    opt_level,
+                    target,
+                    ..CompileOptions::default()
+                };
+                let program = compile_program_with(&ast, options).expect("Code to compile");
+                let mut emulator = Emulator::new(&program, target);
+                assert_eq!(
+                    emulator.run(100_000),
+                    StopReason::EndOfProgram,
+                    "page size {page_size}"
+                );
+                let values: Vec<_> = emulator
+                    .output_log()
+                    .iter()
+                    .map(|write| write.value)
+                    .collect();
+                assert_eq!(values, [30], "page size {page_size}, {opt_level:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn swap() {
+        let code = "a = 3\nb = 5\nswap a, b\nc = a - b";
+        let program = compile(code);
+        // only the slots of a, b and c are touched, no temp variable
+        assert!(program
+            .iter()
+            .filter(|instruction| instruction.variant == InstructionVariant::SVA)
+            .all(|instruction| instruction.arg < Some(3)));
+        let mut emulator = Emulator::new(&program, Target::MCN_16);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        assert_eq!(emulator.state().slots[..3], [5, 3, 2]);
+    }
+
+    #[test]
+    fn min_max() {
+        let code = "use io\nuse math\na = io.read(0)\nb = math.min(a, 4)\nc = math.max(7, a)\nd = math.max(a, b) + math.min(2, 9)";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        emulator.set_input(0, 5);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        assert_eq!(emulator.state().slots[..4], [5, 4, 7, 7]);
+
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        emulator.set_input(0, -3);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        assert_eq!(emulator.state().slots[..4], [-3, -3, 7, -1]);
+    }
+
+    #[test]
+    fn unary_and_shifts() {
+        let code = "use io\na = io.read(0)\nb = -a\nc = ~(a + 1) & 0xff\nd = a << 2 ^ b >> 1";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        emulator.set_input(0, 6);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        assert_eq!(emulator.state().slots[..4], [6, -6, 0xf8, -27]);
+    }
+
+    #[test]
+    fn builtin_in() {
+        let code = "x = in(2) & 0b1111\ny = 3 - in(1)";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        emulator.set_input(2, 0x5a);
+        emulator.set_input(1, 1);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        assert_eq!(emulator.state().slots[..2], [0xa, 2]);
+    }
+
+    #[test]
+    fn builtin_out() {
+        let code = "x = 4\nout(3, x + 1)\nout(0, in(5))";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        emulator.set_input(5, 9);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+
+        let values: Vec<_> = emulator
+            .output_log()
+            .iter()
+            .map(|write| (write.port, write.value))
+            .collect();
+        assert_eq!(values, [(3, 5), (0, 9)]);
+    }
+
+    #[test]
+    fn assertions() {
+        let code = "a = in(0)\nassert a < 16\nb = a";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let options = CompileOptions {
+            assertions: true,
+            ..CompileOptions::default()
+        };
+        let (program, _) = compile_program_with_debug_info(&ast, options).expect("Code to compile");
+
+        let mut emulator = Emulator::new(&program, Target::MCN_16);
+        emulator.set_input(0, 3);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        emulator.reset();
+        emulator.set_input(0, 20);
+        assert_eq!(emulator.run(100), StopReason::Halted);
+        assert_eq!(
+            emulator.current_location(),
+            Some(Range(Location(1, 1), Location(1, 13)))
+        );
+
+        // left out by default
+        assert_eq!(compile(code).len(), compile("a = in(0)\nb = a").len());
+    }
+
+    #[test]
+    fn cycle_limit() {
+        let code = "use io\nforever\n  io.write(io.read(0) * 2, 0)\nend";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        emulator.set_input(0, 21);
+        assert_eq!(emulator.run(10), StopReason::CycleLimit);
+        assert!(emulator.cycles() >= 10);
+        assert_eq!(
+            emulator.output_log().first(),
+            Some(&OutputWrite {
+                port: 0,
+                value: 42,
+                cycle: 5,
+            })
+        );
+    }
+
+    #[test]
+    fn steps_through_binary() {
+        let code = "a = 300\nb = a + 1";
+        let binary: Vec<_> = compile(code).iter().map(Instruction::to_bin).collect();
+        let mut emulator = Emulator::from_binary(&binary, Target::MCN_16).expect("Binary to load");
+
+        while emulator.state().pc < 3 {
+            assert_eq!(emulator.step(), None);
+        }
+        assert_eq!(emulator.state().a, 300);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        assert_eq!(emulator.step(), Some(StopReason::EndOfProgram));
+        assert_eq!(emulator.state().slots[..2], [300, 301]);
+
+        emulator.reset();
+        assert_eq!(emulator.state(), &State::default());
+    }
+
+    #[test]
+    fn breakpoints() {
+        let code = "use io\nforever\n  io.write(io.read(0) + 1, 0)\nend";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        let line = Range(Location(2, 3), Location(2, 29));
+        assert_eq!(emulator.add_breakpoint_in(line), [0]);
+
+        assert_eq!(emulator.run(100), StopReason::Breakpoint(0));
+        assert!(emulator.output_log().is_empty());
+        assert_eq!(emulator.run(100), StopReason::Breakpoint(0));
+        assert_eq!(emulator.output_log().len(), 1);
+
+        assert!(emulator.remove_breakpoint(0));
+        assert_eq!(emulator.run(100), StopReason::CycleLimit);
+    }
+
+    #[test]
+    fn watchpoints() {
+        let code = "a = 1\nb = 2\na = 1\na = b + 4";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        emulator.add_watch(Watch::Slot(0));
+
+        let StopReason::Watchpoint(hit) = emulator.run(100) else {
+            panic!("Expected a watchpoint");
+        };
+        assert_eq!((hit.old, hit.new), (0, 1));
+        assert_eq!(hit.location, Some(Range(Location(0, 5), Location(0, 5))));
+
+        let StopReason::Watchpoint(hit) = emulator.run(100) else {
+            panic!("Expected a watchpoint");
+        };
+        assert_eq!((hit.old, hit.new), (1, 6));
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+    }
+
+    #[test]
+    fn step_statement() {
+        let code = "use io\na = 2\nforever\n  io.write(a, 1)\nend";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+
+        let mut lines = vec![];
+        for _ in 0..3 {
+            assert_eq!(emulator.step_statement(100), None);
+            lines.push(emulator.current_location().map(|location| location.0 .0));
+        }
+        assert_eq!(lines, [Some(3), Some(3), Some(3)]);
+        assert_eq!(emulator.output_log().len(), 2);
+    }
+
+    #[test]
+    fn estimate_matches_execution() {
+        let code = "use io\na = io.read(1) * 3\nio.write(a + 1, 0)";
+        let instructions = compile(code);
+        let timing = Target::MCN_16.timing;
+        let estimate: u64 = instructions
+            .iter()
+            .map(|instr| u64::from(timing.cycles(instr.variant)))
+            .sum();
+
+        let mut emulator = Emulator::new(&instructions, Target::MCN_16);
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        assert_eq!(emulator.cycles(), estimate);
+        assert_eq!(
+            emulator.ticks(),
+            estimate * u64::from(timing.ticks_per_cycle)
+        );
+    }
+
+    #[test]
+    fn wait() {
+        let ticks = |code: &str| {
+            let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+            assert_eq!(emulator.run(1000), StopReason::EndOfProgram);
+            emulator.ticks()
+        };
+        assert_eq!(
+            ticks("a = 1\ninline DELAY = 20\nwait DELAY * 3") - ticks("a = 1"),
+            60
+        );
+
+        let tokens = tokenize("wait 15").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(&tokens).expect("Code to parse");
+        let errors = compile_program(&ast).expect_err("Wait to not fit the target");
+        assert_eq!(
+            errors[0].typ.get_message(),
+            "mcn-16 can only wait multiples of 10 ticks, up to 640 ticks"
+        );
+    }
+
+    #[test]
+    fn io_handlers() {
+        let code = "use io\nio.write(io.read(3) + 1, 5)";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        let writes = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&writes);
+        emulator.on_input(|port| (port == 3).then_some(41));
+        emulator.on_output(move |write| log.borrow_mut().push(write));
+
+        assert_eq!(emulator.run(100), StopReason::EndOfProgram);
+        assert_eq!(emulator.state().inputs[3], 41);
+        assert_eq!(writes.borrow().as_slice(), emulator.output_log());
+        assert_eq!(emulator.state().outputs[5], 42);
+    }
+
+    #[test]
+    fn detects_infinite_loops() {
+        let code = "use io\na = 0\nwhile a < 5\n  io.write(a, 0)\nend";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        emulator.detect_loops(true);
+        assert!(matches!(
+            emulator.run(1000),
+            StopReason::PossibleInfiniteLoop(_)
+        ));
+        assert!(emulator.cycles() < 100);
+
+        let code = "a = 0\nforever\n  a += 1\nend";
+        let mut emulator = Emulator::new(&compile(code), Target::MCN_16);
+        emulator.detect_loops(true);
+        assert_eq!(emulator.run(1000), StopReason::CycleLimit);
+    }
+
+    #[test]
+    fn loads_debug_info() {
+        let code = "use io\na = 0\nforever\n  io.write(a, 0)\nend";
+        let ast = Parser::new()
+            .produce_ast(&tokenize(code).expect("Code to tokenize"))
+            .expect("Code to parse");
+        let (instructions, mut debug_info) =
+            compile_program_with_debug_info(&ast, CompileOptions::default())
+                .expect("Code to compile");
+        assert_eq!(debug_info.modules, ["io"]);
+        assert_eq!(debug_info.symbols[0].name, "a");
+        assert_eq!(debug_info.target(), Some(&Target::MCN_16));
+
+        let binary: Vec<_> = instructions.iter().map(Instruction::to_bin).collect();
+        let mut emulator = Emulator::from_binary(&binary, Target::MCN_16).expect("Binary to load");
+        assert!(emulator.load_debug_info(&debug_info));
+        let line = Range(Location(3, 1), Location(3, u16::MAX));
+        let entries = emulator.add_breakpoint_in(line);
+        assert_eq!(emulator.run(100), StopReason::Breakpoint(entries[0]));
+
+        debug_info.version += 1;
+        assert!(!emulator.load_debug_info(&debug_info));
+    }
+}
@@ -0,0 +1,457 @@
+mod export_tests {
+    use redstone_compiler::export::{
+        barrels,
+        datapack::{self, MAX_COMMANDS},
+        litematic,
+        nbt::Tag,
+        report::{report, Jump},
+        schem::{self, Version},
+        structure,
+        units::{split, Manifest},
+        BitOrder, Encoding, Layout, ReadError, Region, RomLayout, AIR,
+    };
+    use redstone_compiler::{
+        backend::{
+            checksum::{crc32, differences, Difference},
+            compile_program, compile_program_with, disassemble, Checksums, CompileOptions,
+            Instruction, Target,
+        },
+        frontend::{tokenize, Parser},
+    };
+
+    fn layout() -> Layout {
+        Layout {
+            bit_step: [0, 1, 0],
+            word_step: [-1, 0, 0],
+            page_step: [0, 0, 2],
+            encoding: Encoding::Blocks {
+                one: "one".to_string(),
+                zero: "zero".to_string(),
+            },
+            ..Layout::default()
+        }
+    }
+
+    #[test]
+    fn region() {
+        let region = Region::new(&[0b101, 0xFFFF, 1], 2, &layout());
+        assert_eq!(region.size, [2, 16, 3]);
+        assert_eq!(region.palette, [AIR, "one", "zero"]);
+        // x changes fastest, then z, the first word is at the highest x
+        let at = |x: u32, y: u32, z: u32| region.blocks[((y * 3 + z) * 2 + x) as usize];
+        assert_eq!(
+            [at(1, 0, 0), at(1, 1, 0), at(1, 2, 0), at(1, 3, 0)],
+            [1, 2, 1, 2]
+        );
+        assert_eq!(at(0, 15, 0), 1);
+        assert_eq!([at(1, 0, 2), at(1, 1, 2)], [1, 2]);
+        assert_eq!([at(0, 0, 2), at(1, 0, 1)], [0, 0]);
+    }
+
+    #[test]
+    fn schematic_is_gzipped() {
+        let region = Region::new(&[0x1234; 100], 64, &layout());
+        let bytes = schem::write(&region, Version::V2);
+        assert_eq!(bytes[..3], [0x1f, 0x8b, 8]);
+        let len = u32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+        // stored blocks add 5 bytes each, the header and the trailer 18
+        assert_eq!(bytes.len(), len as usize + 5 + 18);
+    }
+
+    #[test]
+    fn litematic_block_states() {
+        let layout = Layout {
+            encoding: Encoding::Barrels,
+            ..layout()
+        };
+        let region = Region::new(&[0x0001, 0x0010], 64, &layout);
+        let bytes = litematic::write(&region, "rom", 0);
+        // small enough to be a single stored block after the 10 byte header
+        let nbt = &bytes[15..bytes.len() - 8];
+        let name = b"\x08\x00\x04Name\x00\x10minecraft:barrel";
+        let facing = b"\x08\x00\x06facing\x00\x02up";
+        assert!(nbt.windows(name.len()).any(|window| window == name));
+        assert!(nbt.windows(facing.len()).any(|window| window == facing));
+        // 8 barrels with 2 bits each, a single long
+        let states = b"\x0c\x00\x0bBlockStates\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x55\x55";
+        assert!(nbt.windows(states.len()).any(|window| window == states));
+    }
+
+    #[test]
+    fn datapack_parts() {
+        let region = Region::new(&[0xFFFF, 0xFFFF, 0], 64, &layout());
+        let files = datapack::files(&region, "rom");
+        let file = |path: &str| {
+            let (_, contents) = files.iter().find(|(name, _)| name == path).unwrap();
+            contents.as_str()
+        };
+        // the first word is at the highest x, its ones and the next become a fill
+        let blocks = file("data/rom/functions/blocks_0.mcfunction");
+        assert!(blocks.starts_with("setblock ~0 ~0 ~0 zero\nfill ~1 ~0 ~0 ~2 ~0 ~0 one\n"));
+        assert!(file("data/rom/functions/place.mcfunction").contains("function rom:part_0"));
+
+        // with space between all bits every block needs its own command
+        let words = vec![0x5555; MAX_COMMANDS / 16 + 1];
+        let region = Region::new(&words, words.len(), &Layout::default());
+        let files = datapack::files(&region, "rom");
+        let parts: Vec<_> = files
+            .iter()
+            .filter(|(path, _)| path.contains("blocks_"))
+            .map(|(_, contents)| contents.lines().count())
+            .collect();
+        assert_eq!(parts.len(), 2);
+        assert!(parts.iter().all(|&commands| commands < MAX_COMMANDS));
+        assert_eq!(parts.iter().sum::<usize>(), words.len() * 16);
+    }
+
+    #[test]
+    fn structure_leaves_out_air() {
+        let region = Region::new(&[0xFFFF, 0], 64, &Layout::default());
+        let bytes = structure::write(&region);
+        // a single stored block after the 10 byte header
+        let (_, structure) = Tag::from_bytes(&bytes[15..bytes.len() - 8]).unwrap();
+        let Some(Tag::List(blocks)) = structure.get("blocks") else {
+            panic!("structures have blocks");
+        };
+        assert_eq!(blocks.len(), 32);
+        let Some(Tag::List(palette)) = structure.get("palette") else {
+            panic!("structures have a palette");
+        };
+        assert_eq!(palette.len(), 2);
+    }
+
+    #[test]
+    fn barrels() {
+        let layout = Layout {
+            encoding: Encoding::Barrels,
+            ..layout()
+        };
+        let region = Region::new(&[0xF310], 64, &layout);
+        assert_eq!(region.size, [1, 4, 1]);
+        assert_eq!(region.palette, [AIR, "minecraft:barrel[facing=up]"]);
+        let counts: Vec<Vec<i8>> = region
+            .entities
+            .iter()
+            .map(|(_, entity)| {
+                let [(_, Tag::List(items))] = &entity.data[..] else {
+                    panic!("barrels only have items");
+                };
+                items
+                    .iter()
+                    .map(|item| match item {
+                        Tag::Compound(tags) => match tags[2].1 {
+                            Tag::Byte(count) => count,
+                            _ => panic!("count is a byte"),
+                        },
+                        _ => panic!("items are compounds"),
+                    })
+                    .collect()
+            })
+            .collect();
+        // signal 1 needs a single item, 3 needs 2/14 of 27 stacks and 15 all of them
+        assert!(counts[0].is_empty());
+        assert_eq!(counts[1], [1]);
+        assert_eq!(counts[2].iter().map(|&c| i32::from(c)).sum::<i32>(), 247);
+        assert_eq!(counts[3], [64; 27]);
+        assert_eq!(region.entities[3].0, [0, 3, 0]);
+    }
+
+    #[test]
+    fn barrel_commands() {
+        // the layout's encoding doesn't matter, every nibble is a barrel
+        let commands = barrels::commands(&[0x0010, 0], 64, &layout());
+        assert_eq!(commands.len(), 8);
+        assert_eq!(
+            commands[1],
+            "data modify block ~0 ~1 ~0 Items set value [{Slot:0b,id:\"minecraft:redstone\",Count:1b}]"
+        );
+        assert_eq!(
+            commands[4],
+            "data modify block ~-1 ~0 ~0 Items set value []"
+        );
+    }
+
+    #[test]
+    fn read_back() {
+        let words = [0x0123, 0xFEDC, 0x8001, 0, 0x7777];
+        let layouts = [
+            layout(),
+            Layout {
+                encoding: Encoding::Barrels,
+                bit_order: BitOrder::MsbFirst,
+                ..layout()
+            },
+            Layout {
+                encoding: Encoding::Torches {
+                    base: "minecraft:stone".to_string(),
+                },
+                page_offsets: vec![[0, 0, 0], [5, 0, 0]],
+                ..Layout::default()
+            },
+        ];
+        for layout in layouts {
+            for version in [Version::V2, Version::V3] {
+                let region = Region::new(&words, 2, &layout);
+                let read = schem::read(&schem::write(&region, version)).unwrap();
+                assert_eq!(read, region);
+                assert_eq!(layout.read(&read, 2), words, "{layout:?}");
+            }
+        }
+    }
+
+    /// Gzip with a single stored deflate block
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let len = data.len() as u16;
+        let mut bytes = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 1];
+        bytes.extend(len.to_le_bytes());
+        bytes.extend((!len).to_le_bytes());
+        bytes.extend(data);
+        bytes.extend(crc32(data).to_le_bytes());
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn corrupt_schematic() {
+        let sized = |[width, height, length]: [i16; 3], data: Vec<u8>| {
+            let tag = Tag::compound([
+                ("Version", Tag::Int(2)),
+                ("Width", Tag::Short(width)),
+                ("Height", Tag::Short(height)),
+                ("Length", Tag::Short(length)),
+                ("Palette", Tag::compound([("minecraft:stone", Tag::Int(0))])),
+                ("BlockData", Tag::ByteArray(data)),
+            ]);
+            schem::read(&gzip(&tag.to_bytes("Schematic")))
+        };
+        let schematic = |data| sized([2, 1, 1], data);
+        assert!(schematic(vec![0, 0]).is_ok());
+        assert_eq!(
+            schematic(vec![0, 1]),
+            Err(ReadError::Corrupt("a block isn't in the palette"))
+        );
+        assert_eq!(
+            schematic(vec![0x80; 6]),
+            Err(ReadError::Corrupt("a palette index is longer than 5 bytes"))
+        );
+        assert_eq!(
+            schematic(vec![0]),
+            Err(ReadError::Corrupt(
+                "the number of blocks doesn't match the size"
+            ))
+        );
+        // its volume doesn't even fit into 32 bits, nothing is allocated for it
+        assert_eq!(
+            sized([-1, -1, -1], vec![0]),
+            Err(ReadError::Corrupt(
+                "the number of blocks doesn't match the size"
+            ))
+        );
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        let words = [0x3231, 0x3433, 0x3635, 0x3837, 0x0039];
+        let checksums = Checksums::new(&words, 2);
+        assert_eq!(checksums.pages.len(), 3);
+        assert_eq!(checksums.pages[0], crc32(b"1234"));
+        assert_eq!(checksums.program, crc32(b"123456789\0"));
+
+        let mut pasted = words.to_vec();
+        pasted[1] = 0;
+        pasted.pop();
+        assert_eq!(
+            differences(&words, &pasted),
+            [
+                Difference {
+                    address: 1,
+                    expected: Some(0x3433),
+                    actual: Some(0),
+                },
+                Difference {
+                    address: 4,
+                    expected: Some(0x0039),
+                    actual: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn compressed_schematic() {
+        // exported by mcnc and compressed again by another program
+        let bytes = include_bytes!("schematics/countdown.schem");
+        let region = schem::read(bytes).unwrap();
+        let words = Layout::default().read(&region, 64);
+        let code = include_str!("programs/countdown.mcn");
+        let ast = Parser::new().produce_ast(&tokenize(code).unwrap()).unwrap();
+        let expected: Vec<_> = compile_program(&ast)
+            .unwrap()
+            .iter()
+            .map(Instruction::to_bin)
+            .collect();
+        assert_eq!(words, expected);
+        assert!(disassemble(&words).is_ok());
+    }
+
+    #[test]
+    fn jumps_in_report() {
+        let code = include_str!("programs/countdown.mcn");
+        let ast = Parser::new().produce_ast(&tokenize(code).unwrap()).unwrap();
+        let options = CompileOptions {
+            target: Target {
+                page_size: 8,
+                ..Target::MCN_16
+            },
+            ..CompileOptions::default()
+        };
+        let instructions = compile_program_with(&ast, options).unwrap();
+        let report = report(&instructions, &options.target);
+        assert_eq!(report.page_count, 3);
+        assert_eq!(report.instructions[9].page, 1);
+        assert_eq!(report.instructions[1].cycle, 1);
+        // the page of every disc jump is loaded right before it
+        assert_eq!(
+            report.jumps.last(),
+            Some(&Jump {
+                from: 20,
+                to: Some(5),
+                conditional: true,
+                disc: true,
+            })
+        );
+        assert!(report.jumps.iter().all(|jump| jump.to.is_some()));
+    }
+
+    #[test]
+    fn split_units() {
+        let code = include_str!("programs/countdown.mcn");
+        let ast = Parser::new().produce_ast(&tokenize(code).unwrap()).unwrap();
+        let target = Target {
+            page_size: 8,
+            ..Target::MCN_16
+        };
+        let options = CompileOptions {
+            target,
+            ..CompileOptions::default()
+        };
+        let instructions = compile_program_with(&ast, options).unwrap();
+        assert_eq!(split(&instructions, &target, None).len(), 1);
+
+        let units = split(&instructions, &target, Some(2));
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[1].first_page, 2);
+        assert_eq!(units[0].words.len(), 16);
+        assert_eq!(
+            units
+                .iter()
+                .flat_map(|unit| unit.words.clone())
+                .collect::<Vec<_>>(),
+            instructions
+                .iter()
+                .map(Instruction::to_bin)
+                .collect::<Vec<_>>()
+        );
+        // the loop jumps back from the third page to the first
+        assert_eq!(units[1].jumps_to, [0]);
+
+        let files = vec!["a.schem".to_string(), "b.schem".to_string()];
+        let manifest = Manifest::new(&units, files, &target, Some(2));
+        assert_eq!(manifest.units[1].first_address, 16);
+        assert_eq!(manifest.units[1].page_count, 1);
+        assert_eq!(manifest.instruction_count, instructions.len());
+    }
+
+    #[test]
+    fn toml_layout() {
+        let layout = Layout::parse_toml(include_str!("../layouts/standard.toml")).unwrap();
+        assert_eq!(layout, Layout::default());
+
+        let layout = Layout::parse_toml(
+            "bit-order = \"msb-first\"\n\
+             page-offsets = [[0, 0, 0], [0, 0, 10]]\n\
+             [encoding]\n\
+             type = \"torches\"\n\
+             base = \"stone\"\n",
+        )
+        .unwrap();
+        assert_eq!(layout.bit_order, BitOrder::MsbFirst);
+        assert_eq!(
+            layout.encoding,
+            Encoding::Torches {
+                base: "stone".to_string()
+            }
+        );
+        // the highest bit is at the bottom, the third page continues after the second
+        let region = Region::new(&[1, 0, 0], 1, &layout);
+        assert_eq!(region.size, [1, 32, 15]);
+        let torch = region
+            .palette
+            .iter()
+            .position(|block| block == "minecraft:redstone_torch");
+        assert_eq!(region.blocks[(31 * 15) as usize], torch.unwrap() as u32);
+
+        for (text, code) in [
+            ("bit-step = [0, 1]", "invalid-value"),
+            ("colour = 1", "unknown-key"),
+            ("pages-per-unit = 0", "invalid-value"),
+            ("[encoding]\none = \"a\"", "missing-key"),
+            (
+                "[encoding]\ntype = \"barrels\"\nbase = \"a\"",
+                "unknown-key",
+            ),
+            ("[encoding]\ntype = \"wool\"", "unknown-encoding"),
+        ] {
+            let error = Layout::parse_toml(text).unwrap_err();
+            assert_eq!(error.typ.get_code(), code, "{text}");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn standard_layout() {
+        let layout: Layout =
+            serde_json::from_str(include_str!("../layouts/standard.json")).unwrap();
+        assert_eq!(layout, Layout::default());
+    }
+
+    #[test]
+    fn program_json_shape() {
+        use redstone_compiler::{
+            backend::compile,
+            export::json::{program_json, JSON_FORMAT_VERSION},
+        };
+
+        let code = "a = 1\nforever\n  a += 1\nend";
+        let ast = Parser::new().produce_ast(&tokenize(code).unwrap()).unwrap();
+        let options = CompileOptions::default();
+        let (instructions, debug_info) = compile(&ast, options).unwrap().into_parts();
+        let json = program_json(&instructions, &debug_info, &options.target);
+        assert!(json.is_compatible());
+        assert_eq!(json.format_version, JSON_FORMAT_VERSION);
+        assert_eq!(json.page_count, 1);
+        assert_eq!(json.instructions.len(), instructions.len());
+        for (address, (entry, instruction)) in
+            json.instructions.iter().zip(&instructions).enumerate()
+        {
+            assert_eq!(entry.address, address);
+            assert_eq!(entry.page, 0);
+            assert_eq!(entry.word, instruction.to_bin());
+            assert_eq!(entry.text, format!("{instruction}"));
+            assert_eq!(entry.location, instruction.orig_location);
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            let value = serde_json::to_value(&json).unwrap();
+            assert_eq!(value["formatVersion"], JSON_FORMAT_VERSION);
+            assert!(value["instructions"][0]["mnemonic"].is_string());
+            assert_eq!(
+                serde_json::from_value::<redstone_compiler::export::json::ProgramJson>(value)
+                    .unwrap(),
+                json
+            );
+        }
+    }
+}
@@ -1,12 +1,12 @@
 mod lexer_tests {
 
-    fn token_types(code: &str) -> Result<Vec<TokenType>, Error> {
+    fn token_types(code: &str) -> Result<Vec<TokenType<'_>>, Error> {
         Ok(tokenize(code)?.into_iter().map(|t| t.typ).collect())
     }
     use std::iter::once;
 
     use redstone_compiler::{
-        frontend::{tokenize, EqualityOperator, Operator, TokenType},
+        frontend::{tokenize, EqualityOperator, Location, Operator, Range, TokenType},
         Error,
     };
 
@@ -40,6 +40,46 @@ mod lexer_tests {
         assert_eq!(expected, ast);
     }
 
+    #[test]
+    fn new_operators() {
+        use Operator::*;
+        let code = "/ % << >> /= %= <<= >>= ~ < -(";
+        let expected = [
+            TokenType::BinaryOperator(Div),
+            TokenType::BinaryOperator(Mod),
+            TokenType::BinaryOperator(ShiftLeft),
+            TokenType::BinaryOperator(ShiftRight),
+            TokenType::IOperator(Div),
+            TokenType::IOperator(Mod),
+            TokenType::IOperator(ShiftLeft),
+            TokenType::IOperator(ShiftRight),
+            TokenType::Not,
+            TokenType::EqOperator(EqualityOperator::Less),
+            TokenType::BinaryOperator(Minus),
+            TokenType::OpenParen,
+            TokenType::Eof,
+        ];
+        let ast = token_types(code).expect("Code to compile");
+        assert_eq!(expected[..], ast);
+    }
+
+    #[test]
+    fn text() {
+        let code = "static_assert(1, \"too big\")";
+        let expected = [
+            TokenType::StaticAssert,
+            TokenType::OpenFuncParen,
+            TokenType::Number(1),
+            TokenType::Comma,
+            TokenType::Text("too big"),
+            TokenType::CloseParen,
+            TokenType::Eof,
+        ];
+        assert_eq!(token_types(code).expect("Code to tokenize")[..], expected);
+        let error = token_types("\"unterminated\n\"").expect_err("Text to end on its line");
+        assert_eq!(error.typ.get_code(), "unterminated-text");
+    }
+
     #[test]
     fn iop() {
         use Operator::*;
@@ -65,4 +105,72 @@ mod lexer_tests {
         let ast = token_types(code).expect("Code to compile");
         assert_eq!(expected, ast);
     }
+
+    #[test]
+    fn number_out_of_range() {
+        let error = tokenize("a = 40000").expect_err("Number not to fit");
+        assert_eq!(error.typ.get_code(), "invalid-number");
+    }
+
+    #[test]
+    fn identifiers_borrow_the_source() {
+        let code = String::from("a_b = c");
+        let tokens = tokenize(&code).expect("Code to tokenize");
+        let TokenType::Identifier(name) = tokens[0].typ else {
+            panic!("Expected an identifier");
+        };
+        assert_eq!(name, "a_b");
+        assert!(std::ptr::eq(name.as_ptr(), code.as_ptr()));
+    }
+
+    #[test]
+    fn steps() {
+        let code = "a++ b-- c - -1";
+        let expected = vec![
+            TokenType::Identifier("a"),
+            TokenType::Step(Operator::Plus),
+            TokenType::Identifier("b"),
+            TokenType::Step(Operator::Minus),
+            TokenType::Identifier("c"),
+            TokenType::BinaryOperator(Operator::Minus),
+            TokenType::Number(-1),
+            TokenType::Eof,
+        ];
+        assert_eq!(token_types(code).expect("Code to tokenize"), expected);
+    }
+
+    #[test]
+    fn locations_and_offsets() {
+        let code = "a = 1\nbb += 22\n";
+        for token in tokenize(code).unwrap() {
+            let TokenType::Identifier(name) = token.typ else {
+                continue;
+            };
+            let bytes = token.location.byte_range(code).unwrap();
+            assert_eq!(&code[bytes.clone()], name);
+            assert_eq!(Range::from_byte_range(code, bytes), Some(token.location));
+        }
+
+        let code = "äb\nc";
+        assert_eq!(Location(0, 2).offset(code), Some(2));
+        assert_eq!(Location(0, 3).offset(code), Some(3));
+        assert_eq!(Location(0, 4).offset(code), None);
+        assert_eq!(Location(1, 1).offset(code), Some(4));
+        assert_eq!(Location(2, 1).offset(code), None);
+        assert_eq!(Location::from_offset(code, 1), None);
+        assert_eq!(Location::from_offset(code, 4), Some(Location(1, 1)));
+        assert_eq!(
+            Range(Location(0, 1), Location(0, 1)).byte_range(code),
+            Some(0..2)
+        );
+
+        let range = Range(Location(0, 3), Location(1, 2));
+        assert!(range.contains(Location(0, 3)));
+        assert!(range.contains(Location(1, 2)));
+        assert!(!range.contains(Location(1, 3)));
+        assert!(range.intersects(Range(Location(1, 2), Location(1, 5))));
+        assert!(!range.intersects(Range(Location(0, 1), Location(0, 2))));
+        assert!(range.contains_range(Range(Location(0, 5), Location(1, 1))));
+        assert!(Range(Location(0, 3), Location(0, 4)) < range);
+    }
 }
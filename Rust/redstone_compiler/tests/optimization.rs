@@ -0,0 +1,93 @@
+//! Runs every program in `tests/programs` compiled at each optimization level and
+//! checks that they write the same values as the unoptimized one
+
+mod optimization_tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use redstone_compiler::{
+        backend::{compile_program_with, CompileOptions, OptLevel, Target},
+        emulator::{Emulator, StopReason},
+        frontend::{tokenize, Parser},
+    };
+
+    const MAX_CYCLES: u64 = 5000;
+
+    fn programs() -> Vec<PathBuf> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+        let mut programs: Vec<_> = fs::read_dir(dir)
+            .expect("tests/programs to exist")
+            .map(|entry| entry.expect("Directory entry to be readable").path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "mcn"))
+            .collect();
+        programs.sort();
+        programs
+    }
+
+    /// Output writes of the program, `None` if it doesn't compile.
+    /// The n-th read of port p gets `p + 3n`
+    fn trace(code: &str, opt_level: OptLevel) -> Option<(Vec<(u8, i16)>, StopReason)> {
+        let ast = Parser::new().produce_ast(&tokenize(code).ok()?).ok()?;
+        let options = CompileOptions {
+            opt_level,
+            ..CompileOptions::default()
+        };
+        let instructions = compile_program_with(&ast, options).ok()?;
+
+        let mut emulator = Emulator::new(&instructions, Target::MCN_16);
+        let mut reads = [0; 8];
+        emulator.on_input(move |port| {
+            let value = i16::from(port) + 3 * reads[usize::from(port)];
+            reads[usize::from(port)] += 1;
+            Some(value)
+        });
+        let reason = emulator.run(MAX_CYCLES);
+        let writes = emulator
+            .output_log()
+            .iter()
+            .map(|write| (write.port, write.value))
+            .collect();
+        Some((writes, reason))
+    }
+
+    #[test]
+    fn opt_levels_agree() {
+        let mut compared = 0;
+        for program in programs() {
+            let code = fs::read_to_string(&program).expect("Program to be readable");
+            let Some((expected, expected_reason)) = trace(&code, OptLevel::O0) else {
+                continue;
+            };
+            for opt_level in &OptLevel::ALL[1..] {
+                let (actual, reason) =
+                    trace(&code, *opt_level).expect("Optimized program to compile");
+                // faster code gets further before the cycle limit
+                let finished = reason != StopReason::CycleLimit;
+                let expected_finished = expected_reason != StopReason::CycleLimit;
+                let len = if finished && expected_finished {
+                    expected.len().max(actual.len())
+                } else {
+                    expected.len().min(actual.len())
+                };
+                assert_eq!(
+                    actual.get(..len),
+                    expected.get(..len),
+                    "{} writes other values at {opt_level:?}",
+                    program.display()
+                );
+                if expected_finished {
+                    assert_eq!(
+                        reason,
+                        expected_reason,
+                        "{} stops differently at {opt_level:?}",
+                        program.display()
+                    );
+                }
+            }
+            compared += 1;
+        }
+        assert!(compared > 0, "No program in tests/programs compiles");
+    }
+}
@@ -0,0 +1,84 @@
+mod project_tests {
+    use redstone_compiler::{
+        backend::{OptLevel, Target},
+        frontend::{Location, Range},
+        project::Manifest,
+    };
+
+    #[test]
+    fn round_trip() {
+        let mut manifest = Manifest::new("blink");
+        manifest.includes.push("lib/\"quoted\"".to_string());
+        manifest.opt_level = OptLevel::O0;
+        manifest.page_size = Some(16);
+        let parsed = Manifest::parse(&manifest.to_string()).expect("Manifest to parse");
+        assert_eq!(parsed, manifest);
+        assert_eq!(parsed.compile_options().target.page_size, 16);
+        assert_eq!(parsed.compile_options().opt_level, OptLevel::O0);
+    }
+
+    #[test]
+    fn settings() {
+        let text = "# comment\n[project]\nname = \"blink\" # the name\nincludes = [ \"a\", \"b\", ]\n\n[options]\nopt-level = 0\n";
+        let manifest = Manifest::parse(text).expect("Manifest to parse");
+        assert_eq!(manifest.name, "blink");
+        assert_eq!(manifest.target, &Target::MCN_16);
+        assert_eq!(manifest.entry, "src/main.mcn");
+        assert_eq!(manifest.includes, ["a", "b"]);
+        assert_eq!(manifest.opt_level, OptLevel::O0);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text: &str| {
+            let error = Manifest::parse(text).expect_err("Manifest to be invalid");
+            (error.typ.get_code(), error.location)
+        };
+        let range = |line, start, end| Range(Location(line, start), Location(line, end));
+
+        assert_eq!(
+            error("[project]\nentry = \"a\""),
+            ("missing-key", Range::default())
+        );
+        assert_eq!(
+            error("[project]\nname = 5"),
+            ("invalid-value", range(1, 8, 8))
+        );
+        assert_eq!(
+            error("[project]\nname \"a\""),
+            ("expected-character", range(1, 6, 6))
+        );
+        assert_eq!(
+            error("[project]\nname = \"a"),
+            ("unclosed-string", range(1, 8, 9))
+        );
+        assert_eq!(
+            error("[project]\nnam = \"a\""),
+            ("unknown-key", range(1, 1, 3))
+        );
+        assert_eq!(
+            error("[project]\ntarget = \"x\""),
+            ("unknown-target", range(1, 10, 12))
+        );
+        assert_eq!(
+            error("[options]\nopt-level = 2"),
+            ("invalid-opt-level", range(1, 13, 13))
+        );
+        assert_eq!(
+            error("[options]\npage-size = 257"),
+            ("invalid-page-size", range(1, 13, 15))
+        );
+        assert_eq!(
+            error("[options]\npage-size = 0"),
+            ("invalid-page-size", range(1, 13, 13))
+        );
+        assert_eq!(
+            error("[project]\nname = \"a\"\nname = \"b\""),
+            ("duplicate-key", range(2, 1, 4))
+        );
+        assert_eq!(
+            error("[project]\nname = \"a\" x"),
+            ("unexpected-character", range(1, 12, 12))
+        );
+    }
+}
@@ -0,0 +1,85 @@
+//! Compiles every program in `tests/programs` and compares the assembly or the
+//! diagnostics with the `.snap` file next to it.
+//! Run with `UPDATE_SNAPSHOTS=1` to write the snapshots instead
+
+mod snapshot_tests {
+    use std::{
+        env,
+        fmt::Write,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use redstone_compiler::{
+        backend::compile_program,
+        frontend::{tokenize, Parser},
+        Error,
+    };
+
+    fn programs() -> Vec<PathBuf> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/programs");
+        let mut programs: Vec<_> = fs::read_dir(dir)
+            .expect("tests/programs to exist")
+            .map(|entry| entry.expect("Directory entry to be readable").path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "mcn"))
+            .collect();
+        programs.sort();
+        programs
+    }
+
+    /// One instruction per line with its address and source, or one line per error
+    fn render(code: &str) -> String {
+        let result = tokenize(code)
+            .map_err(|error| vec![error])
+            .and_then(|tokens| Parser::new().produce_ast(&tokens))
+            .and_then(|ast| compile_program(&ast));
+        let mut snapshot = String::new();
+        match result {
+            Ok(instructions) => {
+                for (address, instr) in instructions.iter().enumerate() {
+                    let location = format!("{:?}", instr.orig_location);
+                    writeln!(snapshot, "{address:>4}  {location:<12} {instr}").unwrap();
+                }
+            }
+            Err(errors) => {
+                for Error { typ, location, .. } in &errors {
+                    let (code, message) = (typ.get_code(), typ.get_message());
+                    writeln!(snapshot, "error[{code}] {location:?}: {message}").unwrap();
+                }
+            }
+        }
+        snapshot
+    }
+
+    #[test]
+    fn programs_match_snapshots() {
+        let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+        let programs = programs();
+        assert!(!programs.is_empty(), "No programs in tests/programs");
+
+        let mut mismatches = vec![];
+        for program in programs {
+            let code = fs::read_to_string(&program).expect("Program to be readable");
+            let actual = render(&code);
+            let snapshot = program.with_extension("snap");
+            if update {
+                fs::write(&snapshot, actual).expect("Snapshot to be writable");
+                continue;
+            }
+            let expected = fs::read_to_string(&snapshot).unwrap_or_default();
+            if actual != expected {
+                mismatches.push(format!(
+                    "{}\n--- expected\n{expected}--- actual\n{actual}",
+                    program.display()
+                ));
+            }
+        }
+        assert!(
+            mismatches.is_empty(),
+            "{} program(s) differ from their snapshots, rerun with UPDATE_SNAPSHOTS=1 \
+             if the changes are intended\n\n{}",
+            mismatches.len(),
+            mismatches.join("\n")
+        );
+    }
+}
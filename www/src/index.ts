@@ -1,4 +1,4 @@
-import init, { compile } from "mcn-ls";
+import init, { export_schematic, tokenize_line } from "mcn-ls";
 
 import * as monaco from "monaco-editor";
 
@@ -15,22 +15,51 @@ import {
   BrowserMessageReader,
   BrowserMessageWriter,
 } from "vscode-jsonrpc/browser";
-import { tokenProvider } from "./languageDef";
 import { languageConfig } from "./languageDef";
 
-const compileCode = (
+type CompileArtifacts = {
+  assembly: string;
+  binary: number[];
+  sourceMap: unknown[];
+};
+
+const compileCode = async (
+  client: MonacoLanguageClient,
   editor: monaco.editor.IStandaloneCodeEditor,
   out: HTMLElement
-): any => {
+) => {
   try {
-    const asm = compile(editor.getValue());
-    out.innerText = asm;
+    const result: CompileArtifacts = await client.sendRequest(
+      "workspace/executeCommand",
+      { command: "mcn.compile", arguments: [editor.getModel()!.uri.toString()] }
+    );
+    out.innerText = result.assembly;
     out.classList.remove("error");
   } catch (error) {
     out.classList.add("error");
   }
 };
 
+const downloadSchematic = (
+  editor: monaco.editor.IStandaloneCodeEditor,
+  out: HTMLElement
+) => {
+  let bytes: Uint8Array;
+  try {
+    bytes = export_schematic(editor.getValue(), 2, undefined);
+  } catch (error) {
+    out.innerText = String(error);
+    out.classList.add("error");
+    return;
+  }
+  const url = URL.createObjectURL(new Blob([bytes]));
+  const link = document.createElement("a");
+  link.href = url;
+  link.download = "rom.schem";
+  link.click();
+  URL.revokeObjectURL(url);
+};
+
 const languageWorker = new Worker(
   new URL("./worker/languageWorker.ts", import.meta.url),
   { type: "module" }
@@ -63,6 +92,27 @@ const vividColors = {
   deepRed: "BE5046",
 };
 
+// no token spans multiple lines, so the state only gets passed along
+class LineState implements monaco.languages.IState {
+  constructor(readonly value: number) {}
+
+  clone() {
+    return new LineState(this.value);
+  }
+
+  equals(other: monaco.languages.IState) {
+    return other instanceof LineState && other.value === this.value;
+  }
+}
+
+const tokensProvider: monaco.languages.TokensProvider = {
+  getInitialState: () => new LineState(0),
+  tokenize: (line, state) => {
+    const result = tokenize_line(line, (state as LineState).value);
+    return { tokens: result.tokens, endState: new LineState(result.endState) };
+  },
+};
+
 init().then(async () => {
   const code = localStorage.getItem("monaco-editor-code") ?? "hi";
 
@@ -71,7 +121,7 @@ init().then(async () => {
   monaco.languages.register({ id: "mcn-16" });
 
   monaco.languages.setLanguageConfiguration("mcn-16", languageConfig);
-  monaco.languages.setMonarchTokensProvider("mcn-16", tokenProvider);
+  monaco.languages.setTokensProvider("mcn-16", tokensProvider);
 
   monaco.editor.defineTheme("mcn-16-dark", {
     base: "vs-dark",
@@ -127,14 +177,6 @@ init().then(async () => {
   window.onbeforeunload = () => {
     localStorage.setItem("monaco-editor-code", editor.getValue());
   };
-  const out = document.getElementById("out")!;
-
-  editor.onDidChangeModelContent(() => {
-    compileCode(editor, out);
-  });
-
-  compileCode(editor, out);
-
   const languageClient = new MonacoLanguageClient({
     name: "MCN Language Client",
     clientOptions: {
@@ -148,7 +190,19 @@ init().then(async () => {
     },
   });
 
-  languageClient.start();
+  await languageClient.start();
+
+  const out = document.getElementById("out")!;
+
+  editor.onDidChangeModelContent(() => {
+    compileCode(languageClient, editor, out);
+  });
+
+  compileCode(languageClient, editor, out);
+
+  document
+    .getElementById("download-schematic")!
+    .addEventListener("click", () => downloadSchematic(editor, out));
 
   document.querySelector("#loading")?.remove();
 });
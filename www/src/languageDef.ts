@@ -1,80 +1,5 @@
 import * as monaco from "monaco-editor";
 
-export const tokenProvider: monaco.languages.IMonarchLanguage = {
-  brackets: [{ open: "(", close: ")", token: "delimiter.parenthesis" }],
-  defaultToken: "invalid",
-  ignoreCase: false,
-
-  tokenPostfix: ".mcn-16",
-  keywords: [
-    "inline",
-    "if",
-    "elif",
-    "elseif",
-    "else",
-    "forever",
-    "while",
-    "end",
-    "pass",
-    "use",
-    "var",
-    "debug",
-  ],
-
-  operators: [
-    "+",
-    "-",
-    "*",
-    "&",
-    "|",
-    "^",
-    "+=",
-    "-=",
-    "*=",
-    "&=",
-    "|=",
-    "^=",
-    "=",
-    "==",
-    "!=",
-    "<",
-    ">",
-    "<=",
-    ">=",
-  ],
-
-  symbols: /[+\-*&|^]=?|!=|[=<>]=?/,
-
-  tokenizer: {
-    root: [
-      { include: "@numbers" },
-      { include: "@whitespace" },
-      // delimiters and operators
-      [/[()]/, "@brackets"],
-      [/[,\.;]/, "punctuation.separator"],
-      [/@symbols/, "operator"],
-      // identifiers and keywords
-      [
-        /[\w][\d\w]*/,
-        {
-          cases: {
-            "@keywords": "keyword",
-            "@default": "identifier",
-          },
-        },
-      ],
-    ],
-    whitespace: [
-      [/[ \t\r\n;]+/, ""],
-      [/#.*/, "comment"],
-    ],
-    numbers: [
-      [/0x[0-9a-f]+/, "number.hex"],
-      [/0b[01]+/, "number.binary"],
-      [/\d+/, "number"],
-    ],
-  },
-};
 export const languageConfig: monaco.languages.LanguageConfiguration = {
   comments: {
     lineComment: "#",
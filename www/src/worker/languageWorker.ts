@@ -1,17 +1,15 @@
 import {
   BrowserMessageReader,
   BrowserMessageWriter,
+  CancellationToken,
   InitializeResult,
+  LSPErrorCodes,
+  ResponseError,
   ServerCapabilities,
   createConnection,
 } from "vscode-languageserver/browser";
 
-import init, { receive_message, LspServer } from "mcn-ls";
-
-const log = (_) => {
-  console.log(_);
-  return _;
-};
+import init, { LspServer } from "mcn-ls";
 
 // self.onmessage = async ({ data }) => {
 //   console.log(data);
@@ -37,40 +35,40 @@ init().then(async () => {
     return true;
   }
 
-  connection.onRequest(async (method, params, token) => {
-    receive_message(
-      JSON.stringify({ kind: "request", method, params, token }, null, 2)
-    );
-  });
-
-  connection.onNotification(async (method, params) => {
-    receive_message(
-      JSON.stringify({ kind: "notification", method, params }, null, 2)
-    );
-  });
-
   connection.onInitialize(async (params) => {
     lsp = LspServer.new(sendNotification, sendRequest);
-    console.log("init", params);
-    receive_message(JSON.stringify({ kind: "init", params }, null, 2));
-    return log(lsp.initialize(params));
+    return lsp.initialize(params);
   });
 
-  connection.onDidOpenTextDocument(async (params) => {
-    console.log("open", params);
-    lsp.reload_document(params.textDocument.text, params.textDocument.version);
+  // waits until the server says the documents stopped changing,
+  // newer changes cancel the request so stale diagnostics are never reported
+  async function settle(uri: string | undefined, token: CancellationToken) {
+    let delay: number;
+    while ((delay = lsp.diagnostic_delay(uri)) > 0) {
+      await new Promise((resolve) => setTimeout(resolve, delay));
+      if (token.isCancellationRequested) {
+        throw new ResponseError(LSPErrorCodes.RequestCancelled, "cancelled");
+      }
+    }
+  }
+
+  connection.onRequest(async (method, params: any, token) => {
+    if (method === "textDocument/diagnostic") {
+      await settle(params.textDocument.uri, token);
+    } else if (method === "workspace/diagnostic") {
+      await settle(undefined, token);
+    }
+    return lsp.handle_request(method, params);
   });
 
-  connection.onDidChangeTextDocument(async (params) => {
-    console.log("change", params);
-    receive_message("change")
-    lsp.reload_document(
-      params.contentChanges[0].text,
-      params.textDocument.version
-    );
+  connection.onNotification(async (method, params) => {
+    lsp.handle_notification(method, params);
   });
 
-  receive_message("worker initialized");
+  // the connection would handle this itself, the server does its own tracing
+  connection.onNotification("$/setTrace", async (params) => {
+    lsp.handle_notification("$/setTrace", params);
+  });
 
   connection.listen();
 });